@@ -2,7 +2,17 @@
 //! 
 //! CPU、メモリ、ビデオ、ディスクを統合
 
-use crate::cpu::{Cpu, CpuType, MemoryBus};
+use crate::cpu::{Cpu, CpuType, MemoryBus, Registers};
+use crate::cpu::assembler::Assembler;
+use crate::cassette::{self, CassetteDeck};
+use crate::interrupt::InterruptController;
+use crate::mouse::MouseCard;
+use crate::clock::{ClockSource, NoSlotClock, ThunderClock};
+use crate::videx::VidexCard;
+use crate::profiler::{access, Debugger, DebuggerState};
+use crate::expr::EvalContext;
+use crate::trace::TraceEntry;
+use crate::rewind::{Input, Rewind, WriteHit};
 use crate::memory::{AppleModel, Memory};
 use crate::video::Video;
 use crate::disk::{Disk2InterfaceCard, DiskFormat, DSK_SIZE, NIB_SIZE};
@@ -18,8 +28,34 @@ pub struct Apple2 {
     pub video: Video,
     /// Disk IIインターフェースカード
     pub disk: Disk2InterfaceCard,
+    /// 割り込みコントローラ（IRQ要求元のOR）
+    pub irq: InterruptController,
+    /// カセットテープデッキ
+    pub cassette: CassetteDeck,
+    /// マウスカード（挿していなければNone）
+    pub mouse: Option<MouseCard>,
+    /// ThunderClock Plusカード（挿していなければNone）
+    pub thunderclock: Option<ThunderClock>,
+    /// ROM下のNo-Slot Clock（挿していなければNone）
+    pub no_slot_clock: Option<NoSlotClock>,
+    /// リアルタイムクロックの時刻の元
+    pub clock: ClockSource,
+    /// Videx 80桁カード（挿していなければNone）
+    pub videx: Option<VidexCard>,
+    /// デバッガ（ブレークポイント・ウォッチポイント・ステップ実行）
+    pub debugger: Debugger,
     /// 累積サイクル数
     pub total_cycles: u64,
+    /// 実行した命令数（巻き戻しの位置）
+    pub instructions: u64,
+    /// 巻き戻し用のスナップショットと入力記録（`enable_rewind` で有効）
+    pub rewind: Option<Rewind>,
+    /// 巻き戻しの再実行中
+    replaying: bool,
+    /// 実行中のフレームの開始サイクル
+    frame_begin: u64,
+    /// 実行中のフレームの終了サイクル（ブレークで中断したフレームは次回ここまで続ける）
+    frame_end: u64,
     /// フレームカウンター
     pub frame_count: u64,
     /// エミュレーション実行中フラグ
@@ -57,12 +93,99 @@ pub struct Apple2 {
 /// メモリバスの実装（Disk II I/Oを含む）
 impl MemoryBus for Apple2 {
     fn read(&mut self, address: u16) -> u8 {
+        // バンクは読み取りで切り替わる前に決める
+        let bank = self.debugger.watches(access::READ).then(|| self.memory.bank_of(address, false));
+        let value = self.bus_read(address);
+        // No-Slot ClockはROMの読み取りを観測し、D0に時刻のビットを出す
+        let rom_visible = address >= 0xC100 && (address < 0xD000 || !self.memory.switches.lc_read_enable);
+        let value = match self.no_slot_clock.as_mut() {
+            Some(nsc) if rom_visible => match nsc.access(address, &mut self.clock) {
+                Some(bit) => (value & 0xFE) | bit,
+                None => value,
+            },
+            _ => value,
+        };
+        if let Some(bank) = bank {
+            self.debugger.bus_access(address, bank, access::READ, Some(value));
+        }
+        value
+    }
+
+    fn write(&mut self, address: u16, value: u8) {
+        if self.debugger.watches(access::WRITE) {
+            let bank = self.memory.bank_of(address, true);
+            self.debugger.bus_access(address, bank, access::WRITE, Some(value));
+        }
+        match address {
+            // マウスカード（ファームウェアのコマンド）
+            0xC080..=0xC7FF if self.mouse.as_ref().is_some_and(|m| m.claims(address)) => {
+                if let Some(mouse) = self.mouse.as_mut() {
+                    mouse.write(address, value, &mut self.memory.main_ram[..], &mut self.irq);
+                }
+            }
+            // ThunderClock Plus（uPD1990ACとファームウェアのコマンド）
+            0xC080..=0xC7FF if self.thunderclock.as_ref().is_some_and(|c| c.claims(address)) => {
+                if let Some(card) = self.thunderclock.as_mut() {
+                    card.write(address, value, &mut self.memory.main_ram[..], &mut self.clock);
+                }
+            }
+            // Videx 80桁カード（CRTC、画面RAM、ファームウェアのスタブ）
+            0xC080..=0xCFFF if self.videx.as_ref().is_some_and(|v| v.claims(address)) => {
+                if let Some(videx) = self.videx.as_mut() {
+                    videx.write(address, value, &mut self.memory.main_ram[..]);
+                }
+            }
+            // カセット出力 ($C020)
+            0xC020..=0xC02F => {
+                self.cassette.toggle_output(self.total_cycles);
+                self.memory.write(address, value);
+            }
+            // スピーカー ($C030)
+            0xC030..=0xC03F => {
+                self.speaker_clicks.push(self.total_cycles);
+                self.memory.write(address, value);
+            }
+            // Disk II I/O (スロット6: $C0E0-$C0EF)
+            0xC0E0..=0xC0EF => {
+                self.disk.cumulative_cycles = self.total_cycles;
+                self.disk.io_write((address & 0x0F) as u8, value);
+            }
+            // 他のアドレスはメモリシステムに委譲
+            _ => self.memory.write(address, value),
+        }
+    }
+}
+
+impl Apple2 {
+    /// バス読み取り（No-Slot Clockの観測前）
+    fn bus_read(&mut self, address: u16) -> u8 {
         match address {
+            // マウスカード（スロットI/OとスロットROM）
+            0xC080..=0xC7FF if self.mouse.as_ref().is_some_and(|m| m.claims(address)) => {
+                self.mouse.as_ref().map_or(0, |m| m.read(address))
+            }
+            // ThunderClock Plus（スロットI/OとスロットROM）
+            0xC080..=0xC7FF if self.thunderclock.as_ref().is_some_and(|c| c.claims(address)) => {
+                self.thunderclock.as_ref().map_or(0, |c| c.read(address))
+            }
+            // Videx 80桁カード（スロットI/O、スロットROM、拡張ROM領域の画面RAM）
+            0xC080..=0xCFFF if self.videx.as_ref().is_some_and(|v| v.claims(address)) => {
+                self.videx.as_mut().map_or(0, |v| v.read(address))
+            }
+            // カセット出力 ($C020-$C02F)
+            0xC020..=0xC02F => {
+                self.cassette.toggle_output(self.total_cycles);
+                self.memory.read(address)
+            }
             // スピーカー ($C030-$C03F)
             0xC030..=0xC03F => {
                 self.speaker_clicks.push(self.total_cycles);
                 self.memory.read(address)
             }
+            // カセット入力 ($C060/$C068)
+            0xC060 | 0xC068 => {
+                if self.cassette.read_input(self.total_cycles) { 0x80 } else { 0x00 }
+            }
             // パドル/ジョイスティック ($C064-$C06F, $C070-$C07F)
             0xC064..=0xC07F => {
                 // パドル読み取り時にサイクル情報を渡す
@@ -102,25 +225,6 @@ impl MemoryBus for Apple2 {
         }
     }
 
-    fn write(&mut self, address: u16, value: u8) {
-        match address {
-            // スピーカー ($C030)
-            0xC030..=0xC03F => {
-                self.speaker_clicks.push(self.total_cycles);
-                self.memory.write(address, value);
-            }
-            // Disk II I/O (スロット6: $C0E0-$C0EF)
-            0xC0E0..=0xC0EF => {
-                self.disk.cumulative_cycles = self.total_cycles;
-                self.disk.io_write((address & 0x0F) as u8, value);
-            }
-            // 他のアドレスはメモリシステムに委譲
-            _ => self.memory.write(address, value),
-        }
-    }
-}
-
-impl Apple2 {
     /// 新しいエミュレータインスタンスを作成
     pub fn new(model: AppleModel) -> Self {
         // Apple IIe Enhanced は 65C02、それ以外は 6502
@@ -136,7 +240,20 @@ impl Apple2 {
             memory: Memory::new(model),
             video: Video::new(),
             disk,
+            irq: InterruptController::new(),
+            cassette: CassetteDeck::new(),
+            mouse: None,
+            thunderclock: None,
+            no_slot_clock: None,
+            clock: ClockSource::host(),
+            videx: None,
+            debugger: Debugger::new(),
             total_cycles: 0,
+            instructions: 0,
+            rewind: None,
+            replaying: false,
+            frame_begin: 0,
+            frame_end: 0,
             frame_count: 0,
             running: true,
             speaker_clicks: Vec::with_capacity(4096),
@@ -246,6 +363,15 @@ impl Apple2 {
         // ディスクコントローラーをリセット
         self.disk.reset();
         
+        // RESETラインで全カードのIRQが解放される
+        self.irq.clear();
+        if let Some(mouse) = self.mouse.as_mut() {
+            mouse.reset(&mut self.irq);
+        }
+        if let Some(videx) = self.videx.as_mut() {
+            videx.reset();
+        }
+        
         // ディスクブート用のゼロページ初期化
         // P5 PROMはこれらの値を使用してブートセクタを読み込む
         // 標準的なMonitor ROMはこれらを初期化するが、直接ブートする場合は手動で設定
@@ -293,11 +419,18 @@ impl Apple2 {
             // Boot status is logged by main.rs
         }
         // No message when no disk - normal operation
+        
+        self.debugger.call_stack.clear();
+        
+        // リセットは入力として記録しないので、巻き戻しはここから
+        self.restart_rewind();
     }
 
     /// 1命令を実行
     pub fn step(&mut self) -> u32 {
         let pc = self.cpu.regs.pc;
+        self.debugger.set_pc(pc);
+        self.instructions += 1;
         
         // Monitor ROMスタブモード: PCがMonitor ROM領域に入ったらスタブを実行
         if self.monitor_stub_mode {
@@ -329,13 +462,49 @@ impl Apple2 {
         
         // CPUを一時的に取り出して実行
         let mut cpu = std::mem::take(&mut self.cpu);
-        let cycles = cpu.step(self);
+        cpu.irq_pending = self.irq.line();
+        let cycles = match self.cassette_trap(&mut cpu) {
+            Some(cycles) => cycles,
+            None => self.cpu_step(&mut cpu),
+        };
         self.cpu = cpu;
         self.total_cycles += cycles as u64;
+        self.record_clock_read(self.instructions - 1);
         
         cycles
     }
     
+    /// CPUを1ステップ進め、コールスタックを更新する
+    #[inline]
+    fn cpu_step(&mut self, cpu: &mut Cpu) -> u32 {
+        let pc = cpu.regs.pc;
+        let cycles = cpu.step(self);
+        self.debugger.call_stack.observe(pc, cpu);
+        cycles
+    }
+    
+    /// カセット高速ロード: Monitor READ/WRITEに入ったら瞬時に処理
+    ///
+    /// 処理した場合は消費サイクル数を返す。
+    #[inline]
+    fn cassette_trap(&mut self, cpu: &mut Cpu) -> Option<u32> {
+        let pc = cpu.regs.pc;
+        if !self.cassette.fast_load || !(pc == 0xFECD || pc == 0xFEFD) {
+            return None;
+        }
+        if !cassette::is_monitor_trap(self, pc) {
+            return None;
+        }
+        let mut deck = std::mem::take(&mut self.cassette);
+        let resume = cassette::monitor_trap(&mut deck, self, pc, self.total_cycles);
+        self.cassette = deck;
+        let resume = resume?;
+        // ROMと同様にX=0で戻り先へ
+        cpu.regs.x = 0;
+        cpu.regs.pc = resume;
+        Some(6)
+    }
+
     /// PC値を記録（起動ブースト用）
     #[inline]
     fn record_pc(&mut self, pc: u16) {
@@ -841,11 +1010,64 @@ impl Apple2 {
     }
 
     /// 指定サイクル数だけ実行
+    ///
+    /// デバッガのブレークポイント・ウォッチポイントで止まった場合はそこで戻る。
     pub fn run_cycles(&mut self, target_cycles: u64) {
         let start = self.total_cycles;
-        while self.running && (self.total_cycles - start) < target_cycles {
+        while self.running && self.debugger.is_running() && (self.total_cycles - start) < target_cycles {
+            let armed = self.debugger.is_armed();
+            if armed && self.check_break_before(&self.cpu.regs.clone()) {
+                break;
+            }
             self.step();
+            if armed && !self.debugger.is_running() {
+                break;
+            }
+        }
+    }
+
+    /// ステップオーバー（JSR/BRKは戻るまで実行）。実行は `run_frame` / `run_cycles` で進む
+    pub fn step_over(&mut self) {
+        let regs = &self.cpu.regs;
+        let opcode = self.memory.peek(regs.pc);
+        self.debugger.step_over(regs.pc, opcode, regs.sp);
+    }
+
+    /// ステップアウト（今のサブルーチンから戻るまで実行）
+    pub fn step_out(&mut self) {
+        self.debugger.step_out(self.cpu.regs.sp);
+    }
+
+    /// 指定アドレスまで実行
+    pub fn run_to(&mut self, address: u16) {
+        self.debugger.run_to(address);
+    }
+
+    /// N命令だけ実行
+    pub fn run_instructions(&mut self, count: u64) {
+        self.debugger.run_instructions(count);
+    }
+
+    /// Nサイクルだけ実行
+    pub fn run_for_cycles(&mut self, cycles: u64) {
+        self.debugger.run_until_cycle(self.total_cycles + cycles);
+    }
+
+    /// 命令の実行前にブレークポイントと実行ウォッチポイントを調べ、実行する命令をトレースに記録する
+    ///
+    /// 巻き戻しの再実行中は、記録した入力をこの命令位置で与え直す。
+    #[inline]
+    fn check_break_before(&mut self, regs: &Registers) -> bool {
+        if self.replaying {
+            self.replay_inputs();
+        }
+        let exec_bank = self.debugger.watches(access::EXECUTE).then(|| self.memory.bank_of(regs.pc, false));
+        let ctx = EvalContext { regs, memory: &self.memory, cycles: self.total_cycles };
+        if self.debugger.before_instruction(&ctx, exec_bank) {
+            return true;
         }
+        self.debugger.trace(|| TraceEntry::capture(regs, &self.memory, self.total_cycles));
+        false
     }
 
     /// 1フレーム分（約17030サイクル、60Hz）を実行
@@ -868,8 +1090,27 @@ impl Apple2 {
         const CYCLES_PER_FRAME: u64 = 17030;
         const CYCLES_PER_SCANLINE: u64 = 65;
         
-        let target = self.total_cycles + CYCLES_PER_FRAME;
-        let frame_start = self.total_cycles;
+        // デバッガで停止中は実行しない（ステップ実行は1命令だけ）
+        match self.debugger.state {
+            DebuggerState::Running => {}
+            DebuggerState::Stepping => {
+                self.debugger.trace(|| TraceEntry::capture(&self.cpu.regs, &self.memory, self.total_cycles));
+                self.step();
+                self.debugger.step_complete();
+                self.refresh_watch_exprs();
+                self.render_video();
+                return;
+            }
+            DebuggerState::Paused | DebuggerState::BreakpointHit => return,
+        }
+        
+        // ブレークで中断したフレームは続きから実行する
+        if self.total_cycles >= self.frame_end || self.frame_end - self.total_cycles > CYCLES_PER_FRAME {
+            self.frame_begin = self.total_cycles;
+            self.frame_end = self.total_cycles + CYCLES_PER_FRAME;
+        }
+        let target = self.frame_end;
+        let frame_start = self.frame_begin;
         
         // CPUを一時的に取り出して実行
         let mut cpu = std::mem::take(&mut self.cpu);
@@ -878,28 +1119,262 @@ impl Apple2 {
             let frame_cycles = self.total_cycles - frame_start;
             self.memory.scanline = (frame_cycles / CYCLES_PER_SCANLINE) as u16;
             
+            // デバッガ: ブレークポイントがなければ判定1回だけの高速パス
+            let armed = self.debugger.is_armed();
+            if armed && self.check_break_before(&cpu.regs) {
+                break;
+            }
+            
             // SafeFast: CPUのPCとメモリを観測（IOB検証付き）
             self.disk.observe_pc_with_memory(cpu.regs.pc, &self.memory.main_ram[..]);
             
-            let cycles = cpu.step(self);
+            // 命令境界でIRQラインをサンプリング
+            cpu.irq_pending = self.irq.line();
+            let cycles = match self.cassette_trap(&mut cpu) {
+                Some(cycles) => cycles,
+                None => self.cpu_step(&mut cpu),
+            };
             self.total_cycles += cycles as u64;
+            self.record_clock_read(self.instructions);
+            self.instructions += 1;
+            
+            // ウォッチポイントは命令の途中で当たるので、実行し終えてから止まる
+            if armed && !self.debugger.is_running() {
+                break;
+            }
         }
         self.cpu = cpu;
         
+        // フレームの途中で止まった場合は画面だけ更新
+        if !self.debugger.is_running() {
+            self.refresh_watch_exprs();
+            self.render_video();
+            return;
+        }
+        
         // フレーム終了後はVBL期間
         self.memory.scanline = 192;
+        if let Some(mouse) = self.mouse.as_mut() {
+            mouse.vbl(&mut self.irq);
+        }
         
         self.frame_count += 1;
+        self.record_rewind_frame();
         
-        // ビデオを更新
-        self.video.render(&self.memory);
+        self.refresh_watch_exprs();
+        self.render_video();
+    }
+
+    /// デバッガのウォッチ式を評価し直す
+    fn refresh_watch_exprs(&mut self) {
+        if !self.debugger.watch_exprs().is_empty() {
+            let ctx = EvalContext { regs: &self.cpu.regs, memory: &self.memory, cycles: self.total_cycles };
+            self.debugger.refresh_watch_exprs(&ctx);
+        }
+    }
+
+    /// ビデオを更新（テキストモードでVidexが有効ならその画面）
+    fn render_video(&mut self) {
+        match self.videx.as_ref() {
+            Some(videx) if videx.display_enabled() && self.memory.switches.text_mode => {
+                self.video.render_videx(videx)
+            }
+            _ => self.video.render(&self.memory),
+        }
     }
 
     /// キー入力を処理
     pub fn key_down(&mut self, key: u8) {
+        self.record_input(Input::Key(key));
         self.memory.set_key(key);
     }
 
+    /// パドルの値を設定（巻き戻し用に変化を記録）
+    pub fn set_paddle(&mut self, paddle: usize, value: u8) {
+        let current = [self.memory.switches.paddle0, self.memory.switches.paddle1, self.memory.switches.paddle2, self.memory.switches.paddle3];
+        if current.get(paddle).is_some_and(|&v| v != value) {
+            self.record_input(Input::Paddle(paddle, value));
+        }
+        self.memory.set_paddle(paddle, value);
+    }
+
+    /// ボタンの状態を設定（巻き戻し用に変化を記録）
+    pub fn set_button(&mut self, button: usize, pressed: bool) {
+        let current = [self.memory.switches.button0, self.memory.switches.button1, self.memory.switches.button2];
+        if current.get(button).is_some_and(|&v| v != pressed) {
+            self.record_input(Input::Button(button, pressed));
+        }
+        self.memory.set_button(button, pressed);
+    }
+
+    /// マウスカードにホストのポインタ位置とボタンを渡す（巻き戻し用に変化を記録）
+    pub fn set_mouse(&mut self, fx: f32, fy: f32, down: bool) {
+        let Some(card) = self.mouse.as_mut() else { return };
+        let before = (card.position(), card.button());
+        card.set_host_position(fx, fy);
+        card.set_button(down);
+        if (card.position(), card.button()) != before {
+            self.record_input(Input::Mouse(fx, fy, down));
+        }
+    }
+
+    // ========================================
+    // 巻き戻し
+    // ========================================
+
+    /// 巻き戻しを有効にする（`interval` フレームごとのスナップショットを `capacity` 個保持）
+    pub fn enable_rewind(&mut self, interval: u64, capacity: usize) {
+        let mut rewind = Rewind::new(interval, capacity);
+        rewind.push_snapshot(self.instructions, self.save_state(), self.debugger.call_stack.clone());
+        self.rewind = Some(rewind);
+    }
+
+    /// 巻き戻しの記録を今の状態からやり直す（記録できない変化の後に呼ぶ）
+    pub fn restart_rewind(&mut self) {
+        if self.rewind.is_none() || self.replaying {
+            return;
+        }
+        let state = self.save_state();
+        if let Some(rewind) = self.rewind.as_mut() {
+            rewind.clear();
+            rewind.push_snapshot(self.instructions, state, self.debugger.call_stack.clone());
+        }
+    }
+
+    fn record_input(&mut self, input: Input) {
+        if self.replaying {
+            return;
+        }
+        if let Some(rewind) = self.rewind.as_mut() {
+            rewind.record_input(self.instructions, input);
+        }
+    }
+
+    /// 命令の実行中に時計カードが読んだホストの時刻を記録する（`executed` はその命令より前の命令数）
+    #[inline]
+    fn record_clock_read(&mut self, executed: u64) {
+        let Some(at) = self.clock.take_sample() else { return };
+        if self.replaying {
+            return;
+        }
+        if let Some(rewind) = self.rewind.as_mut() {
+            rewind.record_input(executed, Input::Clock(at));
+        }
+    }
+
+    /// フレームの終わりを記録し、間隔ごとにスナップショットを取る
+    fn record_rewind_frame(&mut self) {
+        if self.replaying {
+            return;
+        }
+        let Some(rewind) = self.rewind.as_mut() else { return };
+        rewind.mark_frame(self.frame_count, self.instructions);
+        if rewind.snapshot_due(self.frame_count) {
+            let state = self.save_state();
+            if let Some(rewind) = self.rewind.as_mut() {
+                rewind.push_snapshot(self.instructions, state, self.debugger.call_stack.clone());
+            }
+        }
+    }
+
+    /// 再実行中、今の命令位置までの入力を与え、探している書き込みを記録する
+    fn replay_inputs(&mut self) {
+        let Some(rewind) = self.rewind.as_mut() else { return };
+        if let Some((pc, value)) = self.debugger.take_probe_hit() {
+            rewind.last_write = Some(WriteHit { instructions: self.instructions, pc, value });
+        }
+        while let Some(input) = rewind.next_input(self.instructions) {
+            match input {
+                Input::Key(key) => self.memory.set_key(key),
+                Input::Paddle(paddle, value) => self.memory.set_paddle(paddle, value),
+                Input::Button(button, pressed) => self.memory.set_button(button, pressed),
+                Input::Mouse(fx, fy, down) => {
+                    if let Some(card) = self.mouse.as_mut() {
+                        card.set_host_position(fx, fy);
+                        card.set_button(down);
+                    }
+                }
+                Input::Clock(at) => self.clock.replay_host(Some(at)),
+            }
+        }
+    }
+
+    /// 命令位置 `target` へ戻る（直前のスナップショットから再実行し、停止した状態になる）
+    pub fn rewind_to(&mut self, target: u64) -> Result<(), String> {
+        if target > self.instructions {
+            return Err(format!("Cannot rewind forward (at instruction {})", self.instructions));
+        }
+        self.replay(target, None)?;
+        if let Some(rewind) = self.rewind.as_mut() {
+            rewind.truncate(target);
+        }
+        Ok(())
+    }
+
+    /// N命令戻る
+    pub fn step_back(&mut self, count: u64) -> Result<(), String> {
+        self.rewind_to(self.instructions.saturating_sub(count))
+    }
+
+    /// Nフレーム戻る（そのフレームの終わりの位置）
+    pub fn rewind_frames(&mut self, frames: u64) -> Result<(), String> {
+        let frame = self.frame_count.checked_sub(frames).ok_or("Not that many frames recorded")?;
+        let target = self.rewind.as_ref()
+            .ok_or("Rewind is not enabled (use --rewind)")?
+            .frame_end(frame)
+            .ok_or_else(|| format!("Frame {} is no longer recorded", frame))?;
+        self.rewind_to(target)
+    }
+
+    /// `address` に最後に書き込んだ命令の直後へ戻る（記録の範囲内で探す）
+    pub fn rewind_to_last_write(&mut self, address: u16) -> Result<WriteHit, String> {
+        let now = self.instructions;
+        let oldest = self.rewind.as_ref().ok_or("Rewind is not enabled (use --rewind)")?.oldest();
+        // 一番古いスナップショットから今まで再実行して、最後の書き込みを探す
+        self.replay_from(oldest, now, Some(address))?;
+        let hit = self.rewind.as_mut().and_then(|r| r.last_write.take());
+        let hit = hit.ok_or_else(|| format!("No write to ${:04X} in the last {} instructions", address, now - oldest))?;
+        self.rewind_to(hit.instructions)?;
+        Ok(hit)
+    }
+
+    /// `target` の直前のスナップショットから再実行する
+    fn replay(&mut self, target: u64, probe: Option<u16>) -> Result<(), String> {
+        let from = self.rewind.as_ref()
+            .ok_or("Rewind is not enabled (use --rewind)")?
+            .snapshot_before(target)
+            .ok_or("Not recorded that far back")?;
+        self.replay_from(from, target, probe)
+    }
+
+    /// 命令位置 `from` のスナップショットを読み込み、`target` まで再実行する
+    fn replay_from(&mut self, from: u64, target: u64, probe: Option<u16>) -> Result<(), String> {
+        let rewind = self.rewind.as_mut().ok_or("Rewind is not enabled (use --rewind)")?;
+        let (state, calls) = rewind.snapshot_at(from).ok_or("Not recorded that far back")?;
+        rewind.begin_replay(from);
+        rewind.last_write = None;
+        self.load_state(&state)?;
+        self.debugger.call_stack = calls;
+        self.instructions = from;
+
+        self.replaying = true;
+        self.debugger.begin_replay(target - from, probe);
+        while self.running && self.debugger.is_running() && self.instructions < target {
+            self.run_frame();
+        }
+        // 目標の位置で与える入力（命令を実行しなかった場合も含む）
+        self.replay_inputs();
+        self.debugger.end_replay();
+        self.replaying = false;
+        self.clock.replay_host(None);
+
+        // 再実行中の音（スピーカーとディスクのヘッド）は鳴らさない
+        self.speaker_clicks.clear();
+        self.disk.take_mech_events();
+        self.render_video();
+        Ok(())
+    }
+
     /// キーストローブが有効かどうかを確認
     #[allow(dead_code)]
     pub fn has_key_strobe(&self) -> bool {
@@ -915,7 +1390,21 @@ impl Apple2 {
     pub fn take_speaker_clicks(&mut self) -> Vec<u64> {
         std::mem::take(&mut self.speaker_clicks)
     }
-    
+
+    /// アセンブルしてRAM（メインまたはAUX）に直接書き込む
+    /// 書き込んだ次のアドレスを返す
+    pub fn assemble(&mut self, assembler: &mut Assembler, address: u16, source: &str, aux: bool) -> Result<u16, String> {
+        assembler.cpu_type = self.cpu.cpu_type;
+        let bytes = assembler.assemble(source, address)?;
+        let ram = if aux { &mut self.memory.aux_ram } else { &mut self.memory.main_ram };
+        let mut addr = address;
+        for byte in bytes {
+            ram[addr as usize] = byte;
+            addr = addr.wrapping_add(1);
+        }
+        Ok(addr)
+    }
+
     /// 現在の状態をセーブステートとして取得
     pub fn save_state(&self) -> SaveState {
         SaveState {
@@ -930,6 +1419,8 @@ impl Apple2 {
                 total_cycles: self.cpu.total_cycles,
                 irq_pending: self.cpu.irq_pending,
                 nmi_pending: self.cpu.nmi_pending,
+                irq_sources: self.irq.asserted().bits(),
+                irq_disable_latched: self.cpu.irq_disable_latched,
             },
             memory: MemoryState {
                 ram: self.memory.main_ram.to_vec(),
@@ -947,6 +1438,15 @@ impl Apple2 {
                 col80: self.memory.switches.col_80,
                 altchar: self.memory.switches.alt_char,
                 keyboard_latch: self.memory.switches.keyboard_strobe,
+                aux_ram: self.memory.aux_ram.to_vec(),
+                store80: self.memory.switches.store_80,
+                ramrd: self.memory.switches.ramrd,
+                ramwrt: self.memory.switches.ramwrt,
+                altzp: self.memory.switches.altzp,
+                dhires: self.memory.switches.dhires,
+                buttons: [self.memory.switches.button0, self.memory.switches.button1, self.memory.switches.button2],
+                paddles: [self.memory.switches.paddle0, self.memory.switches.paddle1, self.memory.switches.paddle2, self.memory.switches.paddle3],
+                paddle_trigger_cycle: self.memory.switches.paddle_trigger_cycle,
             },
             disk: DiskState {
                 curr_drive: self.disk.curr_drive,
@@ -976,6 +1476,14 @@ impl Apple2 {
             },
             total_cycles: self.total_cycles,
             frame_count: self.frame_count,
+            frame_begin: self.frame_begin,
+            frame_end: self.frame_end,
+            clock: Some(self.clock.save_state()),
+            thunderclock: self.thunderclock.as_ref().map(|c| c.save_state()),
+            no_slot_clock: self.no_slot_clock.as_ref().map(|c| c.save_state()),
+            mouse: self.mouse.as_ref().map(|m| m.save_state()),
+            videx: self.videx.as_ref().map(|v| v.save_state()),
+            cassette: Some(self.cassette.save_state()),
         }
     }
     
@@ -984,6 +1492,8 @@ impl Apple2 {
         if state.version != SaveState::CURRENT_VERSION {
             return Err("Incompatible save state version");
         }
+        // コールスタックは保存していない
+        self.debugger.call_stack.clear();
         
         // CPU状態を復元
         self.cpu.regs.a = state.cpu.a;
@@ -995,6 +1505,8 @@ impl Apple2 {
         self.cpu.total_cycles = state.cpu.total_cycles;
         self.cpu.irq_pending = state.cpu.irq_pending;
         self.cpu.nmi_pending = state.cpu.nmi_pending;
+        self.irq.set_bits(state.cpu.irq_sources);
+        self.cpu.irq_disable_latched = state.cpu.irq_disable_latched;
         
         // メモリ状態を復元
         if state.memory.ram.len() == self.memory.main_ram.len() {
@@ -1018,6 +1530,17 @@ impl Apple2 {
         self.memory.switches.col_80 = state.memory.col80;
         self.memory.switches.alt_char = state.memory.altchar;
         self.memory.switches.keyboard_strobe = state.memory.keyboard_latch;
+        if state.memory.aux_ram.len() == self.memory.aux_ram.len() {
+            self.memory.aux_ram.copy_from_slice(&state.memory.aux_ram);
+        }
+        self.memory.switches.store_80 = state.memory.store80;
+        self.memory.switches.ramrd = state.memory.ramrd;
+        self.memory.switches.ramwrt = state.memory.ramwrt;
+        self.memory.switches.altzp = state.memory.altzp;
+        self.memory.switches.dhires = state.memory.dhires;
+        [self.memory.switches.button0, self.memory.switches.button1, self.memory.switches.button2] = state.memory.buttons;
+        [self.memory.switches.paddle0, self.memory.switches.paddle1, self.memory.switches.paddle2, self.memory.switches.paddle3] = state.memory.paddles;
+        self.memory.switches.paddle_trigger_cycle = state.memory.paddle_trigger_cycle;
         
         // ディスク状態を復元
         self.disk.curr_drive = state.disk.curr_drive;
@@ -1039,9 +1562,31 @@ impl Apple2 {
         self.video.flash_state = state.video.flash_state;
         self.video.flash_counter = state.video.frame_count as u32;
         
+        // 周辺機器の状態を復元（保存時に挿していなかったカードはそのまま）
+        if let Some(clock) = &state.clock {
+            self.clock.load_state(clock);
+        }
+        if let (Some(card), Some(saved)) = (self.thunderclock.as_mut(), &state.thunderclock) {
+            card.load_state(saved);
+        }
+        if let (Some(nsc), Some(saved)) = (self.no_slot_clock.as_mut(), &state.no_slot_clock) {
+            nsc.load_state(saved);
+        }
+        if let (Some(mouse), Some(saved)) = (self.mouse.as_mut(), &state.mouse) {
+            mouse.load_state(saved);
+        }
+        if let (Some(videx), Some(saved)) = (self.videx.as_mut(), &state.videx) {
+            videx.load_state(saved);
+        }
+        if let Some(cassette) = &state.cassette {
+            self.cassette.load_state(cassette);
+        }
+        
         // グローバル状態を復元
         self.total_cycles = state.total_cycles;
         self.frame_count = state.frame_count;
+        self.frame_begin = state.frame_begin;
+        self.frame_end = state.frame_end;
         
         Ok(())
     }
@@ -1239,3 +1784,100 @@ pub fn create_test_rom() -> Vec<u8> {
     
     rom
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::MemoryBank;
+    use crate::profiler::{BreakReason, Watchpoint};
+
+    #[test]
+    fn debugger_stops_at_breakpoints_and_writes() {
+        let mut emu = Apple2::new(AppleModel::AppleIIPlus);
+        // $0300: LDA #$01 / STA $10 / INC $10 / JMP $0300
+        let code = [0xA9, 0x01, 0x85, 0x10, 0xE6, 0x10, 0x4C, 0x00, 0x03];
+        emu.memory.main_ram[0x0300..0x0300 + code.len()].copy_from_slice(&code);
+        emu.cpu.regs.pc = 0x0300;
+
+        let id = emu.debugger.add_breakpoint(0x0304);
+        emu.run_frame();
+        assert_eq!(emu.cpu.regs.pc, 0x0304);
+        assert_eq!(emu.debugger.take_break(), Some(BreakReason::Breakpoint { id, address: 0x0304 }));
+
+        // 停止中は進まない、再開すると次の周回で止まる
+        let cycles = emu.total_cycles;
+        emu.run_frame();
+        assert_eq!(emu.total_cycles, cycles);
+        emu.debugger.resume();
+        emu.run_frame();
+        assert_eq!(emu.cpu.regs.pc, 0x0304);
+        assert_eq!(emu.debugger.breakpoints()[0].hit_count, 2);
+
+        // ウォッチポイントはアクセスした命令の直後で止まる（同じ値の書き込みでも）
+        emu.debugger.remove_breakpoint(id);
+        let wp = emu.debugger.add_watchpoint(Watchpoint::parse("w:10@main=01").unwrap());
+        emu.debugger.resume();
+        emu.run_cycles(1000);
+        assert_eq!(emu.cpu.regs.pc, 0x0304);
+        assert_eq!(
+            emu.debugger.take_break(),
+            Some(BreakReason::Watchpoint {
+                id: wp, address: 0x10, access: access::WRITE, bank: MemoryBank::Main, value: Some(1), pc: 0x0302,
+            })
+        );
+
+        // 別のバンクには反応しない、実行ウォッチポイントは命令の前で止まる
+        emu.debugger.remove_watchpoint(wp);
+        emu.debugger.add_watchpoint(Watchpoint::parse("rw:10@aux").unwrap());
+        let wp = emu.debugger.add_watchpoint(Watchpoint::parse("x:0306").unwrap());
+        emu.debugger.resume();
+        emu.run_cycles(1000);
+        assert_eq!(emu.cpu.regs.pc, 0x0306);
+        assert_eq!(emu.debugger.take_break().map(|r| matches!(r, BreakReason::Watchpoint { id, .. } if id == wp)), Some(true));
+        assert_eq!(emu.debugger.watchpoints()[0].hit_count, 0);
+    }
+
+    #[test]
+    fn debugger_steps_over_and_out_of_subroutines() {
+        let mut emu = Apple2::new(AppleModel::AppleIIPlus);
+        // $0300: JSR $0310 / NOP / JMP $0304
+        // $0310: INX / JSR $0320 / RTS
+        // $0320: INY / RTS
+        let code: [(u16, &[u8]); 3] = [
+            (0x0300, &[0x20, 0x10, 0x03, 0xEA, 0x4C, 0x04, 0x03]),
+            (0x0310, &[0xE8, 0x20, 0x20, 0x03, 0x60]),
+            (0x0320, &[0xC8, 0x60]),
+        ];
+        for (addr, bytes) in code {
+            emu.memory.main_ram[addr as usize..addr as usize + bytes.len()].copy_from_slice(bytes);
+        }
+        emu.cpu.regs.pc = 0x0300;
+        emu.cpu.regs.x = 0;
+        emu.cpu.regs.y = 0;
+
+        emu.step_over();
+        emu.run_frame();
+        assert_eq!((emu.cpu.regs.pc, emu.cpu.regs.x, emu.cpu.regs.y), (0x0303, 1, 1));
+        assert!(matches!(emu.debugger.take_break(), Some(BreakReason::Goal { pc: 0x0303, .. })));
+
+        emu.cpu.regs.pc = 0x0300;
+        emu.run_to(0x0320);
+        emu.run_frame();
+        assert_eq!(emu.cpu.regs.pc, 0x0320);
+        emu.step_out();
+        emu.run_frame();
+        assert_eq!(emu.cpu.regs.pc, 0x0314);
+        emu.step_out();
+        emu.run_frame();
+        assert_eq!(emu.cpu.regs.pc, 0x0303);
+
+        emu.run_instructions(3);
+        emu.run_frame();
+        assert_eq!(emu.cpu.regs.pc, 0x0304);
+        let cycles = emu.total_cycles;
+        emu.run_for_cycles(30);
+        emu.run_frame();
+        assert!(emu.total_cycles >= cycles + 30 && emu.total_cycles < cycles + 33);
+        assert_eq!(emu.debugger.state, DebuggerState::Paused);
+    }
+}
@@ -1,12 +1,21 @@
 //! Klaus2m5 6502機能テストランナー
 //! 
 //! 使用方法: cargo run --bin cpu_test
+//!
+//! サブコマンド:
+//!   single-step [--65c02] [--bus] [DIR] [OPCODE...]
+//!     Tom Harte SingleStepTestsを実行（DIR省略時は A2RS_SST_DIR、なければ tests/65x02）
+//!   decimal [--6502 | --65c02]
+//!     Bruce Clarkの10進モードテストを実行（省略時は両方）
 
 use std::fs;
+use std::path::PathBuf;
 use std::time::Instant;
 
 // メインクレートからCPUモジュールを使用
 use a2rs::cpu::{Cpu, CpuType, MemoryBus};
+use a2rs::cpu::decimal;
+use a2rs::cpu::single_step::{self, CheckOptions};
 
 /// テスト用メモリ（64KB フラットメモリ）
 struct TestMemory {
@@ -39,6 +48,122 @@ impl MemoryBus for TestMemory {
 }
 
 fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.first().map(String::as_str) {
+        Some("single-step") => run_single_step(&args[1..]),
+        Some("decimal") => run_decimal(&args[1..]),
+        _ => run_functional_tests(),
+    }
+}
+
+/// 10進モードテストを実行
+fn run_decimal(args: &[String]) {
+    let cpu_types = match args.first().map(String::as_str) {
+        Some("--6502") => vec![CpuType::Cpu6502],
+        Some("--65c02") => vec![CpuType::Cpu65C02],
+        _ => vec![CpuType::Cpu6502, CpuType::Cpu65C02],
+    };
+
+    println!("===========================================");
+    println!("  Bruce Clark Decimal Mode Test");
+    println!("===========================================\n");
+
+    let mut all_passed = true;
+    for cpu_type in cpu_types {
+        println!("----------------------------------------");
+        println!("CPU:  {:?}", cpu_type);
+        println!("----------------------------------------");
+        all_passed &= run_decimal_test(cpu_type);
+        all_passed &= check_decimal_cycles(cpu_type);
+        println!();
+    }
+    if !all_passed {
+        std::process::exit(1);
+    }
+}
+
+fn run_decimal_test(cpu_type: CpuType) -> bool {
+    let start_time = Instant::now();
+    let result = match decimal::run(cpu_type) {
+        Ok(result) => result,
+        Err(e) => {
+            println!("Assembly error: {}", e);
+            return false;
+        }
+    };
+    println!("Total cycles: {}", result.cycles);
+    println!("Elapsed: {:?}", start_time.elapsed());
+
+    if !result.completed {
+        println!("\n*** TEST INCOMPLETE *** (PC=${:04X})", result.pc);
+    } else if let Some(failure) = &result.failure {
+        println!("\n*** TEST FAILED ***");
+        println!("  {}", failure);
+    } else {
+        println!("\n*** TEST PASSED! ***");
+    }
+    result.passed()
+}
+
+/// 10進モードのADC/SBCのサイクル数を確認（65C02は1サイクル多い）
+fn check_decimal_cycles(cpu_type: CpuType) -> bool {
+    let expected = decimal::expected_cycles(cpu_type);
+    let mut passed = true;
+    for (name, opcode) in [("ADC #", 0x69u8), ("SBC #", 0xE9u8)] {
+        let cycles = decimal::measure_cycles(cpu_type, opcode);
+        let ok = cycles == expected;
+        println!("Decimal {} cycles: {} (expected {}) {}", name, cycles, expected, if ok { "OK" } else { "FAIL" });
+        passed &= ok;
+    }
+    passed
+}
+
+/// Tom Harte SingleStepTestsを実行
+fn run_single_step(args: &[String]) {
+    let mut cpu_type = CpuType::Cpu6502;
+    let mut options = CheckOptions::default();
+    let mut root = single_step::resolve_root(PathBuf::from(single_step::DEFAULT_ROOT));
+    let mut opcodes = Vec::new();
+    for arg in args {
+        match arg.as_str() {
+            "--65c02" => cpu_type = CpuType::Cpu65C02,
+            "--bus" => options.check_bus = true,
+            _ => match u8::from_str_radix(arg.trim_start_matches('$'), 16) {
+                Ok(op) if arg.len() <= 3 => opcodes.push(op),
+                _ => root = PathBuf::from(arg),
+            },
+        }
+    }
+    if opcodes.is_empty() {
+        opcodes = single_step::implemented_opcodes(cpu_type);
+    }
+
+    let dir = single_step::test_dir(&root, cpu_type);
+    println!("===========================================");
+    println!("  SingleStepTests Runner");
+    println!("===========================================\n");
+    println!("Dir:  {}", dir.display());
+    println!("CPU:  {:?}", cpu_type);
+    println!("Bus:  {}\n", if options.check_bus { "checked" } else { "not checked" });
+
+    let start_time = Instant::now();
+    match single_step::run_dir(&dir, cpu_type, &opcodes, options) {
+        Ok(results) => {
+            single_step::print_report(&results);
+            println!("  Elapsed: {:?}\n", start_time.elapsed());
+            if results.iter().any(|r| !r.all_passed()) {
+                std::process::exit(1);
+            }
+        }
+        Err(e) => {
+            println!("Error: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Klaus2m5機能テストを実行
+fn run_functional_tests() {
     println!("===========================================");
     println!("  Klaus2m5 6502 Functional Test Runner");
     println!("===========================================\n");
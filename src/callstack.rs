@@ -0,0 +1,155 @@
+//! シャドウコールスタック
+//!
+//! JSR、BRK、IRQ/NMIの受け付けでフレームを積み、戻りアドレスを積んだ後のスタックポインタを覚えておく。
+//! 6502のプログラムはスタックを自由に操作するので、RTS/RTIの戻り先とフレームを突き合わせるのではなく
+//! スタックポインタで判断する。RTS/RTI/TXSの後でSPがフレームの位置より上にあれば、そのフレームの
+//! 戻りアドレスはもう取り出されている（終わっている）。
+//!
+//! - PLA/PLAで戻りアドレスを捨ててから上位へRTSする: 両方のフレームがまとめて外れる
+//! - PHA/PHA/RTSによる間接ジャンプ: SPはフレームより上に戻らないので、フレームはそのまま
+//! - JMPで抜けた後の呼び出し: 同じ深さ以下に残っていたフレームは新しいフレームで置き換わる
+
+use crate::cpu::{Cpu, StepKind};
+
+/// 保持するフレームの上限（スタック1ページに収まる数より多め）
+const DEPTH_LIMIT: usize = 256;
+
+/// フレームの種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameKind {
+    Jsr,
+    Brk,
+    Irq,
+    Nmi,
+}
+
+impl FrameKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            FrameKind::Jsr => "JSR",
+            FrameKind::Brk => "BRK",
+            FrameKind::Irq => "IRQ",
+            FrameKind::Nmi => "NMI",
+        }
+    }
+}
+
+/// 呼び出し1つ
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Frame {
+    pub kind: FrameKind,
+    /// 呼び出した命令のPC（割り込みは割り込まれた命令）
+    pub caller: u16,
+    /// 呼び出し先
+    pub target: u16,
+    /// RTS/RTIで戻る先
+    pub return_address: u16,
+    /// 戻りアドレスを積んだ後のSP
+    pub sp: u8,
+}
+
+impl Frame {
+    /// SPが `sp` のとき、まだ戻りアドレスがスタックに残っているか
+    pub fn is_live(&self, sp: u8) -> bool {
+        self.sp >= sp
+    }
+}
+
+/// シャドウコールスタック
+#[derive(Debug, Clone, Default)]
+pub struct CallStack {
+    /// 外側から順
+    frames: Vec<Frame>,
+}
+
+impl CallStack {
+    pub fn new() -> Self {
+        CallStack { frames: Vec::new() }
+    }
+
+    pub fn clear(&mut self) {
+        self.frames.clear();
+    }
+
+    /// フレーム（外側から順）
+    pub fn frames(&self) -> &[Frame] {
+        &self.frames
+    }
+
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// 1ステップ実行した後に呼ぶ（`pc` は実行前のPC）
+    #[inline]
+    pub fn observe(&mut self, pc: u16, cpu: &Cpu) {
+        let sp = cpu.regs.sp;
+        let (kind, return_address) = match cpu.last_step {
+            StepKind::Opcode(0x20) => (FrameKind::Jsr, pc.wrapping_add(3)),
+            StepKind::Opcode(0x00) => (FrameKind::Brk, pc.wrapping_add(2)),
+            StepKind::Irq => (FrameKind::Irq, pc),
+            StepKind::Nmi => (FrameKind::Nmi, pc),
+            // RTS / RTI / TXS
+            StepKind::Opcode(0x60 | 0x40 | 0x9A) => {
+                self.unwind(sp);
+                return;
+            }
+            _ => return,
+        };
+        // 同じ深さ以下に残っているフレームはもう無効
+        while self.frames.last().is_some_and(|f| f.sp <= sp) {
+            self.frames.pop();
+        }
+        if self.frames.len() == DEPTH_LIMIT {
+            self.frames.remove(0);
+        }
+        self.frames.push(Frame { kind, caller: pc, target: cpu.regs.pc, return_address, sp });
+    }
+
+    /// SPより下に積まれたフレームを外す
+    fn unwind(&mut self, sp: u8) {
+        while self.frames.last().is_some_and(|f| !f.is_live(sp)) {
+            self.frames.pop();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::apple2::Apple2;
+    use crate::memory::AppleModel;
+
+    #[test]
+    fn tracks_calls_through_stack_tricks() {
+        let mut emu = Apple2::new(AppleModel::AppleIIPlus);
+        let program: &[(u16, &[u8])] = &[
+            // $0300: JSR $0310 / BRK
+            (0x0300, &[0x20, 0x10, 0x03, 0x00]),
+            // $0310: JSR $0320 / NOP / RTS
+            (0x0310, &[0x20, 0x20, 0x03, 0xEA, 0x60]),
+            // $0320: LDA #$03 / PHA / LDA #$2F / PHA / RTS（$0330へジャンプ）
+            (0x0320, &[0xA9, 0x03, 0x48, 0xA9, 0x2F, 0x48, 0x60]),
+            // $0330: PLA / PLA（$0310への戻りを捨てる）/ RTS（$0300の呼び出し元へ）
+            (0x0330, &[0x68, 0x68, 0x60]),
+        ];
+        for &(address, bytes) in program {
+            emu.memory.main_ram[address as usize..address as usize + bytes.len()].copy_from_slice(bytes);
+        }
+        emu.cpu.regs.pc = 0x0300;
+        emu.cpu.regs.sp = 0xFF;
+
+        let mut depths = Vec::new();
+        while emu.cpu.regs.pc != 0x0303 {
+            emu.step();
+            depths.push((emu.cpu.regs.pc, emu.debugger.call_stack.len()));
+        }
+        // JSRごとに深くなり、RTSによる間接ジャンプでは変わらず、PLA/PLA後のRTSで2段戻る
+        assert_eq!(depths[..2], [(0x0310, 1), (0x0320, 2)]);
+        assert!(depths.contains(&(0x0330, 2)));
+        assert_eq!(depths.last(), Some(&(0x0303, 0)));
+    }
+}
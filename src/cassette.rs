@@ -0,0 +1,626 @@
+//! カセットテープ インターフェース
+//!
+//! Apple II/II+/IIe のカセットポートをエミュレートする。
+//! - `$C020` (カセット出力): アクセスごとに出力がトグルする
+//! - `$C060` (カセット入力): ビット7にテープ入力のレベルを返す
+//!
+//! テープは「レベルが反転するサイクル位置（エッジ）」の列として保持する。
+//! WAVはゼロクロスを検出してエッジに変換し、CT2はデータブロックから
+//! Monitor ROMと同じタイミングの波形を合成する。
+//!
+//! CT2ファイルはこのエミュレータのブロック列形式:
+//! マジック `CT2\0` の後に `[長さ u16 LE][データ]` を繰り返す（チェックサムは含まない）。
+//!
+//! Monitorの読み込み/書き込み時のタイミング:
+//! - リーダー: 約770Hz（半周期650サイクル）
+//! - 同期ビット: 半周期200サイクル + 250サイクル
+//! - ビット0: 2kHz（半周期250サイクル）、ビット1: 1kHz（半周期500サイクル）
+//! - データの後にチェックサム（$FFと全バイトのXOR）が1バイト続く
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::cpu::MemoryBus;
+use crate::savestate::CassetteState;
+use crate::wav::{self, WavWriter};
+
+/// CPUクロック（1フレーム17030サイクル × 60フレーム）
+pub const CPU_HZ: u64 = 17030 * 60;
+
+/// CT2ファイルのマジック
+const CT2_MAGIC: &[u8; 4] = b"CT2\0";
+
+/// 合成時の半周期（サイクル）
+const LEADER_HALF: u64 = 650;
+const SYNC_HALF_1: u64 = 200;
+const SYNC_HALF_2: u64 = 250;
+const ZERO_HALF: u64 = 250;
+const ONE_HALF: u64 = 500;
+
+/// 合成時のリーダー長（秒）。Monitor WRITEと同じ約10秒
+const LEADER_SECS: u64 = 10;
+/// ブロック間の無音（秒）
+const GAP_SECS: u64 = 1;
+
+/// 解析時の閾値（サイクル）
+const LEADER_MIN_HALF: u64 = 550;
+const LEADER_MAX_HALF: u64 = 900;
+const LEADER_MIN_COUNT: usize = 64;
+const SYNC_MAX_HALF: u64 = 400;
+/// 1周期がこれ未満ならビット0
+const BIT_THRESHOLD: u64 = 750;
+/// 1周期がこれ以上ならデータの終わり
+const BIT_MAX: u64 = 1200;
+
+/// Monitor ROMのエントリポイント
+const MONITOR_WRITE: u16 = 0xFECD;
+const MONITOR_READ: u16 = 0xFEFD;
+/// READ成功時の戻り先（BELL）とエラー時の戻り先（PRERR）
+const MONITOR_BELL: u16 = 0xFF3A;
+const MONITOR_PRERR: u16 = 0xFF2D;
+/// ゼロページのA1/A2とチェックサム
+const A1L: u16 = 0x3C;
+const A2L: u16 = 0x3E;
+const CHKSUM: u16 = 0x2E;
+
+/// テープ上の1データブロック
+#[derive(Debug, Clone)]
+pub struct TapeBlock {
+    /// データ（末尾にチェックサムを含む）
+    pub data: Vec<u8>,
+    /// ブロック先頭（リーダー開始）のエッジ番号
+    pub start_edge: usize,
+    /// ブロック末尾の次のエッジ番号
+    pub end_edge: usize,
+}
+
+impl TapeBlock {
+    /// チェックサムが正しいか
+    pub fn checksum_ok(&self) -> bool {
+        self.data.iter().fold(0xFF, |acc, &b| acc ^ b) == 0
+    }
+
+    /// チェックサムを除いたデータ
+    pub fn payload(&self) -> &[u8] {
+        &self.data[..self.data.len().saturating_sub(1)]
+    }
+}
+
+/// テープイメージ
+#[derive(Debug, Clone, Default)]
+pub struct Tape {
+    /// レベルが反転するテープ上の位置（サイクル、昇順）
+    pub edges: Vec<u64>,
+    /// 解析済みのデータブロック
+    pub blocks: Vec<TapeBlock>,
+    /// ファイル名
+    pub name: Option<String>,
+}
+
+impl Tape {
+    /// エッジ列からテープを作成（データブロックを解析）
+    pub fn from_edges(edges: Vec<u64>) -> Self {
+        let blocks = decode_blocks(&edges);
+        Tape { edges, blocks, name: None }
+    }
+
+    /// データブロック（チェックサムなし）からテープを合成
+    pub fn from_blocks(blocks: &[Vec<u8>]) -> Self {
+        let mut edges = Vec::new();
+        let mut time = CPU_HZ / 2;
+        for block in blocks {
+            time = synthesize_block(&mut edges, time, block);
+            time += GAP_SECS * CPU_HZ;
+        }
+        Tape::from_edges(edges)
+    }
+
+    /// ファイルから読み込む（拡張子 .ct2 はCT2、それ以外はWAV）
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let data = fs::read(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let mut tape = if is_ct2(path) || data.starts_with(CT2_MAGIC) {
+            Tape::from_blocks(&parse_ct2(&data)?)
+        } else {
+            let wav = wav::parse_wav(&data).map_err(|e| format!("{}: {}", path.display(), e))?;
+            Tape::from_edges(wav_to_edges(&wav.samples, wav.sample_rate))
+        };
+        tape.name = Some(path.display().to_string());
+        Ok(tape)
+    }
+
+    /// ファイルに保存する（拡張子 .ct2 はCT2、それ以外はWAV）
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let result = if is_ct2(path) {
+            let blocks: Vec<Vec<u8>> = self.blocks.iter().map(|b| b.payload().to_vec()).collect();
+            fs::write(path, build_ct2(&blocks))
+        } else {
+            write_edges_wav(path, &self.edges, self.length())
+        };
+        result.map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    /// テープの長さ（サイクル）
+    pub fn length(&self) -> u64 {
+        self.edges.last().map_or(0, |&e| e + CPU_HZ / 2)
+    }
+
+    /// 位置 `time` 以降に始まる最初のブロック
+    ///
+    /// リーダーの途中にいる場合はそのブロックを返す。
+    pub fn next_block(&self, time: u64) -> Option<&TapeBlock> {
+        self.blocks.iter().find(|b| self.edges[b.end_edge - 1] > time)
+    }
+}
+
+fn is_ct2(path: &Path) -> bool {
+    path.extension().and_then(|e| e.to_str()).is_some_and(|e| e.eq_ignore_ascii_case("ct2"))
+}
+
+/// CT2ファイルを解析してブロック列を返す
+pub fn parse_ct2(data: &[u8]) -> Result<Vec<Vec<u8>>, String> {
+    if !data.starts_with(CT2_MAGIC) {
+        return Err("Not a CT2 file".to_string());
+    }
+    let mut blocks = Vec::new();
+    let mut pos = CT2_MAGIC.len();
+    while pos < data.len() {
+        if pos + 2 > data.len() {
+            return Err("Truncated CT2 block header".to_string());
+        }
+        let len = u16::from_le_bytes([data[pos], data[pos + 1]]) as usize;
+        pos += 2;
+        if pos + len > data.len() {
+            return Err("Truncated CT2 block".to_string());
+        }
+        blocks.push(data[pos..pos + len].to_vec());
+        pos += len;
+    }
+    Ok(blocks)
+}
+
+/// ブロック列からCT2ファイルを作成
+pub fn build_ct2(blocks: &[Vec<u8>]) -> Vec<u8> {
+    let mut out = CT2_MAGIC.to_vec();
+    for block in blocks {
+        out.extend_from_slice(&(block.len().min(u16::MAX as usize) as u16).to_le_bytes());
+        out.extend_from_slice(&block[..block.len().min(u16::MAX as usize)]);
+    }
+    out
+}
+
+/// 1ブロック分の波形（リーダー・同期・データ・チェックサム）をエッジ列に追加
+///
+/// `start` はリーダー開始位置、戻り値はブロック終了位置。
+fn synthesize_block(edges: &mut Vec<u64>, start: u64, data: &[u8]) -> u64 {
+    let mut time = start;
+    let mut push = |edges: &mut Vec<u64>, half: u64| {
+        edges.push(time);
+        time += half;
+    };
+    for _ in 0..(LEADER_SECS * CPU_HZ / (LEADER_HALF * 2)) * 2 {
+        push(edges, LEADER_HALF);
+    }
+    push(edges, SYNC_HALF_1);
+    push(edges, SYNC_HALF_2);
+    let checksum = data.iter().fold(0xFFu8, |acc, &b| acc ^ b);
+    for &byte in data.iter().chain(std::iter::once(&checksum)) {
+        for bit in (0..8).rev() {
+            let half = if byte & (1 << bit) != 0 { ONE_HALF } else { ZERO_HALF };
+            push(edges, half);
+            push(edges, half);
+        }
+    }
+    // 最後の半周期を閉じる
+    push(edges, ONE_HALF);
+    edges.push(time);
+    time
+}
+
+/// エッジ列からデータブロックを解析
+fn decode_blocks(edges: &[u64]) -> Vec<TapeBlock> {
+    let halves: Vec<u64> = edges.windows(2).map(|w| w[1] - w[0]).collect();
+    let n = halves.len();
+    let is_leader = |h: u64| h > LEADER_MIN_HALF && h < LEADER_MAX_HALF;
+    let mut blocks = Vec::new();
+    let mut i = 0;
+    while i < n {
+        // リーダーを探す
+        let leader_start = i;
+        while i < n && is_leader(halves[i]) {
+            i += 1;
+        }
+        if i - leader_start < LEADER_MIN_COUNT {
+            i = i.max(leader_start + 1);
+            continue;
+        }
+        // 同期ビット（短い半周期×2）
+        if i + 1 >= n || halves[i] >= SYNC_MAX_HALF || halves[i + 1] >= SYNC_MAX_HALF {
+            continue;
+        }
+        i += 2;
+
+        // データビット（MSBから）
+        let mut data = Vec::new();
+        let mut byte = 0u8;
+        let mut bits = 0;
+        while i + 1 < n {
+            let full = halves[i] + halves[i + 1];
+            if full >= BIT_MAX {
+                break;
+            }
+            byte = (byte << 1) | (full >= BIT_THRESHOLD) as u8;
+            bits += 1;
+            if bits == 8 {
+                data.push(byte);
+                byte = 0;
+                bits = 0;
+            }
+            i += 2;
+        }
+        if data.len() >= 2 {
+            blocks.push(TapeBlock { data, start_edge: leader_start, end_edge: (i + 1).min(edges.len()) });
+        }
+    }
+    blocks
+}
+
+/// WAVサンプルのゼロクロスをエッジ（サイクル位置）に変換
+///
+/// ノイズでの誤検出を避けるため、ピークの1/16のヒステリシスを持たせる。
+pub fn wav_to_edges(samples: &[f32], sample_rate: u32) -> Vec<u64> {
+    let peak = samples.iter().fold(0.0f32, |m, s| m.max(s.abs()));
+    let threshold = peak / 16.0;
+    let mut edges = Vec::new();
+    let mut level = false;
+    let mut prev = 0.0f32;
+    for (i, &s) in samples.iter().enumerate() {
+        let flip = if level { s < -threshold } else { s > threshold };
+        if flip {
+            level = !level;
+            // 前のサンプルとの間で線形補間してゼロクロス位置を求める
+            let frac = if (s - prev).abs() > f32::EPSILON { (-prev / (s - prev)).clamp(0.0, 1.0) } else { 0.0 };
+            let pos = (i as f64 - 1.0 + frac as f64).max(0.0);
+            edges.push((pos * CPU_HZ as f64 / sample_rate as f64) as u64);
+        }
+        prev = s;
+    }
+    edges
+}
+
+/// エッジ列を矩形波のWAV（44.1kHz）として書き出す
+fn write_edges_wav(path: &Path, edges: &[u64], length: u64) -> io::Result<()> {
+    const RATE: u64 = 44100;
+    const AMPLITUDE: i16 = 0x4000;
+    let mut wav = WavWriter::create(path, RATE as u32, 1)?;
+    let mut level = false;
+    let mut written = 0u64;
+    for &edge in edges.iter().chain(std::iter::once(&length)) {
+        let target = edge * RATE / CPU_HZ;
+        let sample = if level { AMPLITUDE } else { -AMPLITUDE };
+        wav.write_run(sample, target.saturating_sub(written) as usize)?;
+        written = written.max(target);
+        level = !level;
+    }
+    wav.finish()
+}
+
+/// 録音中のテープ
+struct TapeRecording {
+    edges: Vec<u64>,
+    /// 録音開始時のCPUサイクル
+    origin: u64,
+    /// 高速書き込みで挿入した時間（サイクル）
+    inserted: u64,
+}
+
+impl TapeRecording {
+    fn time(&self, cycle: u64) -> u64 {
+        cycle.saturating_sub(self.origin) + self.inserted
+    }
+}
+
+/// 仮想テープデッキ
+#[derive(Default)]
+pub struct CassetteDeck {
+    /// 装着中のテープ
+    tape: Option<Tape>,
+    /// 再生開始時のCPUサイクル（再生中のみ）
+    playing_since: Option<u64>,
+    /// 停止時のテープ位置（サイクル）
+    position: u64,
+    /// 次に通過するエッジ番号
+    edge_cursor: usize,
+    /// 録音中のテープ
+    recording: Option<TapeRecording>,
+    /// Monitor READ/WRITEをトラップして瞬時に読み書きする
+    pub fast_load: bool,
+}
+
+impl CassetteDeck {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// テープを装着（停止・巻き戻し状態）
+    pub fn insert(&mut self, tape: Tape) {
+        self.tape = Some(tape);
+        self.playing_since = None;
+        self.position = 0;
+        self.edge_cursor = 0;
+    }
+
+    /// ファイルからテープを装着
+    pub fn load(&mut self, path: &Path) -> Result<(), String> {
+        let tape = Tape::load(path)?;
+        log::info!("Tape loaded: {} ({} blocks, {:.1} s)", path.display(), tape.blocks.len(), tape.length() as f64 / CPU_HZ as f64);
+        self.insert(tape);
+        Ok(())
+    }
+
+    /// テープを取り出す
+    pub fn eject(&mut self) {
+        self.tape = None;
+        self.playing_since = None;
+        self.position = 0;
+        self.edge_cursor = 0;
+    }
+
+    /// 装着中のテープ
+    pub fn tape(&self) -> Option<&Tape> {
+        self.tape.as_ref()
+    }
+
+    /// テープ位置と録音の進み具合を保存（テープの内容は含まない）
+    pub fn save_state(&self) -> CassetteState {
+        CassetteState {
+            position: self.position,
+            playing_since: self.playing_since,
+            edge_cursor: self.edge_cursor,
+            recorded: self.recording.as_ref().map(|rec| (rec.edges.len(), rec.inserted)),
+        }
+    }
+
+    /// テープ位置を復元し、録音中なら保存した時点より後に録音した分を捨てる
+    pub fn load_state(&mut self, state: &CassetteState) {
+        self.position = state.position;
+        self.playing_since = state.playing_since.filter(|_| self.tape.is_some());
+        self.edge_cursor = state.edge_cursor;
+        if let (Some(rec), Some((edges, inserted))) = (self.recording.as_mut(), state.recorded) {
+            rec.edges.truncate(edges);
+            rec.inserted = inserted;
+        }
+    }
+
+    /// 再生中か
+    pub fn is_playing(&self) -> bool {
+        self.playing_since.is_some()
+    }
+
+    /// 再生開始
+    pub fn play(&mut self, cycle: u64) {
+        if self.tape.is_some() && self.playing_since.is_none() {
+            self.playing_since = Some(cycle);
+        }
+    }
+
+    /// 停止
+    pub fn stop(&mut self, cycle: u64) {
+        self.position = self.tape_position(cycle);
+        self.playing_since = None;
+    }
+
+    /// 再生/停止を切り替え
+    pub fn toggle_play(&mut self, cycle: u64) {
+        if self.is_playing() {
+            self.stop(cycle);
+        } else {
+            self.play(cycle);
+        }
+    }
+
+    /// 先頭まで巻き戻す
+    pub fn rewind(&mut self, cycle: u64) {
+        self.position = 0;
+        self.edge_cursor = 0;
+        if self.playing_since.is_some() {
+            self.playing_since = Some(cycle);
+        }
+    }
+
+    /// 現在のテープ位置（サイクル）
+    pub fn tape_position(&self, cycle: u64) -> u64 {
+        match self.playing_since {
+            Some(since) => self.position + cycle.saturating_sub(since),
+            None => self.position,
+        }
+    }
+
+    /// テープ位置を設定（再生状態は維持）
+    fn seek(&mut self, time: u64, cycle: u64) {
+        self.position = time;
+        if self.playing_since.is_some() {
+            self.playing_since = Some(cycle);
+        }
+        self.edge_cursor = self.tape.as_ref().map_or(0, |t| t.edges.partition_point(|&e| e <= time));
+    }
+
+    /// $C060: カセット入力のレベル
+    pub fn read_input(&mut self, cycle: u64) -> bool {
+        let time = self.tape_position(cycle);
+        let Some(tape) = &self.tape else {
+            return false;
+        };
+        let edges = &tape.edges;
+        // 巻き戻された場合は位置を探し直す
+        if self.edge_cursor > 0 && edges.get(self.edge_cursor - 1).is_some_and(|&e| e > time) {
+            self.edge_cursor = edges.partition_point(|&e| e <= time);
+        }
+        while self.edge_cursor < edges.len() && edges[self.edge_cursor] <= time {
+            self.edge_cursor += 1;
+        }
+        self.edge_cursor % 2 == 1
+    }
+
+    /// $C020: カセット出力のトグル
+    pub fn toggle_output(&mut self, cycle: u64) {
+        if let Some(rec) = &mut self.recording {
+            let time = rec.time(cycle);
+            rec.edges.push(time);
+        }
+    }
+
+    /// 録音中か
+    pub fn is_recording(&self) -> bool {
+        self.recording.is_some()
+    }
+
+    /// 録音開始
+    pub fn start_recording(&mut self, cycle: u64) {
+        self.recording = Some(TapeRecording { edges: Vec::new(), origin: cycle, inserted: 0 });
+    }
+
+    /// 録音を終了してテープを返す
+    pub fn stop_recording(&mut self) -> Option<Tape> {
+        self.recording.take().map(|rec| Tape::from_edges(rec.edges))
+    }
+
+    /// 状態表示用の文字列
+    pub fn status_text(&self, cycle: u64) -> String {
+        let mmss = |c: u64| {
+            let secs = c / CPU_HZ;
+            format!("{}:{:02}", secs / 60, secs % 60)
+        };
+        if let Some(rec) = &self.recording {
+            return format!("REC {}", mmss(rec.time(cycle)));
+        }
+        match &self.tape {
+            Some(tape) => format!(
+                "{} {}/{}",
+                if self.is_playing() { "PLAY" } else { "STOP" },
+                mmss(self.tape_position(cycle).min(tape.length())),
+                mmss(tape.length())
+            ),
+            None => "(none)".to_string(),
+        }
+    }
+
+    /// Monitor READ: 次のブロックをA1..A2に読み込む
+    ///
+    /// 成功時はチェックサム一致/不一致を返す。ブロックが無ければ `None`。
+    pub fn fast_read(&mut self, cycle: u64, len: usize) -> Option<(Vec<u8>, bool)> {
+        let time = self.tape_position(cycle);
+        let tape = self.tape.as_ref()?;
+        let block = tape.next_block(time)?.clone();
+        let end_time = tape.edges[block.end_edge - 1];
+        self.seek(end_time, cycle);
+
+        let mut data = block.payload().to_vec();
+        let ok = data.len() == len && block.checksum_ok();
+        data.resize(len, 0);
+        Some((data, ok))
+    }
+
+    /// Monitor WRITE: データを1ブロックとして録音テープに追加
+    ///
+    /// 録音中でなければ何もせず `false` を返す。
+    pub fn fast_write(&mut self, cycle: u64, data: &[u8]) -> bool {
+        let Some(rec) = &mut self.recording else {
+            return false;
+        };
+        let start = rec.time(cycle).max(rec.edges.last().map_or(0, |&e| e + ONE_HALF));
+        let end = synthesize_block(&mut rec.edges, start, data);
+        rec.inserted += end - rec.time(cycle);
+        true
+    }
+}
+
+/// Monitor READ/WRITEのトラップ位置か（ROM内容も確認）
+///
+/// 入口の命令はROMによって異なる（IIeは先頭でSTA $C007する）ため、
+/// 戻り先のBELL（LDA #$87）とPRERR（LDA #$C5）で本物のMonitorか判定する。
+pub fn is_monitor_trap<B: MemoryBus>(bus: &mut B, pc: u16) -> bool {
+    (pc == MONITOR_READ || pc == MONITOR_WRITE)
+        && bus.read(MONITOR_BELL) == 0xA9
+        && bus.read(MONITOR_BELL + 1) == 0x87
+        && bus.read(MONITOR_PRERR) == 0xA9
+        && bus.read(MONITOR_PRERR + 1) == 0xC5
+}
+
+/// Monitor READ/WRITEを瞬時に実行し、戻り先のPCを返す
+///
+/// トラップできない場合（テープが無い、録音していない）は `None` を返し、
+/// ROMのルーチンをそのまま実行させる。
+pub fn monitor_trap<B: MemoryBus>(deck: &mut CassetteDeck, bus: &mut B, pc: u16, cycle: u64) -> Option<u16> {
+    let a1 = u16::from_le_bytes([bus.read(A1L), bus.read(A1L + 1)]);
+    let a2 = u16::from_le_bytes([bus.read(A2L), bus.read(A2L + 1)]);
+    if a2 < a1 {
+        return None;
+    }
+    let len = (a2 - a1) as usize + 1;
+    let next_a1 = a2.wrapping_add(1).to_le_bytes();
+
+    match pc {
+        MONITOR_READ => {
+            let (data, ok) = deck.fast_read(cycle, len)?;
+            let mut checksum = 0xFFu8;
+            for (i, &b) in data.iter().enumerate() {
+                bus.write(a1.wrapping_add(i as u16), b);
+                checksum ^= b;
+            }
+            bus.write(CHKSUM, checksum);
+            bus.write(A1L, next_a1[0]);
+            bus.write(A1L + 1, next_a1[1]);
+            Some(if ok { MONITOR_BELL } else { MONITOR_PRERR })
+        }
+        MONITOR_WRITE => {
+            let data: Vec<u8> = (0..len).map(|i| bus.read(a1.wrapping_add(i as u16))).collect();
+            if !deck.fast_write(cycle, &data) {
+                return None;
+            }
+            bus.write(A1L, next_a1[0]);
+            bus.write(A1L + 1, next_a1[1]);
+            Some(MONITOR_BELL)
+        }
+        _ => None,
+    }
+}
+
+/// 録音したテープの既定の保存先
+pub fn recording_path(dir: &Path) -> PathBuf {
+    let secs = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    dir.join(format!("tape_{}.wav", secs))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn synthesized_blocks_decode() {
+        let blocks = vec![vec![0x34, 0x12], (0..=255u8).collect::<Vec<u8>>()];
+        let tape = Tape::from_blocks(&blocks);
+        assert_eq!(tape.blocks.len(), 2);
+        for (decoded, original) in tape.blocks.iter().zip(&blocks) {
+            assert!(decoded.checksum_ok());
+            assert_eq!(decoded.payload(), &original[..]);
+        }
+        assert_eq!(parse_ct2(&build_ct2(&blocks)).unwrap(), blocks);
+    }
+
+    #[test]
+    fn input_level_follows_edges() {
+        let mut deck = CassetteDeck::new();
+        deck.insert(Tape::from_edges(vec![100, 200, 300]));
+        assert!(!deck.read_input(1000));
+        deck.play(1000);
+        assert!(!deck.read_input(1050));
+        assert!(deck.read_input(1150));
+        assert!(!deck.read_input(1250));
+        deck.rewind(1300);
+        assert!(!deck.read_input(1350));
+        assert!(deck.read_input(1400));
+    }
+}
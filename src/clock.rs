@@ -0,0 +1,508 @@
+//! リアルタイムクロック（ThunderClock Plus / No-Slot Clock）
+//!
+//! どちらもホストの時刻（`chrono`）を読む。テストなどで結果を固定したい場合は
+//! `ClockSource` で時刻を凍結するか、ずらす。ゲスト側から時刻を設定すると、
+//! ホストの時計は変えずに差分として保持する。
+//!
+//! 巻き戻しの再実行で同じ時刻を読めるよう、読んだホストの時刻は `take_sample` で取り出して
+//! 入力として記録し、再実行中は `replay_host` で与えた時刻を使う。
+//!
+//! - ThunderClock Plus: スロットカード。ProDOSが認識する識別バイトと、
+//!   ファームウェアのREAD（$Cn08）/WRITE（$Cn0B）を差し替えたスタブを持つ。
+//!   `$C0n0` ではuPD1990ACのシリアルインターフェースも直接操作できる。
+//! - No-Slot Clock（DS1216E）: ROMソケットの下に挿すクロック。ROM読み取りの
+//!   アドレス線A2/A0で64bitの認識パターンを送ると、続く64回の読み取りのD0に
+//!   時刻が出てくる。
+
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, NaiveDateTime, Timelike};
+
+use crate::savestate::{ClockState, NoSlotClockState, ThunderClockState};
+
+/// ThunderClock Plusを挿す既定のスロット
+pub const THUNDERCLOCK_SLOT: usize = 5;
+
+/// エミュレータ内の時刻の元
+#[derive(Debug, Clone, Default)]
+pub struct ClockSource {
+    /// 凍結した時刻（Noneならホストの時刻）
+    frozen: Option<NaiveDateTime>,
+    /// ホストの時刻（または凍結した時刻）からのずれ
+    offset: Duration,
+    /// 再実行中に使うホストの時刻（記録した値）
+    replayed: Option<NaiveDateTime>,
+    /// 最後に読んだホストの時刻（`take_sample` で取り出すまで保持）
+    sampled: Option<NaiveDateTime>,
+}
+
+impl ClockSource {
+    /// ホストの時刻をそのまま使う
+    pub fn host() -> Self {
+        Self::default()
+    }
+
+    /// 指定した時刻で凍結する
+    pub fn frozen(at: NaiveDateTime) -> Self {
+        ClockSource { frozen: Some(at), ..Self::default() }
+    }
+
+    /// 時刻を秒単位でずらす
+    pub fn with_offset_secs(mut self, secs: i64) -> Self {
+        self.offset = Duration::seconds(secs);
+        self
+    }
+
+    /// 凍結しているか
+    pub fn is_frozen(&self) -> bool {
+        self.frozen.is_some()
+    }
+
+    /// 時刻のずれ（秒）
+    pub fn offset_secs(&self) -> i64 {
+        self.offset.num_seconds()
+    }
+
+    /// 凍結した時刻とずれ（ミリ秒）を保存
+    pub fn save_state(&self) -> ClockState {
+        ClockState {
+            frozen_ms: self.frozen.map(|at| at.and_utc().timestamp_millis()),
+            offset_ms: self.offset.num_milliseconds(),
+        }
+    }
+
+    /// 凍結した時刻とずれを復元
+    pub fn load_state(&mut self, state: &ClockState) {
+        self.frozen = state.frozen_ms.and_then(DateTime::from_timestamp_millis).map(|at| at.naive_utc());
+        self.offset = Duration::milliseconds(state.offset_ms);
+    }
+
+    /// 現在の時刻
+    pub fn now(&mut self) -> NaiveDateTime {
+        match self.frozen {
+            Some(at) => at + self.offset,
+            None => self.host_now() + self.offset,
+        }
+    }
+
+    /// ゲストから時刻を設定する（凍結中はその時刻に置き換える）
+    pub fn set(&mut self, at: NaiveDateTime) {
+        match self.frozen {
+            Some(_) => {
+                self.frozen = Some(at);
+                self.offset = Duration::zero();
+            }
+            None => self.offset = at - self.host_now(),
+        }
+    }
+
+    /// 最後に読んだホストの時刻を取り出す（読んでいなければNone）
+    pub fn take_sample(&mut self) -> Option<NaiveDateTime> {
+        self.sampled.take()
+    }
+
+    /// 再実行中に使うホストの時刻を与える（Noneでホストの時刻に戻す）
+    pub fn replay_host(&mut self, at: Option<NaiveDateTime>) {
+        self.replayed = at;
+        self.sampled = None;
+    }
+
+    fn host_now(&mut self) -> NaiveDateTime {
+        if let Some(at) = self.replayed {
+            return at;
+        }
+        let at = Local::now().naive_local();
+        self.sampled = Some(at);
+        at
+    }
+}
+
+/// "YYYY-MM-DD HH:MM:SS"（`T` 区切り、秒・時刻の省略も可）を解析する
+pub fn parse_datetime(text: &str) -> Result<NaiveDateTime, String> {
+    let text = text.trim();
+    for format in ["%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M"] {
+        if let Ok(dt) = NaiveDateTime::parse_from_str(text, format) {
+            return Ok(dt);
+        }
+    }
+    NaiveDate::parse_from_str(text, "%Y-%m-%d")
+        .ok()
+        .and_then(|d| d.and_hms_opt(0, 0, 0))
+        .ok_or_else(|| format!("Invalid date/time '{}' (expected YYYY-MM-DD HH:MM:SS)", text))
+}
+
+fn to_bcd(value: u32) -> u8 {
+    (((value / 10) % 10) << 4 | (value % 10)) as u8
+}
+
+fn from_bcd(value: u8) -> u32 {
+    (value >> 4) as u32 * 10 + (value & 0x0F) as u32
+}
+
+// ============================================================
+// ThunderClock Plus
+// ============================================================
+
+/// uPD1990ACのシフトレジスタのビット数
+const UPD1990_BITS: u32 = 40;
+
+/// uPD1990ACのコマンド（C2-C0）
+const UPD_REGISTER_HOLD: u8 = 0;
+const UPD_REGISTER_SHIFT: u8 = 1;
+const UPD_TIME_SET: u8 = 2;
+const UPD_TIME_READ: u8 = 3;
+
+/// `$C0n0` への書き込みのビット割り当て
+const TC_DATA_IN: u8 = 0x01;
+const TC_STROBE: u8 = 0x10;
+const TC_CLOCK: u8 = 0x20;
+
+/// ファームウェアのスタブが叩くレジスタ
+const TC_FW_READ: u8 = 0x08;
+const TC_FW_WRITE: u8 = 0x09;
+
+/// 入力バッファ（ファームウェアのREADが文字列を書き込む）
+const INPUT_BUFFER: usize = 0x0200;
+
+/// ThunderClock Plus互換カード
+pub struct ThunderClock {
+    slot: usize,
+    rom: [u8; 256],
+    /// uPD1990ACの40bitシフトレジスタ
+    shift: u64,
+    /// ラッチしたコマンド
+    command: u8,
+    /// 前回の `$C0n0` への書き込み（エッジ検出用）
+    last_control: u8,
+    /// ファームウェアの数値モード（'#' を書き込むと有効）
+    numeric: bool,
+}
+
+impl ThunderClock {
+    pub fn new(slot: usize) -> Self {
+        ThunderClock {
+            slot,
+            rom: build_thunderclock_rom(slot),
+            shift: 0,
+            command: UPD_REGISTER_HOLD,
+            last_control: 0,
+            numeric: false,
+        }
+    }
+
+    /// 挿しているスロット
+    pub fn slot(&self) -> usize {
+        self.slot
+    }
+
+    /// uPD1990ACとファームウェアの状態を保存
+    pub fn save_state(&self) -> ThunderClockState {
+        ThunderClockState {
+            shift: self.shift,
+            command: self.command,
+            last_control: self.last_control,
+            numeric: self.numeric,
+        }
+    }
+
+    /// uPD1990ACとファームウェアの状態を復元
+    pub fn load_state(&mut self, state: &ThunderClockState) {
+        self.shift = state.shift;
+        self.command = state.command;
+        self.last_control = state.last_control;
+        self.numeric = state.numeric;
+    }
+
+    /// カードが応答するアドレスか（スロットI/OまたはスロットROM）
+    pub fn claims(&self, address: u16) -> bool {
+        let io = 0xC080 + (self.slot as u16) * 16;
+        let rom = 0xC000 + (self.slot as u16) * 256;
+        (io..io + 16).contains(&address) || (rom..rom + 256).contains(&address)
+    }
+
+    /// バス読み取り（`claims` が真のアドレスのみ）
+    pub fn read(&self, address: u16) -> u8 {
+        if address >= 0xC100 {
+            return self.rom[(address & 0xFF) as usize];
+        }
+        // DATA OUTはビット7
+        if address & 0x0F == 0 {
+            ((self.shift & 1) as u8) << 7
+        } else {
+            0
+        }
+    }
+
+    /// バス書き込み（`claims` が真のアドレスのみ）
+    ///
+    /// `ram` はファームウェアのREADが文字列を書き込むメインRAM。
+    pub fn write(&mut self, address: u16, value: u8, ram: &mut [u8], clock: &mut ClockSource) {
+        if address >= 0xC100 {
+            return;
+        }
+        match (address & 0x0F) as u8 {
+            0x00 => self.write_control(value, clock),
+            TC_FW_READ => self.firmware_read(ram, clock),
+            TC_FW_WRITE => match value & 0x7F {
+                b'#' => self.numeric = true,
+                b'&' | b'>' | b'<' => self.numeric = false,
+                _ => {}
+            },
+            _ => {}
+        }
+    }
+
+    /// uPD1990ACの制御線
+    fn write_control(&mut self, value: u8, clock: &mut ClockSource) {
+        let rising = |bit: u8| value & bit != 0 && self.last_control & bit == 0;
+        if rising(TC_STROBE) {
+            self.command = (value >> 1) & 0x07;
+            match self.command {
+                UPD_TIME_READ => self.shift = encode_upd1990(clock.now()),
+                UPD_TIME_SET => {
+                    if let Some(at) = decode_upd1990(self.shift, clock.now()) {
+                        clock.set(at);
+                    }
+                }
+                _ => {}
+            }
+        }
+        if rising(TC_CLOCK) && self.command == UPD_REGISTER_SHIFT {
+            let data_in = (value & TC_DATA_IN) as u64;
+            self.shift = (self.shift >> 1) | (data_in << (UPD1990_BITS - 1));
+        }
+        self.last_control = value;
+    }
+
+    /// ファームウェアのREAD: 入力バッファに時刻文字列を書き込む
+    ///
+    /// 数値モードは "mo,dw,dt,hr,mn,sc"（ProDOSのクロックドライバが読む形式）、
+    /// 通常は "WED JAN 07 14:05:09"。末尾にCRを付ける。
+    fn firmware_read(&self, ram: &mut [u8], clock: &mut ClockSource) {
+        let now = clock.now();
+        let text = if self.numeric {
+            format!(
+                "{:02},{:02},{:02},{:02},{:02},{:02}",
+                now.month(),
+                now.weekday().num_days_from_sunday(),
+                now.day(),
+                now.hour(),
+                now.minute(),
+                now.second()
+            )
+        } else {
+            now.format("%a %b %d %H:%M:%S").to_string().to_uppercase()
+        };
+        for (i, byte) in text.bytes().chain(std::iter::once(b'\r')).enumerate() {
+            ram[INPUT_BUFFER + i] = byte | 0x80;
+        }
+    }
+}
+
+/// uPD1990ACのレジスタ形式（LSBから秒, 分, 時, 日（BCD）, 曜日, 月（4bit））
+fn encode_upd1990(now: NaiveDateTime) -> u64 {
+    to_bcd(now.second()) as u64
+        | (to_bcd(now.minute()) as u64) << 8
+        | (to_bcd(now.hour()) as u64) << 16
+        | (to_bcd(now.day()) as u64) << 24
+        | (now.weekday().num_days_from_sunday() as u64) << 32
+        | (now.month() as u64) << 36
+}
+
+/// uPD1990ACのレジスタから時刻を復元（年は持たないので `now` の年を使う）
+fn decode_upd1990(shift: u64, now: NaiveDateTime) -> Option<NaiveDateTime> {
+    let byte = |i: u32| (shift >> (i * 8)) as u8;
+    NaiveDate::from_ymd_opt(now.year(), ((shift >> 36) & 0x0F) as u32, from_bcd(byte(3)))?
+        .and_hms_opt(from_bcd(byte(2)), from_bcd(byte(1)), from_bcd(byte(0)))
+}
+
+/// ThunderClock PlusのスロットROMを生成
+fn build_thunderclock_rom(slot: usize) -> [u8; 256] {
+    let mut rom = [0u8; 256];
+    let page = 0xC0 + slot as u8;
+    let io = 0x80 + (slot as u8) * 16;
+    // ProDOSのクロックカード識別バイト（$Cn00/$Cn02/$Cn04/$Cn06）
+    rom[0x00] = 0x08;
+    rom[0x02] = 0x28;
+    rom[0x04] = 0x58;
+    rom[0x06] = 0x70;
+    // $Cn08: READ, $Cn0B: WRITE
+    rom[0x08..0x0B].copy_from_slice(&[0x4C, 0x20, page]);
+    rom[0x0B..0x0E].copy_from_slice(&[0x4C, 0x28, page]);
+    // READ: STA $C0n8 / LDA #$8D / RTS
+    rom[0x20..0x26].copy_from_slice(&[0x8D, io + TC_FW_READ, 0xC0, 0xA9, 0x8D, 0x60]);
+    // WRITE: STA $C0n9 / RTS
+    rom[0x28..0x2C].copy_from_slice(&[0x8D, io + TC_FW_WRITE, 0xC0, 0x60]);
+    rom
+}
+
+// ============================================================
+// No-Slot Clock (DS1216E)
+// ============================================================
+
+/// 認識パターン（LSBから順に送る）
+const NSC_PATTERN: u64 = 0x5CA3_3AC5_5CA3_3AC5;
+
+/// No-Slot Clock
+#[derive(Debug, Clone, Default)]
+pub struct NoSlotClock {
+    /// 認識パターンの一致したビット数
+    matched_bits: u32,
+    /// パターンが一致してクロックレジスタが有効か
+    enabled: bool,
+    /// クロックレジスタ上の位置（0-63）
+    position: u32,
+    /// 読み出す時刻（パターン一致時に取り込む）
+    register: u64,
+    /// 書き込まれた値
+    written: u64,
+    /// 書き込まれたビット数
+    written_bits: u32,
+}
+
+impl NoSlotClock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 認識パターンとクロックレジスタの状態を保存
+    pub fn save_state(&self) -> NoSlotClockState {
+        NoSlotClockState {
+            matched_bits: self.matched_bits,
+            enabled: self.enabled,
+            position: self.position,
+            register: self.register,
+            written: self.written,
+            written_bits: self.written_bits,
+        }
+    }
+
+    /// 認識パターンとクロックレジスタの状態を復元
+    pub fn load_state(&mut self, state: &NoSlotClockState) {
+        self.matched_bits = state.matched_bits;
+        self.enabled = state.enabled;
+        self.position = state.position;
+        self.register = state.register;
+        self.written = state.written;
+        self.written_bits = state.written_bits;
+    }
+
+    /// ROMの読み取りを観測する
+    ///
+    /// A2がLowなら1bit書き込み（A0がデータ）、Highなら読み取り。
+    /// クロックがデータを出す場合はD0の値を返す。
+    pub fn access(&mut self, address: u16, clock: &mut ClockSource) -> Option<u8> {
+        let read = address & 0x04 != 0;
+        let bit = (address & 0x01) as u64;
+
+        if !self.enabled {
+            if read || bit != (NSC_PATTERN >> self.matched_bits) & 1 {
+                self.matched_bits = 0;
+                return None;
+            }
+            self.matched_bits += 1;
+            if self.matched_bits == 64 {
+                self.enabled = true;
+                self.position = 0;
+                self.register = encode_nsc(clock.now());
+                self.written = 0;
+                self.written_bits = 0;
+            }
+            return None;
+        }
+
+        let result = if read {
+            Some(((self.register >> self.position) & 1) as u8)
+        } else {
+            self.written |= bit << self.position;
+            self.written_bits += 1;
+            None
+        };
+        self.position += 1;
+        if self.position == 64 {
+            if self.written_bits == 64 {
+                if let Some(at) = decode_nsc(self.written) {
+                    clock.set(at);
+                }
+            }
+            self.enabled = false;
+            self.matched_bits = 0;
+        }
+        result
+    }
+}
+
+/// DS1216のレジスタ形式（LSBから1/100秒, 秒, 分, 時（24時間）, 曜日(1-7), 日, 月, 年（BCD））
+fn encode_nsc(now: NaiveDateTime) -> u64 {
+    let bytes = [
+        to_bcd(now.nanosecond() / 10_000_000),
+        to_bcd(now.second()),
+        to_bcd(now.minute()),
+        to_bcd(now.hour()),
+        to_bcd(now.weekday().number_from_sunday()),
+        to_bcd(now.day()),
+        to_bcd(now.month()),
+        to_bcd(now.year().rem_euclid(100) as u32),
+    ];
+    u64::from_le_bytes(bytes)
+}
+
+/// DS1216のレジスタから時刻を復元（年は1940-2039として扱う）
+fn decode_nsc(value: u64) -> Option<NaiveDateTime> {
+    let b = value.to_le_bytes();
+    let yy = from_bcd(b[7]) as i32;
+    let year = if yy < 40 { 2000 + yy } else { 1900 + yy };
+    NaiveDate::from_ymd_opt(year, from_bcd(b[6] & 0x1F), from_bcd(b[5] & 0x3F))?
+        .and_hms_opt(from_bcd(b[3] & 0x3F), from_bcd(b[2]), from_bcd(b[1]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixed() -> ClockSource {
+        ClockSource::frozen(parse_datetime("2024-03-05 14:07:09").unwrap())
+    }
+
+    #[test]
+    fn thunderclock_numeric_read() {
+        let mut card = ThunderClock::new(THUNDERCLOCK_SLOT);
+        let mut clock = fixed().with_offset_secs(60);
+        let mut ram = vec![0u8; 0x10000];
+        assert_eq!([card.read(0xC500), card.read(0xC502), card.read(0xC504), card.read(0xC506)], [0x08, 0x28, 0x58, 0x70]);
+
+        card.write(0xC0D9, b'#' | 0x80, &mut ram, &mut clock);
+        card.write(0xC0D8, 0, &mut ram, &mut clock);
+        let text: String = ram[0x200..0x211].iter().map(|&b| (b & 0x7F) as char).collect();
+        assert_eq!(text, "03,02,05,14,08,09");
+        assert_eq!(ram[0x211], 0x8D);
+
+        // uPD1990ACのTIME READ → 秒を1bitずつシフトして読む
+        card.write(0xC0D0, (UPD_TIME_READ << 1) | TC_STROBE, &mut ram, &mut clock);
+        card.write(0xC0D0, UPD_REGISTER_SHIFT << 1, &mut ram, &mut clock);
+        card.write(0xC0D0, (UPD_REGISTER_SHIFT << 1) | TC_STROBE, &mut ram, &mut clock);
+        let mut seconds = 0;
+        for i in 0..8 {
+            seconds |= (card.read(0xC0D0) >> 7) << i;
+            card.write(0xC0D0, (UPD_REGISTER_SHIFT << 1) | TC_CLOCK, &mut ram, &mut clock);
+            card.write(0xC0D0, UPD_REGISTER_SHIFT << 1, &mut ram, &mut clock);
+        }
+        assert_eq!(seconds, 0x09);
+    }
+
+    #[test]
+    fn no_slot_clock_pattern_and_read() {
+        let mut nsc = NoSlotClock::new();
+        let mut clock = fixed();
+        // 通常のROM読み取りでは何も出てこない
+        assert_eq!(nsc.access(0xC304, &mut clock), None);
+        for i in 0..64 {
+            let bit = ((NSC_PATTERN >> i) & 1) as u16;
+            assert_eq!(nsc.access(0xC300 | bit, &mut clock), None);
+        }
+        let mut value = 0u64;
+        for i in 0..64 {
+            value |= (nsc.access(0xC304, &mut clock).unwrap() as u64) << i;
+        }
+        assert_eq!(value.to_le_bytes()[1..], [0x09, 0x07, 0x14, 0x03, 0x05, 0x03, 0x24]);
+        assert_eq!(nsc.access(0xC304, &mut clock), None);
+    }
+}
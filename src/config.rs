@@ -71,6 +71,42 @@ pub struct Config {
     /// 音量 (0.0 - 1.0)
     #[serde(default = "default_volume")]
     pub volume: f32,
+    /// ディスクドライブ機械音のボリューム（0.0 - 1.0、0で無音）
+    #[serde(default = "default_disk_sound_volume")]
+    pub disk_sound_volume: f32,
+    /// AppleMouse IIカードをスロット4に挿すか
+    #[serde(default)]
+    pub mouse_card: bool,
+    /// ホストのマウスでパドル0/1とPB0/PB1を操作するモード（off / absolute / relative）
+    #[serde(default)]
+    pub mouse_paddle: crate::gamepad::MousePaddleMode,
+    /// 相対モードの感度（画面の幅だけ動かしたときのフルスケールに対する割合）
+    #[serde(default = "default_mouse_paddle_sensitivity")]
+    pub mouse_paddle_sensitivity: f32,
+    /// Videx VideoTerm 80桁カードをスロット3に挿すか（Apple II/II+用）
+    #[serde(default)]
+    pub videx_card: bool,
+    /// ThunderClock Plusカードをスロット5に挿すか
+    #[serde(default)]
+    pub thunderclock: bool,
+    /// ROMの下にNo-Slot Clockを挿すか
+    #[serde(default)]
+    pub no_slot_clock: bool,
+    /// クロックを固定する時刻（"YYYY-MM-DD HH:MM:SS"、None = ホストの時刻）
+    #[serde(default)]
+    pub clock_freeze: Option<String>,
+    /// クロックの時刻をずらす秒数
+    #[serde(default)]
+    pub clock_offset_secs: i64,
+    /// オーディオ再生のサンプルレート (Hz)
+    #[serde(default = "default_audio_sample_rate")]
+    pub audio_sample_rate: u32,
+    /// オーディオ出力バッファの遅延 (ms)
+    #[serde(default = "default_audio_latency_ms")]
+    pub audio_latency_ms: u32,
+    /// オーディオ出力デバイス名（None = システム既定、`--list-audio-devices` で一覧表示）
+    #[serde(default)]
+    pub audio_device: Option<String>,
     /// 品質レベル（0-4）
     pub quality_level: i32,
     /// 自動品質調整
@@ -101,6 +137,10 @@ fn default_disk_dir() -> String { "disks".to_string() }
 fn default_screenshot_dir() -> String { "screenshots".to_string() }
 fn default_save_dir() -> String { "saves".to_string() }
 fn default_volume() -> f32 { 0.5 }
+fn default_disk_sound_volume() -> f32 { 0.5 }
+fn default_mouse_paddle_sensitivity() -> f32 { 1.0 }
+fn default_audio_sample_rate() -> u32 { crate::sound::SAMPLE_RATE }
+fn default_audio_latency_ms() -> u32 { crate::sound::DEFAULT_LATENCY_MS }
 
 impl Default for Config {
     fn default() -> Self {
@@ -113,6 +153,18 @@ impl Default for Config {
             fast_disk: true,
             sound_enabled: true,
             volume: default_volume(),
+            disk_sound_volume: default_disk_sound_volume(),
+            mouse_card: false,
+            mouse_paddle: crate::gamepad::MousePaddleMode::Off,
+            mouse_paddle_sensitivity: default_mouse_paddle_sensitivity(),
+            videx_card: false,
+            thunderclock: false,
+            no_slot_clock: false,
+            clock_freeze: None,
+            clock_offset_secs: 0,
+            audio_sample_rate: default_audio_sample_rate(),
+            audio_latency_ms: default_audio_latency_ms(),
+            audio_device: None,
             quality_level: 4,
             auto_quality: true,
             window_width: 560,
@@ -127,6 +179,16 @@ impl Default for Config {
 }
 
 impl Config {
+    /// オーディオ再生の設定（範囲外の値は丸める）
+    pub fn audio_settings(&self) -> crate::sound::AudioSettings {
+        crate::sound::AudioSettings {
+            sample_rate: self.audio_sample_rate,
+            latency_ms: self.audio_latency_ms,
+            device: self.audio_device.clone(),
+        }
+        .sanitized()
+    }
+
     /// 設定ファイルを読み込む（実行ファイルと同じディレクトリから）
     pub fn load() -> Self {
         Self::load_from(get_config_path())
@@ -0,0 +1,661 @@
+//! デバッガのコマンドコンソール
+//!
+//! Apple IIのモニタに倣った書式で、数値は16進（`$` や `0x` は付けても付けなくてもよい）。
+//! 範囲は `START.END`（モニタと同じ）または `START LEN` で指定する。
+//! `bp` `d` `g` `until` のアドレスにはシンボル名も使える（16進として読めるものは16進を優先）。
+//! デバッガパネルのConsoleタブと、ヘッドレス時の標準入力（`--console`）から使う。
+//!
+//! メモリの表示・検索はソフトスイッチやウォッチポイントに触れないように `Memory::peek` で読む。
+
+use std::collections::VecDeque;
+
+use crate::apple2::Apple2;
+use crate::cpu::disassembler::disassemble_with_symbols;
+use crate::expr::{EvalContext, Expr};
+use crate::profiler::{parse_hex, BreakCondition, Watchpoint};
+use crate::trace::{TraceTrigger, Tracer};
+
+/// 出力を保持する行数
+const OUTPUT_LIMIT: usize = 200;
+
+/// コマンドの一覧（`help`）
+const HELP: &[&str] = &[
+    "bp [ADDR [if EXPR]]   set/list breakpoints",
+    "bc ID|*               clear breakpoint/watchpoint",
+    "wp [SPEC]             set/list watchpoints ([rwx:]S[-E][@BANK][=V])",
+    "we [EXPR]             add/list watch expressions",
+    "? EXPR                evaluate an expression",
+    "m [ADDR[.END]|ADDR LEN]  memory dump",
+    "d [ADDR [COUNT]]      disassemble",
+    "a ADDR[/aux] INSTR    assemble a line (labels kept for the session)",
+    "r [A=.. X=.. PC=..]   show/set registers",
+    "g [ADDR]              go (from ADDR)",
+    "s [N]                 step N instructions",
+    "n / out               step over / step out",
+    "until ADDR            run to ADDR",
+    "cycles N              run N cycles",
+    "fill S.E V / S L V    fill memory",
+    "load FILE ADDR        load binary file",
+    "save ADDR LEN FILE    save memory to file",
+    "find [S.E] BYTES..    search memory",
+    "bt                    show the call stack",
+    "sym [NAME|ADDR]       look up symbols",
+    "sym load FILE         load symbols (.dbg, VICE, Merlin)",
+    "trace FILE [ring=N] [start=T] [stop=T]  trace to file (T: ADDR or cycle:N)",
+    "trace [off|save|pause]  trace status / stop / write ring / toggle",
+    "trace export BIN TXT  convert a binary trace to text",
+    "back [N]              step back N instructions (needs --rewind)",
+    "rewind N              go back N frames",
+    "lastwrite ADDR        go back to the last write of ADDR",
+];
+
+/// コマンドコンソール
+pub struct Console {
+    /// 入力中の行
+    pub input: String,
+    /// 出力（古い順）
+    output: VecDeque<String>,
+    /// 入力履歴
+    history: Vec<String>,
+    /// 履歴を辿っている位置（`history.len()` なら新しい行）
+    history_pos: usize,
+    /// `m` を引数なしで続けたときのアドレス
+    next_memory: u16,
+    /// `d` を引数なしで続けたときのアドレス（Noneなら現在のPC）
+    next_disasm: Option<u16>,
+}
+
+impl Default for Console {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Console {
+    pub fn new() -> Self {
+        Console {
+            input: String::new(),
+            output: VecDeque::new(),
+            history: Vec::new(),
+            history_pos: 0,
+            next_memory: 0,
+            next_disasm: None,
+        }
+    }
+
+    /// 出力
+    pub fn output(&self) -> &VecDeque<String> {
+        &self.output
+    }
+
+    /// 出力に1行追加
+    pub fn print(&mut self, line: impl Into<String>) {
+        self.output.push_back(line.into());
+        while self.output.len() > OUTPUT_LIMIT {
+            self.output.pop_front();
+        }
+    }
+
+    /// 入力中の行を実行して出力に残す
+    pub fn submit(&mut self, emu: &mut Apple2) {
+        let line = std::mem::take(&mut self.input);
+        self.print(format!("> {}", line));
+        if !line.trim().is_empty() && self.history.last() != Some(&line) {
+            self.history.push(line.clone());
+        }
+        self.history_pos = self.history.len();
+        let lines = match self.execute(emu, &line) {
+            Ok(lines) => lines,
+            Err(e) => vec![format!("Error: {}", e)],
+        };
+        for line in lines {
+            self.print(line);
+        }
+    }
+
+    /// 履歴を1つ戻る
+    pub fn history_prev(&mut self) {
+        if self.history_pos > 0 {
+            self.history_pos -= 1;
+            self.input = self.history[self.history_pos].clone();
+        }
+    }
+
+    /// 履歴を1つ進む
+    pub fn history_next(&mut self) {
+        if self.history_pos < self.history.len() {
+            self.history_pos += 1;
+            self.input = self.history.get(self.history_pos).cloned().unwrap_or_default();
+        }
+    }
+
+    /// コマンドを1行実行して出力を返す
+    pub fn execute(&mut self, emu: &mut Apple2, line: &str) -> Result<Vec<String>, String> {
+        let line = line.trim();
+        let (command, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let rest = rest.trim();
+        let args: Vec<&str> = rest.split_whitespace().collect();
+        match command.to_ascii_lowercase().as_str() {
+            "" => Ok(Vec::new()),
+            "help" | "h" => Ok(HELP.iter().map(|s| s.to_string()).collect()),
+            "bp" => breakpoint(emu, rest),
+            "bc" => clear(emu, &args),
+            "wp" => watchpoint(emu, rest),
+            "we" => watch_expr(emu, rest),
+            "?" => {
+                let expr = Expr::parse(rest)?;
+                let ctx = EvalContext { regs: &emu.cpu.regs, memory: &emu.memory, cycles: emu.total_cycles };
+                let value = expr.eval(&ctx);
+                Ok(vec![format!("{} = ${:X} ({})", expr, value, value)])
+            }
+            "m" => self.memory(emu, &args),
+            "d" => self.disassemble(emu, &args),
+            "a" => assemble(emu, rest),
+            "r" => registers(emu, &args),
+            "g" => {
+                if let Some(text) = args.first() {
+                    emu.cpu.regs.pc = resolve(emu, text)?;
+                }
+                emu.debugger.resume();
+                self.next_disasm = None;
+                Ok(vec![format!("Running from ${:04X}", emu.cpu.regs.pc)])
+            }
+            "s" => {
+                match args.first() {
+                    Some(text) => emu.run_instructions(parse_count(text)?),
+                    None => emu.debugger.step(),
+                }
+                self.next_disasm = None;
+                Ok(Vec::new())
+            }
+            "n" => {
+                emu.step_over();
+                self.next_disasm = None;
+                Ok(Vec::new())
+            }
+            "out" => {
+                emu.step_out();
+                self.next_disasm = None;
+                Ok(Vec::new())
+            }
+            "until" => {
+                let address = resolve(emu, args.first().ok_or("Usage: until ADDR")?)?;
+                emu.run_to(address);
+                self.next_disasm = None;
+                Ok(Vec::new())
+            }
+            "cycles" => {
+                emu.run_for_cycles(parse_count(args.first().ok_or("Usage: cycles N")?)?);
+                Ok(Vec::new())
+            }
+            "fill" => fill(emu, &args),
+            "load" => load(emu, &args),
+            "save" => save(emu, &args),
+            "find" => find(emu, &args),
+            "bt" => Ok(backtrace(emu)),
+            "sym" => symbols(emu, &args),
+            "trace" => trace(emu, &args),
+            "back" => {
+                let count = args.first().map(|text| parse_count(text)).transpose()?.unwrap_or(1);
+                emu.step_back(count)?;
+                self.next_disasm = None;
+                Ok(vec![rewound(emu)])
+            }
+            "rewind" => {
+                emu.rewind_frames(parse_count(args.first().ok_or("Usage: rewind N")?)?)?;
+                self.next_disasm = None;
+                Ok(vec![rewound(emu)])
+            }
+            "lastwrite" => {
+                let address = resolve(emu, args.first().ok_or("Usage: lastwrite ADDR")?)?;
+                let hit = emu.rewind_to_last_write(address)?;
+                self.next_disasm = None;
+                Ok(vec![
+                    format!("${:04X} = ${:02X} written by ${:04X}", address, hit.value, hit.pc),
+                    rewound(emu),
+                ])
+            }
+            _ => Err(format!("Unknown command '{}' (try help)", command)),
+        }
+    }
+
+    /// `m [ADDR[.END] | ADDR LEN]`（8バイトずつ、ASCII付き）
+    fn memory(&mut self, emu: &Apple2, args: &[&str]) -> Result<Vec<String>, String> {
+        let (start, end) = match args.first() {
+            Some(text) => parse_range(text, args.get(1).copied(), 0x40)?,
+            None => (self.next_memory, self.next_memory.saturating_add(0x3F)),
+        };
+        let mut lines = Vec::new();
+        let mut addr = start as u32;
+        while addr <= end as u32 {
+            let count = (end as u32 + 1 - addr).min(8);
+            let bytes: Vec<u8> = (0..count).map(|i| emu.memory.peek((addr + i) as u16)).collect();
+            let hex: Vec<String> = bytes.iter().map(|b| format!("{:02X}", b)).collect();
+            let ascii: String = bytes.iter().map(|&b| {
+                let c = b & 0x7F;
+                if (0x20..0x7F).contains(&c) { c as char } else { '.' }
+            }).collect();
+            lines.push(format!("{:04X}: {:<23}  {}", addr, hex.join(" "), ascii));
+            addr += 8;
+        }
+        self.next_memory = end.wrapping_add(1);
+        Ok(lines)
+    }
+
+    /// `d [ADDR [COUNT]]`
+    fn disassemble(&mut self, emu: &Apple2, args: &[&str]) -> Result<Vec<String>, String> {
+        let mut addr = match args.first() {
+            Some(text) => resolve(emu, text)?,
+            None => self.next_disasm.unwrap_or(emu.cpu.regs.pc),
+        };
+        let count = match args.get(1) {
+            Some(text) => parse_count(text)?,
+            None => 16,
+        };
+        let symbols = &emu.debugger.symbols;
+        let mut lines = Vec::new();
+        for _ in 0..count {
+            if let Some(name) = symbols.name(addr) {
+                lines.push(format!("{}:", name));
+            }
+            let ins = disassemble_with_symbols(emu.cpu.cpu_type, addr, |a| emu.memory.peek(a), symbols);
+            let bytes: Vec<String> = (0..ins.len as u16).map(|i| format!("{:02X}", emu.memory.peek(addr.wrapping_add(i)))).collect();
+            let marker = if addr == emu.cpu.regs.pc { ">" } else { " " };
+            lines.push(format!("{}{:04X}: {:<9} {}", marker, addr, bytes.join(" "), ins.text));
+            addr = addr.wrapping_add(ins.len as u16);
+        }
+        self.next_disasm = Some(addr);
+        Ok(lines)
+    }
+}
+
+/// `a ADDR[/aux] INSTR`（書き込んだ命令を逆アセンブルして返す）
+fn assemble(emu: &mut Apple2, rest: &str) -> Result<Vec<String>, String> {
+    let (target, source) = rest.split_once(char::is_whitespace).ok_or("Usage: a ADDR[/aux] INSTR")?;
+    let (address, aux) = match target.split_once('/') {
+        Some((address, bank)) if bank.eq_ignore_ascii_case("aux") => (address, true),
+        Some((address, bank)) if bank.eq_ignore_ascii_case("main") => (address, false),
+        Some((_, bank)) => return Err(format!("Unknown bank '{}' (main or aux)", bank)),
+        None => (target, false),
+    };
+    let start = resolve(emu, address)?;
+    // 同じセッションのラベルを使うため、デバッガのアセンブラを借りる
+    let mut assembler = std::mem::take(&mut emu.debugger.assembler);
+    let result = emu.assemble(&mut assembler, start, source.trim(), aux);
+    emu.debugger.assembler = assembler;
+    let end = result?;
+
+    let ram = if aux { &emu.memory.aux_ram } else { &emu.memory.main_ram };
+    let read = |a: u16| ram[a as usize];
+    let mut lines = Vec::new();
+    let mut addr = start;
+    while addr != end {
+        let ins = disassemble_with_symbols(emu.cpu.cpu_type, addr, read, &emu.debugger.symbols);
+        let len = (ins.len as u16).min(end.wrapping_sub(addr));
+        let bytes: Vec<String> = (0..len).map(|i| format!("{:02X}", read(addr.wrapping_add(i)))).collect();
+        lines.push(format!("{:04X}{}: {:<9} {}", addr, if aux { "/aux" } else { "" }, bytes.join(" "), ins.text));
+        addr = addr.wrapping_add(len);
+    }
+    Ok(lines)
+}
+
+/// `bp [ADDR [if EXPR]]`
+fn breakpoint(emu: &mut Apple2, rest: &str) -> Result<Vec<String>, String> {
+    if rest.is_empty() {
+        let lines: Vec<String> = emu.debugger.breakpoints().iter().map(|bp| {
+            let address = emu.debugger.symbols.describe(bp.address);
+            let mut line = format!("#{} {}{} hits={}", bp.id, address, if bp.enabled { "" } else { " (off)" }, bp.hit_count);
+            if let Some(condition) = &bp.condition {
+                line.push_str(&format!(" if {}", condition));
+            }
+            line
+        }).collect();
+        return Ok(if lines.is_empty() { vec!["No breakpoints".to_string()] } else { lines });
+    }
+    let (addr_text, condition) = match rest.split_once(" if ") {
+        Some((addr, condition)) => (addr, Some(condition)),
+        None => (rest, None),
+    };
+    let address = resolve(emu, addr_text.trim())?;
+    let id = match condition {
+        Some(text) => emu.debugger.add_conditional_breakpoint(address, BreakCondition::Expr(Expr::parse(text)?)),
+        None => emu.debugger.add_breakpoint(address),
+    };
+    Ok(vec![format!("Breakpoint #{} at {}", id, emu.debugger.symbols.describe(address))])
+}
+
+/// `bc ID|*`
+fn clear(emu: &mut Apple2, args: &[&str]) -> Result<Vec<String>, String> {
+    let text = args.first().ok_or("Usage: bc ID|*")?;
+    if *text == "*" {
+        let ids: Vec<u32> = emu.debugger.breakpoints().iter().map(|bp| bp.id)
+            .chain(emu.debugger.watchpoints().iter().map(|wp| wp.id))
+            .collect();
+        for &id in &ids {
+            emu.debugger.remove_breakpoint(id);
+            emu.debugger.remove_watchpoint(id);
+        }
+        return Ok(vec![format!("Cleared {} breakpoints/watchpoints", ids.len())]);
+    }
+    let id: u32 = text.trim_start_matches('#').parse().map_err(|_| format!("Invalid id '{}'", text))?;
+    if emu.debugger.remove_breakpoint(id) || emu.debugger.remove_watchpoint(id) {
+        Ok(vec![format!("Cleared #{}", id)])
+    } else {
+        Err(format!("No breakpoint or watchpoint #{}", id))
+    }
+}
+
+/// `wp [SPEC]`
+fn watchpoint(emu: &mut Apple2, rest: &str) -> Result<Vec<String>, String> {
+    if rest.is_empty() {
+        let lines: Vec<String> = emu.debugger.watchpoints().iter().map(|wp| {
+            let mut line = format!("#{} {}{} hits={}", wp.id, wp, if wp.enabled { "" } else { " (off)" }, wp.hit_count);
+            if let Some(pc) = wp.last_pc {
+                line.push_str(&format!(" PC=${:04X}", pc));
+            }
+            line
+        }).collect();
+        return Ok(if lines.is_empty() { vec!["No watchpoints".to_string()] } else { lines });
+    }
+    let wp = Watchpoint::parse(rest)?;
+    let text = wp.to_string();
+    let id = emu.debugger.add_watchpoint(wp);
+    Ok(vec![format!("Watchpoint #{} {}", id, text)])
+}
+
+/// `we [EXPR]`
+fn watch_expr(emu: &mut Apple2, rest: &str) -> Result<Vec<String>, String> {
+    if !rest.is_empty() {
+        emu.debugger.add_watch_expr(rest)?;
+    }
+    let ctx = EvalContext { regs: &emu.cpu.regs, memory: &emu.memory, cycles: emu.total_cycles };
+    emu.debugger.refresh_watch_exprs(&ctx);
+    Ok(emu.debugger.watch_exprs().iter().enumerate().map(|(i, w)| {
+        let value = w.value.unwrap_or_default();
+        format!("{}: {} = ${:X} ({})", i, w.expr, value, value)
+    }).collect())
+}
+
+/// `r [A=.. X=.. Y=.. SP=.. PC=.. P=..]`
+fn registers(emu: &mut Apple2, args: &[&str]) -> Result<Vec<String>, String> {
+    for arg in args {
+        let (name, value) = arg.split_once('=').ok_or_else(|| format!("Expected REG=VALUE, got '{}'", arg))?;
+        let value = parse_address(value)?;
+        let regs = &mut emu.cpu.regs;
+        let byte = u8::try_from(value).map_err(|_| format!("Value too large for {}", name));
+        match name.to_ascii_lowercase().as_str() {
+            "a" => regs.a = byte?,
+            "x" => regs.x = byte?,
+            "y" => regs.y = byte?,
+            "s" | "sp" => regs.sp = byte?,
+            "p" => regs.status = byte?,
+            "pc" => regs.pc = value,
+            _ => return Err(format!("Unknown register '{}'", name)),
+        }
+    }
+    let r = &emu.cpu.regs;
+    Ok(vec![format!(
+        "A={:02X} X={:02X} Y={:02X} P={:02X} S={:02X} PC={:04X}  {}",
+        r.a, r.x, r.y, r.status, r.sp, r.pc, flag_string(r.status)
+    )])
+}
+
+/// "NV-BDIZC" 形式（立っていないフラグは小文字）
+fn flag_string(p: u8) -> String {
+    "NV-BDIZC".chars().enumerate().map(|(i, c)| {
+        if p & (0x80 >> i) != 0 { c } else { c.to_ascii_lowercase() }
+    }).collect()
+}
+
+/// `fill START.END VALUE` / `fill START LEN VALUE`
+fn fill(emu: &mut Apple2, args: &[&str]) -> Result<Vec<String>, String> {
+    let (range, len, value) = match args {
+        [range, value] => (*range, None, *value),
+        [start, len, value] => (*start, Some(*len), *value),
+        _ => return Err("Usage: fill START.END VALUE".to_string()),
+    };
+    let (start, end) = parse_range(range, len, 1)?;
+    let value = parse_byte(value)?;
+    for addr in start..=end {
+        emu.memory.poke(addr, value);
+    }
+    Ok(vec![format!("Filled ${:04X}-${:04X} with ${:02X}", start, end, value)])
+}
+
+/// `load FILE ADDR`
+fn load(emu: &mut Apple2, args: &[&str]) -> Result<Vec<String>, String> {
+    let [path, addr] = args else { return Err("Usage: load FILE ADDR".to_string()) };
+    let start = parse_address(addr)?;
+    let data = std::fs::read(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    let len = data.len().min(0x10000 - start as usize);
+    for (i, &byte) in data[..len].iter().enumerate() {
+        emu.memory.poke(start + i as u16, byte);
+    }
+    Ok(vec![format!("Loaded {} bytes at ${:04X}", len, start)])
+}
+
+/// `save ADDR LEN FILE`
+fn save(emu: &mut Apple2, args: &[&str]) -> Result<Vec<String>, String> {
+    let [addr, len, path] = args else { return Err("Usage: save ADDR LEN FILE".to_string()) };
+    let (start, end) = parse_range(addr, Some(len), 1)?;
+    let data: Vec<u8> = (start..=end).map(|a| emu.memory.peek(a)).collect();
+    std::fs::write(path, &data).map_err(|e| format!("Failed to write {}: {}", path, e))?;
+    Ok(vec![format!("Saved ${:04X}-${:04X} ({} bytes) to {}", start, end, data.len(), path)])
+}
+
+/// `find [START.END] BYTES..`
+fn find(emu: &mut Apple2, args: &[&str]) -> Result<Vec<String>, String> {
+    let (start, end, bytes) = match args.first() {
+        Some(range) if range.contains('.') => {
+            let (start, end) = parse_range(range, None, 1)?;
+            (start, end, &args[1..])
+        }
+        _ => (0x0000, 0xFFFF, args),
+    };
+    let pattern = bytes.iter().map(|b| parse_byte(b)).collect::<Result<Vec<u8>, String>>()?;
+    if pattern.is_empty() {
+        return Err("Usage: find [START.END] BYTES..".to_string());
+    }
+    let mut lines = Vec::new();
+    let last = (end as u32 + 1).saturating_sub(pattern.len() as u32);
+    for addr in start as u32..=last {
+        let matched = pattern.iter().enumerate().all(|(i, &b)| emu.memory.peek((addr + i as u32) as u16) == b);
+        if matched {
+            lines.push(format!("${:04X}", addr));
+            if lines.len() == 32 {
+                lines.push("(more matches not shown)".to_string());
+                break;
+            }
+        }
+    }
+    if lines.is_empty() {
+        lines.push("Not found".to_string());
+    }
+    Ok(lines)
+}
+
+/// `sym [NAME|ADDR]` / `sym load FILE`
+fn symbols(emu: &mut Apple2, args: &[&str]) -> Result<Vec<String>, String> {
+    let symbols = &mut emu.debugger.symbols;
+    match args {
+        [] => Ok(vec![format!("{} symbols", symbols.len())]),
+        ["load", path] => {
+            let count = symbols.load(path)?;
+            Ok(vec![format!("Loaded {} symbols from {}", count, path)])
+        }
+        [text] => {
+            if let Some(address) = symbols.lookup(text) {
+                return Ok(vec![format!("{} = ${:04X}", text.to_ascii_uppercase(), address)]);
+            }
+            let address = parse_address(text)?;
+            match symbols.location(address) {
+                Some(location) => Ok(vec![format!("${:04X} = {}", address, location)]),
+                None => Err(format!("No symbol near ${:04X}", address)),
+            }
+        }
+        _ => Err("Usage: sym [NAME|ADDR] / sym load FILE".to_string()),
+    }
+}
+
+/// `trace ...`
+fn trace(emu: &mut Apple2, args: &[&str]) -> Result<Vec<String>, String> {
+    let debugger = &mut emu.debugger;
+    match args {
+        [] => Ok(vec![match debugger.tracer() {
+            Some(t) => format!(
+                "Tracing to {}{}: {} instructions{}",
+                t.path(), if t.is_ring() { " (ring)" } else { "" }, t.count(), if t.is_active() { "" } else { " (paused)" }
+            ),
+            None => "Not tracing".to_string(),
+        }]),
+        ["off"] => {
+            let path = debugger.tracer().map(|t| t.path().to_string()).ok_or("Not tracing")?;
+            let count = debugger.stop_trace()?;
+            Ok(vec![format!("Wrote {} instructions to {}", count, path)])
+        }
+        ["save"] => {
+            let tracer = debugger.tracer_mut().ok_or("Not tracing")?;
+            let count = tracer.save()?;
+            Ok(vec![format!("Wrote {} instructions to {}", count, tracer.path())])
+        }
+        ["pause"] => {
+            let tracer = debugger.tracer_mut().ok_or("Not tracing")?;
+            tracer.toggle();
+            Ok(vec![format!("Trace {}", if tracer.is_active() { "resumed" } else { "paused" })])
+        }
+        ["export", input, output] => {
+            let mut file = std::fs::File::create(output).map_err(|e| format!("Failed to create {}: {}", output, e))?;
+            let count = crate::trace::export_text(input, &debugger.symbols, &mut file)?;
+            Ok(vec![format!("Exported {} instructions to {}", count, output)])
+        }
+        [path, options @ ..] => {
+            let (mut ring, mut start, mut stop) = (None, None, None);
+            for option in options {
+                match option.split_once('=') {
+                    Some(("ring", n)) => ring = Some(parse_count(n)? as usize),
+                    Some(("start", t)) => start = Some(TraceTrigger::parse(t, &debugger.symbols)?),
+                    Some(("stop", t)) => stop = Some(TraceTrigger::parse(t, &debugger.symbols)?),
+                    _ => return Err(format!("Unknown trace option '{}'", option)),
+                }
+            }
+            let mut tracer = Tracer::new(path, ring, emu.cpu.cpu_type, &debugger.symbols)?;
+            tracer.set_triggers(start, stop);
+            debugger.start_trace(tracer)?;
+            Ok(vec![format!("Tracing to {}", path)])
+        }
+    }
+}
+
+/// `bt`（内側から順、戻りアドレスがもうスタックにないフレームには印を付ける）
+fn backtrace(emu: &Apple2) -> Vec<String> {
+    let symbols = &emu.debugger.symbols;
+    let frames = emu.debugger.call_stack.frames();
+    let mut lines = vec![format!("PC {}", symbols.describe(emu.cpu.regs.pc))];
+    for (i, frame) in frames.iter().rev().enumerate() {
+        lines.push(format!(
+            "#{} {} {} from {}, returns to ${:04X} (S=${:02X}){}",
+            i, frame.kind.name(), symbols.describe(frame.target), symbols.describe(frame.caller),
+            frame.return_address, frame.sp, if frame.is_live(emu.cpu.regs.sp) { "" } else { " gone" }
+        ));
+    }
+    if frames.is_empty() {
+        lines.push("(no calls recorded)".to_string());
+    }
+    lines
+}
+
+/// 巻き戻した位置
+fn rewound(emu: &Apple2) -> String {
+    format!("At instruction {} (frame {}), PC=${:04X}", emu.instructions, emu.frame_count, emu.cpu.regs.pc)
+}
+
+/// アドレスまたはシンボル名
+fn resolve(emu: &Apple2, text: &str) -> Result<u16, String> {
+    parse_hex(text)
+        .or_else(|| emu.debugger.symbols.lookup(text))
+        .ok_or_else(|| format!("Invalid address or unknown symbol '{}'", text))
+}
+
+fn parse_address(text: &str) -> Result<u16, String> {
+    parse_hex(text).ok_or_else(|| format!("Invalid address '{}'", text))
+}
+
+fn parse_byte(text: &str) -> Result<u8, String> {
+    parse_hex(text)
+        .and_then(|v| u8::try_from(v).ok())
+        .ok_or_else(|| format!("Invalid byte '{}'", text))
+}
+
+/// 回数（`$` 付きなら16進、それ以外は10進）
+fn parse_count(text: &str) -> Result<u64, String> {
+    let parsed = match text.strip_prefix('$') {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None => text.parse().ok(),
+    };
+    parsed.ok_or_else(|| format!("Invalid count '{}'", text))
+}
+
+/// "START.END" または "START" と長さ（省略時は `default_len`）から範囲を求める
+fn parse_range(text: &str, len: Option<&str>, default_len: u32) -> Result<(u16, u16), String> {
+    if let Some((start, end)) = text.split_once('.') {
+        let (start, end) = (parse_address(start)?, parse_address(end)?);
+        if end < start {
+            return Err(format!("Invalid range '{}'", text));
+        }
+        return Ok((start, end));
+    }
+    let start = parse_address(text)?;
+    let len = match len {
+        Some(len) => parse_hex(len).map(u32::from).filter(|&l| l > 0).ok_or_else(|| format!("Invalid length '{}'", len))?,
+        None => default_len,
+    };
+    Ok((start, (start as u32 + len - 1).min(0xFFFF) as u16))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::AppleModel;
+
+    #[test]
+    fn monitor_style_commands() {
+        let mut emu = Apple2::new(AppleModel::AppleIIPlus);
+        let mut console = Console::new();
+        let mut run = |emu: &mut Apple2, line: &str| console.execute(emu, line).unwrap();
+
+        run(&mut emu, "fill 300.30F EA");
+        emu.memory.main_ram[0x0308..0x030B].copy_from_slice(&[0x20, 0xED, 0xFD]);
+        assert_eq!(run(&mut emu, "m 300 8"), vec!["0300: EA EA EA EA EA EA EA EA  jjjjjjjj"]);
+        assert_eq!(run(&mut emu, "d 307 2"), vec![" 0307: EA        NOP", " 0308: 20 ED FD  JSR COUT"]);
+        assert_eq!(run(&mut emu, "find 300.3FF 20 ED FD"), vec!["$0308"]);
+
+        run(&mut emu, "r a=12 pc=300");
+        assert_eq!((emu.cpu.regs.a, emu.cpu.regs.pc), (0x12, 0x0300));
+        assert_eq!(run(&mut emu, "bp 308 if a == $12"), vec!["Breakpoint #1 at $0308"]);
+        run(&mut emu, "g");
+        emu.run_frame();
+        assert_eq!(emu.cpu.regs.pc, 0x0308);
+        assert_eq!(run(&mut emu, "bp cout"), vec!["Breakpoint #2 at $FDED (COUT)"]);
+        assert_eq!(run(&mut emu, "sym FDF2"), vec!["$FDF2 = COUT1+2"]);
+        run(&mut emu, "bc *");
+        assert!(emu.debugger.breakpoints().is_empty());
+        assert!(console.execute(&mut emu, "bogus").is_err());
+    }
+
+    #[test]
+    fn assemble_into_main_and_aux() {
+        let mut emu = Apple2::new(AppleModel::AppleIIe);
+        let mut console = Console::new();
+        let mut run = |emu: &mut Apple2, line: &str| console.execute(emu, line).unwrap();
+
+        assert_eq!(run(&mut emu, "a 300 LOOP: LDA #$41"), vec!["0300: A9 41     LDA #$41"]);
+        // ラベルはセッション中残る
+        assert_eq!(run(&mut emu, "a 302 JMP LOOP"), vec!["0302: 4C 00 03  JMP $0300"]);
+        assert_eq!(emu.memory.main_ram[0x0300..0x0305], [0xA9, 0x41, 0x4C, 0x00, 0x03]);
+
+        assert_eq!(run(&mut emu, "a 300/aux JSR $FDED"), vec!["0300/aux: 20 ED FD  JSR COUT"]);
+        assert_eq!(emu.memory.aux_ram[0x0300..0x0303], [0x20, 0xED, 0xFD]);
+        assert_eq!(emu.memory.main_ram[0x0300], 0xA9);
+        assert!(console.execute(&mut emu, "a 300 FOO").is_err());
+    }
+}
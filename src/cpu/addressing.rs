@@ -5,7 +5,7 @@
 use super::{Cpu, MemoryBus};
 
 /// アドレッシングモードの種類
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[allow(dead_code)]
 pub enum AddressingMode {
     /// 即値（Immediate） - #$nn
@@ -30,9 +30,15 @@ pub enum AddressingMode {
     IndirectY,
     /// 間接（ゼロページ、65C02のみ） - ($nn)
     IndirectZeroPage,
+    /// 絶対間接,X（JMPのみ、65C02のみ） - ($nnnn,X)
+    AbsoluteIndirectX,
     /// 相対（ブランチ命令用） - $nn
     Relative,
-    /// 暗黙的/アキュムレータ
+    /// ゼロページ＋相対（BBR/BBS用、65C02のみ） - $nn,$nn
+    ZeroPageRelative,
+    /// アキュムレータ - A
+    Accumulator,
+    /// 暗黙的
     Implied,
 }
 
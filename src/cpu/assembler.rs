@@ -0,0 +1,797 @@
+//! ラインアセンブラ
+//!
+//! デバッガから命令を直接メモリに書き込むための簡易アセンブラ。
+//! 6502/65C02の全アドレッシングモードと、セッション内で保持されるラベルに対応する。
+//!
+//! 書式:
+//! - `LABEL:` 行頭のラベル定義（現在のアドレス）
+//! - `LABEL = expr` / `LABEL EQU expr` 定数定義
+//! - `.BYTE` / `DFB` / `DB`、`.WORD` / `DW` / `DA` データ定義
+//! - 数値: `$FF`（16進）、`%1010`（2進）、`123`（10進）、`'A'`、`"A"`（上位ビット付き）、`*`（現在のアドレス）
+//! - 式: `LABEL+1`、`*-2`、`<LABEL`（下位バイト）、`>LABEL`（上位バイト）
+//! - `;` 以降はコメント
+
+use std::collections::HashMap;
+
+use super::addressing::AddressingMode;
+use super::CpuType;
+
+use AddressingMode as M;
+
+/// オペコード表（ニーモニック, アドレッシングモード, オペコード, 65C02専用か）
+/// RMB/SMB/BBR/BBSはビット番号付きニーモニックとして別途処理する
+pub(super) const OPCODES: &[(&str, AddressingMode, u8, bool)] = &[
+    ("ADC", M::Immediate, 0x69, false),
+    ("ADC", M::ZeroPage, 0x65, false),
+    ("ADC", M::ZeroPageX, 0x75, false),
+    ("ADC", M::Absolute, 0x6D, false),
+    ("ADC", M::AbsoluteX, 0x7D, false),
+    ("ADC", M::AbsoluteY, 0x79, false),
+    ("ADC", M::IndirectX, 0x61, false),
+    ("ADC", M::IndirectY, 0x71, false),
+    ("ADC", M::IndirectZeroPage, 0x72, true),
+    ("AND", M::Immediate, 0x29, false),
+    ("AND", M::ZeroPage, 0x25, false),
+    ("AND", M::ZeroPageX, 0x35, false),
+    ("AND", M::Absolute, 0x2D, false),
+    ("AND", M::AbsoluteX, 0x3D, false),
+    ("AND", M::AbsoluteY, 0x39, false),
+    ("AND", M::IndirectX, 0x21, false),
+    ("AND", M::IndirectY, 0x31, false),
+    ("AND", M::IndirectZeroPage, 0x32, true),
+    ("ASL", M::Accumulator, 0x0A, false),
+    ("ASL", M::ZeroPage, 0x06, false),
+    ("ASL", M::ZeroPageX, 0x16, false),
+    ("ASL", M::Absolute, 0x0E, false),
+    ("ASL", M::AbsoluteX, 0x1E, false),
+    ("BCC", M::Relative, 0x90, false),
+    ("BCS", M::Relative, 0xB0, false),
+    ("BEQ", M::Relative, 0xF0, false),
+    ("BIT", M::ZeroPage, 0x24, false),
+    ("BIT", M::Absolute, 0x2C, false),
+    ("BIT", M::Immediate, 0x89, true),
+    ("BIT", M::ZeroPageX, 0x34, true),
+    ("BIT", M::AbsoluteX, 0x3C, true),
+    ("BMI", M::Relative, 0x30, false),
+    ("BNE", M::Relative, 0xD0, false),
+    ("BPL", M::Relative, 0x10, false),
+    ("BRA", M::Relative, 0x80, true),
+    ("BRK", M::Implied, 0x00, false),
+    ("BVC", M::Relative, 0x50, false),
+    ("BVS", M::Relative, 0x70, false),
+    ("CLC", M::Implied, 0x18, false),
+    ("CLD", M::Implied, 0xD8, false),
+    ("CLI", M::Implied, 0x58, false),
+    ("CLV", M::Implied, 0xB8, false),
+    ("CMP", M::Immediate, 0xC9, false),
+    ("CMP", M::ZeroPage, 0xC5, false),
+    ("CMP", M::ZeroPageX, 0xD5, false),
+    ("CMP", M::Absolute, 0xCD, false),
+    ("CMP", M::AbsoluteX, 0xDD, false),
+    ("CMP", M::AbsoluteY, 0xD9, false),
+    ("CMP", M::IndirectX, 0xC1, false),
+    ("CMP", M::IndirectY, 0xD1, false),
+    ("CMP", M::IndirectZeroPage, 0xD2, true),
+    ("CPX", M::Immediate, 0xE0, false),
+    ("CPX", M::ZeroPage, 0xE4, false),
+    ("CPX", M::Absolute, 0xEC, false),
+    ("CPY", M::Immediate, 0xC0, false),
+    ("CPY", M::ZeroPage, 0xC4, false),
+    ("CPY", M::Absolute, 0xCC, false),
+    ("DEA", M::Implied, 0x3A, true),
+    ("DEC", M::Accumulator, 0x3A, true),
+    ("DEC", M::ZeroPage, 0xC6, false),
+    ("DEC", M::ZeroPageX, 0xD6, false),
+    ("DEC", M::Absolute, 0xCE, false),
+    ("DEC", M::AbsoluteX, 0xDE, false),
+    ("DEX", M::Implied, 0xCA, false),
+    ("DEY", M::Implied, 0x88, false),
+    ("EOR", M::Immediate, 0x49, false),
+    ("EOR", M::ZeroPage, 0x45, false),
+    ("EOR", M::ZeroPageX, 0x55, false),
+    ("EOR", M::Absolute, 0x4D, false),
+    ("EOR", M::AbsoluteX, 0x5D, false),
+    ("EOR", M::AbsoluteY, 0x59, false),
+    ("EOR", M::IndirectX, 0x41, false),
+    ("EOR", M::IndirectY, 0x51, false),
+    ("EOR", M::IndirectZeroPage, 0x52, true),
+    ("INA", M::Implied, 0x1A, true),
+    ("INC", M::Accumulator, 0x1A, true),
+    ("INC", M::ZeroPage, 0xE6, false),
+    ("INC", M::ZeroPageX, 0xF6, false),
+    ("INC", M::Absolute, 0xEE, false),
+    ("INC", M::AbsoluteX, 0xFE, false),
+    ("INX", M::Implied, 0xE8, false),
+    ("INY", M::Implied, 0xC8, false),
+    ("JMP", M::Absolute, 0x4C, false),
+    ("JMP", M::Indirect, 0x6C, false),
+    ("JMP", M::AbsoluteIndirectX, 0x7C, true),
+    ("JSR", M::Absolute, 0x20, false),
+    ("LDA", M::Immediate, 0xA9, false),
+    ("LDA", M::ZeroPage, 0xA5, false),
+    ("LDA", M::ZeroPageX, 0xB5, false),
+    ("LDA", M::Absolute, 0xAD, false),
+    ("LDA", M::AbsoluteX, 0xBD, false),
+    ("LDA", M::AbsoluteY, 0xB9, false),
+    ("LDA", M::IndirectX, 0xA1, false),
+    ("LDA", M::IndirectY, 0xB1, false),
+    ("LDA", M::IndirectZeroPage, 0xB2, true),
+    ("LDX", M::Immediate, 0xA2, false),
+    ("LDX", M::ZeroPage, 0xA6, false),
+    ("LDX", M::ZeroPageY, 0xB6, false),
+    ("LDX", M::Absolute, 0xAE, false),
+    ("LDX", M::AbsoluteY, 0xBE, false),
+    ("LDY", M::Immediate, 0xA0, false),
+    ("LDY", M::ZeroPage, 0xA4, false),
+    ("LDY", M::ZeroPageX, 0xB4, false),
+    ("LDY", M::Absolute, 0xAC, false),
+    ("LDY", M::AbsoluteX, 0xBC, false),
+    ("LSR", M::Accumulator, 0x4A, false),
+    ("LSR", M::ZeroPage, 0x46, false),
+    ("LSR", M::ZeroPageX, 0x56, false),
+    ("LSR", M::Absolute, 0x4E, false),
+    ("LSR", M::AbsoluteX, 0x5E, false),
+    ("NOP", M::Implied, 0xEA, false),
+    ("ORA", M::Immediate, 0x09, false),
+    ("ORA", M::ZeroPage, 0x05, false),
+    ("ORA", M::ZeroPageX, 0x15, false),
+    ("ORA", M::Absolute, 0x0D, false),
+    ("ORA", M::AbsoluteX, 0x1D, false),
+    ("ORA", M::AbsoluteY, 0x19, false),
+    ("ORA", M::IndirectX, 0x01, false),
+    ("ORA", M::IndirectY, 0x11, false),
+    ("ORA", M::IndirectZeroPage, 0x12, true),
+    ("PHA", M::Implied, 0x48, false),
+    ("PHP", M::Implied, 0x08, false),
+    ("PHX", M::Implied, 0xDA, true),
+    ("PHY", M::Implied, 0x5A, true),
+    ("PLA", M::Implied, 0x68, false),
+    ("PLP", M::Implied, 0x28, false),
+    ("PLX", M::Implied, 0xFA, true),
+    ("PLY", M::Implied, 0x7A, true),
+    ("ROL", M::Accumulator, 0x2A, false),
+    ("ROL", M::ZeroPage, 0x26, false),
+    ("ROL", M::ZeroPageX, 0x36, false),
+    ("ROL", M::Absolute, 0x2E, false),
+    ("ROL", M::AbsoluteX, 0x3E, false),
+    ("ROR", M::Accumulator, 0x6A, false),
+    ("ROR", M::ZeroPage, 0x66, false),
+    ("ROR", M::ZeroPageX, 0x76, false),
+    ("ROR", M::Absolute, 0x6E, false),
+    ("ROR", M::AbsoluteX, 0x7E, false),
+    ("RTI", M::Implied, 0x40, false),
+    ("RTS", M::Implied, 0x60, false),
+    ("SBC", M::Immediate, 0xE9, false),
+    ("SBC", M::ZeroPage, 0xE5, false),
+    ("SBC", M::ZeroPageX, 0xF5, false),
+    ("SBC", M::Absolute, 0xED, false),
+    ("SBC", M::AbsoluteX, 0xFD, false),
+    ("SBC", M::AbsoluteY, 0xF9, false),
+    ("SBC", M::IndirectX, 0xE1, false),
+    ("SBC", M::IndirectY, 0xF1, false),
+    ("SBC", M::IndirectZeroPage, 0xF2, true),
+    ("SEC", M::Implied, 0x38, false),
+    ("SED", M::Implied, 0xF8, false),
+    ("SEI", M::Implied, 0x78, false),
+    ("STA", M::ZeroPage, 0x85, false),
+    ("STA", M::ZeroPageX, 0x95, false),
+    ("STA", M::Absolute, 0x8D, false),
+    ("STA", M::AbsoluteX, 0x9D, false),
+    ("STA", M::AbsoluteY, 0x99, false),
+    ("STA", M::IndirectX, 0x81, false),
+    ("STA", M::IndirectY, 0x91, false),
+    ("STA", M::IndirectZeroPage, 0x92, true),
+    ("STX", M::ZeroPage, 0x86, false),
+    ("STX", M::ZeroPageY, 0x96, false),
+    ("STX", M::Absolute, 0x8E, false),
+    ("STY", M::ZeroPage, 0x84, false),
+    ("STY", M::ZeroPageX, 0x94, false),
+    ("STY", M::Absolute, 0x8C, false),
+    ("STZ", M::ZeroPage, 0x64, true),
+    ("STZ", M::ZeroPageX, 0x74, true),
+    ("STZ", M::Absolute, 0x9C, true),
+    ("STZ", M::AbsoluteX, 0x9E, true),
+    ("TAX", M::Implied, 0xAA, false),
+    ("TAY", M::Implied, 0xA8, false),
+    ("TRB", M::ZeroPage, 0x14, true),
+    ("TRB", M::Absolute, 0x1C, true),
+    ("TSB", M::ZeroPage, 0x04, true),
+    ("TSB", M::Absolute, 0x0C, true),
+    ("TSX", M::Implied, 0xBA, false),
+    ("TXA", M::Implied, 0x8A, false),
+    ("TXS", M::Implied, 0x9A, false),
+    ("TYA", M::Implied, 0x98, false),
+];
+
+/// オペランドの構文上の形
+#[derive(Debug, Clone, Copy)]
+enum Operand<'a> {
+    /// オペランドなし
+    None,
+    /// `A`
+    Accumulator,
+    /// `#expr`
+    Immediate(&'a str),
+    /// `expr`
+    Direct(&'a str),
+    /// `expr,X`
+    IndexedX(&'a str),
+    /// `expr,Y`
+    IndexedY(&'a str),
+    /// `(expr,X)`
+    IndirectX(&'a str),
+    /// `(expr),Y`
+    IndirectY(&'a str),
+    /// `(expr)`
+    Indirect(&'a str),
+    /// `expr,expr`（BBR/BBS用）
+    Pair(&'a str, &'a str),
+}
+
+/// 1行の解析結果
+#[derive(Debug, Clone)]
+enum Statement<'a> {
+    /// 空行（ラベルのみを含む）
+    Empty,
+    /// 命令
+    Instruction(String, &'a str),
+    /// バイトデータ
+    Bytes(&'a str),
+    /// ワードデータ（リトルエンディアン）
+    Words(&'a str),
+    /// 定数定義
+    Equate(&'a str),
+}
+
+/// アセンブラ
+///
+/// ラベルはセッション中保持され、以降の行やアセンブルから参照できる。
+#[derive(Debug, Clone)]
+pub struct Assembler {
+    /// 対象CPU（65C02命令の可否を決定）
+    pub cpu_type: CpuType,
+    /// ラベル（大文字で保持）
+    labels: HashMap<String, u16>,
+}
+
+impl Default for Assembler {
+    fn default() -> Self {
+        Self::new(CpuType::Cpu6502)
+    }
+}
+
+impl Assembler {
+    /// 新しいアセンブラを作成
+    pub fn new(cpu_type: CpuType) -> Self {
+        Assembler {
+            cpu_type,
+            labels: HashMap::new(),
+        }
+    }
+
+    /// ラベルを定義（既存のラベルは上書き）
+    pub fn define_label(&mut self, name: &str, value: u16) {
+        self.labels.insert(name.to_ascii_uppercase(), value);
+    }
+
+    /// ラベルの値を取得
+    pub fn label(&self, name: &str) -> Option<u16> {
+        self.labels.get(&name.to_ascii_uppercase()).copied()
+    }
+
+    /// 全ラベルを取得
+    pub fn labels(&self) -> &HashMap<String, u16> {
+        &self.labels
+    }
+
+    /// 全ラベルを消去
+    pub fn clear_labels(&mut self) {
+        self.labels.clear();
+    }
+
+    /// 1行をアセンブル
+    ///
+    /// 行頭のラベルは`pc`の値で定義される。前方参照は使えない（`assemble`を使う）。
+    pub fn assemble_line(&mut self, line: &str, pc: u16) -> Result<Vec<u8>, String> {
+        let (label, statement) = parse_line(line)?;
+        if let Statement::Equate(expr) = statement {
+            let name = label.ok_or("Missing label for equate")?;
+            let value = self.eval(expr, pc)?.ok_or_else(|| undefined(expr))?;
+            self.define_label(name, value);
+            return Ok(Vec::new());
+        }
+        if let Some(name) = label {
+            self.define_label(name, pc);
+        }
+        let (bytes, _) = self.encode(&statement, pc, true, false)?;
+        Ok(bytes)
+    }
+
+    /// 複数行をアセンブル（2パス、前方参照可）
+    ///
+    /// `origin`から連続したバイト列を返す。エラーには行番号が付く。
+    pub fn assemble(&mut self, source: &str, origin: u16) -> Result<Vec<u8>, String> {
+        let lines = source
+            .lines()
+            .enumerate()
+            .map(|(i, line)| parse_line(line).map_err(|e| format!("line {}: {}", i + 1, e)))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // このソースで定義するラベルは前回の値を使わない（別のoriginで再アセンブルした場合に古い値で
+        // オペランドの長さや分岐先が決まってしまうため）
+        for name in lines.iter().filter_map(|(label, _)| *label) {
+            self.labels.remove(&name.to_ascii_uppercase());
+        }
+
+        // パス1: ラベルのアドレスを確定（未解決のオペランドは絶対アドレスとして扱う）
+        let mut forced_abs = vec![false; lines.len()];
+        let mut pc = origin;
+        for (i, (label, statement)) in lines.iter().enumerate() {
+            if let Statement::Equate(expr) = statement {
+                let name = label.ok_or_else(|| format!("line {}: Missing label for equate", i + 1))?;
+                let value = self
+                    .eval(expr, pc)
+                    .and_then(|v| v.ok_or_else(|| undefined(expr)))
+                    .map_err(|e| format!("line {}: {}", i + 1, e))?;
+                self.define_label(name, value);
+                continue;
+            }
+            if let Some(name) = label {
+                self.define_label(name, pc);
+            }
+            let (bytes, unresolved) = self
+                .encode(statement, pc, false, false)
+                .map_err(|e| format!("line {}: {}", i + 1, e))?;
+            forced_abs[i] = unresolved;
+            pc = pc.wrapping_add(bytes.len() as u16);
+        }
+
+        // パス2: 確定したラベルでコードを生成
+        let mut output = Vec::new();
+        let mut pc = origin;
+        for (i, (_, statement)) in lines.iter().enumerate() {
+            if let Statement::Equate(_) = statement {
+                continue;
+            }
+            let (bytes, _) = self
+                .encode(statement, pc, true, forced_abs[i])
+                .map_err(|e| format!("line {}: {}", i + 1, e))?;
+            pc = pc.wrapping_add(bytes.len() as u16);
+            output.extend(bytes);
+        }
+        Ok(output)
+    }
+
+    /// 文をバイト列に変換
+    ///
+    /// `strict`がfalseの場合、未定義ラベルは0として扱い、戻り値の2番目でそれを通知する。
+    /// `force_abs`がtrueの場合、ゼロページに収まる値でも絶対アドレスを使う。
+    fn encode(&self, statement: &Statement, pc: u16, strict: bool, force_abs: bool) -> Result<(Vec<u8>, bool), String> {
+        let mut unresolved = false;
+        let mut value_of = |expr: &str, pc: u16| -> Result<Option<u16>, String> {
+            let value = self.eval(expr, pc)?;
+            if value.is_none() {
+                if strict {
+                    return Err(undefined(expr));
+                }
+                unresolved = true;
+            }
+            Ok(value)
+        };
+
+        let bytes = match statement {
+            Statement::Empty | Statement::Equate(_) => Vec::new(),
+            Statement::Bytes(list) => {
+                let mut bytes = Vec::new();
+                for item in split_list(list) {
+                    let value = value_of(item, pc)?.unwrap_or(0);
+                    bytes.push(check_byte(value, item)?);
+                }
+                bytes
+            }
+            Statement::Words(list) => {
+                let mut bytes = Vec::new();
+                for item in split_list(list) {
+                    let value = value_of(item, pc)?.unwrap_or(0);
+                    bytes.extend_from_slice(&value.to_le_bytes());
+                }
+                bytes
+            }
+            Statement::Instruction(mnemonic, operand) => {
+                if let Some((base, bit)) = bit_mnemonic(mnemonic) {
+                    self.require_65c02(mnemonic)?;
+                    let opcode = base | (bit << 4);
+                    match (base, parse_operand(operand)) {
+                        (0x07 | 0x87, Operand::Direct(expr)) => {
+                            let zp = value_of(expr, pc)?.unwrap_or(0);
+                            vec![opcode, check_byte(zp, expr)?]
+                        }
+                        (0x0F | 0x8F, Operand::Pair(zp_expr, target_expr)) => {
+                            let zp = value_of(zp_expr, pc)?.unwrap_or(0);
+                            let target = value_of(target_expr, pc)?;
+                            let offset = match target {
+                                Some(target) => relative_offset(pc.wrapping_add(3), target)?,
+                                None => 0,
+                            };
+                            vec![opcode, check_byte(zp, zp_expr)?, offset]
+                        }
+                        _ => return Err(format!("Invalid operand for {}: '{}'", mnemonic, operand)),
+                    }
+                } else {
+                    let modes: Vec<_> = OPCODES.iter().filter(|e| e.0 == mnemonic.as_str()).collect();
+                    if modes.is_empty() {
+                        return Err(format!("Unknown mnemonic '{}'", mnemonic));
+                    }
+                    let has = |mode: AddressingMode| modes.iter().any(|e| e.1 == mode);
+
+                    // 値の大きさとニーモニックからアドレッシングモードを決定
+                    let (mode, value) = match parse_operand(operand) {
+                        Operand::None if has(M::Implied) => (M::Implied, None),
+                        Operand::None | Operand::Accumulator => (M::Accumulator, None),
+                        Operand::Immediate(expr) => (M::Immediate, Some((expr, value_of(expr, pc)?))),
+                        Operand::Direct(expr) if has(M::Relative) => (M::Relative, Some((expr, value_of(expr, pc)?))),
+                        Operand::Direct(expr) => {
+                            let value = value_of(expr, pc)?;
+                            (zp_or_abs(value, force_abs, has(M::ZeroPage), M::ZeroPage, M::Absolute), Some((expr, value)))
+                        }
+                        Operand::IndexedX(expr) => {
+                            let value = value_of(expr, pc)?;
+                            (zp_or_abs(value, force_abs, has(M::ZeroPageX), M::ZeroPageX, M::AbsoluteX), Some((expr, value)))
+                        }
+                        Operand::IndexedY(expr) => {
+                            let value = value_of(expr, pc)?;
+                            (zp_or_abs(value, force_abs, has(M::ZeroPageY), M::ZeroPageY, M::AbsoluteY), Some((expr, value)))
+                        }
+                        Operand::IndirectX(expr) if has(M::AbsoluteIndirectX) => {
+                            (M::AbsoluteIndirectX, Some((expr, value_of(expr, pc)?)))
+                        }
+                        Operand::IndirectX(expr) => (M::IndirectX, Some((expr, value_of(expr, pc)?))),
+                        Operand::IndirectY(expr) => (M::IndirectY, Some((expr, value_of(expr, pc)?))),
+                        Operand::Indirect(expr) if has(M::Indirect) => (M::Indirect, Some((expr, value_of(expr, pc)?))),
+                        Operand::Indirect(expr) => (M::IndirectZeroPage, Some((expr, value_of(expr, pc)?))),
+                        Operand::Pair(_, _) => return Err(format!("Invalid operand for {}: '{}'", mnemonic, operand)),
+                    };
+
+                    // 絶対アドレスしかない命令はゼロページ指定でも絶対アドレスで出力
+                    let mode = match mode {
+                        M::ZeroPage if !has(M::ZeroPage) => M::Absolute,
+                        M::ZeroPageX if !has(M::ZeroPageX) => M::AbsoluteX,
+                        M::ZeroPageY if !has(M::ZeroPageY) => M::AbsoluteY,
+                        other => other,
+                    };
+
+                    let entry = modes
+                        .iter()
+                        .find(|e| e.1 == mode)
+                        .ok_or_else(|| format!("Invalid addressing mode for {}: '{}'", mnemonic, operand))?;
+                    if entry.3 {
+                        self.require_65c02(mnemonic)?;
+                    }
+                    let opcode = entry.2;
+
+                    match (mode, value) {
+                        (M::Implied | M::Accumulator, _) => vec![opcode],
+                        (M::Relative, Some((_, target))) => {
+                            let offset = match target {
+                                Some(target) => relative_offset(pc.wrapping_add(2), target)?,
+                                None => 0,
+                            };
+                            vec![opcode, offset]
+                        }
+                        (M::Absolute | M::AbsoluteX | M::AbsoluteY | M::Indirect | M::AbsoluteIndirectX, Some((_, v))) => {
+                            let [lo, hi] = v.unwrap_or(0).to_le_bytes();
+                            vec![opcode, lo, hi]
+                        }
+                        (_, Some((expr, v))) => vec![opcode, check_byte(v.unwrap_or(0), expr)?],
+                        (_, None) => vec![opcode],
+                    }
+                }
+            }
+        };
+        Ok((bytes, unresolved))
+    }
+
+    /// 65C02専用命令のチェック
+    fn require_65c02(&self, mnemonic: &str) -> Result<(), String> {
+        if self.cpu_type == CpuType::Cpu65C02 {
+            Ok(())
+        } else {
+            Err(format!("{} requires 65C02", mnemonic))
+        }
+    }
+
+    /// 式を評価（未定義ラベルを含む場合はNone）
+    fn eval(&self, expr: &str, pc: u16) -> Result<Option<u16>, String> {
+        let expr = expr.trim();
+        if let Some(rest) = expr.strip_prefix('<') {
+            return Ok(self.eval(rest, pc)?.map(|v| v & 0xFF));
+        }
+        if let Some(rest) = expr.strip_prefix('>') {
+            return Ok(self.eval(rest, pc)?.map(|v| v >> 8));
+        }
+        if expr.is_empty() {
+            return Err("Missing operand".to_string());
+        }
+
+        let chars: Vec<char> = expr.chars().collect();
+        let mut pos = 0;
+        let mut result: Option<u16> = Some(0);
+        let mut negate = false;
+        loop {
+            let (term, next) = self.eval_term(&chars, pos, pc)?;
+            result = match (result, term) {
+                (Some(acc), Some(t)) if negate => Some(acc.wrapping_sub(t)),
+                (Some(acc), Some(t)) => Some(acc.wrapping_add(t)),
+                _ => None,
+            };
+            pos = skip_spaces(&chars, next);
+            match chars.get(pos) {
+                None => return Ok(result),
+                Some('+') => negate = false,
+                Some('-') => negate = true,
+                Some(c) => return Err(format!("Unexpected '{}' in '{}'", c, expr)),
+            }
+            pos += 1;
+        }
+    }
+
+    /// 項を1つ評価し、値と次の位置を返す
+    fn eval_term(&self, chars: &[char], pos: usize, pc: u16) -> Result<(Option<u16>, usize), String> {
+        let pos = skip_spaces(chars, pos);
+        let text: String = chars.iter().collect();
+        let parse_digits = |start: usize, radix: u32| -> Result<(Option<u16>, usize), String> {
+            let end = (start..chars.len()).find(|&i| !chars[i].is_digit(radix)).unwrap_or(chars.len());
+            let digits: String = chars[start..end].iter().collect();
+            u16::from_str_radix(&digits, radix)
+                .map(|v| (Some(v), end))
+                .map_err(|_| format!("Invalid number in '{}'", text))
+        };
+
+        match chars.get(pos) {
+            Some('$') => parse_digits(pos + 1, 16),
+            Some('%') => parse_digits(pos + 1, 2),
+            Some(c) if c.is_ascii_digit() => parse_digits(pos, 10),
+            Some('*') => Ok((Some(pc), pos + 1)),
+            Some(&quote @ ('\'' | '"')) => {
+                let ch = *chars.get(pos + 1).ok_or_else(|| format!("Invalid character in '{}'", text))?;
+                if !ch.is_ascii() {
+                    return Err(format!("Invalid character in '{}'", text));
+                }
+                // 'A'は通常のASCII、"A"は上位ビット付き（Apple IIの画面コード）
+                let value = if quote == '"' { ch as u16 | 0x80 } else { ch as u16 };
+                let end = if chars.get(pos + 2) == Some(&quote) { pos + 3 } else { pos + 2 };
+                Ok((Some(value), end))
+            }
+            Some(c) if is_label_start(*c) => {
+                let end = (pos..chars.len()).find(|&i| !is_label_char(chars[i])).unwrap_or(chars.len());
+                let name: String = chars[pos..end].iter().collect();
+                Ok((self.label(&name), end))
+            }
+            _ => Err(format!("Invalid expression '{}'", text)),
+        }
+    }
+}
+
+/// 行を解析してラベルと文に分ける
+fn parse_line(line: &str) -> Result<(Option<&str>, Statement<'_>), String> {
+    let line = strip_comment(line).trim();
+    if line.is_empty() {
+        return Ok((None, Statement::Empty));
+    }
+
+    // LABEL: ...
+    let (label, rest) = match line.find(':') {
+        Some(i) if line[..i].chars().all(is_label_char) && line[..i].starts_with(is_label_start) => {
+            (Some(&line[..i]), line[i + 1..].trim())
+        }
+        _ => (None, line),
+    };
+
+    let (word, operand) = split_word(rest);
+    if word.is_empty() {
+        return Ok((label, Statement::Empty));
+    }
+
+    // LABEL = expr / LABEL EQU expr
+    if label.is_none() && word.starts_with(is_label_start) {
+        let (second, value) = split_word(operand);
+        if second == "=" || second.eq_ignore_ascii_case("EQU") {
+            return Ok((Some(word), Statement::Equate(value)));
+        }
+        if let Some(value) = operand.strip_prefix('=') {
+            return Ok((Some(word), Statement::Equate(value.trim())));
+        }
+    }
+
+    let upper = word.to_ascii_uppercase();
+    let statement = match upper.as_str() {
+        ".BYTE" | ".DB" | "DFB" | "DB" => Statement::Bytes(operand),
+        ".WORD" | ".DW" | "DW" | "DA" => Statement::Words(operand),
+        "EQU" | "=" => return Err("Missing label for equate".to_string()),
+        _ => Statement::Instruction(upper, operand),
+    };
+    Ok((label, statement))
+}
+
+/// オペランドの形を判定
+fn parse_operand(operand: &str) -> Operand<'_> {
+    let s = operand.trim();
+    if s.is_empty() {
+        return Operand::None;
+    }
+    if s.eq_ignore_ascii_case("A") {
+        return Operand::Accumulator;
+    }
+    if let Some(expr) = s.strip_prefix('#') {
+        return Operand::Immediate(expr.trim());
+    }
+    if let Some(inner) = s.strip_prefix('(') {
+        if let Some((body, index)) = inner.rsplit_once(')') {
+            let index = index.trim();
+            if index.is_empty() {
+                if let Some((expr, reg)) = body.rsplit_once(',') {
+                    if reg.trim().eq_ignore_ascii_case("X") {
+                        return Operand::IndirectX(expr.trim());
+                    }
+                }
+                return Operand::Indirect(body.trim());
+            }
+            if let Some(reg) = index.strip_prefix(',') {
+                if reg.trim().eq_ignore_ascii_case("Y") {
+                    return Operand::IndirectY(body.trim());
+                }
+            }
+        }
+    }
+    if let Some((expr, reg)) = s.rsplit_once(',') {
+        let reg = reg.trim();
+        if reg.eq_ignore_ascii_case("X") {
+            return Operand::IndexedX(expr.trim());
+        }
+        if reg.eq_ignore_ascii_case("Y") {
+            return Operand::IndexedY(expr.trim());
+        }
+        return Operand::Pair(expr.trim(), reg);
+    }
+    Operand::Direct(s)
+}
+
+/// RMBn/SMBn/BBRn/BBSnのベースオペコードとビット番号
+fn bit_mnemonic(mnemonic: &str) -> Option<(u8, u8)> {
+    if mnemonic.len() != 4 {
+        return None;
+    }
+    let bit = mnemonic[3..].parse::<u8>().ok().filter(|&b| b < 8)?;
+    let base = match &mnemonic[..3] {
+        "RMB" => 0x07,
+        "SMB" => 0x87,
+        "BBR" => 0x0F,
+        "BBS" => 0x8F,
+        _ => return None,
+    };
+    Some((base, bit))
+}
+
+/// ゼロページに収まればゼロページ形式を選ぶ
+fn zp_or_abs(value: Option<u16>, force_abs: bool, has_zp: bool, zp: AddressingMode, abs: AddressingMode) -> AddressingMode {
+    match value {
+        Some(v) if v <= 0xFF && has_zp && !force_abs => zp,
+        // 未解決のラベルは絶対アドレスとみなす
+        _ => abs,
+    }
+}
+
+/// 分岐先から相対オフセットを計算
+fn relative_offset(next_pc: u16, target: u16) -> Result<u8, String> {
+    let offset = target.wrapping_sub(next_pc) as i16;
+    if (-128..=127).contains(&offset) {
+        Ok(offset as u8)
+    } else {
+        Err(format!("Branch out of range (${:04X})", target))
+    }
+}
+
+/// 値が1バイトに収まるかチェック
+fn check_byte(value: u16, expr: &str) -> Result<u8, String> {
+    u8::try_from(value).map_err(|_| format!("Value out of range: '{}'", expr))
+}
+
+fn undefined(expr: &str) -> String {
+    format!("Undefined label in '{}'", expr.trim())
+}
+
+/// コメント（`;`以降）を除去（文字リテラル中の`;`は残す）
+fn strip_comment(line: &str) -> &str {
+    let mut quote = None;
+    for (i, c) in line.char_indices() {
+        match (quote, c) {
+            (None, ';') => return &line[..i],
+            (None, '\'' | '"') => quote = Some(c),
+            (Some(q), c) if c == q => quote = None,
+            _ => {}
+        }
+    }
+    line
+}
+
+/// 先頭の単語と残りに分割
+fn split_word(s: &str) -> (&str, &str) {
+    let s = s.trim();
+    match s.find(char::is_whitespace) {
+        Some(i) => (&s[..i], s[i..].trim()),
+        None => (s, ""),
+    }
+}
+
+/// カンマ区切りのリストを分割
+fn split_list(list: &str) -> impl Iterator<Item = &str> {
+    list.split(',').map(str::trim).filter(|s| !s.is_empty())
+}
+
+fn skip_spaces(chars: &[char], mut pos: usize) -> usize {
+    while chars.get(pos).is_some_and(|c| c.is_whitespace()) {
+        pos += 1;
+    }
+    pos
+}
+
+fn is_label_start(c: char) -> bool {
+    c.is_ascii_alphabetic() || c == '_' || c == '.'
+}
+
+fn is_label_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_' || c == '.'
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_addressing_modes() {
+        let mut asm = Assembler::new(CpuType::Cpu6502);
+        assert_eq!(asm.assemble_line("LDA #$12", 0x300).unwrap(), vec![0xA9, 0x12]);
+        assert_eq!(asm.assemble_line("LDA $12", 0x300).unwrap(), vec![0xA5, 0x12]);
+        assert_eq!(asm.assemble_line("LDA $1234,X", 0x300).unwrap(), vec![0xBD, 0x34, 0x12]);
+        assert_eq!(asm.assemble_line("LDX $12,Y", 0x300).unwrap(), vec![0xB6, 0x12]);
+        assert_eq!(asm.assemble_line("STA ($12,X)", 0x300).unwrap(), vec![0x81, 0x12]);
+        assert_eq!(asm.assemble_line("STA ($12),Y", 0x300).unwrap(), vec![0x91, 0x12]);
+        assert_eq!(asm.assemble_line("JMP ($03F0)", 0x300).unwrap(), vec![0x6C, 0xF0, 0x03]);
+        assert_eq!(asm.assemble_line("ASL", 0x300).unwrap(), vec![0x0A]);
+        assert_eq!(asm.assemble_line("ROR A", 0x300).unwrap(), vec![0x6A]);
+        assert_eq!(asm.assemble_line("JSR $FDED", 0x300).unwrap(), vec![0x20, 0xED, 0xFD]);
+        assert_eq!(asm.assemble_line("BNE $0300", 0x302).unwrap(), vec![0xD0, 0xFC]);
+        assert!(asm.assemble_line("STZ $12", 0x300).is_err());
+        assert!(asm.assemble_line("BNE $0400", 0x300).is_err());
+    }
+
+    #[test]
+    fn test_65c02_and_labels() {
+        let mut asm = Assembler::new(CpuType::Cpu65C02);
+        assert_eq!(asm.assemble_line("LDA ($12)", 0x300).unwrap(), vec![0xB2, 0x12]);
+        assert_eq!(asm.assemble_line("JMP ($1234,X)", 0x300).unwrap(), vec![0x7C, 0x34, 0x12]);
+        assert_eq!(asm.assemble_line("INC A", 0x300).unwrap(), vec![0x1A]);
+        assert_eq!(asm.assemble_line("SMB3 $20", 0x300).unwrap(), vec![0xB7, 0x20]);
+
+        let code = asm
+            .assemble("COUT = $FDED\nLOOP: LDA MSG,X\n BEQ DONE\n JSR COUT\n INX\n BRA LOOP\nDONE: RTS\nMSG: DFB \"H\",0", 0x300)
+            .unwrap();
+        assert_eq!(
+            code,
+            vec![0xBD, 0x0C, 0x03, 0xF0, 0x06, 0x20, 0xED, 0xFD, 0xE8, 0x80, 0xF5, 0x60, 0xC8, 0x00]
+        );
+        assert_eq!(asm.label("done"), Some(0x030B));
+        assert_eq!(asm.assemble_line("BBR0 $12,LOOP", 0x30E).unwrap(), vec![0x0F, 0x12, 0xEF]);
+    }
+
+    #[test]
+    fn test_reassemble_at_new_origin() {
+        let mut asm = Assembler::new(CpuType::Cpu6502);
+        let source = " LDA DATA\n BNE DONE\n NOP\nDONE: RTS\nDATA: DFB 1";
+        // 1回目でDATAはゼロページの$0007になる（前方参照なので絶対アドレス）
+        assert_eq!(asm.assemble(source, 0x0000).unwrap(), vec![0xAD, 0x07, 0x00, 0xD0, 0x01, 0xEA, 0x60, 0x01]);
+        // 別のoriginでは前回のDATAの値でパス1の長さを決めない
+        assert_eq!(asm.assemble(source, 0x4000).unwrap(), vec![0xAD, 0x07, 0x40, 0xD0, 0x01, 0xEA, 0x60, 0x01]);
+        assert_eq!(asm.label("DATA"), Some(0x4007));
+    }
+}
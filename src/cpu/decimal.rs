@@ -0,0 +1,305 @@
+//! Bruce Clark の10進モードテスト（"Decimal Mode" 付録B）
+//!
+//! N1/N2の全組み合わせ（不正なBCD値を含む）と両方のキャリーについて、
+//! ADC/SBCの実際の結果と、バイナリ演算で求めた予測値を比較する。
+//! 終了時 ERROR が0なら成功。`JSR A6502`/`JSR S6502` は65C02用に置き換えて使う。
+
+use super::assembler::Assembler;
+use super::{flags, Cpu, CpuType, MemoryBus};
+
+/// テストプログラム（6502用）
+pub const SOURCE: &str = "
+ERROR = $00
+N1 = $01
+N2 = $02
+N1L = $03
+N1H = $04
+N2L = $05
+N2H = $06          ; 2バイト
+DA = $08
+DNVZC = $09
+HA = $0A
+HNVZC = $0B
+AR = $0C
+NF = $0D
+VF = $0E
+ZF = $0F
+CF = $10
+
+START:  JSR TEST
+HALT:   JMP HALT
+
+TEST:   LDY #1
+        STY ERROR
+        LDA #0
+        STA N1
+        STA N2
+LOOP1:  LDA N2
+        AND #$0F
+        STA N2L
+        LDA N2
+        AND #$F0
+        STA N2H
+        ORA #$0F
+        STA N2H+1
+LOOP2:  LDA N1
+        AND #$0F
+        STA N1L
+        LDA N1
+        AND #$F0
+        STA N1H
+        JSR ADD
+        JSR A6502
+        JSR COMPARE
+        BNE DONE
+        JSR SUB
+        JSR S6502
+        JSR COMPARE
+        BNE DONE
+        INC N1
+        BNE LOOP2
+        INC N2
+        BNE LOOP1
+        DEY
+        BPL LOOP1
+        LDA #0
+        STA ERROR
+DONE:   RTS
+
+ADD:    SED
+        CPY #1
+        LDA N1
+        ADC N2
+        STA DA
+        PHP
+        PLA
+        STA DNVZC
+        CLD
+        CPY #1
+        LDA N1
+        ADC N2
+        STA HA
+        PHP
+        PLA
+        STA HNVZC
+        CPY #1
+        LDA N1L
+        ADC N2L
+        CMP #$0A
+        LDX #0
+        BCC A1
+        INX
+        ADC #5
+        AND #$0F
+        SEC
+A1:     ORA N1H
+        ADC N2H,X
+        PHP
+        BCS A2
+        CMP #$A0
+        BCC A3
+A2:     ADC #$5F
+        SEC
+A3:     STA AR
+        PHP
+        PLA
+        STA CF
+        PLA
+        STA VF
+        RTS
+
+SUB:    SED
+        CPY #1
+        LDA N1
+        SBC N2
+        STA DA
+        PHP
+        PLA
+        STA DNVZC
+        CLD
+        CPY #1
+        LDA N1
+        SBC N2
+        STA HA
+        PHP
+        PLA
+        STA HNVZC
+        RTS
+
+SUB1:   CPY #1
+        LDA N1L
+        SBC N2L
+        LDX #0
+        BCS S11
+        INX
+        SBC #5
+        AND #$0F
+        CLC
+S11:    ORA N1H
+        SBC N2H,X
+        BCS S12
+        SBC #$5F
+S12:    STA AR
+        RTS
+
+SUB2:   CPY #1
+        LDA N1L
+        SBC N2L
+        LDX #0
+        BCS S21
+        INX
+        AND #$0F
+        CLC
+S21:    ORA N1H
+        SBC N2H,X
+        BCS S22
+        SBC #$5F
+S22:    CPX #0
+        BEQ S23
+        SBC #6
+S23:    STA AR
+        RTS
+
+COMPARE: LDA DA
+        CMP AR
+        BNE C1
+        LDA DNVZC
+        EOR NF
+        AND #$80
+        BNE C1
+        LDA DNVZC
+        EOR VF
+        AND #$40
+        BNE C1
+        LDA DNVZC
+        EOR ZF
+        AND #2
+        BNE C1
+        LDA DNVZC
+        EOR CF
+        AND #1
+C1:     RTS
+
+A6502:  LDA VF
+        STA NF
+        LDA HNVZC
+        STA ZF
+        RTS
+
+S6502:  JSR SUB1
+        LDA HNVZC
+        STA NF
+        STA VF
+        STA ZF
+        STA CF
+        RTS
+
+A65C02: LDA AR
+        PHP
+        PLA
+        STA NF
+        STA ZF
+        RTS
+
+S65C02: JSR SUB2
+        LDA AR
+        PHP
+        PLA
+        STA NF
+        STA ZF
+        LDA HNVZC
+        STA VF
+        STA CF
+        RTS
+";
+
+/// 実行するサイクル数の上限
+const MAX_CYCLES: u64 = 200_000_000;
+
+/// テストの結果
+#[derive(Debug, Clone, Default)]
+pub struct DecimalResult {
+    pub cycles: u64,
+    /// HALTに到達したか
+    pub completed: bool,
+    /// 終了時のPC
+    pub pc: u16,
+    /// 失敗したケースの説明（成功ならNone）
+    pub failure: Option<String>,
+}
+
+impl DecimalResult {
+    pub fn passed(&self) -> bool {
+        self.completed && self.failure.is_none()
+    }
+}
+
+/// 64KBのフラットメモリ
+struct FlatMemory {
+    ram: Vec<u8>,
+}
+
+impl MemoryBus for FlatMemory {
+    fn read(&mut self, address: u16) -> u8 {
+        self.ram[address as usize]
+    }
+
+    fn write(&mut self, address: u16, value: u8) {
+        self.ram[address as usize] = value;
+    }
+}
+
+/// CPU種別に合わせたテストプログラム
+pub fn source(cpu_type: CpuType) -> String {
+    match cpu_type {
+        CpuType::Cpu6502 => SOURCE.to_string(),
+        CpuType::Cpu65C02 => SOURCE
+            .replace("JSR A6502", "JSR A65C02")
+            .replace("JSR S6502", "JSR S65C02"),
+    }
+}
+
+/// テストを実行
+pub fn run(cpu_type: CpuType) -> Result<DecimalResult, String> {
+    let mut assembler = Assembler::new(cpu_type);
+    let code = assembler.assemble(&source(cpu_type), 0x0200)?;
+    let halt = assembler.label("HALT").ok_or("HALT label not found")?;
+
+    let mut memory = FlatMemory { ram: vec![0; 65536] };
+    memory.ram[0x0200..0x0200 + code.len()].copy_from_slice(&code);
+    memory.ram[0xFFFC] = 0x00;
+    memory.ram[0xFFFD] = 0x02;
+    let mut cpu = Cpu::new(cpu_type);
+    cpu.reset(&mut memory);
+
+    let mut cycles: u64 = 0;
+    while cpu.regs.pc != halt && cycles < MAX_CYCLES {
+        cycles += cpu.step(&mut memory) as u64;
+    }
+    let ram = &memory.ram;
+    let completed = cpu.regs.pc == halt;
+    let failure = (completed && ram[0x00] != 0).then(|| format!(
+        "N1=${:02X} N2=${:02X} C={}: actual A=${:02X} P=${:02X}, predicted A=${:02X} N={:02X} V={:02X} Z={:02X} C={:02X}",
+        ram[0x01], ram[0x02], cpu.regs.y, ram[0x08], ram[0x09],
+        ram[0x0C], ram[0x0D], ram[0x0E], ram[0x0F], ram[0x10]
+    ));
+    Ok(DecimalResult { cycles, completed, pc: cpu.regs.pc, failure })
+}
+
+/// 10進モードのADC/SBC（即値）のサイクル数（65C02は1サイクル多い）
+pub fn expected_cycles(cpu_type: CpuType) -> u32 {
+    match cpu_type {
+        CpuType::Cpu6502 => 2,
+        CpuType::Cpu65C02 => 3,
+    }
+}
+
+/// 10進モードで1命令（`opcode #$01`）を実行したサイクル数
+pub fn measure_cycles(cpu_type: CpuType, opcode: u8) -> u32 {
+    let mut memory = FlatMemory { ram: vec![0; 65536] };
+    memory.ram[0x0300..0x0302].copy_from_slice(&[opcode, 0x01]);
+    let mut cpu = Cpu::new(cpu_type);
+    cpu.regs.pc = 0x0300;
+    cpu.regs.status |= flags::DECIMAL;
+    cpu.step(&mut memory)
+}
@@ -0,0 +1,104 @@
+//! 逆アセンブラ
+//!
+//! デバッガのコンソールで使う。オペコード表はラインアセンブラと共通で、
+//! 表にないオペコード（NMOSの非公式命令など）は `???` と表示する。
+//! シンボルテーブルを渡すと、オペランドのアドレスを名前で表示する（即値はそのまま）。
+
+use super::addressing::AddressingMode;
+use super::assembler::OPCODES;
+use super::CpuType;
+use crate::symbols::SymbolTable;
+
+use AddressingMode as M;
+
+/// 逆アセンブルした1命令
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Disassembly {
+    /// 命令の長さ（1-3バイト）
+    pub len: u8,
+    /// ニーモニックとオペランド（例: "LDA #$10"）
+    pub text: String,
+}
+
+/// `address` の1命令を逆アセンブル（`read` でメモリを読む）
+pub fn disassemble(cpu_type: CpuType, address: u16, read: impl Fn(u16) -> u8) -> Disassembly {
+    disassemble_with_symbols(cpu_type, address, read, &SymbolTable::new())
+}
+
+/// `address` の1命令を逆アセンブルし、オペランドのアドレスをシンボル名で表示する
+pub fn disassemble_with_symbols(cpu_type: CpuType, address: u16, read: impl Fn(u16) -> u8, symbols: &SymbolTable) -> Disassembly {
+    // ゼロページは2桁、それ以外は4桁
+    let zp = |value: u8| symbols.name(value as u16).map_or_else(|| format!("${:02X}", value), str::to_string);
+    let abs = |value: u16| symbols.name(value).map_or_else(|| format!("${:04X}", value), str::to_string);
+    let opcode = read(address);
+    let b1 = read(address.wrapping_add(1));
+    let b2 = read(address.wrapping_add(2));
+    let word = u16::from_le_bytes([b1, b2]);
+    let cmos = cpu_type == CpuType::Cpu65C02;
+
+    // RMBn/SMBn（$x7）とBBRn/BBSn（$xF）は65C02のビット命令
+    if cmos && opcode & 0x07 == 0x07 {
+        let bit = (opcode >> 4) & 0x07;
+        let set = opcode & 0x80 != 0;
+        return if opcode & 0x0F == 0x07 {
+            Disassembly { len: 2, text: format!("{}{} {}", if set { "SMB" } else { "RMB" }, bit, zp(b1)) }
+        } else {
+            let target = address.wrapping_add(3).wrapping_add(b2 as i8 as u16);
+            Disassembly { len: 3, text: format!("{}{} {},{}", if set { "BBS" } else { "BBR" }, bit, zp(b1), abs(target)) }
+        };
+    }
+
+    // 同じオペコードの別名（INA / INC A）は後の表記を使う
+    let Some(&(mnemonic, mode, _, _)) = OPCODES
+        .iter()
+        .rev()
+        .find(|&&(_, _, op, cmos_only)| op == opcode && (cmos || !cmos_only))
+    else {
+        return Disassembly { len: 1, text: "???".to_string() };
+    };
+
+    let (len, operand) = match mode {
+        M::Implied => (1, String::new()),
+        M::Accumulator => (1, " A".to_string()),
+        M::Immediate => (2, format!(" #${:02X}", b1)),
+        M::ZeroPage => (2, format!(" {}", zp(b1))),
+        M::ZeroPageX => (2, format!(" {},X", zp(b1))),
+        M::ZeroPageY => (2, format!(" {},Y", zp(b1))),
+        M::IndirectX => (2, format!(" ({},X)", zp(b1))),
+        M::IndirectY => (2, format!(" ({}),Y", zp(b1))),
+        M::IndirectZeroPage => (2, format!(" ({})", zp(b1))),
+        M::Relative => (2, format!(" {}", abs(address.wrapping_add(2).wrapping_add(b1 as i8 as u16)))),
+        M::Absolute => (3, format!(" {}", abs(word))),
+        M::AbsoluteX => (3, format!(" {},X", abs(word))),
+        M::AbsoluteY => (3, format!(" {},Y", abs(word))),
+        M::Indirect => (3, format!(" ({})", abs(word))),
+        M::AbsoluteIndirectX => (3, format!(" ({},X)", abs(word))),
+        M::ZeroPageRelative => (3, format!(" {},{}", zp(b1), abs(address.wrapping_add(3).wrapping_add(b2 as i8 as u16)))),
+    };
+    Disassembly { len, text: format!("{}{}", mnemonic, operand) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn disassembles_addressing_modes() {
+        let mem = [0xA9, 0x10, 0x20, 0xED, 0xFD, 0xD0, 0xFB, 0x1A, 0x8F, 0x12, 0xFD, 0xB1, 0x28];
+        let dis = |cpu, addr: u16| disassemble(cpu, 0x0300 + addr, |a| mem.get((a - 0x0300) as usize).copied().unwrap_or(0));
+
+        assert_eq!(dis(CpuType::Cpu6502, 0).text, "LDA #$10");
+        assert_eq!(dis(CpuType::Cpu6502, 2), Disassembly { len: 3, text: "JSR $FDED".to_string() });
+        assert_eq!(dis(CpuType::Cpu6502, 5).text, "BNE $0302");
+        assert_eq!(dis(CpuType::Cpu6502, 7), Disassembly { len: 1, text: "???".to_string() });
+        assert_eq!(dis(CpuType::Cpu65C02, 7).text, "INC A");
+        assert_eq!(dis(CpuType::Cpu65C02, 8).text, "BBS0 $12,$0308");
+        assert_eq!(dis(CpuType::Cpu6502, 11).text, "LDA ($28),Y");
+
+        let symbols = SymbolTable::builtin();
+        let sym = |addr: u16| disassemble_with_symbols(CpuType::Cpu6502, 0x0300 + addr, |a| mem.get((a - 0x0300) as usize).copied().unwrap_or(0), &symbols).text;
+        assert_eq!(sym(2), "JSR COUT");
+        assert_eq!(sym(11), "LDA (BASL),Y");
+        assert_eq!(sym(0), "LDA #$10");
+    }
+}
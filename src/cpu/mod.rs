@@ -6,6 +6,10 @@
 mod opcodes;
 mod opcodes2;
 pub mod addressing;
+pub mod assembler;
+pub mod decimal;
+pub mod disassembler;
+pub mod single_step;
 
 /// CPUのステータスレジスタのフラグビット
 pub mod flags {
@@ -89,6 +93,17 @@ pub trait MemoryBus {
     fn write(&mut self, address: u16, value: u8);
 }
 
+/// 直前の `Cpu::step` で実行したもの（デバッガのコールスタック用）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StepKind {
+    /// 命令（オペコード）
+    Opcode(u8),
+    /// IRQを受け付けた
+    Irq,
+    /// NMIを受け付けた
+    Nmi,
+}
+
 /// 6502 CPUエミュレータ
 #[derive(Debug, Clone)]
 pub struct Cpu {
@@ -100,7 +115,7 @@ pub struct Cpu {
     pub total_cycles: u64,
     /// 現在の命令で消費したサイクル
     pub cycles: u32,
-    /// IRQ（割り込み要求）ライン
+    /// IRQ（割り込み要求）ライン（Apple2では割り込みコントローラのORを毎命令前に設定）
     pub irq_pending: bool,
     /// NMI（ノンマスカブル割り込み）ライン
     pub nmi_pending: bool,
@@ -108,6 +123,10 @@ pub struct Cpu {
     nmi_edge_detected: bool,
     /// 前回のNMIライン状態
     prev_nmi: bool,
+    /// CLI/SEI/PLP直後の割り込みポーリングで使うIフラグ（変更前の値）
+    pub irq_disable_latched: Option<bool>,
+    /// 直前のstepで実行したもの
+    pub last_step: StepKind,
 }
 
 impl Default for Cpu {
@@ -128,6 +147,8 @@ impl Cpu {
             nmi_pending: false,
             nmi_edge_detected: false,
             prev_nmi: false,
+            irq_disable_latched: None,
+            last_step: StepKind::Opcode(0xEA),
         }
     }
 
@@ -140,6 +161,7 @@ impl Cpu {
         self.regs.pc = (high << 8) | low;
         self.cycles = 7; // リセットには7サイクル必要
         self.total_cycles += 7;
+        self.irq_disable_latched = None;
     }
 
     /// NMI（ノンマスカブル割り込み）を処理
@@ -161,9 +183,6 @@ impl Cpu {
 
     /// IRQ（割り込み要求）を処理
     fn handle_irq<M: MemoryBus>(&mut self, memory: &mut M) {
-        if self.regs.get_flag(flags::IRQ_DISABLE) {
-            return;
-        }
         // PCをスタックにプッシュ
         self.push_word(memory, self.regs.pc);
         // ステータスレジスタをプッシュ（Bフラグはクリア）
@@ -182,6 +201,12 @@ impl Cpu {
     pub fn step<M: MemoryBus>(&mut self, memory: &mut M) -> u32 {
         self.cycles = 0;
 
+        // 割り込みのポーリングは前の命令の最終サイクルで行われるため、
+        // CLI/SEI/PLPによるIフラグの変化は次の命令の後から有効になる
+        let irq_disabled = self.irq_disable_latched
+            .take()
+            .unwrap_or(self.regs.get_flag(flags::IRQ_DISABLE));
+
         // NMIのエッジ検出（立ち下がりで発生）
         if self.nmi_pending && !self.prev_nmi {
             self.nmi_edge_detected = true;
@@ -190,13 +215,15 @@ impl Cpu {
 
         // NMI処理（最優先）
         if self.nmi_edge_detected {
+            self.last_step = StepKind::Nmi;
             self.handle_nmi(memory);
             self.total_cycles += self.cycles as u64;
             return self.cycles;
         }
 
-        // IRQ処理
-        if self.irq_pending && !self.regs.get_flag(flags::IRQ_DISABLE) {
+        // IRQ処理（irq_pendingはIRQラインの状態）
+        if self.irq_pending && !irq_disabled {
+            self.last_step = StepKind::Irq;
             self.handle_irq(memory);
             self.total_cycles += self.cycles as u64;
             return self.cycles;
@@ -204,9 +231,15 @@ impl Cpu {
 
         // 命令をフェッチ
         let opcode = self.fetch_byte(memory);
+        self.last_step = StepKind::Opcode(opcode);
         
         // 命令を実行
+        let i_before = self.regs.get_flag(flags::IRQ_DISABLE);
         self.execute_opcode(memory, opcode);
+        if matches!(opcode, 0x58 | 0x78 | 0x28) {
+            // CLI / SEI / PLP
+            self.irq_disable_latched = Some(i_before);
+        }
 
         self.total_cycles += self.cycles as u64;
         self.cycles
@@ -355,27 +355,36 @@ impl Cpu {
         let carry = if self.regs.get_flag(flags::CARRY) { 1u16 } else { 0u16 };
         
         if self.regs.get_flag(flags::DECIMAL) {
-            // BCDモード
-            let mut low = (self.regs.a & 0x0F) as u16 + (value & 0x0F) as u16 + carry;
-            let mut high = (self.regs.a >> 4) as u16 + (value >> 4) as u16;
-            
-            if low > 9 {
-                low -= 10;
-                high += 1;
+            // BCDモード（Bruce Clark "Decimal Mode" 付録Aの手順に従う）
+            // 不正なBCD値（$A-$F）を含む入力でも実機と同じ結果になる
+            let a = self.regs.a as u16;
+            let b = value as u16;
+            let mut low = (a & 0x0F) + (b & 0x0F) + carry;
+            if low >= 0x0A {
+                low = ((low + 0x06) & 0x0F) + 0x10;
             }
+            let mut result = (a & 0xF0) + (b & 0xF0) + low;
             
-            let result = if high > 9 {
-                self.regs.set_flag(flags::CARRY, true);
-                (((high - 10) << 4) | (low & 0x0F)) as u8
-            } else {
-                self.regs.set_flag(flags::CARRY, false);
-                ((high << 4) | (low & 0x0F)) as u8
-            };
+            // N/Vは上位桁の補正前の値から決まる
+            let n = (result & 0x80) != 0;
+            let v = ((a ^ result) & (b ^ result) & 0x80) != 0;
+            if result >= 0xA0 {
+                result += 0x60;
+            }
+            let result8 = result as u8;
             
+            self.regs.set_flag(flags::CARRY, result >= 0x100);
+            self.regs.set_flag(flags::OVERFLOW, v);
             if self.cpu_type == CpuType::Cpu65C02 {
-                self.regs.update_zero_negative_flags(result);
+                // 65C02: N/Zは補正後の値から決まり、1サイクル余分にかかる
+                self.regs.update_zero_negative_flags(result8);
+                self.cycles += 1;
+            } else {
+                // NMOS 6502: Zはバイナリ加算の結果から決まる
+                self.regs.set_flag(flags::NEGATIVE, n);
+                self.regs.set_flag(flags::ZERO, (a + b + carry) as u8 == 0);
             }
-            self.regs.a = result;
+            self.regs.a = result8;
         } else {
             let result = self.regs.a as u16 + value as u16 + carry;
             let result8 = result as u8;
@@ -455,27 +464,47 @@ impl Cpu {
     // SBC - Subtract with Carry
     //--------------------------------------------------
     fn do_sbc(&mut self, value: u8) {
-        // SBCはADCの補数として実装
         if self.regs.get_flag(flags::DECIMAL) {
-            let carry = if self.regs.get_flag(flags::CARRY) { 0i16 } else { 1i16 };
-            let mut low = (self.regs.a & 0x0F) as i16 - (value & 0x0F) as i16 - carry;
-            let mut high = (self.regs.a >> 4) as i16 - (value >> 4) as i16;
-            
-            if low < 0 {
-                low += 10;
-                high -= 1;
-            }
+            // BCDモード（Bruce Clark "Decimal Mode" 付録Aの手順に従う）
+            let borrow = if self.regs.get_flag(flags::CARRY) { 0i16 } else { 1i16 };
+            let a = self.regs.a as i16;
+            let b = value as i16;
+            let low = (a & 0x0F) - (b & 0x0F) - borrow;
+            let binary = a - b - borrow;
             
-            let result = if high < 0 {
-                self.regs.set_flag(flags::CARRY, false);
-                (((high + 10) << 4) | (low & 0x0F)) as u8
+            let result = if self.cpu_type == CpuType::Cpu65C02 {
+                // 65C02: 全体を引いてから補正
+                let mut result = binary;
+                if result < 0 {
+                    result -= 0x60;
+                }
+                if low < 0 {
+                    result -= 0x06;
+                }
+                result as u8
             } else {
-                self.regs.set_flag(flags::CARRY, true);
-                ((high << 4) | (low & 0x0F)) as u8
+                // NMOS 6502: 下位桁と上位桁をそれぞれ補正
+                let low = if low < 0 { ((low - 0x06) & 0x0F) - 0x10 } else { low };
+                let mut result = (a & 0xF0) - (b & 0xF0) + low;
+                if result < 0 {
+                    result -= 0x60;
+                }
+                result as u8
             };
             
+            // C/Vはバイナリ減算と同じ（NMOSではN/Zも同じ）
+            self.regs.set_flag(flags::CARRY, binary >= 0);
+            let binary = binary as u8;
+            self.regs.set_flag(
+                flags::OVERFLOW,
+                ((self.regs.a ^ value) & (self.regs.a ^ binary) & 0x80) != 0
+            );
             if self.cpu_type == CpuType::Cpu65C02 {
+                // 65C02: N/Zは補正後の値から決まり、1サイクル余分にかかる
                 self.regs.update_zero_negative_flags(result);
+                self.cycles += 1;
+            } else {
+                self.regs.update_zero_negative_flags(binary);
             }
             self.regs.a = result;
         } else {
@@ -0,0 +1,288 @@
+//! Tom Harte SingleStepTests ハーネス
+//!
+//! https://github.com/SingleStepTests/65x02 のJSONテスト（1オペコード1ファイル）を読み込み、
+//! 記録用メモリバス上で1命令ずつ実行して、レジスタ・RAM・サイクル数・バスアクセスを検証する。
+//!
+//! ディレクトリ構成（既定）:
+//! - `tests/65x02/6502/v1/00.json` ～ `ff.json`
+//! - `tests/65x02/wdc65c02/v1/00.json` ～ `ff.json`
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use super::{Cpu, CpuType, MemoryBus};
+
+/// テストデータの既定ルート（環境変数 `A2RS_SST_DIR` で上書き可能）
+pub const DEFAULT_ROOT: &str = "tests/65x02";
+
+/// テストデータのルートを指定する環境変数
+pub const ROOT_ENV: &str = "A2RS_SST_DIR";
+
+/// テストデータのルート（`A2RS_SST_DIR` があればそれ、なければ `default`）
+pub fn resolve_root(default: PathBuf) -> PathBuf {
+    std::env::var(ROOT_ENV).map(PathBuf::from).unwrap_or(default)
+}
+
+/// CPUの状態（初期状態/期待される最終状態）
+#[derive(Debug, Clone, Deserialize)]
+pub struct TestState {
+    pub pc: u16,
+    pub s: u8,
+    pub a: u8,
+    pub x: u8,
+    pub y: u8,
+    pub p: u8,
+    /// (アドレス, 値)
+    pub ram: Vec<(u16, u8)>,
+}
+
+/// 1件のテストケース
+#[derive(Debug, Clone, Deserialize)]
+pub struct TestCase {
+    pub name: String,
+    pub initial: TestState,
+    #[serde(rename = "final")]
+    pub expected: TestState,
+    /// サイクルごとのバスアクセス (アドレス, 値, "read"/"write")
+    pub cycles: Vec<(u16, u8, String)>,
+}
+
+/// バスアクセスの記録
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BusAccess {
+    pub address: u16,
+    pub value: u8,
+    pub write: bool,
+}
+
+/// アクセスを記録するフラットな64KBメモリ
+pub struct RecordingBus {
+    pub ram: Box<[u8; 65536]>,
+    pub log: Vec<BusAccess>,
+}
+
+impl Default for RecordingBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RecordingBus {
+    pub fn new() -> Self {
+        RecordingBus {
+            ram: Box::new([0; 65536]),
+            log: Vec::with_capacity(16),
+        }
+    }
+}
+
+impl MemoryBus for RecordingBus {
+    fn read(&mut self, address: u16) -> u8 {
+        let value = self.ram[address as usize];
+        self.log.push(BusAccess { address, value, write: false });
+        value
+    }
+
+    fn write(&mut self, address: u16, value: u8) {
+        self.ram[address as usize] = value;
+        self.log.push(BusAccess { address, value, write: true });
+    }
+}
+
+/// 検証オプション
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CheckOptions {
+    /// サイクルごとのバスアクセスも比較する
+    pub check_bus: bool,
+}
+
+/// 1オペコード分の結果
+#[derive(Debug, Clone, Default)]
+pub struct OpcodeResult {
+    pub opcode: u8,
+    pub total: usize,
+    pub passed: usize,
+    /// レジスタまたはRAMの不一致数
+    pub state_failures: usize,
+    /// サイクル数の不一致数
+    pub cycle_failures: usize,
+    /// バスアクセスの不一致数（`check_bus`時のみ）
+    pub bus_failures: usize,
+    /// 最初の失敗の説明
+    pub first_failure: Option<String>,
+}
+
+impl OpcodeResult {
+    pub fn all_passed(&self) -> bool {
+        self.passed == self.total
+    }
+}
+
+/// CPU種別に対応するテストディレクトリ
+pub fn test_dir(root: &Path, cpu_type: CpuType) -> PathBuf {
+    match cpu_type {
+        CpuType::Cpu6502 => root.join("6502").join("v1"),
+        CpuType::Cpu65C02 => root.join("wdc65c02").join("v1"),
+    }
+}
+
+/// 1件のテストを実行（失敗時は理由を返す）
+///
+/// 戻り値: (状態一致, サイクル数一致, バス一致, 説明)
+fn run_case(cpu: &mut Cpu, bus: &mut RecordingBus, case: &TestCase, options: CheckOptions) -> (bool, bool, bool, Option<String>) {
+    let init = &case.initial;
+    for &(addr, value) in &init.ram {
+        bus.ram[addr as usize] = value;
+    }
+    bus.log.clear();
+
+    cpu.regs.pc = init.pc;
+    cpu.regs.sp = init.s;
+    cpu.regs.a = init.a;
+    cpu.regs.x = init.x;
+    cpu.regs.y = init.y;
+    cpu.regs.status = init.p;
+    cpu.irq_pending = false;
+    cpu.nmi_pending = false;
+
+    let cycles = cpu.step(bus) as usize;
+
+    let exp = &case.expected;
+    let mut reason = None;
+    let regs = &cpu.regs;
+    let regs_ok = regs.pc == exp.pc && regs.sp == exp.s && regs.a == exp.a
+        && regs.x == exp.x && regs.y == exp.y && regs.status == exp.p;
+    if !regs_ok {
+        reason = Some(format!(
+            "{}: regs PC={:04X} S={:02X} A={:02X} X={:02X} Y={:02X} P={:02X}, expected PC={:04X} S={:02X} A={:02X} X={:02X} Y={:02X} P={:02X}",
+            case.name, regs.pc, regs.sp, regs.a, regs.x, regs.y, regs.status,
+            exp.pc, exp.s, exp.a, exp.x, exp.y, exp.p
+        ));
+    }
+    let mut ram_ok = true;
+    for &(addr, value) in &exp.ram {
+        let actual = bus.ram[addr as usize];
+        if actual != value {
+            ram_ok = false;
+            reason.get_or_insert_with(|| format!("{}: ${:04X}={:02X}, expected {:02X}", case.name, addr, actual, value));
+            break;
+        }
+    }
+
+    let cycles_ok = cycles == case.cycles.len();
+    if !cycles_ok {
+        reason.get_or_insert_with(|| format!("{}: {} cycles, expected {}", case.name, cycles, case.cycles.len()));
+    }
+
+    let bus_ok = !options.check_bus || {
+        let expected = case.cycles.iter().map(|(addr, value, kind)| BusAccess {
+            address: *addr,
+            value: *value,
+            write: kind == "write",
+        });
+        let mismatch = expected.zip(bus.log.iter()).position(|(e, a)| e != *a);
+        match mismatch {
+            Some(i) => {
+                reason.get_or_insert_with(|| format!("{}: bus cycle {} differs: {:?}", case.name, i, bus.log[i]));
+                false
+            }
+            None if bus.log.len() != case.cycles.len() => {
+                reason.get_or_insert_with(|| format!("{}: {} bus accesses, expected {}", case.name, bus.log.len(), case.cycles.len()));
+                false
+            }
+            None => true,
+        }
+    };
+
+    // 次のテストのために触れたアドレスを0に戻す
+    for &(addr, _) in init.ram.iter().chain(exp.ram.iter()) {
+        bus.ram[addr as usize] = 0;
+    }
+    for access in &bus.log {
+        bus.ram[access.address as usize] = 0;
+    }
+
+    (regs_ok && ram_ok, cycles_ok, bus_ok, reason)
+}
+
+/// 1オペコード分のテストファイルを実行
+pub fn run_file(path: &Path, opcode: u8, cpu_type: CpuType, options: CheckOptions) -> Result<OpcodeResult, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    run_json(&text, opcode, cpu_type, options).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+/// 1オペコード分のテスト（JSON文字列）を実行
+pub fn run_json(text: &str, opcode: u8, cpu_type: CpuType, options: CheckOptions) -> Result<OpcodeResult, String> {
+    let cases: Vec<TestCase> = serde_json::from_str(text).map_err(|e| e.to_string())?;
+
+    let mut result = OpcodeResult {
+        opcode,
+        total: cases.len(),
+        ..Default::default()
+    };
+    let mut cpu = Cpu::new(cpu_type);
+    let mut bus = RecordingBus::new();
+    for case in &cases {
+        let (state_ok, cycles_ok, bus_ok, reason) = run_case(&mut cpu, &mut bus, case, options);
+        if !state_ok {
+            result.state_failures += 1;
+        }
+        if !cycles_ok {
+            result.cycle_failures += 1;
+        }
+        if !bus_ok {
+            result.bus_failures += 1;
+        }
+        if state_ok && cycles_ok && bus_ok {
+            result.passed += 1;
+        } else if result.first_failure.is_none() {
+            result.first_failure = reason;
+        }
+    }
+    Ok(result)
+}
+
+/// ディレクトリ内の指定オペコードのテストを実行
+///
+/// ファイルが存在しないオペコードは結果に含めない。
+pub fn run_dir(dir: &Path, cpu_type: CpuType, opcodes: &[u8], options: CheckOptions) -> Result<Vec<OpcodeResult>, String> {
+    if !dir.is_dir() {
+        return Err(format!("Test directory not found: {}", dir.display()));
+    }
+    let mut results = Vec::new();
+    for &opcode in opcodes {
+        let path = dir.join(format!("{:02x}.json", opcode));
+        if path.exists() {
+            results.push(run_file(&path, opcode, cpu_type, options)?);
+        }
+    }
+    Ok(results)
+}
+
+/// CPUが実装しているオペコードの一覧
+///
+/// NMOS 6502の未定義オペコードは実装していないため除外する。
+pub fn implemented_opcodes(cpu_type: CpuType) -> Vec<u8> {
+    (0..=255u8)
+        .filter(|&op| cpu_type == CpuType::Cpu65C02 || crate::profiler::opcode_name(op) != "???")
+        .collect()
+}
+
+/// 結果を表形式で出力
+pub fn print_report(results: &[OpcodeResult]) {
+    println!("  OP   total  passed  state  cycles    bus");
+    for r in results {
+        println!(
+            "  {:02X} {:>7} {:>7} {:>6} {:>7} {:>6}  {}",
+            r.opcode, r.total, r.passed, r.state_failures, r.cycle_failures, r.bus_failures,
+            if r.all_passed() { "OK" } else { "FAIL" }
+        );
+        if let Some(reason) = &r.first_failure {
+            println!("       first failure: {}", reason);
+        }
+    }
+    let failed = results.iter().filter(|r| !r.all_passed()).count();
+    println!("\n  {} opcodes, {} passed, {} failed", results.len(), results.len() - failed, failed);
+}
@@ -425,6 +425,20 @@ pub struct Disk2InterfaceCard {
     disk_io_count_prev: u64,
     /// 起動ブースト: 前回I/O頻度チェックサイクル
     disk_io_check_cycle: u64,
+    /// 機械音用: ヘッドの動き（取り出されるまで蓄積）
+    mech_events: Vec<DiskMechEvent>,
+}
+
+/// 機械音イベントの蓄積上限（取り出されない場合に無制限に増えないように）
+const MECH_EVENT_LIMIT: usize = 4096;
+
+/// ドライブの機械的な動き（効果音用、サイクルはイベント発生時の累積サイクル）
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DiskMechEvent {
+    /// ヘッドが1フェーズ移動した
+    Step(u64),
+    /// ヘッドがストッパー（トラック0/終端）に当たって動けなかった
+    Bump(u64),
 }
 
 /// motor-offディレイ（サイクル数）
@@ -527,6 +541,7 @@ impl Disk2InterfaceCard {
             disk_io_count: 0,
             disk_io_count_prev: 0,
             disk_io_check_cycle: 0,
+            mech_events: Vec::new(),
         }
     }
 
@@ -600,6 +615,11 @@ impl Disk2InterfaceCard {
         }
     }
     
+    /// 前回の呼び出し以降のヘッドの動きを取り出す
+    pub fn take_mech_events(&mut self) -> Vec<DiskMechEvent> {
+        std::mem::take(&mut self.mech_events)
+    }
+    
     /// motor-off予約をチェックして実行
     fn check_scheduled_motor_off(&mut self) {
         if self.motor_off_scheduled_cycle > 0 
@@ -1429,6 +1449,13 @@ impl Disk2InterfaceCard {
 
         // フェーズを更新（0-79の範囲、ハーフトラック）
         let new_phase = (drive.phase + direction).clamp(0, 79);
+        if self.mech_events.len() < MECH_EVENT_LIMIT {
+            if new_phase != drive.phase {
+                self.mech_events.push(DiskMechEvent::Step(self.cumulative_cycles));
+            } else if direction != 0 {
+                self.mech_events.push(DiskMechEvent::Bump(self.cumulative_cycles));
+            }
+        }
         if new_phase != drive.phase {
             drive.phase = new_phase;
             drive.phase_precise = new_phase as f32;
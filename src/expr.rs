@@ -0,0 +1,450 @@
+//! デバッガの式（条件付きブレークポイントとウォッチ式）
+//!
+//! 例: `a == $10 && [$C000] >= $80`, `w[$36] != $FDF0`, `c && !text`, `cycles > 1000000`
+//!
+//! - 数値: `$FF`（16進）、`0xFF`、`255`
+//! - レジスタ: `a` `x` `y` `sp` `pc` `p`、フラグ: `n` `v` `b` `d` `i` `z` `c`
+//! - メモリ: `[addr]` でバイト、`w[addr]` でワード（現在のバンク構成で読む）
+//! - ソフトスイッチ: `text` `mixed` `page2` `hires` `store80` `col80` `altchar` `dhires`
+//!   `ramrd` `ramwrt` `altzp` `lcbank2` `lcread` `lcwrite`
+//! - `cycles`: 起動からのサイクル数
+//! - 演算子（C言語と同じ優先順位）: `!` `~` `-`（単項）、`*` `/` `%`、`+` `-`、`<<` `>>`、
+//!   `<` `<=` `>` `>=`、`==` `!=`、`&`、`^`、`|`、`&&`、`||`
+//!
+//! 値はすべて整数で、真偽は0以外が真。0での除算は0になる。
+
+use crate::cpu::{flags, Registers};
+use crate::memory::{Memory, SoftSwitches};
+
+/// 式の評価に使うマシンの状態
+pub struct EvalContext<'a> {
+    pub regs: &'a Registers,
+    pub memory: &'a Memory,
+    pub cycles: u64,
+}
+
+/// 解析済みの式
+#[derive(Debug, Clone)]
+pub struct Expr {
+    source: String,
+    root: Node,
+}
+
+impl Expr {
+    /// 式を解析
+    pub fn parse(source: &str) -> Result<Expr, String> {
+        let tokens = tokenize(source)?;
+        let mut parser = Parser { tokens, pos: 0 };
+        let root = parser.expr(0)?;
+        if let Some(token) = parser.tokens.get(parser.pos) {
+            return Err(format!("Unexpected '{}' in expression", token));
+        }
+        Ok(Expr { source: source.trim().to_string(), root })
+    }
+
+    /// 値を求める
+    pub fn eval(&self, ctx: &EvalContext) -> i64 {
+        self.root.eval(ctx)
+    }
+
+    /// 真偽を求める
+    pub fn is_true(&self, ctx: &EvalContext) -> bool {
+        self.eval(ctx) != 0
+    }
+
+    /// 元の文字列
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+}
+
+impl std::fmt::Display for Expr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+// ============================================================
+// 構文木
+// ============================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Register {
+    A,
+    X,
+    Y,
+    Sp,
+    Pc,
+    P,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Switch {
+    Text,
+    Mixed,
+    Page2,
+    Hires,
+    Store80,
+    Col80,
+    AltChar,
+    DHires,
+    RamRd,
+    RamWrt,
+    AltZp,
+    LcBank2,
+    LcRead,
+    LcWrite,
+}
+
+impl Switch {
+    fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "text" => Switch::Text,
+            "mixed" => Switch::Mixed,
+            "page2" => Switch::Page2,
+            "hires" => Switch::Hires,
+            "store80" => Switch::Store80,
+            "col80" => Switch::Col80,
+            "altchar" => Switch::AltChar,
+            "dhires" => Switch::DHires,
+            "ramrd" => Switch::RamRd,
+            "ramwrt" => Switch::RamWrt,
+            "altzp" => Switch::AltZp,
+            "lcbank2" => Switch::LcBank2,
+            "lcread" => Switch::LcRead,
+            "lcwrite" => Switch::LcWrite,
+            _ => return None,
+        })
+    }
+
+    fn get(self, switches: &SoftSwitches) -> bool {
+        match self {
+            Switch::Text => switches.text_mode,
+            Switch::Mixed => switches.mixed_mode,
+            Switch::Page2 => switches.page2,
+            Switch::Hires => switches.hires,
+            Switch::Store80 => switches.store_80,
+            Switch::Col80 => switches.col_80,
+            Switch::AltChar => switches.alt_char,
+            Switch::DHires => switches.dhires,
+            Switch::RamRd => switches.ramrd,
+            Switch::RamWrt => switches.ramwrt,
+            Switch::AltZp => switches.altzp,
+            Switch::LcBank2 => switches.lc_bank2,
+            Switch::LcRead => switches.lc_read_enable,
+            Switch::LcWrite => switches.lc_write_enable,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum UnaryOp {
+    Not,
+    Complement,
+    Negate,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BinaryOp {
+    Mul,
+    Div,
+    Rem,
+    Add,
+    Sub,
+    Shl,
+    Shr,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Eq,
+    Ne,
+    BitAnd,
+    BitXor,
+    BitOr,
+    And,
+    Or,
+}
+
+impl BinaryOp {
+    /// 演算子と優先順位（大きいほど強く結合）
+    fn from_token(token: &Token) -> Option<(BinaryOp, u8)> {
+        let Token::Op(op) = token else { return None };
+        Some(match *op {
+            "*" => (BinaryOp::Mul, 10),
+            "/" => (BinaryOp::Div, 10),
+            "%" => (BinaryOp::Rem, 10),
+            "+" => (BinaryOp::Add, 9),
+            "-" => (BinaryOp::Sub, 9),
+            "<<" => (BinaryOp::Shl, 8),
+            ">>" => (BinaryOp::Shr, 8),
+            "<" => (BinaryOp::Lt, 7),
+            "<=" => (BinaryOp::Le, 7),
+            ">" => (BinaryOp::Gt, 7),
+            ">=" => (BinaryOp::Ge, 7),
+            "==" => (BinaryOp::Eq, 6),
+            "!=" => (BinaryOp::Ne, 6),
+            "&" => (BinaryOp::BitAnd, 5),
+            "^" => (BinaryOp::BitXor, 4),
+            "|" => (BinaryOp::BitOr, 3),
+            "&&" => (BinaryOp::And, 2),
+            "||" => (BinaryOp::Or, 1),
+            _ => return None,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Node {
+    Number(i64),
+    Register(Register),
+    Flag(u8),
+    Switch(Switch),
+    Cycles,
+    Byte(Box<Node>),
+    Word(Box<Node>),
+    Unary(UnaryOp, Box<Node>),
+    Binary(BinaryOp, Box<Node>, Box<Node>),
+}
+
+impl Node {
+    fn eval(&self, ctx: &EvalContext) -> i64 {
+        match self {
+            Node::Number(n) => *n,
+            Node::Register(reg) => match reg {
+                Register::A => ctx.regs.a as i64,
+                Register::X => ctx.regs.x as i64,
+                Register::Y => ctx.regs.y as i64,
+                Register::Sp => ctx.regs.sp as i64,
+                Register::Pc => ctx.regs.pc as i64,
+                Register::P => ctx.regs.status as i64,
+            },
+            Node::Flag(bit) => (ctx.regs.status & bit != 0) as i64,
+            Node::Switch(switch) => switch.get(&ctx.memory.switches) as i64,
+            Node::Cycles => ctx.cycles as i64,
+            Node::Byte(addr) => ctx.memory.peek(addr.eval(ctx) as u16) as i64,
+            Node::Word(addr) => {
+                let addr = addr.eval(ctx) as u16;
+                let lo = ctx.memory.peek(addr) as i64;
+                let hi = ctx.memory.peek(addr.wrapping_add(1)) as i64;
+                lo | (hi << 8)
+            }
+            Node::Unary(op, node) => {
+                let v = node.eval(ctx);
+                match op {
+                    UnaryOp::Not => (v == 0) as i64,
+                    UnaryOp::Complement => !v,
+                    UnaryOp::Negate => v.wrapping_neg(),
+                }
+            }
+            Node::Binary(BinaryOp::And, l, r) => (l.eval(ctx) != 0 && r.eval(ctx) != 0) as i64,
+            Node::Binary(BinaryOp::Or, l, r) => (l.eval(ctx) != 0 || r.eval(ctx) != 0) as i64,
+            Node::Binary(op, l, r) => {
+                let (l, r) = (l.eval(ctx), r.eval(ctx));
+                match op {
+                    BinaryOp::Mul => l.wrapping_mul(r),
+                    BinaryOp::Div => l.checked_div(r).unwrap_or(0),
+                    BinaryOp::Rem => l.checked_rem(r).unwrap_or(0),
+                    BinaryOp::Add => l.wrapping_add(r),
+                    BinaryOp::Sub => l.wrapping_sub(r),
+                    BinaryOp::Shl => l.wrapping_shl(r as u32),
+                    BinaryOp::Shr => l.wrapping_shr(r as u32),
+                    BinaryOp::Lt => (l < r) as i64,
+                    BinaryOp::Le => (l <= r) as i64,
+                    BinaryOp::Gt => (l > r) as i64,
+                    BinaryOp::Ge => (l >= r) as i64,
+                    BinaryOp::Eq => (l == r) as i64,
+                    BinaryOp::Ne => (l != r) as i64,
+                    BinaryOp::BitAnd => l & r,
+                    BinaryOp::BitXor => l ^ r,
+                    BinaryOp::BitOr => l | r,
+                    BinaryOp::And | BinaryOp::Or => unreachable!(),
+                }
+            }
+        }
+    }
+}
+
+// ============================================================
+// 字句解析と構文解析
+// ============================================================
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(i64),
+    Ident(String),
+    Op(&'static str),
+}
+
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Token::Number(n) => write!(f, "{}", n),
+            Token::Ident(name) => f.write_str(name),
+            Token::Op(op) => f.write_str(op),
+        }
+    }
+}
+
+/// 記号（長いものを先に照合する）
+const OPERATORS: [&str; 25] = [
+    "<<", ">>", "<=", ">=", "==", "!=", "&&", "||",
+    "*", "/", "%", "+", "-", "<", ">", "&", "^", "|", "!", "~", "(", ")", "[", "]", "=",
+];
+
+fn tokenize(source: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut rest = source;
+    loop {
+        rest = rest.trim_start();
+        let Some(c) = rest.chars().next() else { break };
+        if c == '$' || c.is_ascii_digit() {
+            let (radix, body) = if let Some(hex) = rest.strip_prefix('$') {
+                (16, hex)
+            } else if let Some(hex) = rest.strip_prefix("0x").or_else(|| rest.strip_prefix("0X")) {
+                (16, hex)
+            } else {
+                (10, rest)
+            };
+            let len = body.find(|c: char| !c.is_ascii_alphanumeric()).unwrap_or(body.len());
+            let value = i64::from_str_radix(&body[..len], radix)
+                .map_err(|_| format!("Invalid number '{}'", &rest[..rest.len() - body.len() + len]))?;
+            tokens.push(Token::Number(value));
+            rest = &body[len..];
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            let len = rest.find(|c: char| !c.is_ascii_alphanumeric() && c != '_').unwrap_or(rest.len());
+            tokens.push(Token::Ident(rest[..len].to_ascii_lowercase()));
+            rest = &rest[len..];
+        } else {
+            let op = OPERATORS
+                .iter()
+                .find(|op| rest.starts_with(**op))
+                .ok_or_else(|| format!("Unexpected '{}' in expression", c))?;
+            // "=" は "==" の書き間違いとして扱う
+            tokens.push(Token::Op(if *op == "=" { "==" } else { op }));
+            rest = &rest[op.len()..];
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn expect(&mut self, op: &str) -> Result<(), String> {
+        match self.next() {
+            Some(Token::Op(o)) if o == op => Ok(()),
+            Some(token) => Err(format!("Expected '{}' but found '{}'", op, token)),
+            None => Err(format!("Expected '{}' at end of expression", op)),
+        }
+    }
+
+    /// 優先順位 `min_prec` 以上の二項演算をまとめる
+    fn expr(&mut self, min_prec: u8) -> Result<Node, String> {
+        let mut lhs = self.unary()?;
+        while let Some((op, prec)) = self.tokens.get(self.pos).and_then(BinaryOp::from_token) {
+            if prec < min_prec {
+                break;
+            }
+            self.pos += 1;
+            let rhs = self.expr(prec + 1)?;
+            lhs = Node::Binary(op, Box::new(lhs), Box::new(rhs));
+        }
+        Ok(lhs)
+    }
+
+    fn unary(&mut self) -> Result<Node, String> {
+        let op = match self.tokens.get(self.pos) {
+            Some(Token::Op("!")) => UnaryOp::Not,
+            Some(Token::Op("~")) => UnaryOp::Complement,
+            Some(Token::Op("-")) => UnaryOp::Negate,
+            _ => return self.primary(),
+        };
+        self.pos += 1;
+        Ok(Node::Unary(op, Box::new(self.unary()?)))
+    }
+
+    fn primary(&mut self) -> Result<Node, String> {
+        match self.next() {
+            Some(Token::Number(n)) => Ok(Node::Number(n)),
+            Some(Token::Op("(")) => {
+                let node = self.expr(0)?;
+                self.expect(")")?;
+                Ok(node)
+            }
+            Some(Token::Op("[")) => {
+                let node = self.expr(0)?;
+                self.expect("]")?;
+                Ok(Node::Byte(Box::new(node)))
+            }
+            Some(Token::Ident(name)) if name == "w" && self.tokens.get(self.pos) == Some(&Token::Op("[")) => {
+                self.pos += 1;
+                let node = self.expr(0)?;
+                self.expect("]")?;
+                Ok(Node::Word(Box::new(node)))
+            }
+            Some(Token::Ident(name)) => Self::identifier(&name),
+            Some(token) => Err(format!("Unexpected '{}' in expression", token)),
+            None => Err("Unexpected end of expression".to_string()),
+        }
+    }
+
+    fn identifier(name: &str) -> Result<Node, String> {
+        Ok(match name {
+            "a" => Node::Register(Register::A),
+            "x" => Node::Register(Register::X),
+            "y" => Node::Register(Register::Y),
+            "sp" | "s" => Node::Register(Register::Sp),
+            "pc" => Node::Register(Register::Pc),
+            "p" => Node::Register(Register::P),
+            "n" => Node::Flag(flags::NEGATIVE),
+            "v" => Node::Flag(flags::OVERFLOW),
+            "b" => Node::Flag(flags::BREAK),
+            "d" => Node::Flag(flags::DECIMAL),
+            "i" => Node::Flag(flags::IRQ_DISABLE),
+            "z" => Node::Flag(flags::ZERO),
+            "c" => Node::Flag(flags::CARRY),
+            "cycles" => Node::Cycles,
+            _ => match Switch::from_name(name) {
+                Some(switch) => Node::Switch(switch),
+                None => return Err(format!("Unknown name '{}' in expression", name)),
+            },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::AppleModel;
+
+    #[test]
+    fn evaluates_registers_memory_and_operators() {
+        let mut memory = Memory::new(AppleModel::AppleIIPlus);
+        memory.main_ram[0x36] = 0xF0;
+        memory.main_ram[0x37] = 0xFD;
+        memory.switches.text_mode = true;
+        let regs = Registers { a: 0x10, x: 3, status: flags::CARRY, pc: 0x0300, ..Registers::default() };
+        let ctx = EvalContext { regs: &regs, memory: &memory, cycles: 5000 };
+        let eval = |s: &str| Expr::parse(s).unwrap().eval(&ctx);
+
+        assert_eq!(eval("a == $10 && c && text"), 1);
+        assert_eq!(eval("w[$36] = $FDF0"), 1);
+        assert_eq!(eval("[$30 + x * 2] | $0F"), 0xFF);
+        assert_eq!(eval("1 + 2 * 3 << 1"), 14);
+        assert_eq!(eval("-(pc >> 8) + !z + ~0"), -3);
+        assert_eq!(eval("cycles / 0 || hires"), 0);
+        assert!(Expr::parse("a == ").is_err());
+        assert!(Expr::parse("foo").is_err());
+        assert!(Expr::parse("(a").is_err());
+    }
+}
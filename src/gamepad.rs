@@ -44,6 +44,84 @@ pub struct GamepadState {
     pub connected: bool,
 }
 
+// ============================================================
+// ホストのマウスによるパドル
+// ============================================================
+
+/// ホストのマウスでパドル0/1を動かすモード
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MousePaddleMode {
+    /// 使わない
+    #[default]
+    Off,
+    /// ウィンドウ内の画面上の位置がそのままパドル値
+    Absolute,
+    /// キャプチャ中（F7）の移動量でパドル値を増減
+    Relative,
+}
+
+impl MousePaddleMode {
+    /// "off" / "absolute" / "relative" から変換
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "off" | "none" => Some(MousePaddleMode::Off),
+            "absolute" | "abs" => Some(MousePaddleMode::Absolute),
+            "relative" | "rel" => Some(MousePaddleMode::Relative),
+            _ => None,
+        }
+    }
+}
+
+/// マウスの位置・移動量をパドル0/1の値に変換する
+#[derive(Debug, Clone)]
+pub struct MousePaddle {
+    pub mode: MousePaddleMode,
+    /// 相対モードで画面の幅（高さ）だけ動かしたときに進むフルスケールの割合
+    pub sensitivity: f32,
+    /// 現在のパドル値（0.0 - 1.0）
+    value: (f32, f32),
+    /// 前回のポインタ位置（相対モード）
+    last: Option<(f32, f32)>,
+}
+
+impl MousePaddle {
+    pub fn new(mode: MousePaddleMode, sensitivity: f32) -> Self {
+        MousePaddle { mode, sensitivity, value: (0.5, 0.5), last: None }
+    }
+
+    /// ポインタで操作しているか
+    pub fn is_enabled(&self) -> bool {
+        self.mode != MousePaddleMode::Off
+    }
+
+    /// ポインタ位置（画面に対する割合 0.0-1.0、はみ出してもよい）からパドル値を更新
+    ///
+    /// `captured` は相対モードでキャプチャ中か。入力がない場合はNoneを返す。
+    pub fn update(&mut self, pos: Option<(f32, f32)>, captured: bool) -> Option<(u8, u8)> {
+        match self.mode {
+            MousePaddleMode::Off => return None,
+            MousePaddleMode::Absolute => {
+                let (x, y) = pos?;
+                self.value = (x.clamp(0.0, 1.0), y.clamp(0.0, 1.0));
+            }
+            MousePaddleMode::Relative => {
+                if !captured {
+                    self.last = None;
+                    return None;
+                }
+                let (x, y) = pos?;
+                if let Some((lx, ly)) = self.last {
+                    self.value.0 = (self.value.0 + (x - lx) * self.sensitivity).clamp(0.0, 1.0);
+                    self.value.1 = (self.value.1 + (y - ly) * self.sensitivity).clamp(0.0, 1.0);
+                }
+                self.last = Some((x, y));
+            }
+        }
+        Some(((self.value.0 * 255.0).round() as u8, (self.value.1 * 255.0).round() as u8))
+    }
+}
+
 // ============================================================
 // gilrsが有効な場合の実装
 // ============================================================
@@ -205,3 +283,21 @@ impl GamepadManager {
         false
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mouse_paddle_modes() {
+        let mut abs = MousePaddle::new(MousePaddleMode::Absolute, 1.0);
+        assert_eq!(abs.update(Some((0.0, 1.5)), false), Some((0, 255)));
+        assert_eq!(abs.update(None, false), None);
+
+        let mut rel = MousePaddle::new(MousePaddleMode::Relative, 2.0);
+        assert_eq!(rel.update(Some((0.25, 0.25)), false), None);
+        assert_eq!(rel.update(Some((0.25, 0.25)), true), Some((128, 128)));
+        assert_eq!(rel.update(Some((0.375, 0.125)), true), Some((191, 64)));
+        assert_eq!(MousePaddleMode::from_name("Relative"), Some(MousePaddleMode::Relative));
+    }
+}
@@ -0,0 +1,497 @@
+//! GDBリモートシリアルプロトコル（RSP）のサーバ
+//!
+//! GDBやVS CodeのGDBアダプタから接続して、レジスタ・メモリの読み書き、
+//! ブレークポイント（Z0/Z1）とウォッチポイント（Z2-Z4）、ステップ、継続、停止（Ctrl-C）ができる。
+//! ノンブロッキングのソケットを毎フレーム `poll` するので、ウィンドウでもヘッドレスでも使える。
+//!
+//! レジスタは `target.xml` で a, x, y, p, sp（各8ビット）と pc（16ビット）の順に定義する。
+//! メモリは `Memory::peek` / `poke` で現在のバンク構成のまま読み書きし、ソフトスイッチには触れない。
+
+use std::collections::HashMap;
+use std::io::{ErrorKind, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+
+use crate::apple2::Apple2;
+use crate::profiler::{access, BreakReason, DebuggerState, Watchpoint};
+
+/// GDBに渡すターゲット記述
+const TARGET_XML: &str = r#"<?xml version="1.0"?>
+<!DOCTYPE target SYSTEM "gdb-target.dtd">
+<target version="1.0">
+  <feature name="org.a2rs.6502">
+    <reg name="a" bitsize="8" regnum="0"/>
+    <reg name="x" bitsize="8" regnum="1"/>
+    <reg name="y" bitsize="8" regnum="2"/>
+    <reg name="p" bitsize="8" regnum="3"/>
+    <reg name="sp" bitsize="8" regnum="4"/>
+    <reg name="pc" bitsize="16" regnum="5" type="code_ptr"/>
+  </feature>
+</target>
+"#;
+
+/// 停止のシグナル
+const SIGINT: u8 = 2;
+const SIGTRAP: u8 = 5;
+
+/// 接続中のクライアント
+struct Client {
+    stream: TcpStream,
+    /// 受信した未処理のバイト
+    buffer: Vec<u8>,
+    /// QStartNoAckMode 後は +/- を送らない
+    no_ack: bool,
+}
+
+/// GDBサーバ
+pub struct GdbServer {
+    listener: TcpListener,
+    client: Option<Client>,
+    /// 継続・ステップ中で、止まったら停止パケットを返す
+    waiting: bool,
+    /// GDBが設定したブレークポイント・ウォッチポイント（種類, アドレス, 長さ）→ ID
+    points: HashMap<(u8, u16, u16), u32>,
+    /// 一度でも接続があったか
+    connected_once: bool,
+}
+
+impl GdbServer {
+    /// 待ち受けを開始（"127.0.0.1:1234" など）
+    pub fn bind(address: &str) -> Result<GdbServer, String> {
+        let listener = TcpListener::bind(address).map_err(|e| format!("Failed to listen on {}: {}", address, e))?;
+        listener.set_nonblocking(true).map_err(|e| e.to_string())?;
+        Ok(GdbServer {
+            listener,
+            client: None,
+            waiting: false,
+            points: HashMap::new(),
+            connected_once: false,
+        })
+    }
+
+    /// 待ち受けているアドレス
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        self.listener.local_addr().ok()
+    }
+
+    /// クライアントが接続しているか
+    pub fn is_connected(&self) -> bool {
+        self.client.is_some()
+    }
+
+    /// 接続したクライアントが切断したか（ヘッドレスの終了判定用）
+    pub fn is_finished(&self) -> bool {
+        self.connected_once && self.client.is_none()
+    }
+
+    /// 接続の受け付け、パケットの処理、停止の通知を行う（毎フレーム呼ぶ）
+    pub fn poll(&mut self, emu: &mut Apple2) {
+        if self.client.is_none() {
+            self.accept(emu);
+        }
+        let Some(client) = self.client.as_mut() else { return };
+
+        let mut chunk = [0u8; 4096];
+        loop {
+            match client.stream.read(&mut chunk) {
+                Ok(0) => {
+                    self.disconnect(emu);
+                    return;
+                }
+                Ok(n) => client.buffer.extend_from_slice(&chunk[..n]),
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(_) => {
+                    self.disconnect(emu);
+                    return;
+                }
+            }
+        }
+
+        while let Some(packet) = self.next_packet(emu) {
+            let reply = self.handle(emu, &packet);
+            if let Some(reply) = reply {
+                self.send(&reply);
+            }
+            if self.client.is_none() {
+                return;
+            }
+        }
+
+        // 継続・ステップが止まったら通知
+        if self.waiting && matches!(emu.debugger.state, DebuggerState::Paused | DebuggerState::BreakpointHit) {
+            self.waiting = false;
+            let reply = stop_reply(emu.debugger.take_break(), SIGTRAP);
+            self.send(&reply);
+        }
+    }
+
+    fn accept(&mut self, emu: &mut Apple2) {
+        let Ok((stream, peer)) = self.listener.accept() else { return };
+        if stream.set_nonblocking(true).is_err() {
+            return;
+        }
+        let _ = stream.set_nodelay(true);
+        println!("GDB: client connected from {}", peer);
+        self.client = Some(Client { stream, buffer: Vec::new(), no_ack: false });
+        self.connected_once = true;
+        self.waiting = false;
+        // 接続したら止めた状態で渡す
+        emu.debugger.pause();
+    }
+
+    /// 切断（GDBが置いたブレークポイントを外して実行を再開）
+    fn disconnect(&mut self, emu: &mut Apple2) {
+        if self.client.take().is_some() {
+            println!("GDB: client disconnected");
+        }
+        for (_, id) in self.points.drain() {
+            emu.debugger.remove_breakpoint(id);
+            emu.debugger.remove_watchpoint(id);
+        }
+        self.waiting = false;
+        emu.debugger.resume();
+    }
+
+    /// 受信バッファから1パケット取り出す（ACKを返し、Ctrl-Cは停止として処理）
+    fn next_packet(&mut self, emu: &mut Apple2) -> Option<String> {
+        loop {
+            let client = self.client.as_mut()?;
+            let start = client.buffer.iter().position(|&b| b == b'$' || b == 0x03)?;
+            if client.buffer[start] == 0x03 {
+                client.buffer.drain(..=start);
+                emu.debugger.pause();
+                if self.waiting {
+                    self.waiting = false;
+                    emu.debugger.take_break();
+                    self.send(&format!("S{:02x}", SIGINT));
+                }
+                continue;
+            }
+            let hash = start + client.buffer[start..].iter().position(|&b| b == b'#')?;
+            if client.buffer.len() < hash + 3 {
+                return None;
+            }
+            let data = client.buffer[start + 1..hash].to_vec();
+            let checksum = std::str::from_utf8(&client.buffer[hash + 1..hash + 3])
+                .ok()
+                .and_then(|s| u8::from_str_radix(s, 16).ok());
+            client.buffer.drain(..hash + 3);
+            let valid = checksum == Some(data.iter().fold(0u8, |sum, &b| sum.wrapping_add(b)));
+            if !client.no_ack {
+                let _ = client.stream.write_all(if valid { b"+" } else { b"-" });
+            }
+            if valid {
+                return Some(String::from_utf8_lossy(&data).into_owned());
+            }
+        }
+    }
+
+    /// パケットを送る
+    fn send(&mut self, data: &str) {
+        let Some(client) = self.client.as_mut() else { return };
+        let checksum = data.bytes().fold(0u8, |sum, b| sum.wrapping_add(b));
+        let packet = format!("${}#{:02x}", data, checksum);
+        // ノンブロッキングなので、送りきるまで待つ
+        let mut bytes = packet.as_bytes();
+        while !bytes.is_empty() {
+            match client.stream.write(bytes) {
+                Ok(n) => bytes = &bytes[n..],
+                Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::Interrupted) => {
+                    std::thread::sleep(std::time::Duration::from_millis(1));
+                }
+                Err(_) => return,
+            }
+        }
+    }
+
+    /// 1パケットを処理して応答を返す（Noneなら応答しない）
+    fn handle(&mut self, emu: &mut Apple2, packet: &str) -> Option<String> {
+        let (command, body) = packet.split_at(packet.chars().next().map_or(0, |c| c.len_utf8()));
+        let reply = match command {
+            "?" => stop_reply(emu.debugger.last_break, SIGTRAP),
+            "g" => {
+                let r = &emu.cpu.regs;
+                let [pc_lo, pc_hi] = r.pc.to_le_bytes();
+                hex(&[r.a, r.x, r.y, r.status, r.sp, pc_lo, pc_hi])
+            }
+            "G" => match parse_hex_bytes(body) {
+                Some(b) if b.len() >= 7 => {
+                    let r = &mut emu.cpu.regs;
+                    (r.a, r.x, r.y, r.status, r.sp) = (b[0], b[1], b[2], b[3], b[4]);
+                    r.pc = u16::from_le_bytes([b[5], b[6]]);
+                    "OK".to_string()
+                }
+                _ => "E01".to_string(),
+            },
+            "p" => match usize::from_str_radix(body, 16) {
+                Ok(n) => read_register(emu, n).unwrap_or_else(|| "E01".to_string()),
+                Err(_) => "E01".to_string(),
+            },
+            "P" => match body.split_once('=').and_then(|(n, v)| Some((usize::from_str_radix(n, 16).ok()?, parse_hex_bytes(v)?))) {
+                Some((n, bytes)) if write_register(emu, n, &bytes) => "OK".to_string(),
+                _ => "E01".to_string(),
+            },
+            "m" => match parse_addr_len(body) {
+                Some((addr, len)) => {
+                    let bytes: Vec<u8> = (0..len).map(|i| emu.memory.peek(addr.wrapping_add(i))).collect();
+                    hex(&bytes)
+                }
+                None => "E01".to_string(),
+            },
+            "M" => {
+                let parsed = body.split_once(':').and_then(|(range, data)| Some((parse_addr_len(range)?, parse_hex_bytes(data)?)));
+                match parsed {
+                    Some(((addr, len), bytes)) if bytes.len() == len as usize => {
+                        for (i, &b) in bytes.iter().enumerate() {
+                            emu.memory.poke(addr.wrapping_add(i as u16), b);
+                        }
+                        "OK".to_string()
+                    }
+                    _ => "E01".to_string(),
+                }
+            }
+            "Z" | "z" => self.breakpoint(emu, command == "Z", body),
+            "c" => {
+                jump(emu, body);
+                emu.debugger.resume();
+                self.waiting = true;
+                return None;
+            }
+            "s" => {
+                jump(emu, body);
+                emu.debugger.step();
+                self.waiting = true;
+                return None;
+            }
+            "v" => {
+                if body == "Cont?" {
+                    "vCont;c;C;s;S".to_string()
+                } else if let Some(actions) = body.strip_prefix("Cont;") {
+                    // スレッドは1つなので最初の動作だけ使う
+                    match actions.chars().next() {
+                        Some('c') | Some('C') => emu.debugger.resume(),
+                        Some('s') | Some('S') => emu.debugger.step(),
+                        _ => return Some("E01".to_string()),
+                    }
+                    self.waiting = true;
+                    return None;
+                } else {
+                    String::new()
+                }
+            }
+            "q" => query(body),
+            "Q" if body == "StartNoAckMode" => {
+                self.send("OK");
+                if let Some(client) = self.client.as_mut() {
+                    client.no_ack = true;
+                }
+                return None;
+            }
+            "H" => "OK".to_string(),
+            "T" => "OK".to_string(),
+            "D" => {
+                self.send("OK");
+                self.disconnect(emu);
+                return None;
+            }
+            "k" => {
+                self.disconnect(emu);
+                return None;
+            }
+            _ => String::new(),
+        };
+        Some(reply)
+    }
+
+    /// `Z`/`z` 種類,アドレス,長さ
+    fn breakpoint(&mut self, emu: &mut Apple2, insert: bool, body: &str) -> String {
+        let mut parts = body.split(',');
+        let kind = parts.next().and_then(|s| s.parse::<u8>().ok());
+        let addr = parts.next().and_then(|s| u16::from_str_radix(s, 16).ok());
+        let len = parts.next().and_then(|s| u16::from_str_radix(s.split(';').next().unwrap_or(s), 16).ok());
+        let (Some(kind), Some(addr), Some(len)) = (kind, addr, len) else { return "E01".to_string() };
+        let watch = match kind {
+            0 | 1 => None,
+            2 => Some(access::WRITE),
+            3 => Some(access::READ),
+            4 => Some(access::READ | access::WRITE),
+            _ => return String::new(),
+        };
+        // ブレークポイントの長さは命令の種類なので区別しない
+        let key = (kind, addr, if watch.is_some() { len } else { 0 });
+        if !insert {
+            if let Some(id) = self.points.remove(&key) {
+                emu.debugger.remove_breakpoint(id);
+                emu.debugger.remove_watchpoint(id);
+            }
+            return "OK".to_string();
+        }
+        if self.points.contains_key(&key) {
+            return "OK".to_string();
+        }
+        let id = match watch {
+            None => emu.debugger.add_breakpoint(addr),
+            Some(kind) => emu.debugger.add_watchpoint(Watchpoint {
+                id: 0,
+                start: addr,
+                end: addr.saturating_add(len.max(1) - 1),
+                access: kind,
+                bank: None,
+                value: None,
+                enabled: true,
+                hit_count: 0,
+                last_pc: None,
+            }),
+        };
+        self.points.insert(key, id);
+        "OK".to_string()
+    }
+}
+
+/// 停止理由のパケット
+fn stop_reply(reason: Option<BreakReason>, default_signal: u8) -> String {
+    match reason {
+        Some(BreakReason::Watchpoint { address, access: kind, .. }) if kind & (access::READ | access::WRITE) != 0 => {
+            let name = if kind & access::WRITE != 0 { "watch" } else { "rwatch" };
+            format!("T{:02x}{}:{:x};", SIGTRAP, name, address)
+        }
+        Some(_) => format!("S{:02x}", SIGTRAP),
+        None => format!("S{:02x}", default_signal),
+    }
+}
+
+/// `c addr` / `s addr` の再開アドレス
+fn jump(emu: &mut Apple2, body: &str) {
+    if let Ok(addr) = u16::from_str_radix(body, 16) {
+        emu.cpu.regs.pc = addr;
+    }
+}
+
+/// `q` クエリ
+fn query(body: &str) -> String {
+    if body.starts_with("Supported") {
+        "PacketSize=4000;qXfer:features:read+;QStartNoAckMode+;vContSupported+".to_string()
+    } else if let Some(rest) = body.strip_prefix("Xfer:features:read:target.xml:") {
+        // オフセット,長さ で切り出して返す（m: 続きあり、l: 最後）
+        let parsed = rest.split_once(',').and_then(|(o, l)| Some((usize::from_str_radix(o, 16).ok()?, usize::from_str_radix(l, 16).ok()?)));
+        match parsed {
+            Some((offset, len)) if offset <= TARGET_XML.len() => {
+                let end = (offset + len).min(TARGET_XML.len());
+                let prefix = if end < TARGET_XML.len() { 'm' } else { 'l' };
+                format!("{}{}", prefix, &TARGET_XML[offset..end])
+            }
+            _ => "E01".to_string(),
+        }
+    } else {
+        match body {
+            "Attached" => "1".to_string(),
+            "C" => "QC1".to_string(),
+            "fThreadInfo" => "m1".to_string(),
+            "sThreadInfo" => "l".to_string(),
+            "Offsets" => "Text=0;Data=0;Bss=0".to_string(),
+            _ => String::new(),
+        }
+    }
+}
+
+fn read_register(emu: &Apple2, n: usize) -> Option<String> {
+    let r = &emu.cpu.regs;
+    Some(match n {
+        0 => hex(&[r.a]),
+        1 => hex(&[r.x]),
+        2 => hex(&[r.y]),
+        3 => hex(&[r.status]),
+        4 => hex(&[r.sp]),
+        5 => hex(&r.pc.to_le_bytes()),
+        _ => return None,
+    })
+}
+
+fn write_register(emu: &mut Apple2, n: usize, bytes: &[u8]) -> bool {
+    let r = &mut emu.cpu.regs;
+    match (n, bytes) {
+        (0, [v, ..]) => r.a = *v,
+        (1, [v, ..]) => r.x = *v,
+        (2, [v, ..]) => r.y = *v,
+        (3, [v, ..]) => r.status = *v,
+        (4, [v, ..]) => r.sp = *v,
+        (5, [lo, hi, ..]) => r.pc = u16::from_le_bytes([*lo, *hi]),
+        _ => return false,
+    }
+    true
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn parse_hex_bytes(text: &str) -> Option<Vec<u8>> {
+    if !text.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..text.len()).step_by(2).map(|i| u8::from_str_radix(text.get(i..i + 2)?, 16).ok()).collect()
+}
+
+/// "addr,len"
+fn parse_addr_len(text: &str) -> Option<(u16, u16)> {
+    let (addr, len) = text.split_once(',')?;
+    Some((u16::from_str_radix(addr, 16).ok()?, u16::from_str_radix(len, 16).ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::AppleModel;
+    use std::time::Duration;
+
+    /// パケットを送り、応答が来るまでサーバを回す
+    fn request(server: &mut GdbServer, emu: &mut Apple2, client: &mut TcpStream, data: &str) -> String {
+        let checksum = data.bytes().fold(0u8, |sum, b| sum.wrapping_add(b));
+        client.write_all(format!("${}#{:02x}", data, checksum).as_bytes()).unwrap();
+        let mut received = Vec::new();
+        for _ in 0..500 {
+            server.poll(emu);
+            if emu.debugger.is_running() || emu.debugger.state == DebuggerState::Stepping {
+                emu.run_frame();
+            }
+            let mut chunk = [0u8; 1024];
+            if let Ok(n) = client.read(&mut chunk) {
+                received.extend_from_slice(&chunk[..n]);
+            }
+            let text = String::from_utf8_lossy(&received).into_owned();
+            if let Some(start) = text.find('$') {
+                if let Some(end) = text[start..].find('#') {
+                    return text[start + 1..start + end].to_string();
+                }
+            }
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        panic!("no reply to {}", data);
+    }
+
+    #[test]
+    fn serves_registers_memory_breakpoints_and_stepping() {
+        let mut emu = Apple2::new(AppleModel::AppleIIPlus);
+        // $0300: INX / INX / JMP $0300
+        emu.memory.main_ram[0x0300..0x0305].copy_from_slice(&[0xE8, 0xE8, 0x4C, 0x00, 0x03]);
+        let mut server = GdbServer::bind("127.0.0.1:0").unwrap();
+        let mut client = TcpStream::connect(server.local_addr().unwrap()).unwrap();
+        client.set_nonblocking(true).unwrap();
+
+        assert_eq!(request(&mut server, &mut emu, &mut client, "QStartNoAckMode"), "OK");
+        assert_eq!(emu.debugger.state, DebuggerState::Paused);
+        assert_eq!(request(&mut server, &mut emu, &mut client, "P5=0003"), "OK");
+        assert_eq!(request(&mut server, &mut emu, &mut client, "P1=00"), "OK");
+        assert_eq!(request(&mut server, &mut emu, &mut client, "m300,3"), "e8e84c");
+        assert_eq!(request(&mut server, &mut emu, &mut client, "M310,2:abcd"), "OK");
+        assert_eq!(emu.memory.main_ram[0x0311], 0xCD);
+
+        assert_eq!(request(&mut server, &mut emu, &mut client, "s"), "S05");
+        assert_eq!(emu.cpu.regs.pc, 0x0301);
+        assert_eq!(request(&mut server, &mut emu, &mut client, "Z0,302,1"), "OK");
+        assert_eq!(request(&mut server, &mut emu, &mut client, "c"), "S05");
+        assert_eq!(request(&mut server, &mut emu, &mut client, "g"), "00020024fd0203");
+        assert_eq!(request(&mut server, &mut emu, &mut client, "z0,302,1"), "OK");
+        assert!(emu.debugger.breakpoints().is_empty());
+    }
+}
@@ -99,6 +99,16 @@ pub struct EmulatorStatus {
     pub quality_level: i32,   // 0-4
     pub auto_quality: bool,
     pub paused: bool,
+    /// 動画録画中
+    pub recording: bool,
+    /// マウスカードがホストのマウスをキャプチャ中
+    pub mouse_captured: bool,
+    /// テープデッキの状態（"PLAY 0:12/1:30" など）
+    pub tape_status: String,
+    /// テープ高速ロード
+    pub tape_fast_load: bool,
+    /// ディスク機械音のボリューム
+    pub disk_sound_volume: f32,
     #[allow(dead_code)]
     pub disk1_name: Option<String>,
     #[allow(dead_code)]
@@ -127,6 +137,11 @@ impl Default for EmulatorStatus {
             quality_level: 4,
             auto_quality: true,
             paused: false,
+            recording: false,
+            mouse_captured: false,
+            tape_status: String::new(),
+            tape_fast_load: false,
+            disk_sound_volume: 0.5,
             disk1_name: None,
             disk2_name: None,
             disk1_active: false,
@@ -841,10 +856,12 @@ impl Gui {
             _ => "Ultra",
         };
         let auto_str = if status.auto_quality { " (Auto)" } else { "" };
+        let rec_str = if status.recording { " | [REC]" } else { "" };
+        let mouse_str = if status.mouse_captured { " | [MOUSE]" } else { "" };
         
         let full_status = format!(
-            "{} | {} | {} | {} | {} {} | Quality: {}{}",
-            fps_str, speed_str, disk_str, slot_str, sound_str, gamepad_str, quality_str, auto_str
+            "{} | {} | {} | {} | {} {} | Quality: {}{}{}{}",
+            fps_str, speed_str, disk_str, slot_str, sound_str, gamepad_str, quality_str, auto_str, rec_str, mouse_str
         );
         
         self.draw_text(buffer, width, 8, bar_y + 6, &full_status, COLOR_TEXT);
@@ -867,9 +884,9 @@ impl Gui {
         
         // メニューパネル
         let menu_width = 280;
-        let menu_height = 320;
+        let menu_height = 404;
         let menu_x = (width - menu_width) / 2;
-        let menu_y = (height - menu_height) / 2;
+        let menu_y = height.saturating_sub(menu_height) / 2;
         
         // パネル背景
         for y in menu_y..menu_y + menu_height {
@@ -929,6 +946,15 @@ impl Gui {
             ("Disk Dir", disk_dir_str),
             ("Screenshot Dir", screenshot_dir_str),
             ("Save Dir", save_dir_str),
+            ("Tape", status.tape_status.clone()),
+            ("Tape Rewind", String::new()),
+            ("Tape Record", if status.tape_status.starts_with("REC") { "ON" } else { "OFF" }.to_string()),
+            ("Tape Fast Load", if status.tape_fast_load { "ON" } else { "OFF" }.to_string()),
+            ("Disk Sound", if status.disk_sound_volume > 0.0 {
+                format!("{}%", (status.disk_sound_volume * 100.0).round() as u32)
+            } else {
+                "OFF".to_string()
+            }),
         ];
         
         for (i, (label, value)) in items.iter().enumerate() {
@@ -1161,7 +1187,7 @@ impl Gui {
     
     /// オーバーレイメニューの選択を下に移動
     pub fn overlay_down(&mut self) {
-        if self.overlay_selection < 7 {  // 8項目 (0-7)
+        if self.overlay_selection < 12 {  // 13項目 (0-12)
             self.overlay_selection += 1;
         }
     }
@@ -1290,6 +1316,7 @@ fn get_char_pattern(ch: char) -> [u8; 10] {
 // ===================================
 
 use crate::profiler::{Profiler, ProfileCategory, BootStage, Debugger, DebuggerState, opcode_name};
+use crate::console::Console;
 
 /// デバッガパネルの幅
 pub const DEBUGGER_PANEL_WIDTH: usize = 320;
@@ -1318,6 +1345,10 @@ pub enum DebuggerTab {
     Disk,
     /// ブレークポイント
     Breakpoints,
+    /// コールスタック
+    CallStack,
+    /// コマンドコンソール
+    Console,
 }
 
 impl DebuggerTab {
@@ -1328,6 +1359,8 @@ impl DebuggerTab {
             DebuggerTab::Memory => "Memory",
             DebuggerTab::Disk => "Disk",
             DebuggerTab::Breakpoints => "Break",
+            DebuggerTab::CallStack => "Calls",
+            DebuggerTab::Console => "Console",
         }
     }
     
@@ -1338,6 +1371,8 @@ impl DebuggerTab {
             DebuggerTab::Memory,
             DebuggerTab::Disk,
             DebuggerTab::Breakpoints,
+            DebuggerTab::CallStack,
+            DebuggerTab::Console,
         ]
     }
 }
@@ -1352,6 +1387,8 @@ pub struct DebuggerPanel {
     pub memory_offset: u16,
     /// スクロールオフセット
     pub scroll_offset: usize,
+    /// コマンドコンソール（Consoleタブ）
+    pub console: Console,
 }
 
 impl Default for DebuggerPanel {
@@ -1367,6 +1404,7 @@ impl DebuggerPanel {
             current_tab: DebuggerTab::Profiler,
             memory_offset: 0,
             scroll_offset: 0,
+            console: Console::new(),
         }
     }
     
@@ -1433,17 +1471,19 @@ impl DebuggerPanel {
             }
         }
         
-        // タブを描画
+        // タブを描画（名前の長さに合わせて詰め、余りを間隔に振り分ける）
         let tabs = DebuggerTab::all();
-        let tab_width = panel_width / tabs.len();
-        for (i, tab) in tabs.iter().enumerate() {
-            let tx = x_offset + i * tab_width;
+        let names_width: usize = tabs.iter().map(|tab| tab.name().len() * 6).sum();
+        let gap = panel_width.saturating_sub(names_width) / tabs.len();
+        let mut tx = x_offset + gap / 2;
+        for tab in tabs {
             let color = if *tab == self.current_tab {
                 COLOR_DEBUG_HIGHLIGHT
             } else {
                 COLOR_DEBUG_MUTED
             };
-            draw_text_small(buffer, buffer_width, tx + 4, tab_y + 6, tab.name(), color);
+            draw_text_small(buffer, buffer_width, tx, tab_y + 6, tab.name(), color);
+            tx += tab.name().len() * 6 + gap;
         }
         
         // コンテンツエリア
@@ -1465,7 +1505,47 @@ impl DebuggerPanel {
             DebuggerTab::Breakpoints => {
                 self.render_breakpoints(buffer, buffer_width, buffer_height, x_offset, content_y, panel_width, debugger);
             }
+            DebuggerTab::CallStack => {
+                self.render_call_stack(buffer, buffer_width, x_offset, content_y, cpu_regs, debugger);
+            }
+            DebuggerTab::Console => {
+                self.render_console(buffer, buffer_width, buffer_height, x_offset, content_y);
+            }
+        }
+    }
+
+    /// コンソールに入力中か（キー入力をエミュレータに渡さない）
+    pub fn console_active(&self) -> bool {
+        self.visible && self.current_tab == DebuggerTab::Console
+    }
+    
+    fn render_console(
+        &self,
+        buffer: &mut [u32],
+        buffer_width: usize,
+        buffer_height: usize,
+        x_offset: usize,
+        y_start: usize,
+    ) {
+        let line_height = 10;
+        // 最下行は入力行、その上に新しい出力から詰める
+        let rows = buffer_height.saturating_sub(y_start + 4) / line_height;
+        let output = self.console.output();
+        let shown = rows.saturating_sub(1).min(output.len());
+        let mut y = y_start;
+        for line in output.iter().skip(output.len() - shown) {
+            let color = if line.starts_with("> ") {
+                COLOR_DEBUG_MUTED
+            } else if line.starts_with("Error") {
+                COLOR_DEBUG_ERROR
+            } else {
+                COLOR_DEBUG_TEXT
+            };
+            draw_text_small(buffer, buffer_width, x_offset + 4, y, line, color);
+            y += line_height;
         }
+        let prompt = format!("> {}_", self.console.input);
+        draw_text_small(buffer, buffer_width, x_offset + 4, y, &prompt, COLOR_DEBUG_HIGHLIGHT);
     }
     
     fn render_profiler(
@@ -1571,6 +1651,20 @@ impl DebuggerPanel {
         
         let track_text = format!("Track: {}", profiler.disk_info.current_track);
         draw_text_small(buffer, buffer_width, x_offset + 4, y, &track_text, COLOR_DEBUG_TEXT);
+        y += line_height + 4;
+        
+        // オーディオ出力
+        draw_text_small(buffer, buffer_width, x_offset + 4, y, "-- Audio --", COLOR_DEBUG_MUTED);
+        y += line_height;
+        
+        let audio = &profiler.audio;
+        let buffer_text = format!("Buffer: {}/{} @{}Hz", audio.buffered, audio.capacity, audio.sample_rate);
+        draw_text_small(buffer, buffer_width, x_offset + 4, y, &buffer_text, COLOR_DEBUG_TEXT);
+        y += line_height;
+        
+        let xrun_color = if audio.underruns + audio.overruns > 0 { COLOR_DEBUG_WARNING } else { COLOR_DEBUG_TEXT };
+        let xrun_text = format!("Underrun: {}  Overrun: {}", audio.underruns, audio.overruns);
+        draw_text_small(buffer, buffer_width, x_offset + 4, y, &xrun_text, xrun_color);
     }
     
     fn render_cpu(
@@ -1594,15 +1688,24 @@ impl DebuggerPanel {
             DebuggerState::Stepping => COLOR_DEBUG_WARNING,
             DebuggerState::BreakpointHit => COLOR_DEBUG_ERROR,
         };
-        let state_text = format!("State: {:?}", debugger.state);
+        let state_text = match debugger.goal() {
+            Some(goal) => format!("State: {:?} ({})", debugger.state, goal),
+            None => format!("State: {:?}", debugger.state),
+        };
         draw_text_small(buffer, buffer_width, x_offset + 4, y, &state_text, state_color);
-        y += line_height + 4;
+        y += line_height;
+        if let Some(tracer) = debugger.tracer() {
+            let trace_text = format!("Trace: {} {}", tracer.count(), if tracer.is_active() { "rec" } else { "paused" });
+            draw_text_small(buffer, buffer_width, x_offset + 4, y, &trace_text, COLOR_DEBUG_MUTED);
+            y += line_height;
+        }
+        y += 4;
         
         // レジスタ
         draw_text_small(buffer, buffer_width, x_offset + 4, y, "-- Registers --", COLOR_DEBUG_MUTED);
         y += line_height;
         
-        let pc_text = format!("PC: ${:04X}", cpu.pc);
+        let pc_text = format!("PC: {}", debugger.symbols.describe(cpu.pc));
         draw_text_small(buffer, buffer_width, x_offset + 4, y, &pc_text, COLOR_DEBUG_HIGHLIGHT);
         y += line_height;
         
@@ -1636,6 +1739,15 @@ impl DebuggerPanel {
             if cpu.flags & 0x01 != 0 { "1" } else { "0" },
         );
         draw_text_small(buffer, buffer_width, x_offset + 4, y, &flags, COLOR_DEBUG_TEXT);
+        y += line_height;
+        
+        // IRQ要求元
+        if cpu.irq_sources.is_empty() {
+            draw_text_small(buffer, buffer_width, x_offset + 4, y, "IRQ: -", COLOR_DEBUG_TEXT);
+        } else {
+            let irq_text = format!("IRQ: {}", cpu.irq_sources.join(" "));
+            draw_text_small(buffer, buffer_width, x_offset + 4, y, &irq_text, COLOR_DEBUG_WARNING);
+        }
         y += line_height + 4;
         
         // 現在の命令
@@ -1807,22 +1919,118 @@ impl DebuggerPanel {
         } else {
             for bp in bps {
                 let status = if bp.enabled { "[*]" } else { "[ ]" };
-                let bp_text = format!("{} #{}: ${:04X} (hits: {})", status, bp.id, bp.address, bp.hit_count);
+                let mut bp_text = format!("{} #{}: ${:04X} (hits: {})", status, bp.id, bp.address, bp.hit_count);
+                if let Some(condition) = &bp.condition {
+                    bp_text.push_str(&format!(" if {}", condition));
+                }
                 let color = if bp.enabled { COLOR_DEBUG_TEXT } else { COLOR_DEBUG_MUTED };
                 draw_text_small(buffer, buffer_width, x_offset + 4, y, &bp_text, color);
                 y += line_height;
             }
         }
         
+        let wps = debugger.watchpoints();
+        if !wps.is_empty() {
+            y += 8;
+            draw_text_small(buffer, buffer_width, x_offset + 4, y, "-- Watchpoints --", COLOR_DEBUG_MUTED);
+            y += line_height;
+            for wp in wps {
+                let status = if wp.enabled { "[*]" } else { "[ ]" };
+                let mut wp_text = format!("{} #{}: {} (hits: {})", status, wp.id, wp, wp.hit_count);
+                if let Some(pc) = wp.last_pc {
+                    wp_text.push_str(&format!(" PC=${:04X}", pc));
+                }
+                let color = if wp.enabled { COLOR_DEBUG_TEXT } else { COLOR_DEBUG_MUTED };
+                draw_text_small(buffer, buffer_width, x_offset + 4, y, &wp_text, color);
+                y += line_height;
+            }
+        }
+        
+        let watches = debugger.watch_exprs();
+        if !watches.is_empty() {
+            y += 8;
+            draw_text_small(buffer, buffer_width, x_offset + 4, y, "-- Watch --", COLOR_DEBUG_MUTED);
+            y += line_height;
+            for watch in watches {
+                let text = match watch.value {
+                    Some(v) if (0..=0xFFFF).contains(&v) => format!("{} = ${:X} ({})", watch.expr, v, v),
+                    Some(v) => format!("{} = {}", watch.expr, v),
+                    None => format!("{} = ?", watch.expr),
+                };
+                draw_text_small(buffer, buffer_width, x_offset + 4, y, &text, COLOR_DEBUG_TEXT);
+                y += line_height;
+            }
+        }
+        
+        // 最後に止まった理由
+        if let Some(reason) = debugger.last_break {
+            y += 8;
+            draw_text_small(buffer, buffer_width, x_offset + 4, y, "-- Last Break --", COLOR_DEBUG_MUTED);
+            y += line_height;
+            let color = if debugger.state == DebuggerState::BreakpointHit { COLOR_DEBUG_ERROR } else { COLOR_DEBUG_TEXT };
+            draw_text_small(buffer, buffer_width, x_offset + 4, y, &reason.to_string(), color);
+            y += line_height;
+        }
+        
         y += 8;
         draw_text_small(buffer, buffer_width, x_offset + 4, y, "-- Controls --", COLOR_DEBUG_MUTED);
         y += line_height;
-        draw_text_small(buffer, buffer_width, x_offset + 4, y, "F6: Step", COLOR_DEBUG_TEXT);
+        draw_text_small(buffer, buffer_width, x_offset + 4, y, "F6: Step  Shift: Over  Ctrl: Out", COLOR_DEBUG_TEXT);
         y += line_height;
         draw_text_small(buffer, buffer_width, x_offset + 4, y, "F7: Continue", COLOR_DEBUG_TEXT);
         y += line_height;
+        draw_text_small(buffer, buffer_width, x_offset + 4, y, "Shift+F7: Run to memory view addr", COLOR_DEBUG_TEXT);
+        y += line_height;
+        draw_text_small(buffer, buffer_width, x_offset + 4, y, "Ctrl+F7: Run 100 instructions", COLOR_DEBUG_TEXT);
+        y += line_height;
         draw_text_small(buffer, buffer_width, x_offset + 4, y, "F8: Break", COLOR_DEBUG_TEXT);
     }
+    
+    /// コールスタック（内側から順。戻りアドレスがもうスタックにないフレームは灰色）
+    fn render_call_stack(
+        &self,
+        buffer: &mut [u32],
+        buffer_width: usize,
+        x_offset: usize,
+        y_start: usize,
+        cpu: &CpuRegisters,
+        debugger: &Debugger,
+    ) {
+        let mut y = y_start;
+        let line_height = 12;
+        let buffer_height = buffer.len() / buffer_width;
+        let symbols = &debugger.symbols;
+        
+        draw_text_small(buffer, buffer_width, x_offset + 4, y, "-- Call Stack --", COLOR_DEBUG_MUTED);
+        y += line_height;
+        let pc_text = format!("PC: {}  S=${:02X}", symbols.describe(cpu.pc), cpu.sp);
+        draw_text_small(buffer, buffer_width, x_offset + 4, y, &pc_text, COLOR_DEBUG_HIGHLIGHT);
+        y += line_height + 4;
+        
+        let frames = debugger.call_stack.frames();
+        if frames.is_empty() {
+            draw_text_small(buffer, buffer_width, x_offset + 4, y, "(empty)", COLOR_DEBUG_MUTED);
+            return;
+        }
+        for (i, frame) in frames.iter().rev().enumerate() {
+            // 1フレーム2行、入りきらなければ残りの数だけ表示
+            if y + line_height * 3 > buffer_height {
+                let more = format!("... {} more", frames.len() - i);
+                draw_text_small(buffer, buffer_width, x_offset + 4, y, &more, COLOR_DEBUG_MUTED);
+                break;
+            }
+            let live = frame.is_live(cpu.sp);
+            let call_text = format!("#{} {} {}", i, frame.kind.name(), symbols.describe(frame.target));
+            draw_text_small(buffer, buffer_width, x_offset + 4, y, &call_text, if live { COLOR_DEBUG_TEXT } else { COLOR_DEBUG_MUTED });
+            y += line_height;
+            let mut from_text = format!("   from {} ret ${:04X} S=${:02X}", symbols.describe(frame.caller), frame.return_address, frame.sp);
+            if !live {
+                from_text.push_str(" gone");
+            }
+            draw_text_small(buffer, buffer_width, x_offset + 4, y, &from_text, COLOR_DEBUG_MUTED);
+            y += line_height;
+        }
+    }
 }
 
 /// CPU レジスタ情報（デバッガ用）
@@ -1835,6 +2043,8 @@ pub struct CpuRegisters {
     pub sp: u8,
     pub flags: u8,
     pub current_opcode: u8,
+    /// IRQをアサート中の要求元
+    pub irq_sources: Vec<&'static str>,
 }
 
 /// ディスクデバッグ情報
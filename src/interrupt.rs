@@ -0,0 +1,140 @@
+//! 割り込みコントローラ
+//!
+//! Apple IIのIRQラインはオープンコレクタで、各スロットのカードが独立して
+//! アサート/解放する。ここでは要求元ごとに1ビットを割り当て、
+//! CPUには全ビットのORをIRQラインとして渡す。
+
+bitflags::bitflags! {
+    /// IRQ要求元（1要求元につき1ビット）
+    #[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+    pub struct IrqSource: u16 {
+        /// スロット1のカード
+        const SLOT1 = 1 << 1;
+        /// スロット2のカード
+        const SLOT2 = 1 << 2;
+        /// スロット3のカード
+        const SLOT3 = 1 << 3;
+        /// スロット4のカード
+        const SLOT4 = 1 << 4;
+        /// スロット5のカード
+        const SLOT5 = 1 << 5;
+        /// スロット6のカード
+        const SLOT6 = 1 << 6;
+        /// スロット7のカード
+        const SLOT7 = 1 << 7;
+        /// マザーボード上のVBL割り込み
+        const VBL = 1 << 8;
+        /// デバッガからの手動アサート
+        const DEBUGGER = 1 << 15;
+    }
+}
+
+impl IrqSource {
+    /// スロット番号（1-7）に対応する要求元
+    pub fn slot(slot: usize) -> Self {
+        match slot {
+            1..=7 => IrqSource::from_bits_truncate(1 << slot),
+            _ => IrqSource::empty(),
+        }
+    }
+}
+
+/// 割り込みコントローラ
+#[derive(Debug, Clone, Default)]
+pub struct InterruptController {
+    /// 現在アサートしている要求元
+    asserted: IrqSource,
+}
+
+impl InterruptController {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// IRQをアサート
+    pub fn assert(&mut self, source: IrqSource) {
+        self.asserted |= source;
+    }
+
+    /// IRQを解放
+    pub fn release(&mut self, source: IrqSource) {
+        self.asserted &= !source;
+    }
+
+    /// 要求元の状態を設定
+    pub fn set(&mut self, source: IrqSource, active: bool) {
+        self.asserted.set(source, active);
+    }
+
+    /// IRQライン（いずれかの要求元がアサートしていればtrue）
+    pub fn line(&self) -> bool {
+        !self.asserted.is_empty()
+    }
+
+    /// アサート中の要求元
+    pub fn asserted(&self) -> IrqSource {
+        self.asserted
+    }
+
+    /// アサート中の要求元の名前（デバッガ表示用）
+    pub fn asserted_names(&self) -> Vec<&'static str> {
+        self.asserted.iter_names().map(|(name, _)| name).collect()
+    }
+
+    /// 全要求元を解放
+    pub fn clear(&mut self) {
+        self.asserted = IrqSource::empty();
+    }
+
+    /// ビット表現から復元（セーブステート用）
+    pub fn set_bits(&mut self, bits: u16) {
+        self.asserted = IrqSource::from_bits_truncate(bits);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cpu::{flags, Cpu, CpuType, MemoryBus};
+
+    struct Ram(Vec<u8>);
+
+    impl MemoryBus for Ram {
+        fn read(&mut self, address: u16) -> u8 {
+            self.0[address as usize]
+        }
+        fn write(&mut self, address: u16, value: u8) {
+            self.0[address as usize] = value;
+        }
+    }
+
+    #[test]
+    fn test_line_is_or_of_sources() {
+        let mut irq = InterruptController::new();
+        irq.assert(IrqSource::slot(4));
+        irq.assert(IrqSource::SLOT7);
+        irq.release(IrqSource::SLOT4);
+        assert!(irq.line());
+        assert_eq!(irq.asserted_names(), vec!["SLOT7"]);
+        irq.release(IrqSource::SLOT7);
+        assert!(!irq.line());
+    }
+
+    #[test]
+    fn test_cli_delays_irq_by_one_instruction() {
+        let mut ram = Ram(vec![0xEA; 65536]);
+        ram.0[0x0300] = 0x58; // CLI
+        ram.0[0xFFFE] = 0x00;
+        ram.0[0xFFFF] = 0x40;
+        let mut cpu = Cpu::new(CpuType::Cpu6502);
+        cpu.regs.pc = 0x0300;
+        cpu.regs.set_flag(flags::IRQ_DISABLE, true);
+        cpu.irq_pending = true;
+
+        cpu.step(&mut ram); // CLI
+        cpu.step(&mut ram); // NOP（CLI直後はまだ割り込まない）
+        assert_eq!(cpu.regs.pc, 0x0302);
+        cpu.step(&mut ram); // IRQ
+        assert_eq!(cpu.regs.pc, 0x4000);
+    }
+}
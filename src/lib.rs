@@ -7,14 +7,28 @@
 //! - Text, Lo-Res, Hi-Res graphics
 
 pub mod cpu;
+pub mod interrupt;
 pub mod memory;
 pub mod video;
 pub mod disk;
 pub mod disk_log;
+pub mod cassette;
+pub mod mouse;
+pub mod clock;
+pub mod videx;
 pub mod apple2;
 pub mod savestate;
 pub mod sound;
+pub mod wav;
+pub mod recorder;
 pub mod gamepad;
 pub mod config;
 pub mod gui;
 pub mod profiler;
+pub mod expr;
+pub mod console;
+pub mod gdbstub;
+pub mod symbols;
+pub mod trace;
+pub mod rewind;
+pub mod callstack;
@@ -29,6 +29,11 @@ use a2rs::config;
 use a2rs::gui;
 use a2rs::profiler;
 use a2rs::disk_log;
+use a2rs::recorder;
+use a2rs::cassette;
+use a2rs::mouse;
+use a2rs::clock;
+use a2rs::videx;
 
 // テスト専用モジュール（main.rsのみ）
 mod test_cpu;
@@ -39,15 +44,28 @@ use memory::AppleModel;
 #[allow(unused_imports)]
 use cpu::MemoryBus;
 use video::{SCREEN_WIDTH, SCREEN_HEIGHT};
-use sound::{Speaker, AudioOutput};
-use gamepad::GamepadManager;
+use sound::{Speaker, AudioOutput, AudioRecorder, PlaybackClock};
+use disk::DiskMechEvent;
+use mouse::{MouseCard, MOUSE_SLOT};
+use videx::VidexCard;
+use clock::{ClockSource, NoSlotClock, ThunderClock, THUNDERCLOCK_SLOT};
+use recorder::{VideoRecorder, RecordFormat};
+use gamepad::{GamepadManager, MousePaddle, MousePaddleMode};
 use config::{Config, SaveSlots, get_exe_dir};
 use gui::{Gui, EmulatorStatus, ToolbarButton, DiskMenuAction, TOOLBAR_HEIGHT, STATUSBAR_HEIGHT};
 use gui::{DebuggerPanel, CpuRegisters, DiskDebugInfo, DEBUGGER_PANEL_WIDTH};
-use profiler::{Profiler, Debugger};
+use profiler::{BreakCondition, DebuggerState, Profiler, Watchpoint};
+use a2rs::expr::Expr;
+use a2rs::console::Console;
+use a2rs::gdbstub::GdbServer;
+use a2rs::symbols::SymbolTable;
+use a2rs::trace::{self, TraceTrigger, Tracer};
 use clap::Parser;
 use minifb::{Key, Window, WindowOptions, KeyRepeat, MouseMode, MouseButton};
+use std::cell::RefCell;
 use std::fs;
+use std::io::Write;
+use std::rc::Rc;
 use std::path::PathBuf;
 use std::time::{Duration, Instant};
 
@@ -83,9 +101,18 @@ struct Args {
     #[arg(long)]
     headless: bool,
 
-    /// 実行するサイクル数（ヘッドレスモード用）
+    /// 実行するサイクル数（ヘッドレスモード用。コンソールでは1回の実行の上限）
     #[arg(long, default_value = "1000000")]
     cycles: u64,
+
+    /// ヘッドレスモードで標準入力からデバッガコンソールのコマンドを読む（停止した状態で始まる）
+    #[arg(long, requires = "headless")]
+    console: bool,
+
+    /// GDBリモートプロトコルのサーバを開く（ポート番号または "HOST:PORT"）
+    /// ヘッドレスではクライアントが切断するまで動く
+    #[arg(long, value_name = "PORT")]
+    gdb: Option<String>,
     
     /// CPUテストを実行（Klaus2m5 6502 functional test）
     #[arg(long)]
@@ -159,6 +186,122 @@ struct Args {
     /// A2RSホームディレクトリ（相対パスの基準、設定ファイルより優先）
     #[arg(long)]
     home: Option<String>,
+    
+    /// 動画を録画（拡張子で形式を選択: .png=APNG, .gif=GIF, .y4m=Y4M+WAV）
+    /// ヘッドレスモードでも使用可能。ウィンドウ時はShift+F10で録画開始/停止
+    #[arg(long)]
+    record: Option<String>,
+    
+    /// 録画サイズ（幅x高さ、省略時はネイティブの560x384）
+    #[arg(long)]
+    record_size: Option<String>,
+    
+    /// エミュレートした音声をWAVに録音（audio機能なし・ヘッドレスでも使用可能）
+    #[arg(long)]
+    record_audio: Option<String>,
+    
+    /// 音声録音時に$C030の生の1bit波形（1サイクル単位）も<名前>.raw.wavに記録
+    #[arg(long)]
+    record_audio_raw: bool,
+    
+    /// カセットテープイメージ（WAVまたはCT2）。F1メニューのTapeで再生/停止
+    #[arg(long)]
+    tape: Option<String>,
+    
+    /// カセット高速ロード（Monitor READ/WRITEを瞬時に処理）
+    #[arg(long)]
+    tape_fast_load: bool,
+
+    /// AppleMouse IIカードをスロット4に挿す（設定ファイルのmouse_cardでも有効化可能）
+    /// ウィンドウ時はF7でホストのマウスをキャプチャ
+    #[arg(long)]
+    mouse: bool,
+
+    /// PCブレークポイント（16進アドレスかシンボル名、複数指定可）。ヒットすると停止してデバッガを開く
+    /// "ADDR if EXPR" で条件付き（例: "0300 if a == $10 && [$C000] >= $80"）
+    #[arg(long = "break", value_name = "ADDR")]
+    breakpoints: Vec<String>,
+
+    /// 起動後このアドレスに来たら停止してデバッガを開く（16進かシンボル名）
+    #[arg(long, value_name = "ADDR")]
+    run_to: Option<String>,
+
+    /// デバッガのシンボルファイル（ca65 .dbg、VICEラベル、Merlinリスト。複数指定可）
+    #[arg(long = "symbols", value_name = "FILE")]
+    symbol_files: Vec<String>,
+
+    /// 起動後N命令を実行したら停止してデバッガを開く
+    #[arg(long, value_name = "N")]
+    instructions: Option<u64>,
+
+    /// デバッガのBreakタブに表示するウォッチ式（複数指定可。例: "w[$36]", "[$C000] & $7F"）
+    #[arg(long = "watch-expr", value_name = "EXPR")]
+    watch_exprs: Vec<String>,
+
+    /// バスのウォッチポイント "[rwx:]START[-END][@BANK][=VALUE]"（複数指定可）
+    /// 例: w:C030, rw:0400-07FF@aux, x:D000@lc2, w:10=FF
+    #[arg(long = "watch", value_name = "SPEC")]
+    watchpoints: Vec<String>,
+
+    /// 命令トレースをファイルに記録（.txt/.logはテキスト、それ以外はバイナリ）
+    #[arg(long, value_name = "FILE")]
+    trace: Option<String>,
+
+    /// トレースを直前のN命令だけ保持し、ブレーク時と終了時に書き出す
+    #[arg(long, value_name = "N", requires = "trace")]
+    trace_ring: Option<usize>,
+
+    /// トレースの開始条件（16進アドレス、シンボル名、または "cycle:N"）
+    #[arg(long, value_name = "TRIGGER", requires = "trace")]
+    trace_start: Option<String>,
+
+    /// トレースの停止条件（16進アドレス、シンボル名、または "cycle:N"）
+    #[arg(long, value_name = "TRIGGER", requires = "trace")]
+    trace_stop: Option<String>,
+
+    /// バイナリのトレースをテキストにして標準出力に書き、終了する
+    #[arg(long, value_name = "FILE")]
+    trace_export: Option<String>,
+
+    /// 巻き戻しを有効にし、直近SECONDS秒を保持（コンソールの back / rewind / lastwrite）
+    #[arg(long, value_name = "SECONDS")]
+    rewind: Option<u64>,
+
+    /// ホストのマウスでパドル0/1とボタンを操作: off, absolute, relative（relativeはF7でキャプチャ）
+    #[arg(long, value_name = "MODE")]
+    mouse_paddle: Option<String>,
+
+    /// Videx VideoTerm 80桁カードをスロット3に挿す（Apple II/II+用、設定ファイルのvidex_cardでも有効化可能）
+    #[arg(long)]
+    videx: bool,
+
+    /// Videxのファームウェア ROM（1KB）。省略時はエミュレータ内蔵の出力処理を使う
+    #[arg(long, value_name = "FILE")]
+    videx_rom: Option<String>,
+
+    /// Videxの文字ROM（2KB）。省略時は内蔵フォントから生成
+    #[arg(long, value_name = "FILE")]
+    videx_char_rom: Option<String>,
+
+    /// ThunderClock Plusカードをスロット5に挿す（設定ファイルのthunderclockでも有効化可能）
+    #[arg(long)]
+    thunderclock: bool,
+
+    /// ROMの下にNo-Slot Clockを挿す（設定ファイルのno_slot_clockでも有効化可能）
+    #[arg(long)]
+    no_slot_clock: bool,
+
+    /// クロックの時刻を固定する（例: "2024-03-05 14:07:09"）。テストの再現用
+    #[arg(long, value_name = "DATETIME")]
+    clock_freeze: Option<String>,
+
+    /// クロックの時刻をずらす秒数
+    #[arg(long, value_name = "SECONDS", allow_hyphen_values = true)]
+    clock_offset: Option<i64>,
+
+    /// オーディオ出力デバイスの一覧を表示して終了（設定ファイルのaudio_deviceに指定する名前）
+    #[arg(long)]
+    list_audio_devices: bool,
 }
 
 /// スクリーンショットをPNGで保存
@@ -262,6 +405,16 @@ fn collect_disk_files(dir: &str, disks: &mut Vec<String>, depth: usize) {
     }
 }
 
+/// ウィンドウ座標を、アスペクト比を保って描画した画面内の位置（0.0〜1.0）に変換
+fn screen_fraction(mx: f32, my: f32, area_w: usize, area_h: usize, area_y: usize) -> (f32, f32) {
+    let (area_w, area_h) = (area_w.max(1) as f32, area_h.max(1) as f32);
+    let scale = (area_w / SCREEN_WIDTH as f32).min(area_h / SCREEN_HEIGHT as f32);
+    let (w, h) = (SCREEN_WIDTH as f32 * scale, SCREEN_HEIGHT as f32 * scale);
+    let x0 = (area_w - w) / 2.0;
+    let y0 = area_y as f32 + (area_h - h) / 2.0;
+    (((mx - x0) / w).clamp(0.0, 1.0), ((my - y0) / h).clamp(0.0, 1.0))
+}
+
 /// 最速のニアレストネイバースケーリング（アスペクト比維持）
 fn scale_nearest_aspect_fast(src: &[u32], src_w: usize, src_h: usize, dst: &mut [u32], dst_w: usize, dst_h: usize) {
     // アスペクト比を計算
@@ -601,6 +754,17 @@ fn apply_light_sharpen(buffer: &mut [u32], width: usize, height: usize, strength
     }
 }
 
+/// ウィンドウに入力された文字を溜める（デバッガコンソール用）
+struct TypedChars(Rc<RefCell<Vec<char>>>);
+
+impl minifb::InputCallback for TypedChars {
+    fn add_char(&mut self, uni_char: u32) {
+        if let Some(c) = char::from_u32(uni_char) {
+            self.0.borrow_mut().push(c);
+        }
+    }
+}
+
 /// キーコードをApple IIの文字コードに変換
 fn key_to_apple2(key: Key, shift: bool, ctrl: bool) -> Option<u8> {
     // Ctrl+キーの場合、制御文字を返す
@@ -728,6 +892,33 @@ fn main() {
     let disk_log_level = parse_disk_log_level(&args.disk_log);
     disk_log::set_log_level(disk_log_level);
     
+    // オーディオデバイス一覧
+    if args.list_audio_devices {
+        let names = sound::output_device_names();
+        if names.is_empty() {
+            println!("No audio output devices found (or built without the audio feature)");
+        }
+        for name in names {
+            println!("{}", name);
+        }
+        return;
+    }
+    
+    // バイナリトレースのテキスト変換
+    if let Some(path) = args.trace_export.as_deref() {
+        let mut symbols = SymbolTable::builtin();
+        for file in &args.symbol_files {
+            if let Err(e) = symbols.load(file) {
+                eprintln!("{}", e);
+            }
+        }
+        if let Err(e) = trace::export_text(path, &symbols, &mut std::io::stdout().lock()) {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
     // クイックテストモード
     if args.quick_test {
         test_cpu::run_quick_tests();
@@ -910,6 +1101,116 @@ fn main() {
         }
     }
 
+    // マウスカード
+    if args.mouse {
+        emu.mouse = Some(MouseCard::new(MOUSE_SLOT));
+    }
+
+    // シンボル（ブレークポイントより先に読む）
+    for path in &args.symbol_files {
+        match emu.debugger.symbols.load(path) {
+            Ok(count) => log::info!("Loaded {} symbols from {}", count, path),
+            Err(e) => eprintln!("{}", e),
+        }
+    }
+
+    // ブレークポイント
+    for text in &args.breakpoints {
+        let (addr_text, condition) = match text.split_once(" if ") {
+            Some((addr, condition)) => (addr, Some(condition)),
+            None => (text.as_str(), None),
+        };
+        let Some(address) = parse_hex_address(addr_text).or_else(|| emu.debugger.symbols.lookup(addr_text.trim())) else {
+            eprintln!("Invalid breakpoint address: {}", text);
+            continue;
+        };
+        match condition.map(Expr::parse) {
+            None => {
+                emu.debugger.add_breakpoint(address);
+            }
+            Some(Ok(expr)) => {
+                emu.debugger.add_conditional_breakpoint(address, BreakCondition::Expr(expr));
+            }
+            Some(Err(e)) => eprintln!("Invalid breakpoint condition: {}", e),
+        }
+    }
+    if let Some(text) = args.run_to.as_deref() {
+        match parse_hex_address(text).or_else(|| emu.debugger.symbols.lookup(text)) {
+            Some(address) => emu.run_to(address),
+            None => eprintln!("Invalid run-to address: {}", text),
+        }
+    }
+    if let Some(count) = args.instructions {
+        emu.run_instructions(count);
+    }
+    for text in &args.watch_exprs {
+        if let Err(e) = emu.debugger.add_watch_expr(text) {
+            eprintln!("Invalid watch expression: {}", e);
+        }
+    }
+    for spec in &args.watchpoints {
+        match Watchpoint::parse(spec) {
+            Ok(watchpoint) => {
+                emu.debugger.add_watchpoint(watchpoint);
+            }
+            Err(e) => eprintln!("{}", e),
+        }
+    }
+
+    // 命令トレース
+    if let Some(path) = args.trace.as_deref() {
+        let symbols = &emu.debugger.symbols;
+        let parse = |text: Option<&str>| text.map(|t| TraceTrigger::parse(t, symbols)).transpose();
+        let tracer = (|| -> Result<Tracer, String> {
+            let mut tracer = Tracer::new(path, args.trace_ring, emu.cpu.cpu_type, symbols)?;
+            tracer.set_triggers(parse(args.trace_start.as_deref())?, parse(args.trace_stop.as_deref())?);
+            Ok(tracer)
+        })();
+        match tracer.and_then(|tracer| emu.debugger.start_trace(tracer)) {
+            Ok(()) => log::info!("Tracing to {}", path),
+            Err(e) => eprintln!("{}", e),
+        }
+    }
+
+    // 巻き戻し（0.5秒ごとのスナップショット）
+    if let Some(seconds) = args.rewind {
+        emu.enable_rewind(30, (seconds * 2).max(1) as usize);
+    }
+
+    // Videx 80桁カード（設定ファイルで有効な場合はウィンドウ起動時に挿す）
+    let videx_roms = VidexRoms {
+        firmware: args.videx_rom.clone(),
+        char_rom: args.videx_char_rom.clone(),
+    };
+    if args.videx {
+        insert_videx(&mut emu, &videx_roms);
+    }
+
+    // リアルタイムクロック
+    if args.thunderclock {
+        emu.thunderclock = Some(ThunderClock::new(THUNDERCLOCK_SLOT));
+    }
+    if args.no_slot_clock {
+        emu.no_slot_clock = Some(NoSlotClock::new());
+    }
+    if let Some(text) = args.clock_freeze.as_deref() {
+        match clock::parse_datetime(text) {
+            Ok(at) => emu.clock = ClockSource::frozen(at),
+            Err(e) => eprintln!("{}", e),
+        }
+    }
+    if let Some(secs) = args.clock_offset {
+        emu.clock = emu.clock.clone().with_offset_secs(secs);
+    }
+
+    // カセットテープをロード
+    emu.cassette.fast_load = args.tape_fast_load;
+    if let Some(tape_path) = args.tape.as_deref() {
+        if let Err(e) = emu.cassette.load(std::path::Path::new(tape_path)) {
+            eprintln!("Failed to load tape: {}", e);
+        }
+    }
+
     // リセット
     emu.reset();
     
@@ -922,8 +1223,36 @@ fn main() {
         log::info!("Boot boost logging enabled");
     }
 
-    if args.headless {
-        run_headless(&mut emu, args.cycles);
+    // 録画設定
+    let record_opts = RecordOptions {
+        path: args.record.clone(),
+        size: args.record_size.as_deref().and_then(parse_size).unwrap_or((SCREEN_WIDTH, SCREEN_HEIGHT)),
+        audio_path: args.record_audio.clone(),
+        audio_raw: args.record_audio_raw,
+    };
+
+    // GDBサーバ
+    let gdb = match args.gdb.as_deref().map(|addr| GdbServer::bind(&gdb_address(addr))) {
+        Some(Ok(server)) => {
+            if let Some(addr) = server.local_addr() {
+                println!("GDB: listening on {}", addr);
+            }
+            Some(server)
+        }
+        Some(Err(e)) => {
+            eprintln!("{}", e);
+            None
+        }
+        None => None,
+    };
+
+    if args.headless && args.console {
+        run_console(&mut emu, args.cycles);
+    } else if args.headless {
+        match gdb {
+            Some(server) => run_gdb(&mut emu, server),
+            None => run_headless(&mut emu, args.cycles, &record_opts),
+        }
     } else {
         // ウィンドウサイズをパース
         let (width, height) = parse_size(&args.size).unwrap_or((640, 480));
@@ -933,10 +1262,44 @@ fn main() {
             interval: args.profile_interval,
             boot_only: args.profile_boot,
         };
-        run_with_window(&mut emu, args.speed, width, height, args.fullscreen, profile_opts, args.config.clone(), args.home.clone());
+        let mouse_paddle = args.mouse_paddle.as_deref().and_then(|name| {
+            let mode = MousePaddleMode::from_name(name);
+            if mode.is_none() {
+                eprintln!("Unknown mouse paddle mode: {} (off, absolute, relative)", name);
+            }
+            mode
+        });
+        run_with_window(&mut emu, args.speed, width, height, args.fullscreen, profile_opts, record_opts, mouse_paddle, args.config.clone(), args.home.clone(), videx_roms, gdb);
+    }
+
+    // トレースを書き出して閉じる
+    if let Some(path) = emu.debugger.tracer().map(|t| t.path().to_string()) {
+        match emu.debugger.stop_trace() {
+            Ok(count) => println!("Trace: wrote {} instructions to {}", count, path),
+            Err(e) => eprintln!("{}", e),
+        }
     }
 }
 
+/// `--gdb` の待ち受けアドレス（ポート番号だけならローカルホスト）
+fn gdb_address(spec: &str) -> String {
+    if spec.chars().all(|c| c.is_ascii_digit()) {
+        format!("127.0.0.1:{}", spec)
+    } else {
+        spec.to_string()
+    }
+}
+
+/// 16進アドレスを解析（"$C600" / "0xC600" / "C600"）
+fn parse_hex_address(s: &str) -> Option<u16> {
+    let s = s.trim();
+    let digits = s.strip_prefix('$')
+        .or_else(|| s.strip_prefix("0x"))
+        .or_else(|| s.strip_prefix("0X"))
+        .unwrap_or(s);
+    u16::from_str_radix(digits, 16).ok()
+}
+
 fn parse_size(s: &str) -> Option<(usize, usize)> {
     let parts: Vec<&str> = s.split('x').collect();
     if parts.len() == 2 {
@@ -948,14 +1311,240 @@ fn parse_size(s: &str) -> Option<(usize, usize)> {
     }
 }
 
-fn run_headless(emu: &mut Apple2, cycles: u64) {
+fn run_headless(emu: &mut Apple2, cycles: u64, record_opts: &RecordOptions) {
     let start = Instant::now();
-    emu.run_cycles(cycles);
+    let start_cycle = emu.total_cycles;
+    if record_opts.path.is_none() && record_opts.audio_path.is_none() {
+        emu.run_cycles(cycles);
+    } else {
+        // 録画・録音時はフレーム単位で実行して映像と音声を取り込む
+        let mut recorder = match record_opts.path.as_deref() {
+            Some(path) => match start_recording(std::path::Path::new(path), record_opts.size) {
+                Some(rec) => Some(rec),
+                None => return,
+            },
+            None => None,
+        };
+        let mut audio_recorder = match record_opts.audio_path.as_deref() {
+            Some(path) => match start_audio_recording(std::path::Path::new(path), record_opts.audio_raw) {
+                Some(rec) => Some(rec),
+                None => return,
+            },
+            None => None,
+        };
+        let mut speaker = Speaker::new();
+        let end_cycle = emu.total_cycles + cycles;
+        while emu.running && emu.debugger.is_running() && emu.total_cycles < end_cycle {
+            let frame_start_cycle = emu.total_cycles;
+            emu.run_frame();
+            let clicks = emu.take_speaker_clicks();
+            for &cycle in &clicks {
+                speaker.click(cycle);
+            }
+            let samples = speaker.generate_samples(frame_start_cycle, emu.total_cycles - frame_start_cycle);
+            let mut result = Ok(());
+            if let Some(ref mut rec) = audio_recorder {
+                result = rec.push_clicks(&clicks, frame_start_cycle, emu.total_cycles)
+                    .and_then(|_| rec.push_samples(Some(samples)));
+            }
+            if let Some(ref mut rec) = recorder {
+                result = result
+                    .and_then(|_| rec.push_audio(Some(samples), samples.len()))
+                    .and_then(|_| rec.push_frame(emu.get_framebuffer(), SCREEN_WIDTH, SCREEN_HEIGHT));
+            }
+            if let Err(e) = result {
+                eprintln!("Recording failed: {}", e);
+                break;
+            }
+        }
+        if let Some(rec) = recorder {
+            stop_recording(rec);
+        }
+        if let Some(rec) = audio_recorder {
+            stop_audio_recording(rec);
+        }
+    }
     let elapsed = start.elapsed();
     
-    let mhz = (cycles as f64) / elapsed.as_secs_f64() / 1_000_000.0;
-    println!("Executed {} cycles in {:?} ({:.2} MHz effective)", cycles, elapsed, mhz);
-    println!("Final PC: ${:04X}", emu.cpu.regs.pc);
+    let executed = emu.total_cycles - start_cycle;
+    let mhz = (executed as f64) / elapsed.as_secs_f64() / 1_000_000.0;
+    println!("Executed {} cycles in {:?} ({:.2} MHz effective)", executed, elapsed, mhz);
+    if let Some(reason) = emu.debugger.take_break() {
+        println!("Stopped at {}", reason);
+    }
+    println!("Final PC: {}", emu.debugger.symbols.describe(emu.cpu.regs.pc));
+}
+
+/// ヘッドレスのデバッガコンソール（標準入力から1行ずつ実行）
+///
+/// g/s/n などで実行を始めたら、止まるか `cycles` サイクル経つまで進めてから次の行を読む。
+fn run_console(emu: &mut Apple2, cycles: u64) {
+    let mut console = Console::new();
+    emu.debugger.pause();
+    println!("Debugger console: 'help' lists commands, 'quit' exits");
+    let stdin = std::io::stdin();
+    loop {
+        print!("> ");
+        let _ = std::io::stdout().flush();
+        let mut line = String::new();
+        match stdin.read_line(&mut line) {
+            Ok(0) | Err(_) => break,
+            Ok(_) => {}
+        }
+        let line = line.trim();
+        if line.eq_ignore_ascii_case("quit") || line.eq_ignore_ascii_case("q") {
+            break;
+        }
+        match console.execute(emu, line) {
+            Ok(lines) => lines.iter().for_each(|l| println!("{}", l)),
+            Err(e) => println!("Error: {}", e),
+        }
+        
+        let running = |emu: &Apple2| matches!(emu.debugger.state, DebuggerState::Running | DebuggerState::Stepping);
+        if !running(emu) {
+            continue;
+        }
+        let end_cycle = emu.total_cycles + cycles;
+        while emu.running && running(emu) && emu.total_cycles < end_cycle {
+            emu.run_frame();
+        }
+        if running(emu) {
+            emu.debugger.pause();
+            println!("Paused after {} cycles", cycles);
+        }
+        if let Some(reason) = emu.debugger.take_break() {
+            println!("Stopped at {} in {}", reason, emu.debugger.symbols.describe(emu.cpu.regs.pc));
+        }
+        if let Ok(lines) = console.execute(emu, "r") {
+            lines.iter().for_each(|l| println!("{}", l));
+        }
+    }
+}
+
+/// ヘッドレスのGDBサーバ（クライアントが切断するまで）
+///
+/// 継続・ステップ中はフレームを進め、止まっている間はパケットを待つ。
+fn run_gdb(emu: &mut Apple2, mut server: GdbServer) {
+    println!("GDB: waiting for a client (target remote <addr>)");
+    while emu.running && !server.is_finished() {
+        server.poll(emu);
+        if server.is_connected() && matches!(emu.debugger.state, DebuggerState::Running | DebuggerState::Stepping) {
+            emu.run_frame();
+        } else {
+            std::thread::sleep(Duration::from_millis(5));
+        }
+    }
+}
+
+/// 録画オプション
+struct RecordOptions {
+    /// 録画ファイル（指定時は起動直後から録画）
+    path: Option<String>,
+    /// 出力サイズ
+    size: (usize, usize),
+    /// 音声録音ファイル（指定時は起動直後から録音）
+    audio_path: Option<String>,
+    /// 生の1bit波形も記録する
+    audio_raw: bool,
+}
+
+/// 録画を開始（失敗時はメッセージを表示してNone）
+fn start_recording(path: &std::path::Path, size: (usize, usize)) -> Option<VideoRecorder> {
+    match VideoRecorder::start(path, size.0, size.1) {
+        Ok(rec) => {
+            println!("Recording started: {} ({}x{})", path.display(), size.0, size.1);
+            Some(rec)
+        }
+        Err(e) => {
+            eprintln!("Failed to start recording {}: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+/// 録画を終了してファイルを確定
+fn stop_recording(recorder: VideoRecorder) {
+    let path = recorder.path().to_path_buf();
+    let frames = recorder.frames_captured();
+    let audio = recorder.audio_path();
+    match recorder.finish() {
+        Ok(()) => {
+            println!("Recording saved: {} ({} frames)", path.display(), frames);
+            if let Some(audio) = audio {
+                println!("Audio track saved: {}", audio.display());
+            }
+        }
+        Err(e) => eprintln!("Failed to finish recording {}: {}", path.display(), e),
+    }
+}
+
+/// 音声録音を開始（失敗時はメッセージを表示してNone）
+fn start_audio_recording(path: &std::path::Path, raw: bool) -> Option<AudioRecorder> {
+    match AudioRecorder::start(path, raw) {
+        Ok(rec) => {
+            println!("Audio recording started: {}", path.display());
+            Some(rec)
+        }
+        Err(e) => {
+            eprintln!("Failed to start audio recording {}: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+/// 音声録音を終了してファイルを確定
+fn stop_audio_recording(recorder: AudioRecorder) {
+    let path = recorder.path().to_path_buf();
+    let raw_path = recorder.raw_path();
+    let secs = recorder.duration_secs();
+    match recorder.finish() {
+        Ok(()) => {
+            println!("Audio saved: {} ({:.1} s)", path.display(), secs);
+            if let Some(raw_path) = raw_path {
+                println!("Raw speaker track saved: {}", raw_path.display());
+            }
+        }
+        Err(e) => eprintln!("Failed to finish audio recording {}: {}", path.display(), e),
+    }
+}
+
+/// カセット録音を終了してWAVに保存
+fn save_tape_recording(emu: &mut Apple2, dir: &std::path::Path) {
+    let Some(tape) = emu.cassette.stop_recording() else {
+        return;
+    };
+    let _ = fs::create_dir_all(dir);
+    let path = cassette::recording_path(dir);
+    match tape.save(&path) {
+        Ok(()) => println!("Tape saved: {} ({} blocks)", path.display(), tape.blocks.len()),
+        Err(e) => eprintln!("Failed to save tape: {}", e),
+    }
+}
+
+/// Videxカードに読み込むROM（コマンドラインで指定）
+struct VidexRoms {
+    firmware: Option<String>,
+    char_rom: Option<String>,
+}
+
+/// Videxカードをスロット3に挿してROMを読み込む（内蔵80桁のIIeには挿さない）
+fn insert_videx(emu: &mut Apple2, roms: &VidexRoms) {
+    if emu.memory.is_iie() {
+        eprintln!("Videx card is for the Apple II/II+ only; not inserted on {:?}", emu.memory.model);
+        return;
+    }
+    let mut videx = VidexCard::new(&emu.video.char_rom);
+    if let Some(path) = roms.firmware.as_deref() {
+        if let Err(e) = std::fs::read(path).map_err(|e| e.to_string()).and_then(|data| videx.load_firmware(&data)) {
+            eprintln!("Failed to load Videx ROM {}: {}", path, e);
+        }
+    }
+    if let Some(path) = roms.char_rom.as_deref() {
+        if let Err(e) = std::fs::read(path).map_err(|e| e.to_string()).and_then(|data| videx.load_char_rom(&data)) {
+            eprintln!("Failed to load Videx character ROM {}: {}", path, e);
+        }
+    }
+    emu.videx = Some(videx);
 }
 
 /// プロファイラオプション
@@ -966,7 +1555,7 @@ struct ProfileOptions {
     boot_only: bool,
 }
 
-fn run_with_window(emu: &mut Apple2, speed: u32, init_width: usize, init_height: usize, fullscreen: bool, profile_opts: ProfileOptions, config_path: Option<String>, home_path: Option<String>) {
+fn run_with_window(emu: &mut Apple2, speed: u32, init_width: usize, init_height: usize, fullscreen: bool, profile_opts: ProfileOptions, record_opts: RecordOptions, mouse_paddle_mode: Option<MousePaddleMode>, config_path: Option<String>, home_path: Option<String>, videx_roms: VidexRoms, mut gdb: Option<GdbServer>) {
     // 初期ウィンドウサイズ
     // GUI用にツールバーとステータスバーの高さを考慮したウィンドウサイズ
     let gui_height = TOOLBAR_HEIGHT + STATUSBAR_HEIGHT;
@@ -993,6 +1582,10 @@ fn run_with_window(emu: &mut Apple2, speed: u32, init_width: usize, init_height:
 
     window.set_target_fps(60);
     
+    // デバッガコンソール用の文字入力
+    let typed_chars = Rc::new(RefCell::new(Vec::new()));
+    window.set_input_callback(Box::new(TypedChars(Rc::clone(&typed_chars))));
+    
     // GUI初期化
     let mut gui = Gui::new();
     gui.fullscreen = fullscreen;
@@ -1000,9 +1593,8 @@ fn run_with_window(emu: &mut Apple2, speed: u32, init_width: usize, init_height:
     // デバッガパネル初期化
     let mut debugger_panel = DebuggerPanel::new();
     
-    // プロファイラとデバッガ初期化
+    // プロファイラ初期化（デバッガはエミュレータが持つ）
     let mut profiler = Profiler::new();
-    let mut debugger = Debugger::new();
     profiler.enabled = profile_opts.enabled;
     profiler.start_boot();
     
@@ -1036,6 +1628,36 @@ fn run_with_window(emu: &mut Apple2, speed: u32, init_width: usize, init_height:
         home_path.as_deref()
     );
     
+    // 設定ファイルでマウスカードが有効なら挿す
+    if config.mouse_card && emu.mouse.is_none() {
+        emu.mouse = Some(MouseCard::new(MOUSE_SLOT));
+    }
+    if config.videx_card && emu.videx.is_none() {
+        insert_videx(emu, &videx_roms);
+    }
+    if config.thunderclock && emu.thunderclock.is_none() {
+        emu.thunderclock = Some(ThunderClock::new(THUNDERCLOCK_SLOT));
+    }
+    if config.no_slot_clock && emu.no_slot_clock.is_none() {
+        emu.no_slot_clock = Some(NoSlotClock::new());
+    }
+    // コマンドラインで時刻を指定していなければ設定ファイルの値を使う
+    if !emu.clock.is_frozen() && emu.clock.offset_secs() == 0 {
+        if let Some(text) = config.clock_freeze.as_deref() {
+            match clock::parse_datetime(text) {
+                Ok(at) => emu.clock = ClockSource::frozen(at),
+                Err(e) => eprintln!("{}", e),
+            }
+        }
+        emu.clock = emu.clock.clone().with_offset_secs(config.clock_offset_secs);
+    }
+    
+    // マウスでのパドル操作（コマンドライン指定を優先）
+    let mut mouse_paddle = MousePaddle::new(
+        mouse_paddle_mode.unwrap_or(config.mouse_paddle),
+        config.mouse_paddle_sensitivity,
+    );
+    
     // 起動情報を表示
     println!("=== A2RS Apple II Emulator ===");
     println!("Executable dir: {:?}", get_exe_dir());
@@ -1057,6 +1679,8 @@ fn run_with_window(emu: &mut Apple2, speed: u32, init_width: usize, init_height:
     let mut last_mouse_pos: (f32, f32) = (0.0, 0.0);
     let mut last_mouse_move = Instant::now();
     let mut cursor_visible = true;
+    // マウスカードがホストのマウスをキャプチャ中か
+    let mut mouse_captured = false;
 
     let base_frame_duration = Duration::from_micros(16667); // 60 FPS
     let mut prev_keys: Vec<Key> = Vec::new();
@@ -1072,15 +1696,20 @@ fn run_with_window(emu: &mut Apple2, speed: u32, init_width: usize, init_height:
     }
     
     // オーディオ出力を初期化
-    let mut audio_output = match AudioOutput::new() {
+    let audio_settings = config.audio_settings();
+    let mut audio_output = match AudioOutput::open(&audio_settings) {
         Ok(audio) => Some(audio),
         Err(e) => {
             log::warn!("Audio initialization failed: {}", e);
             None
         }
     };
-    let mut speaker = Speaker::new();
+    let mut speaker = Speaker::with_sample_rate(audio_settings.sample_rate);
     speaker.set_volume(config.volume);
+    speaker.set_disk_volume(config.disk_sound_volume);
+    let mut playback_clock = PlaybackClock::new(audio_settings.sample_rate, audio_settings.target_buffered());
+    // 録画・録音用（エミュレート時間どおりのサンプル数で生成する）
+    let mut record_speaker = Speaker::new();
     let mut sound_enabled = true;
     
     // GUIの音量も設定から初期化
@@ -1109,6 +1738,15 @@ fn run_with_window(emu: &mut Apple2, speed: u32, init_width: usize, init_height:
             None
         }
     };
+    
+    // 録画（--record指定時は起動直後から開始、Shift+F10で開始/停止）
+    let mut recording = record_opts.path.as_deref()
+        .and_then(|path| start_recording(std::path::Path::new(path), record_opts.size));
+    let record_format = record_opts.path.as_deref()
+        .and_then(|path| RecordFormat::from_path(std::path::Path::new(path)))
+        .unwrap_or(RecordFormat::Apng);
+    let mut audio_recording = record_opts.audio_path.as_deref()
+        .and_then(|path| start_audio_recording(std::path::Path::new(path), record_opts.audio_raw));
 
     while window.is_open() && emu.running {
         let frame_start = Instant::now();
@@ -1129,7 +1767,7 @@ fn run_with_window(emu: &mut Apple2, speed: u32, init_width: usize, init_height:
             if (mx - last_mouse_pos.0).abs() > 1.0 || (my - last_mouse_pos.1).abs() > 1.0 {
                 last_mouse_pos = (mx, my);
                 last_mouse_move = Instant::now();
-                if !cursor_visible {
+                if !cursor_visible && !mouse_captured {
                     window.set_cursor_visibility(true);
                     cursor_visible = true;
                 }
@@ -1152,6 +1790,32 @@ fn run_with_window(emu: &mut Apple2, speed: u32, init_width: usize, init_height:
             mouse_clicked && !was_down
         };
         
+        // マウスカード: キャプチャ中はポインタの画面内位置とボタンをカードに渡す
+        if mouse_captured && emu.mouse.is_some() {
+            if let Some((mx, my)) = mouse_pos {
+                let gui_h = if gui.fullscreen { 0 } else { TOOLBAR_HEIGHT + STATUSBAR_HEIGHT };
+                let top = if gui.fullscreen { 0 } else { TOOLBAR_HEIGHT };
+                let (fx, fy) = screen_fraction(mx, my, current_window_width, current_window_height.saturating_sub(gui_h), top);
+                emu.set_mouse(fx, fy, mouse_clicked);
+            }
+        }
+        
+        // マウスでのパドル: 位置をパドル0/1、左右ボタンをPB0/PB1に
+        let mouse_paddle_input = if mouse_paddle.is_enabled() {
+            let gui_h = if gui.fullscreen { 0 } else { TOOLBAR_HEIGHT + STATUSBAR_HEIGHT };
+            let top = if gui.fullscreen { 0 } else { TOOLBAR_HEIGHT };
+            let area_h = current_window_height.saturating_sub(gui_h);
+            // 絶対モードでは画面の上にあるときだけボタンを拾う（ツールバーのクリックと区別）
+            let over_screen = mouse_pos.is_some_and(|(_, my)| my >= top as f32 && my < (top + area_h) as f32);
+            let pos = mouse_pos.map(|(mx, my)| screen_fraction(mx, my, current_window_width, area_h, top));
+            mouse_paddle.update(pos, mouse_captured).map(|paddles| {
+                let buttons = over_screen || mouse_captured;
+                (paddles, buttons && mouse_clicked, buttons && window.get_mouse_down(MouseButton::Right))
+            })
+        } else {
+            None
+        };
+        
         // 音量スライダーのドラッグ処理
         if gui.volume_dragging {
             if mouse_clicked {
@@ -1163,7 +1827,7 @@ fn run_with_window(emu: &mut Apple2, speed: u32, init_width: usize, init_height:
             }
         }
         
-        if click_event && !gui.fullscreen {
+        if click_event && !gui.fullscreen && !mouse_captured {
             // 音量スライダーのクリック
             if gui.is_over_volume_slider(current_window_width) {
                 gui.start_volume_drag(current_window_width);
@@ -1191,6 +1855,7 @@ fn run_with_window(emu: &mut Apple2, speed: u32, init_width: usize, init_height:
                                     };
                                     if emu.disk.insert_disk_with_name(drive, &data, format, Some(path.clone())).is_ok() {
                                         println!("Inserted {} into drive {}", path, drive + 1);
+                                        emu.restart_rewind();
                                     }
                                 }
                             }
@@ -1250,6 +1915,7 @@ fn run_with_window(emu: &mut Apple2, speed: u32, init_width: usize, init_height:
                         if let Ok(json) = std::fs::read_to_string(&filepath) {
                             if let Ok(state) = serde_json::from_str(&json) {
                                 if let Ok(_) = emu.load_state(&state) {
+                                    emu.restart_rewind();
                                     println!("Loaded from slot {} ({:?})", current_slot, filepath);
                                     gui.trigger_button_highlight(btn);
                                     speaker.trigger_ui_click();
@@ -1323,6 +1989,7 @@ fn run_with_window(emu: &mut Apple2, speed: u32, init_width: usize, init_height:
                                     };
                                     if emu.disk.insert_disk_with_name(drive, &data, format, Some(path.clone())).is_ok() {
                                         println!("Inserted {} into drive {}", path, drive + 1);
+                                        emu.restart_rewind();
                                     }
                                 }
                             }
@@ -1471,6 +2138,30 @@ fn run_with_window(emu: &mut Apple2, speed: u32, init_width: usize, init_height:
                     7 => { // Save Dir
                         gui.start_text_input(7, &config.save_dir);
                     }
+                    8 => { // Tape 再生/停止
+                        emu.cassette.toggle_play(emu.total_cycles);
+                        emu.restart_rewind();
+                    }
+                    9 => { // Tape Rewind
+                        emu.cassette.rewind(emu.total_cycles);
+                        emu.restart_rewind();
+                    }
+                    10 => { // Tape Record
+                        if emu.cassette.is_recording() {
+                            save_tape_recording(emu, &config.save_dir_path());
+                        } else {
+                            emu.cassette.start_recording(emu.total_cycles);
+                            println!("Tape recording started");
+                        }
+                        emu.restart_rewind();
+                    }
+                    11 => { // Tape Fast Load
+                        emu.cassette.fast_load = !emu.cassette.fast_load;
+                    }
+                    12 => { // Disk Sound（OFF → 25% → 50% → 75% → 100%）
+                        let steps = (speaker.disk_volume() * 4.0).round() as u32;
+                        speaker.set_disk_volume(((steps + 1) % 5) as f32 / 4.0);
+                    }
                     _ => {}
                 }
             }
@@ -1506,7 +2197,7 @@ fn run_with_window(emu: &mut Apple2, speed: u32, init_width: usize, init_height:
             gui.trigger_reset_highlight();
             speaker.trigger_reset_sound();
             profiler.reset();
-            debugger.reset();
+            emu.debugger.reset();
             profiler.start_boot();
             // リセット時にブーストを再開
             if emu.disk.drives[0].disk.disk_loaded {
@@ -1548,6 +2239,9 @@ fn run_with_window(emu: &mut Apple2, speed: u32, init_width: usize, init_height:
             println!("Auto quality: {}", if auto_quality { "ON" } else { "OFF" });
         }
         
+        // 今フレームに入力された文字（コンソール以外では捨てる）
+        let typed: Vec<char> = typed_chars.borrow_mut().drain(..).collect();
+        
         // デバッガパネルが表示中の場合のキー処理
         if debugger_panel.visible {
             // Tabでタブ切り替え（次のタブへ）
@@ -1571,24 +2265,94 @@ fn run_with_window(emu: &mut Apple2, speed: u32, init_width: usize, init_height:
                 }
             }
             
-            // F6: ステップ実行
-            if window.is_key_pressed(Key::F6, KeyRepeat::No) {
-                debugger.step();
+            // コンソールタブ: 文字入力、Enterで実行、↑↓で履歴
+            if debugger_panel.console_active() {
+                let console = &mut debugger_panel.console;
+                console.input.extend(typed.iter().filter(|c| !c.is_control()));
+                if window.is_key_pressed(Key::Backspace, KeyRepeat::Yes) {
+                    console.input.pop();
+                }
+                if window.is_key_pressed(Key::Up, KeyRepeat::No) {
+                    console.history_prev();
+                }
+                if window.is_key_pressed(Key::Down, KeyRepeat::No) {
+                    console.history_next();
+                }
+                if window.is_key_pressed(Key::Enter, KeyRepeat::No) {
+                    console.submit(emu);
+                    // g/s/n などで実行を始めたら一時停止も解除する
+                    if matches!(emu.debugger.state, DebuggerState::Running | DebuggerState::Stepping) {
+                        paused = false;
+                    }
+                }
+            }
+            
+            let shift = window.is_key_down(Key::LeftShift) || window.is_key_down(Key::RightShift);
+            let ctrl = window.is_key_down(Key::LeftCtrl) || window.is_key_down(Key::RightCtrl);
+            
+            // F6: ステップ実行（実行中なら1命令進めて停止）
+            // Shift+F6: ステップオーバー、Ctrl+F6: ステップアウト
+            if window.is_key_pressed(Key::F6, KeyRepeat::Yes) {
+                if shift {
+                    emu.step_over();
+                    paused = false;
+                } else if ctrl {
+                    emu.step_out();
+                    paused = false;
+                } else {
+                    emu.debugger.step();
+                }
             }
             
-            // F7: 継続
+            // F7: 継続、Shift+F7: メモリビューの先頭アドレスまで実行、Ctrl+F7: 100命令実行
             if window.is_key_pressed(Key::F7, KeyRepeat::No) {
-                debugger.resume();
+                if shift {
+                    emu.run_to(debugger_panel.memory_offset);
+                } else if ctrl {
+                    emu.run_instructions(100);
+                } else {
+                    emu.debugger.resume();
+                }
                 paused = false;
             }
             
-            // F8: ブレーク
+            // F8: ブレーク、Shift+F8: トレースの記録を一時停止・再開、Ctrl+F8: 1命令戻る
             if window.is_key_pressed(Key::F8, KeyRepeat::No) {
-                debugger.pause();
-                paused = true;
+                if shift {
+                    match emu.debugger.tracer_mut() {
+                        Some(tracer) => {
+                            tracer.toggle();
+                            println!("Trace: {}", if tracer.is_active() { "recording" } else { "paused" });
+                        }
+                        None => println!("No trace (use --trace or the console 'trace' command)"),
+                    }
+                } else if ctrl {
+                    if let Err(e) = emu.step_back(1) {
+                        println!("{}", e);
+                    }
+                } else {
+                    emu.debugger.pause();
+                }
             }
         } else {
-            // デバッガパネル非表示時のF6/F8
+            // デバッガパネル非表示時のF6/F7/F8
+            // F7でマウスカードのキャプチャ切り替え
+            if window.is_key_pressed(Key::F7, KeyRepeat::No) {
+                if emu.mouse.is_some() || mouse_paddle.mode == MousePaddleMode::Relative {
+                    mouse_captured = !mouse_captured;
+                    window.set_cursor_visibility(!mouse_captured);
+                    cursor_visible = !mouse_captured;
+                    if !mouse_captured {
+                        if let Some(card) = emu.mouse.as_mut() {
+                            card.set_button(false);
+                        }
+                    }
+                    println!("Mouse capture: {}", if mouse_captured { "ON (F7 to release)" } else { "OFF" });
+                } else {
+                    println!("No mouse card installed (use --mouse or --mouse-paddle relative)");
+                }
+            }
+            
             // F6でサウンドON/OFF
             if window.is_key_pressed(Key::F6, KeyRepeat::No) {
                 sound_enabled = !sound_enabled;
@@ -1645,6 +2409,7 @@ fn run_with_window(emu: &mut Apple2, speed: u32, init_width: usize, init_height:
                         Ok(state) => {
                             match emu.load_state(&state) {
                                 Ok(_) => {
+                                    emu.restart_rewind();
                                     println!("State loaded from slot {} ({:?})", current_slot, filepath);
                                 }
                                 Err(e) => println!("Failed to load state: {}", e),
@@ -1657,8 +2422,25 @@ fn run_with_window(emu: &mut Apple2, speed: u32, init_width: usize, init_height:
             }
         }
         
-        // F10でスクリーンショット
-        if window.is_key_pressed(Key::F10, KeyRepeat::No) {
+        // Shift+F10で録画開始/停止
+        let shift_down = window.is_key_down(Key::LeftShift) || window.is_key_down(Key::RightShift);
+        if shift_down && window.is_key_pressed(Key::F10, KeyRepeat::No) {
+            if let Some(rec) = recording.take() {
+                stop_recording(rec);
+            } else {
+                let screenshot_dir = config.screenshot_dir_path();
+                let _ = fs::create_dir_all(&screenshot_dir);
+                let filename = screenshot_dir.join(format!("recording_{}.{}",
+                    std::time::SystemTime::now()
+                        .duration_since(std::time::UNIX_EPOCH)
+                        .unwrap()
+                        .as_secs(),
+                    record_format.extension()));
+                recording = start_recording(&filename, record_opts.size);
+            }
+            speaker.trigger_ui_click();
+        } else if window.is_key_pressed(Key::F10, KeyRepeat::No) {
+            // F10でスクリーンショット
             let screenshot_dir = config.screenshot_dir_path();
             let _ = fs::create_dir_all(&screenshot_dir);
             let filename = screenshot_dir.join(format!("screenshot_{}.png", 
@@ -1704,8 +2486,8 @@ fn run_with_window(emu: &mut Apple2, speed: u32, init_width: usize, init_height:
         let shift = window.is_key_down(Key::LeftShift) || window.is_key_down(Key::RightShift);
         let ctrl = window.is_key_down(Key::LeftCtrl) || window.is_key_down(Key::RightCtrl);
         
-        // メニューが開いている間はエミュレータへのキー入力をブロック
-        let menu_open = gui.is_disk_menu_open() || gui.overlay_visible;
+        // メニューやデバッガコンソールを使っている間はエミュレータへのキー入力をブロック
+        let menu_open = gui.is_disk_menu_open() || gui.overlay_visible || debugger_panel.console_active();
         
         // 現在押されているキーを取得
         let current_keys: Vec<Key> = if menu_open {
@@ -1745,8 +2527,8 @@ fn run_with_window(emu: &mut Apple2, speed: u32, init_width: usize, init_height:
             )
         };
         let (mut button0, mut button1) = (
-            window.is_key_down(Key::LeftAlt) || window.is_key_down(Key::Z),
-            window.is_key_down(Key::RightAlt) || window.is_key_down(Key::X),
+            window.is_key_down(Key::LeftAlt) || (!menu_open && window.is_key_down(Key::Z)),
+            window.is_key_down(Key::RightAlt) || (!menu_open && window.is_key_down(Key::X)),
         );
         
         // ゲームパッドからの入力をマージ
@@ -1774,30 +2556,55 @@ fn run_with_window(emu: &mut Apple2, speed: u32, init_width: usize, init_height:
             }
         }
         
-        // パドル値を設定
+        // マウスのボタンをマージ
+        let mouse_paddles = mouse_paddle_input.filter(|_| !menu_open).map(|(paddles, pb0, pb1)| {
+            button0 |= pb0;
+            button1 |= pb1;
+            paddles
+        });
+        
+        // パドル値を設定（アナログスティック > 方向キー > マウス > 中央）
         if let Some(gx) = gamepad_x {
             // アナログスティックの値を0-255に変換
             let x_value = ((gx + 1.0) * 127.5).clamp(0.0, 255.0) as u8;
-            emu.memory.set_paddle(0, x_value);
+            emu.set_paddle(0, x_value);
         } else {
             // デジタル入力
-            let x_value = if joy_left { 0u8 } else if joy_right { 255u8 } else { 128u8 };
-            emu.memory.set_paddle(0, x_value);
+            let x_value = if joy_left {
+                0u8
+            } else if joy_right {
+                255u8
+            } else {
+                mouse_paddles.map_or(128u8, |(x, _)| x)
+            };
+            emu.set_paddle(0, x_value);
         }
         
         if let Some(gy) = gamepad_y {
             let y_value = ((gy + 1.0) * 127.5).clamp(0.0, 255.0) as u8;
-            emu.memory.set_paddle(1, y_value);
+            emu.set_paddle(1, y_value);
         } else {
-            let y_value = if joy_up { 0u8 } else if joy_down { 255u8 } else { 128u8 };
-            emu.memory.set_paddle(1, y_value);
+            let y_value = if joy_up {
+                0u8
+            } else if joy_down {
+                255u8
+            } else {
+                mouse_paddles.map_or(128u8, |(_, y)| y)
+            };
+            emu.set_paddle(1, y_value);
         }
         
-        emu.memory.set_button(0, button0);
-        emu.memory.set_button(1, button1);
+        emu.set_button(0, button0);
+        emu.set_button(1, button1);
         
         prev_keys = current_keys;
 
+        // GDBのパケットを処理（接続中の停止はGDBに通知する）
+        if let Some(server) = gdb.as_mut() {
+            server.poll(emu);
+        }
+        let gdb_attached = gdb.as_ref().is_some_and(GdbServer::is_connected);
+
         // 一時停止中でなければエミュレーション実行
         if !paused {
             // 速度に応じてフレーム数を調整
@@ -1807,6 +2614,20 @@ fn run_with_window(emu: &mut Apple2, speed: u32, init_width: usize, init_height:
                 emu.run_frame();
             }
             
+            // ブレークポイント・ウォッチポイントで止まったらパネルを開く（トレースはここまでを書き出す）
+            if !gdb_attached {
+                if let Some(reason) = emu.debugger.take_break() {
+                    println!("Debugger: stopped at {} (PC={})", reason, emu.debugger.symbols.describe(emu.cpu.regs.pc));
+                    debugger_panel.visible = true;
+                    if let Some(result) = emu.debugger.tracer_mut().map(|t| t.save()) {
+                        match result {
+                            Ok(count) => println!("Trace: wrote {} instructions", count),
+                            Err(e) => eprintln!("{}", e),
+                        }
+                    }
+                }
+            }
+            
             // プロファイラ: ブート段階の自動検出
             if profiler.enabled {
                 let pc = emu.cpu.regs.pc;
@@ -1862,31 +2683,69 @@ fn run_with_window(emu: &mut Apple2, speed: u32, init_width: usize, init_height:
                 // ディスク情報を更新
                 profiler.disk_info.current_track = emu.disk.drives[emu.disk.curr_drive].current_track();
                 profiler.cpu_info.last_pc = pc;
+                if let Some(ref audio) = audio_output {
+                    profiler.audio = audio.stats();
+                }
             }
             
             // オーディオ処理
-            if sound_enabled {
-                if current_speed == 1 {
-                    // 通常速度時：スピーカークリックを処理
-                    let clicks = emu.take_speaker_clicks();
-                    for cycle in clicks {
-                        speaker.click(cycle);
+            let clicks = emu.take_speaker_clicks();
+            let mut record_result = Ok(());
+            let mut audio_record_result = Ok(());
+            if let Some(ref mut rec) = audio_recording {
+                audio_record_result = rec.push_clicks(&clicks, frame_start_cycle, emu.total_cycles);
+            }
+            let mech_events = emu.disk.take_mech_events();
+            if let Some(ref mut audio) = audio_output {
+                // 実時間の経過に合わせたサンプル数へ、実行したサイクル区間を詰めて変換する
+                for &cycle in &clicks {
+                    speaker.click(cycle);
+                }
+                for event in mech_events {
+                    match event {
+                        DiskMechEvent::Step(cycle) => speaker.disk_step(cycle),
+                        DiskMechEvent::Bump(cycle) => speaker.disk_bump(cycle),
                     }
-                } else {
-                    // 高速モード時はスピーカークリックを破棄
-                    emu.take_speaker_clicks();
                 }
-                
-                // サンプルを生成して再生（リセット音やUIクリック音は常に処理）
-                let cycles_per_frame = emu.total_cycles - frame_start_cycle;
-                if let Some(ref mut audio) = audio_output {
-                    let samples = speaker.generate_samples(frame_start_cycle, cycles_per_frame.max(17030));
-                    audio.play_samples(samples);
+                speaker.set_disk_motor(emu.disk.motor_on);
+                let count = playback_clock.samples_due(audio.buffered_samples());
+                audio.play_samples(speaker.render(frame_start_cycle, emu.total_cycles, count));
+            }
+            if recording.is_some() || audio_recording.is_some() {
+                // 録画・録音は速度に関係なくエミュレート時間どおりに記録する
+                record_speaker.set_enabled(sound_enabled);
+                record_speaker.set_volume(speaker.volume());
+                for &cycle in &clicks {
+                    record_speaker.click(cycle);
+                }
+                let samples = record_speaker.generate_samples(frame_start_cycle, emu.total_cycles - frame_start_cycle);
+                if let Some(ref mut rec) = recording {
+                    record_result = rec.push_audio(Some(samples), samples.len());
+                }
+                if let Some(ref mut rec) = audio_recording {
+                    audio_record_result = audio_record_result.and_then(|_| rec.push_samples(Some(samples)));
+                }
+            }
+            if let Err(e) = audio_record_result {
+                eprintln!("Audio recording failed: {}", e);
+                if let Some(rec) = audio_recording.take() {
+                    stop_audio_recording(rec);
                 }
-            } else {
-                // サウンド無効時はクリックを破棄
-                emu.take_speaker_clicks();
             }
+            
+            // 録画: 表示するフレームを取り込む
+            if let Some(ref mut rec) = recording {
+                let result = record_result.and_then(|_| rec.push_frame(emu.get_framebuffer(), SCREEN_WIDTH, SCREEN_HEIGHT));
+                if let Err(e) = result {
+                    eprintln!("Recording failed: {}", e);
+                    if let Some(rec) = recording.take() {
+                        stop_recording(rec);
+                    }
+                }
+            }
+        } else {
+            // 再開時に停止中の時間をまとめて生成しない
+            playback_clock.reset();
         }
 
         // フレームバッファを取得
@@ -1985,6 +2844,11 @@ fn run_with_window(emu: &mut Apple2, speed: u32, init_width: usize, init_height:
                 quality_level,
                 auto_quality,
                 paused,
+                recording: recording.is_some(),
+                mouse_captured,
+                tape_status: emu.cassette.status_text(emu.total_cycles),
+                tape_fast_load: emu.cassette.fast_load,
+                disk_sound_volume: speaker.disk_volume(),
                 disk1_name: None, // TODO: ディスク名を取得
                 disk2_name: None,
                 disk1_active: disk1_reading && !disk1_writing,  // 読み込み中（書き込みでない）
@@ -2021,6 +2885,11 @@ fn run_with_window(emu: &mut Apple2, speed: u32, init_width: usize, init_height:
                 quality_level,
                 auto_quality,
                 paused,
+                recording: recording.is_some(),
+                mouse_captured,
+                tape_status: emu.cassette.status_text(emu.total_cycles),
+                tape_fast_load: emu.cassette.fast_load,
+                disk_sound_volume: speaker.disk_volume(),
                 disk1_name: None,
                 disk2_name: None,
                 disk1_active: false,
@@ -2046,6 +2915,7 @@ fn run_with_window(emu: &mut Apple2, speed: u32, init_width: usize, init_height:
                 sp: emu.cpu.regs.sp,
                 flags: emu.cpu.regs.status,
                 current_opcode: emu.memory.main_ram[emu.cpu.regs.pc as usize],
+                irq_sources: emu.irq.asserted_names(),
             };
             
             let disk_debug = DiskDebugInfo {
@@ -2068,7 +2938,7 @@ fn run_with_window(emu: &mut Apple2, speed: u32, init_width: usize, init_height:
                 current_window_height,
                 panel_x,
                 &profiler,
-                &debugger,
+                &emu.debugger,
                 &cpu_regs,
                 &emu.memory.main_ram[..],
                 &disk_debug,
@@ -2200,6 +3070,17 @@ fn run_with_window(emu: &mut Apple2, speed: u32, init_width: usize, init_height:
             }
         }
     }
+    
+    // 録画中なら終了してファイルを確定
+    if let Some(rec) = recording.take() {
+        stop_recording(rec);
+    }
+    if let Some(rec) = audio_recording.take() {
+        stop_audio_recording(rec);
+    }
+    if emu.cassette.is_recording() {
+        save_tape_recording(emu, &config.save_dir_path());
+    }
 
     // 設定を保存（読み込んだファイルに保存）
     config.current_slot = current_slot;
@@ -2208,6 +3089,7 @@ fn run_with_window(emu: &mut Apple2, speed: u32, init_width: usize, init_height:
     config.auto_quality = auto_quality;
     // fast_disk は常にONなので保存しない
     config.volume = gui.get_volume();
+    config.disk_sound_volume = speaker.disk_volume();
     if let Err(e) = config.save_to(&config_file_path) {
         eprintln!("Failed to save config to {:?}: {}", config_file_path, e);
     }
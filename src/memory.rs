@@ -4,6 +4,48 @@
 
 use crate::cpu::MemoryBus;
 
+/// アクセス先のメモリ
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MemoryBank {
+    /// メインRAM
+    Main,
+    /// 補助RAM（IIe）
+    Aux,
+    /// Language Card RAM バンク1
+    LcBank1,
+    /// Language Card RAM バンク2
+    LcBank2,
+    /// ROM（スロットROM含む）
+    Rom,
+    /// I/O・ソフトスイッチ（$C000-$C0FF）
+    Io,
+}
+
+impl MemoryBank {
+    pub fn name(&self) -> &'static str {
+        match self {
+            MemoryBank::Main => "main",
+            MemoryBank::Aux => "aux",
+            MemoryBank::LcBank1 => "lc1",
+            MemoryBank::LcBank2 => "lc2",
+            MemoryBank::Rom => "rom",
+            MemoryBank::Io => "io",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "main" => Some(MemoryBank::Main),
+            "aux" => Some(MemoryBank::Aux),
+            "lc1" => Some(MemoryBank::LcBank1),
+            "lc2" => Some(MemoryBank::LcBank2),
+            "rom" => Some(MemoryBank::Rom),
+            "io" => Some(MemoryBank::Io),
+            _ => None,
+        }
+    }
+}
+
 /// Apple IIのモデル
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum AppleModel {
@@ -387,7 +429,7 @@ impl Memory {
                 self.switches.keyboard_strobe &= 0x7F;
                 result
             }
-            0x20..=0x2F => 0x00, // カセットI/O（未実装）
+            0x20..=0x2F => 0x00, // カセット出力（Apple2側でテープデッキに通知）
             0x30..=0x3F => { self.switches.speaker_click = !self.switches.speaker_click; 0x00 }
             0x40..=0x4F => 0x00, // ゲームI/O
             0x50 => { self.switches.text_mode = false; 0x00 }
@@ -422,7 +464,7 @@ impl Memory {
                 }
                 0x00
             }
-            0x60 => 0x00, // カセットI/O
+            0x60 => 0x00, // カセット入力（Apple2側でテープデッキから読む）
             // ゲームポート: ボタン
             0x61 => if self.switches.button0 { 0x80 } else { 0x00 },
             0x62 => if self.switches.button1 { 0x80 } else { 0x00 },
@@ -566,10 +608,9 @@ impl Memory {
         }
         0x00
     }
-}
 
-impl MemoryBus for Memory {
-    fn read(&mut self, address: u16) -> u8 {
+    /// 現在のバンク構成で読み取る（ソフトスイッチを動かさない。デバッガ用）
+    pub fn peek(&self, address: u16) -> u8 {
         match address {
             0x0000..=0x01FF => {
                 if self.is_iie() && self.switches.altzp {
@@ -579,13 +620,15 @@ impl MemoryBus for Memory {
                 }
             }
             0x0200..=0xBFFF => {
-                if self.is_iie() && self.switches.ramrd {
+                if self.aux_selected(address, false) {
                     self.aux_ram[address as usize]
                 } else {
                     self.main_ram[address as usize]
                 }
             }
-            0xC000..=0xC0FF => self.read_soft_switch(address),
+            // I/Oはキーボードラッチ以外は副作用を避けて0
+            0xC000..=0xC00F => self.switches.keyboard_strobe,
+            0xC010..=0xC0FF => 0,
             0xC100..=0xC7FF => {
                 // スロットROM領域 - ROMから読み取り
                 if !self.rom.is_empty() && self.rom.len() >= 16384 {
@@ -634,6 +677,65 @@ impl MemoryBus for Memory {
         }
     }
 
+    /// 現在のバンク構成で書き込む（ソフトスイッチを動かさない。デバッガ用）
+    pub fn poke(&mut self, address: u16, value: u8) {
+        if !(0xC000..=0xC0FF).contains(&address) {
+            self.write(address, value);
+        }
+    }
+
+    /// アドレスへのアクセスが実際に届くメモリ（デバッガのウォッチポイント用）
+    ///
+    /// $E000-$FFFFのLC RAMは両バンク共通なので、選択中のバンクとして返す。
+    pub fn bank_of(&self, address: u16, write: bool) -> MemoryBank {
+        match address {
+            0x0000..=0x01FF => {
+                if self.is_iie() && self.switches.altzp { MemoryBank::Aux } else { MemoryBank::Main }
+            }
+            0x0200..=0xBFFF => {
+                if self.aux_selected(address, write) { MemoryBank::Aux } else { MemoryBank::Main }
+            }
+            0xC000..=0xC0FF => MemoryBank::Io,
+            0xC100..=0xCFFF => MemoryBank::Rom,
+            _ => {
+                let ram = if write { self.switches.lc_write_enable } else { self.switches.lc_read_enable };
+                if !ram {
+                    MemoryBank::Rom
+                } else if self.switches.lc_bank2 {
+                    MemoryBank::LcBank2
+                } else {
+                    MemoryBank::LcBank1
+                }
+            }
+        }
+    }
+
+    /// $0200-$BFFFのアクセスがAUX RAMに向かうか
+    /// 80STOREがオンの場合、表示ページ（$0400-$07FF、HIRES時は$2000-$3FFFも）は
+    /// RAMRD/RAMWRTではなくPAGE2で切り替わる
+    fn aux_selected(&self, address: u16, write: bool) -> bool {
+        if !self.is_iie() {
+            return false;
+        }
+        if self.switches.store_80 {
+            let display_page = matches!(address, 0x0400..=0x07FF)
+                || (self.switches.hires && matches!(address, 0x2000..=0x3FFF));
+            if display_page {
+                return self.switches.page2;
+            }
+        }
+        if write { self.switches.ramwrt } else { self.switches.ramrd }
+    }
+}
+
+impl MemoryBus for Memory {
+    fn read(&mut self, address: u16) -> u8 {
+        match address {
+            0xC000..=0xC0FF => self.read_soft_switch(address),
+            _ => self.peek(address),
+        }
+    }
+
     fn write(&mut self, address: u16, value: u8) {
         match address {
             0x0000..=0x01FF => {
@@ -644,7 +746,7 @@ impl MemoryBus for Memory {
                 }
             }
             0x0200..=0xBFFF => {
-                if self.is_iie() && self.switches.ramwrt {
+                if self.aux_selected(address, true) {
                     self.aux_ram[address as usize] = value;
                 } else {
                     self.main_ram[address as usize] = value;
@@ -670,3 +772,35 @@ impl MemoryBus for Memory {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn store80_page2_routes_display_pages_to_aux() {
+        let mut memory = Memory::new(AppleModel::AppleIIe);
+        memory.switches.store_80 = true;
+        memory.switches.page2 = true;
+        memory.write(0x0400, 0x11);
+        memory.write(0x07FF, 0x12);
+        // HIRESがオフなら$2000-$3FFFはRAMWRTに従う（メイン）
+        memory.write(0x2000, 0x21);
+        memory.switches.hires = true;
+        memory.write(0x2001, 0x22);
+        memory.write(0x3FFF, 0x23);
+        // 表示ページ以外は影響を受けない
+        memory.write(0x0800, 0x31);
+
+        assert_eq!((memory.aux_ram[0x0400], memory.aux_ram[0x07FF]), (0x11, 0x12));
+        assert_eq!((memory.main_ram[0x0400], memory.main_ram[0x07FF]), (0, 0));
+        assert_eq!((memory.main_ram[0x2000], memory.aux_ram[0x2000]), (0x21, 0));
+        assert_eq!((memory.aux_ram[0x2001], memory.aux_ram[0x3FFF]), (0x22, 0x23));
+        assert_eq!((memory.main_ram[0x0800], memory.aux_ram[0x0800]), (0x31, 0));
+        assert_eq!(memory.peek(0x2001), 0x22);
+
+        memory.switches.page2 = false;
+        memory.write(0x0400, 0x41);
+        assert_eq!((memory.main_ram[0x0400], memory.aux_ram[0x0400]), (0x41, 0x11));
+    }
+}
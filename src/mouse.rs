@@ -0,0 +1,422 @@
+//! AppleMouse II インターフェースカード
+//!
+//! 実機のカードは6805マイコンと6821 PIAで構成され、スロットROMのファームウェアが
+//! それを操作する。ここではファームウェアを差し替え、各エントリポイントが
+//! `STA $C0n0+コマンド` でカードにコマンドを渡す小さなスタブにしている。
+//! コマンドの結果はApple IIのマウスファームウェアと同じスクリーンホールに書き込み、
+//! キャリーは `LDA $C0nF / LSR A` で返す。
+//!
+//! スクリーンホール（n = スロット番号）:
+//! - `$0478+n` / `$0578+n`: X座標 下位/上位
+//! - `$04F8+n` / `$05F8+n`: Y座標 下位/上位
+//! - `$0778+n`: ステータス
+//! - `$07F8+n`: モード
+//!
+//! CLAMPMOUSE の最小値/最大値はスロットに関係なく `$0478`/`$0578`（最小）と
+//! `$04F8`/`$05F8`（最大）から読む。
+
+use crate::interrupt::{InterruptController, IrqSource};
+use crate::savestate::MouseState;
+
+/// マウスカードを挿す既定のスロット
+pub const MOUSE_SLOT: usize = 4;
+
+/// ファームウェアのエントリポイント（$Cn12-$Cn19のオフセット表の順）
+const COMMANDS: [MouseCommand; 8] = [
+    MouseCommand::Set,
+    MouseCommand::Serve,
+    MouseCommand::Read,
+    MouseCommand::Clear,
+    MouseCommand::Pos,
+    MouseCommand::Clamp,
+    MouseCommand::Home,
+    MouseCommand::Init,
+];
+
+/// スタブの先頭オフセット
+const STUB_BASE: u8 = 0x20;
+
+/// 1スタブのバイト数（STA abs / LDA abs / LSR A / RTS）
+const STUB_SIZE: u8 = 8;
+
+/// キャリー（結果）を読むレジスタ
+const RESULT_REG: u8 = 0x0F;
+
+/// 座標の既定のクランプ範囲
+const DEFAULT_CLAMP: (i32, i32) = (0, 1023);
+
+/// モードビット
+pub mod mode {
+    /// マウス有効
+    pub const ON: u8 = 0x01;
+    /// 移動で割り込み
+    pub const IRQ_MOVE: u8 = 0x02;
+    /// ボタンで割り込み
+    pub const IRQ_BUTTON: u8 = 0x04;
+    /// VBLで割り込み
+    pub const IRQ_VBL: u8 = 0x08;
+}
+
+/// ステータスビット
+pub mod status {
+    /// ボタンが押されている
+    pub const BUTTON: u8 = 0x80;
+    /// 前回のREADMOUSE時にボタンが押されていた
+    pub const LAST_BUTTON: u8 = 0x40;
+    /// 前回のREADMOUSE以降に移動した
+    pub const MOVED: u8 = 0x20;
+    /// VBLによる割り込み
+    pub const IRQ_VBL: u8 = 0x08;
+    /// ボタンによる割り込み
+    pub const IRQ_BUTTON: u8 = 0x04;
+    /// 移動による割り込み
+    pub const IRQ_MOVE: u8 = 0x02;
+}
+
+/// ファームウェアのコマンド
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum MouseCommand {
+    Set,
+    Serve,
+    Read,
+    Clear,
+    Pos,
+    Clamp,
+    Home,
+    Init,
+}
+
+/// AppleMouse II カード
+pub struct MouseCard {
+    slot: usize,
+    rom: [u8; 256],
+    /// 現在の座標
+    x: i32,
+    y: i32,
+    /// X/Yのクランプ範囲（最小, 最大）
+    clamp: [(i32, i32); 2],
+    /// SETMOUSEで設定したモード
+    mode: u8,
+    /// ホスト側のボタン状態
+    button: bool,
+    /// 前回READMOUSE時のボタン状態
+    last_button: bool,
+    /// 前回のVBL時のボタン状態（ボタン割り込み判定用）
+    vbl_button: bool,
+    /// 前回READMOUSE以降に移動したか
+    moved: bool,
+    /// 前回のVBL以降に移動したか（移動割り込み判定用）
+    moved_since_vbl: bool,
+    /// 未処理の割り込み要因（statusのIRQビット）
+    pending: u8,
+    /// 直前のコマンドの結果（true = キャリーセット）
+    carry: bool,
+}
+
+impl MouseCard {
+    pub fn new(slot: usize) -> Self {
+        MouseCard {
+            slot,
+            rom: build_rom(slot),
+            x: 0,
+            y: 0,
+            clamp: [DEFAULT_CLAMP; 2],
+            mode: 0,
+            button: false,
+            last_button: false,
+            vbl_button: false,
+            moved: false,
+            moved_since_vbl: false,
+            pending: 0,
+            carry: false,
+        }
+    }
+
+    /// 挿しているスロット
+    pub fn slot(&self) -> usize {
+        self.slot
+    }
+
+    /// カードが応答するアドレスか（スロットI/OまたはスロットROM）
+    pub fn claims(&self, address: u16) -> bool {
+        let io = 0xC080 + (self.slot as u16) * 16;
+        let rom = 0xC000 + (self.slot as u16) * 256;
+        (io..io + 16).contains(&address) || (rom..rom + 256).contains(&address)
+    }
+
+    /// バス読み取り（`claims` が真のアドレスのみ）
+    pub fn read(&self, address: u16) -> u8 {
+        if address < 0xC100 {
+            if (address & 0x0F) as u8 == RESULT_REG {
+                self.carry as u8
+            } else {
+                0
+            }
+        } else {
+            self.rom[(address & 0xFF) as usize]
+        }
+    }
+
+    /// バス書き込み（`claims` が真のアドレスのみ）
+    ///
+    /// `ram` はスクリーンホールを読み書きするメインRAM。
+    pub fn write(&mut self, address: u16, value: u8, ram: &mut [u8], irq: &mut InterruptController) {
+        if address >= 0xC100 {
+            return;
+        }
+        if let Some(&command) = COMMANDS.get((address & 0x0F) as usize) {
+            self.execute(command, value, ram, irq);
+        }
+    }
+
+    /// マウスが有効（SETMOUSEでビット0が立っている）か
+    pub fn is_active(&self) -> bool {
+        self.mode & mode::ON != 0
+    }
+
+    /// 現在の座標
+    pub fn position(&self) -> (i32, i32) {
+        (self.x, self.y)
+    }
+
+    /// ホストのポインタ位置を設定（0.0〜1.0で正規化、クランプ範囲に割り当てる）
+    pub fn set_host_position(&mut self, fx: f32, fy: f32) {
+        let map = |f: f32, (lo, hi): (i32, i32)| lo + ((hi - lo) as f32 * f.clamp(0.0, 1.0)).round() as i32;
+        let (x, y) = (map(fx, self.clamp[0]), map(fy, self.clamp[1]));
+        if (x, y) != (self.x, self.y) {
+            self.x = x;
+            self.y = y;
+            self.moved = true;
+            self.moved_since_vbl = true;
+        }
+    }
+
+    /// ホストのボタン状態を設定
+    pub fn set_button(&mut self, down: bool) {
+        self.button = down;
+    }
+
+    /// ホスト側のボタン状態
+    pub fn button(&self) -> bool {
+        self.button
+    }
+
+    /// 座標・モード・割り込みの状態を保存
+    pub fn save_state(&self) -> MouseState {
+        MouseState {
+            x: self.x,
+            y: self.y,
+            clamp: self.clamp,
+            mode: self.mode,
+            button: self.button,
+            last_button: self.last_button,
+            vbl_button: self.vbl_button,
+            moved: self.moved,
+            moved_since_vbl: self.moved_since_vbl,
+            pending: self.pending,
+            carry: self.carry,
+        }
+    }
+
+    /// 座標・モード・割り込みの状態を復元（IRQラインはセーブステートのIRQ要求元で戻す）
+    pub fn load_state(&mut self, state: &MouseState) {
+        self.x = state.x;
+        self.y = state.y;
+        self.clamp = state.clamp;
+        self.mode = state.mode;
+        self.button = state.button;
+        self.last_button = state.last_button;
+        self.vbl_button = state.vbl_button;
+        self.moved = state.moved;
+        self.moved_since_vbl = state.moved_since_vbl;
+        self.pending = state.pending;
+        self.carry = state.carry;
+    }
+
+    /// 垂直帰線期間の開始（フレームごとに呼ぶ）
+    ///
+    /// 実機のカードと同様、割り込みはVBLに同期して発生する。
+    pub fn vbl(&mut self, irq: &mut InterruptController) {
+        if self.is_active() {
+            if self.mode & mode::IRQ_VBL != 0 {
+                self.pending |= status::IRQ_VBL;
+            }
+            if self.mode & mode::IRQ_MOVE != 0 && self.moved_since_vbl {
+                self.pending |= status::IRQ_MOVE;
+            }
+            if self.mode & mode::IRQ_BUTTON != 0 && self.button != self.vbl_button {
+                self.pending |= status::IRQ_BUTTON;
+            }
+        }
+        self.moved_since_vbl = false;
+        self.vbl_button = self.button;
+        irq.set(IrqSource::slot(self.slot), self.pending != 0);
+    }
+
+    /// リセット（モードと割り込みを解除）
+    pub fn reset(&mut self, irq: &mut InterruptController) {
+        self.mode = 0;
+        self.pending = 0;
+        self.carry = false;
+        irq.release(IrqSource::slot(self.slot));
+    }
+
+    fn execute(&mut self, command: MouseCommand, a: u8, ram: &mut [u8], irq: &mut InterruptController) {
+        let n = self.slot;
+        self.carry = false;
+        match command {
+            MouseCommand::Set => {
+                if a > 0x0F {
+                    self.carry = true;
+                } else {
+                    self.mode = a;
+                    ram[0x07F8 + n] = a;
+                    if !self.is_active() {
+                        self.pending = 0;
+                        irq.release(IrqSource::slot(n));
+                    }
+                }
+            }
+            MouseCommand::Serve => {
+                let mut st = ram[0x0778 + n] & !(status::IRQ_VBL | status::IRQ_BUTTON | status::IRQ_MOVE);
+                if self.pending == 0 {
+                    self.carry = true;
+                } else {
+                    st |= self.pending;
+                    self.pending = 0;
+                    irq.release(IrqSource::slot(n));
+                }
+                ram[0x0778 + n] = st;
+            }
+            MouseCommand::Read => {
+                let mut st = 0;
+                if self.button {
+                    st |= status::BUTTON;
+                }
+                if self.last_button {
+                    st |= status::LAST_BUTTON;
+                }
+                if self.moved {
+                    st |= status::MOVED;
+                }
+                self.last_button = self.button;
+                self.moved = false;
+                ram[0x0778 + n] = st;
+                self.store_position(ram);
+            }
+            MouseCommand::Clear => {
+                self.x = 0;
+                self.y = 0;
+                self.store_position(ram);
+            }
+            MouseCommand::Pos => {
+                self.x = read_word(ram, 0x0478 + n, 0x0578 + n);
+                self.y = read_word(ram, 0x04F8 + n, 0x05F8 + n);
+            }
+            MouseCommand::Clamp => {
+                let lo = read_word(ram, 0x0478, 0x0578);
+                let hi = read_word(ram, 0x04F8, 0x05F8);
+                let axis = (a & 1) as usize;
+                self.clamp[axis] = (lo, hi.max(lo));
+                self.x = self.x.clamp(self.clamp[0].0, self.clamp[0].1);
+                self.y = self.y.clamp(self.clamp[1].0, self.clamp[1].1);
+            }
+            MouseCommand::Home => {
+                self.x = self.clamp[0].0;
+                self.y = self.clamp[1].0;
+                self.store_position(ram);
+            }
+            MouseCommand::Init => {
+                self.clamp = [DEFAULT_CLAMP; 2];
+                self.x = 0;
+                self.y = 0;
+                self.mode = 0;
+                self.pending = 0;
+                self.moved = false;
+                self.last_button = false;
+                irq.release(IrqSource::slot(n));
+                ram[0x0778 + n] = 0;
+                ram[0x07F8 + n] = 0;
+                self.store_position(ram);
+            }
+        }
+    }
+
+    fn store_position(&self, ram: &mut [u8]) {
+        let n = self.slot;
+        ram[0x0478 + n] = self.x as u8;
+        ram[0x0578 + n] = (self.x >> 8) as u8;
+        ram[0x04F8 + n] = self.y as u8;
+        ram[0x05F8 + n] = (self.y >> 8) as u8;
+    }
+}
+
+/// 下位/上位バイトから符号付き16bit値を読む
+fn read_word(ram: &[u8], lo: usize, hi: usize) -> i32 {
+    i16::from_le_bytes([ram[lo], ram[hi]]) as i32
+}
+
+/// スロットROMを生成
+fn build_rom(slot: usize) -> [u8; 256] {
+    let mut rom = [0u8; 256];
+    // $Cn00: PR#n で呼ばれても何もしない
+    rom[0x00] = 0x60; // RTS
+    // Pascal 1.1 ファームウェア識別バイトとマウスのID
+    rom[0x05] = 0x38;
+    rom[0x07] = 0x18;
+    rom[0x0B] = 0x01;
+    rom[0x0C] = 0x20;
+    rom[0xFB] = 0xD6;
+
+    let io = 0x80 + (slot as u8) * 16;
+    for (i, _) in COMMANDS.iter().enumerate() {
+        let entry = STUB_BASE + i as u8 * STUB_SIZE;
+        rom[0x12 + i] = entry;
+        let stub = [
+            0x8D, io + i as u8, 0xC0, // STA $C0n0+i
+            0xAD, io + RESULT_REG, 0xC0, // LDA $C0nF
+            0x4A, // LSR A（ビット0をキャリーへ）
+            0x60, // RTS
+        ];
+        rom[entry as usize..entry as usize + stub.len()].copy_from_slice(&stub);
+    }
+    rom
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn firmware_commands_update_screen_holes() {
+        let mut card = MouseCard::new(MOUSE_SLOT);
+        let mut irq = InterruptController::new();
+        let mut ram = vec![0u8; 0x10000];
+        let io = 0xC0C0;
+        assert_eq!(card.read(0xC4FB), 0xD6);
+        assert_eq!(card.read(0xC400 + card.read(0xC414) as u16), 0x8D);
+
+        // X軸を0-279にクランプして右下へ
+        ram[0x0478] = 0;
+        ram[0x0578] = 0;
+        ram[0x04F8] = 279u16 as u8;
+        ram[0x05F8] = (279u16 >> 8) as u8;
+        card.write(io + 5, 0, &mut ram, &mut irq);
+        card.write(io, mode::ON | mode::IRQ_VBL, &mut ram, &mut irq);
+        card.set_host_position(1.0, 0.0);
+        card.set_button(true);
+        card.write(io + 2, 0, &mut ram, &mut irq);
+        assert_eq!(ram[0x047C] as u16 | (ram[0x057C] as u16) << 8, 279);
+        assert_eq!(ram[0x077C], status::BUTTON | status::MOVED);
+
+        // VBL割り込みはSERVEMOUSEで解除される
+        card.vbl(&mut irq);
+        assert!(irq.line());
+        card.write(io + 1, 0, &mut ram, &mut irq);
+        assert!(!irq.line());
+        assert_eq!(card.read(io + RESULT_REG as u16), 0);
+        assert_eq!(ram[0x077C] & status::IRQ_VBL, status::IRQ_VBL);
+        card.write(io + 1, 0, &mut ram, &mut irq);
+        assert_eq!(card.read(io + RESULT_REG as u16), 1);
+    }
+}
@@ -5,6 +5,14 @@
 use std::collections::HashMap;
 use std::time::{Duration, Instant};
 
+use crate::callstack::CallStack;
+use crate::cpu::assembler::Assembler;
+use crate::expr::{EvalContext, Expr};
+use crate::memory::MemoryBank;
+use crate::sound::AudioStats;
+use crate::symbols::SymbolTable;
+use crate::trace::{TraceEntry, Tracer};
+
 /// プロファイラ設定
 pub const PROFILER_ENABLED: bool = true;
 pub const SAMPLE_INTERVAL_MS: u64 = 1000; // 1秒ごとにサンプリング
@@ -238,6 +246,8 @@ pub struct Profiler {
     pub fps: f64,
     /// CPU速度（MHz相当）
     pub cpu_mhz: f64,
+    /// オーディオ出力バッファの統計
+    pub audio: AudioStats,
 }
 
 impl Default for Profiler {
@@ -262,6 +272,7 @@ impl Profiler {
             frame_count: 0,
             fps: 0.0,
             cpu_mhz: 0.0,
+            audio: AudioStats::default(),
         }
     }
     
@@ -452,6 +463,11 @@ impl Profiler {
         lines.push(format!("Instructions: {}", self.cpu_info.instructions_executed));
         lines.push(format!("Last PC: ${:04X}", self.cpu_info.last_pc));
         
+        lines.push("\n--- Audio ---".to_string());
+        lines.push(format!("Buffer: {}/{} samples @ {} Hz",
+            self.audio.buffered, self.audio.capacity, self.audio.sample_rate));
+        lines.push(format!("Underruns: {}  Overruns: {}", self.audio.underruns, self.audio.overruns));
+        
         lines.join("\n")
     }
     
@@ -526,6 +542,14 @@ impl Profiler {
             .map(|&c| format!("{:>5}", c)).collect::<Vec<_>>().join("")));
         lines.push(String::new());
         
+        // オーディオ統計
+        lines.push("## Audio Output".to_string());
+        lines.push(format!("Sample Rate: {} Hz", self.audio.sample_rate));
+        lines.push(format!("Buffer: {} / {} samples", self.audio.buffered, self.audio.capacity));
+        lines.push(format!("Underruns: {}", self.audio.underruns));
+        lines.push(format!("Overruns: {}", self.audio.overruns));
+        lines.push(String::new());
+        
         // CPU統計
         lines.push(format!("## CPU Statistics"));
         lines.push(format!("Total Instructions: {}", self.cpu_info.instructions_executed));
@@ -566,11 +590,11 @@ impl Profiler {
         let mut file = std::fs::File::create(path)?;
         
         // ヘッダー
-        writeln!(file, "timestamp,fps,cpu_mhz,boot_stage,boot_time_s,nibbles_read,sectors_read,sectors_failed,instructions,last_pc")?;
+        writeln!(file, "timestamp,fps,cpu_mhz,boot_stage,boot_time_s,nibbles_read,sectors_read,sectors_failed,instructions,last_pc,audio_underruns,audio_overruns")?;
         
         // データ
         let boot_time = self.boot_elapsed().map(|d| d.as_secs_f64()).unwrap_or(0.0);
-        writeln!(file, "{},{:.2},{:.3},{:?},{:.3},{},{},{},{},{:#06X}",
+        writeln!(file, "{},{:.2},{:.3},{:?},{:.3},{},{},{},{},{:#06X},{},{}",
             chrono::Local::now().format("%Y-%m-%d %H:%M:%S"),
             self.fps,
             self.cpu_mhz,
@@ -580,7 +604,9 @@ impl Profiler {
             self.disk_info.sectors_read,
             self.disk_info.sectors_failed,
             self.cpu_info.instructions_executed,
-            self.cpu_info.last_pc
+            self.cpu_info.last_pc,
+            self.audio.underruns,
+            self.audio.overruns
         )?;
         
         Ok(())
@@ -611,6 +637,13 @@ impl Profiler {
   "cpu": {{
     "instructions": {},
     "last_pc": "{:#06X}"
+  }},
+  "audio": {{
+    "sample_rate": {},
+    "buffer_capacity": {},
+    "buffered": {},
+    "underruns": {},
+    "overruns": {}
   }}
 }}"#,
             chrono::Local::now().format("%Y-%m-%d %H:%M:%S"),
@@ -625,7 +658,12 @@ impl Profiler {
             self.disk_info.current_track,
             &self.disk_info.track_accesses[..],
             self.cpu_info.instructions_executed,
-            self.cpu_info.last_pc
+            self.cpu_info.last_pc,
+            self.audio.sample_rate,
+            self.audio.capacity,
+            self.audio.buffered,
+            self.audio.underruns,
+            self.audio.overruns
         )
     }
     
@@ -747,6 +785,220 @@ pub enum BreakCondition {
     MemEquals(u16, u8),
     /// ヒット回数が特定値以上
     HitCount(u32),
+    /// 式が真
+    Expr(Expr),
+}
+
+impl std::fmt::Display for BreakCondition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BreakCondition::AEquals(v) => write!(f, "a == ${:02X}", v),
+            BreakCondition::XEquals(v) => write!(f, "x == ${:02X}", v),
+            BreakCondition::YEquals(v) => write!(f, "y == ${:02X}", v),
+            BreakCondition::MemEquals(addr, v) => write!(f, "[${:04X}] == ${:02X}", addr, v),
+            BreakCondition::HitCount(n) => write!(f, "hits >= {}", n),
+            BreakCondition::Expr(expr) => write!(f, "{}", expr),
+        }
+    }
+}
+
+/// ウォッチ式（停止中やフレームごとに評価して表示する）
+#[derive(Debug, Clone)]
+pub struct WatchExpr {
+    pub expr: Expr,
+    /// 最後に評価した値
+    pub value: Option<i64>,
+}
+
+/// ウォッチポイントのアクセス種別（ビットの組み合わせ）
+pub mod access {
+    pub const READ: u8 = 0x01;
+    pub const WRITE: u8 = 0x02;
+    pub const EXECUTE: u8 = 0x04;
+
+    /// "rwx" 形式の表記
+    pub fn name(kind: u8) -> String {
+        [(READ, 'r'), (WRITE, 'w'), (EXECUTE, 'x')]
+            .iter()
+            .map(|&(bit, c)| if kind & bit != 0 { c } else { '-' })
+            .collect()
+    }
+}
+
+/// バスアクセスのウォッチポイント
+#[derive(Debug, Clone)]
+pub struct Watchpoint {
+    /// ID（ブレークポイントと共通の番号）
+    pub id: u32,
+    /// 範囲の先頭
+    pub start: u16,
+    /// 範囲の末尾（含む）
+    pub end: u16,
+    /// 対象のアクセス（`access` のビット）
+    pub access: u8,
+    /// 対象のバンク（Noneならどこでも）
+    pub bank: Option<MemoryBank>,
+    /// 読み書きした値のフィルタ
+    pub value: Option<u8>,
+    /// 有効フラグ
+    pub enabled: bool,
+    /// ヒット回数
+    pub hit_count: u32,
+    /// 最後にアクセスした命令のPC
+    pub last_pc: Option<u16>,
+}
+
+impl Watchpoint {
+    /// "[rwx:]START[-END][@BANK][=VALUE]" を解析（例: "w:C030", "rw:0400-07FF@aux", "x:D000@lc2", "w:10=FF"）
+    ///
+    /// アクセス種別を省略すると書き込み。
+    pub fn parse(spec: &str) -> Result<Watchpoint, String> {
+        let spec = spec.trim();
+        let (kind, rest) = match spec.split_once(':') {
+            Some((kind, rest)) => {
+                let mut bits = 0;
+                for c in kind.chars() {
+                    bits |= match c.to_ascii_lowercase() {
+                        'r' => access::READ,
+                        'w' => access::WRITE,
+                        'x' => access::EXECUTE,
+                        _ => return Err(format!("Invalid watch access '{}' (use r, w, x)", kind)),
+                    };
+                }
+                (bits, rest)
+            }
+            None => (access::WRITE, spec),
+        };
+        if kind == 0 {
+            return Err(format!("Missing watch access in '{}'", spec));
+        }
+        let (rest, value) = match rest.split_once('=') {
+            Some((rest, value)) => {
+                let value = parse_hex(value)
+                    .filter(|&v| v <= 0xFF)
+                    .ok_or_else(|| format!("Invalid watch value '{}'", value))?;
+                (rest, Some(value as u8))
+            }
+            None => (rest, None),
+        };
+        let (range, bank) = match rest.split_once('@') {
+            Some((range, bank)) => {
+                let bank = MemoryBank::from_name(bank.trim())
+                    .ok_or_else(|| format!("Invalid bank '{}' (main, aux, lc1, lc2, rom, io)", bank))?;
+                (range, Some(bank))
+            }
+            None => (rest, None),
+        };
+        let (start, end) = match range.split_once('-') {
+            Some((start, end)) => (parse_hex(start), parse_hex(end)),
+            None => (parse_hex(range), parse_hex(range)),
+        };
+        let (start, end) = match (start, end) {
+            (Some(start), Some(end)) if start <= end => (start, end),
+            _ => return Err(format!("Invalid watch range '{}'", range)),
+        };
+        Ok(Watchpoint {
+            id: 0,
+            start,
+            end,
+            access: kind,
+            bank,
+            value,
+            enabled: true,
+            hit_count: 0,
+            last_pc: None,
+        })
+    }
+
+    /// アクセスが条件に合うか
+    ///
+    /// 値フィルタは読み書きした値に対するもので、実行アクセスには一致しない。
+    #[inline]
+    fn matches(&self, address: u16, bank: MemoryBank, kind: u8, value: Option<u8>) -> bool {
+        self.enabled
+            && self.access & kind != 0
+            && (self.start..=self.end).contains(&address)
+            && self.bank.is_none_or(|b| b == bank)
+            && self.value.is_none_or(|v| value == Some(v))
+    }
+}
+
+impl std::fmt::Display for Watchpoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ${:04X}", access::name(self.access), self.start)?;
+        if self.end != self.start {
+            write!(f, "-${:04X}", self.end)?;
+        }
+        if let Some(bank) = self.bank {
+            write!(f, " @{}", bank.name())?;
+        }
+        if let Some(value) = self.value {
+            write!(f, " =${:02X}", value)?;
+        }
+        Ok(())
+    }
+}
+
+/// 16進数を解析（"$C600" / "0xC600" / "C600"）
+pub fn parse_hex(s: &str) -> Option<u16> {
+    let s = s.trim();
+    let digits = s.strip_prefix('$')
+        .or_else(|| s.strip_prefix("0x"))
+        .or_else(|| s.strip_prefix("0X"))
+        .unwrap_or(s);
+    u16::from_str_radix(digits, 16).ok()
+}
+
+/// 実行の目標（ステップオーバー・ステップアウト・指定位置や回数まで実行）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RunGoal {
+    /// アドレスに来たら止まる（`min_sp` があればスタックがその深さまで戻ったときだけ）
+    Address { address: u16, min_sp: Option<u8> },
+    /// RTS/RTIでスタックが `sp` より浅くなったら止まる
+    StepOut { sp: u8 },
+    /// 残りの命令数
+    Instructions(u64),
+    /// このサイクルに達したら止まる
+    Cycles(u64),
+}
+
+impl std::fmt::Display for RunGoal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RunGoal::Address { min_sp: Some(_), .. } => f.write_str("step over"),
+            RunGoal::Address { address, min_sp: None } => write!(f, "run to ${:04X}", address),
+            RunGoal::StepOut { .. } => f.write_str("step out"),
+            RunGoal::Instructions(_) => f.write_str("instruction count"),
+            RunGoal::Cycles(_) => f.write_str("cycle count"),
+        }
+    }
+}
+
+/// 実行が止まった理由
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BreakReason {
+    /// PCブレークポイント（命令の実行前に停止）
+    Breakpoint { id: u32, address: u16 },
+    /// ウォッチポイント（アクセスした命令の直後、実行なら命令の前に停止）
+    Watchpoint { id: u32, address: u16, access: u8, bank: MemoryBank, value: Option<u8>, pc: u16 },
+    /// 実行の目標に達した（命令の実行前に停止）
+    Goal { goal: RunGoal, pc: u16 },
+}
+
+impl std::fmt::Display for BreakReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            BreakReason::Breakpoint { id, address } => write!(f, "breakpoint #{} at ${:04X}", id, address),
+            BreakReason::Watchpoint { id, address, access, bank, value, pc } => {
+                write!(f, "watchpoint #{} {} ${:04X} @{}", id, access::name(access), address, bank.name())?;
+                if let Some(value) = value {
+                    write!(f, " =${:02X}", value)?;
+                }
+                write!(f, " (PC=${:04X})", pc)
+            }
+            BreakReason::Goal { goal, pc } => write!(f, "${:04X} after {}", pc, goal),
+        }
+    }
 }
 
 /// デバッガ
@@ -757,16 +1009,40 @@ pub struct Debugger {
     breakpoints: Vec<Breakpoint>,
     /// 次のブレークポイントID
     next_bp_id: u32,
-    /// ステップオーバーの戻りアドレス
-    step_over_return: Option<u16>,
-    /// トレースログ有効
-    pub trace_enabled: bool,
-    /// トレースログバッファ
-    trace_buffer: Vec<String>,
-    /// トレースバッファサイズ上限
-    trace_buffer_limit: usize,
-    /// ウォッチポイント（メモリアドレス）
-    watchpoints: Vec<(u16, u8)>, // (address, last_value)
+    /// 実行の目標
+    goal: Option<RunGoal>,
+    /// 直前に実行した命令のオペコード（ステップアウト用）
+    last_opcode: u8,
+    /// 命令トレース
+    tracer: Option<Tracer>,
+    /// ウォッチ式
+    watch_exprs: Vec<WatchExpr>,
+    /// ウォッチポイント（バスアクセス）
+    watchpoints: Vec<Watchpoint>,
+    /// 有効なウォッチポイントのアクセス種別の和（バスの高速パス判定用）
+    watch_mask: u8,
+    /// 実行中の命令のPC（ウォッチポイントの記録用）
+    current_pc: u16,
+    /// ラインアセンブラ（ラベルはセッション中保持）
+    pub assembler: Assembler,
+    /// シンボルテーブル（組み込みのROM・DOS・ProDOSのシンボルと読み込んだファイル）
+    pub symbols: SymbolTable,
+    /// シャドウコールスタック（JSR/RTS/BRK/RTIと割り込みから組み立てる）
+    pub call_stack: CallStack,
+    /// 有効なブレークポイントかウォッチポイントがあるか（実行ループの高速パス判定用）
+    armed: bool,
+    /// 再開直後の1命令はブレークポイントを無視する（停止したアドレスから進むため）
+    skip_next: bool,
+    /// 最後に止まった理由
+    pub last_break: Option<BreakReason>,
+    /// 止まったことをまだ通知していない
+    break_pending: bool,
+    /// 巻き戻しの再実行中（目標の命令数だけを見て、ブレークポイント・ウォッチポイント・トレースは無視する）
+    replay: bool,
+    /// 再実行中に書き込みを探すアドレス
+    probe: Option<u16>,
+    /// 見つけた書き込み（PC, 値）
+    probe_hit: Option<(u16, u8)>,
 }
 
 impl Default for Debugger {
@@ -781,11 +1057,213 @@ impl Debugger {
             state: DebuggerState::Running,
             breakpoints: Vec::new(),
             next_bp_id: 1,
-            step_over_return: None,
-            trace_enabled: false,
-            trace_buffer: Vec::new(),
-            trace_buffer_limit: 10000,
+            goal: None,
+            last_opcode: 0,
+            tracer: None,
+            watch_exprs: Vec::new(),
             watchpoints: Vec::new(),
+            watch_mask: 0,
+            current_pc: 0,
+            assembler: Assembler::default(),
+            symbols: SymbolTable::builtin(),
+            call_stack: CallStack::new(),
+            armed: false,
+            skip_next: false,
+            last_break: None,
+            break_pending: false,
+            replay: false,
+            probe: None,
+            probe_hit: None,
+        }
+    }
+
+    /// 実行ループでチェックが必要か
+    #[inline]
+    pub fn is_armed(&self) -> bool {
+        self.armed
+    }
+
+    /// 実行中か（停止中・ステップ待ちでない）
+    #[inline]
+    pub fn is_running(&self) -> bool {
+        self.state == DebuggerState::Running
+    }
+
+    /// バスの `kind` アクセスを監視しているか
+    #[inline]
+    pub fn watches(&self, kind: u8) -> bool {
+        self.watch_mask & kind != 0
+    }
+
+    fn update_armed(&mut self) {
+        self.refresh_armed();
+        self.skip_next = false;
+    }
+
+    fn refresh_armed(&mut self) {
+        self.watch_mask = self.watchpoints.iter().filter(|wp| wp.enabled).fold(0, |mask, wp| mask | wp.access);
+        if self.probe.is_some() {
+            self.watch_mask |= access::WRITE;
+        }
+        self.armed = self.breakpoints.iter().any(|bp| bp.enabled) || self.watch_mask != 0 || self.goal.is_some()
+            || self.tracer.is_some() || self.replay;
+    }
+
+    /// 実行する命令のPCを記録（ウォッチポイントのPC用）
+    #[inline]
+    pub fn set_pc(&mut self, pc: u16) {
+        self.current_pc = pc;
+    }
+
+    /// 命令の実行前に呼ぶ（止まる場合はtrue）
+    ///
+    /// `exec_bank` は実行ウォッチポイントがある場合のPCのバンク。
+    pub fn before_instruction(&mut self, ctx: &EvalContext, exec_bank: Option<MemoryBank>) -> bool {
+        let pc = ctx.regs.pc;
+        self.current_pc = pc;
+        let first = std::mem::take(&mut self.skip_next);
+        if let Some(goal) = self.goal {
+            if self.goal_reached(goal, ctx, first) {
+                self.goal = None;
+                self.update_armed();
+                self.state = DebuggerState::Paused;
+                self.last_break = Some(BreakReason::Goal { goal, pc });
+                self.break_pending = true;
+                return true;
+            }
+        }
+        if first || self.replay {
+            return false;
+        }
+        if let Some(bank) = exec_bank {
+            self.bus_access(pc, bank, access::EXECUTE, None);
+            if self.state == DebuggerState::BreakpointHit {
+                return true;
+            }
+        }
+        match self.find_breakpoint(ctx) {
+            Some(id) => {
+                self.hit(BreakReason::Breakpoint { id, address: pc });
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// バスアクセスをウォッチポイントと照合する
+    ///
+    /// 一致したら記録し、実行中なら止める（命令は最後まで実行される）。
+    pub fn bus_access(&mut self, address: u16, bank: MemoryBank, kind: u8, value: Option<u8>) {
+        let pc = self.current_pc;
+        if self.replay {
+            if kind == access::WRITE && self.probe == Some(address) {
+                self.probe_hit = Some((pc, value.unwrap_or(0)));
+            }
+            return;
+        }
+        let mut found = None;
+        for wp in &mut self.watchpoints {
+            if wp.matches(address, bank, kind, value) {
+                wp.hit_count += 1;
+                wp.last_pc = Some(pc);
+                found.get_or_insert(wp.id);
+            }
+        }
+        let Some(id) = found else { return };
+        if matches!(self.state, DebuggerState::Running | DebuggerState::Stepping) {
+            self.hit(BreakReason::Watchpoint { id, address, access: kind, bank, value, pc });
+        }
+    }
+
+    /// 目標に達したか（再開直後の1命令はアドレスを見ない）
+    fn goal_reached(&mut self, goal: RunGoal, ctx: &EvalContext, first: bool) -> bool {
+        let regs = ctx.regs;
+        match goal {
+            RunGoal::Address { address, min_sp } => {
+                !first && regs.pc == address && min_sp.is_none_or(|sp| regs.sp >= sp)
+            }
+            RunGoal::StepOut { sp } => {
+                // RTS/RTIでスタックが呼び出し元の深さまで戻ったところ
+                let returned = !first && matches!(self.last_opcode, 0x40 | 0x60) && regs.sp > sp;
+                self.last_opcode = ctx.memory.peek(regs.pc);
+                returned
+            }
+            RunGoal::Instructions(0) => true,
+            RunGoal::Instructions(n) => {
+                self.goal = Some(RunGoal::Instructions(n - 1));
+                false
+            }
+            RunGoal::Cycles(end) => ctx.cycles >= end,
+        }
+    }
+
+    /// 目標を決めて実行を再開
+    fn run_goal(&mut self, goal: RunGoal) {
+        self.goal = Some(goal);
+        self.last_opcode = 0;
+        self.update_armed();
+        self.state = DebuggerState::Running;
+        self.skip_next = true;
+    }
+
+    /// ステップオーバー（JSRとBRKは戻ってくるまで実行、ほかは1命令）
+    pub fn step_over(&mut self, pc: u16, opcode: u8, sp: u8) {
+        match opcode {
+            0x20 => self.run_goal(RunGoal::Address { address: pc.wrapping_add(3), min_sp: Some(sp) }),
+            // BRKのハンドラはRTIでBRKの2バイト先に戻る
+            0x00 => self.run_goal(RunGoal::Address { address: pc.wrapping_add(2), min_sp: Some(sp) }),
+            _ => self.step(),
+        }
+    }
+
+    /// ステップアウト（今のサブルーチンからRTS/RTIで戻るまで実行）
+    pub fn step_out(&mut self, sp: u8) {
+        self.run_goal(RunGoal::StepOut { sp });
+    }
+
+    /// 指定アドレスまで実行
+    pub fn run_to(&mut self, address: u16) {
+        self.run_goal(RunGoal::Address { address, min_sp: None });
+    }
+
+    /// N命令だけ実行
+    pub fn run_instructions(&mut self, count: u64) {
+        if count > 0 {
+            self.run_goal(RunGoal::Instructions(count));
+        }
+    }
+
+    /// 指定サイクルまで実行
+    pub fn run_until_cycle(&mut self, cycle: u64) {
+        self.run_goal(RunGoal::Cycles(cycle));
+    }
+
+    /// 実行中の目標
+    pub fn goal(&self) -> Option<RunGoal> {
+        self.goal
+    }
+
+    /// 目標を取り消す
+    fn clear_goal(&mut self) {
+        if self.goal.take().is_some() {
+            self.update_armed();
+        }
+    }
+
+    /// 停止する
+    fn hit(&mut self, reason: BreakReason) {
+        self.clear_goal();
+        self.state = DebuggerState::BreakpointHit;
+        self.last_break = Some(reason);
+        self.break_pending = true;
+    }
+
+    /// 新しく止まった場合にその理由を返す（1回だけ）
+    pub fn take_break(&mut self) -> Option<BreakReason> {
+        if std::mem::take(&mut self.break_pending) {
+            self.last_break
+        } else {
+            None
         }
     }
     
@@ -801,6 +1279,7 @@ impl Debugger {
             hit_count: 0,
             condition: None,
         });
+        self.update_armed();
         
         id
     }
@@ -817,6 +1296,7 @@ impl Debugger {
             hit_count: 0,
             condition: Some(condition),
         });
+        self.update_armed();
         
         id
     }
@@ -825,6 +1305,7 @@ impl Debugger {
     pub fn remove_breakpoint(&mut self, id: u32) -> bool {
         if let Some(pos) = self.breakpoints.iter().position(|bp| bp.id == id) {
             self.breakpoints.remove(pos);
+            self.update_armed();
             true
         } else {
             false
@@ -835,35 +1316,41 @@ impl Debugger {
     pub fn toggle_breakpoint(&mut self, id: u32) -> bool {
         if let Some(bp) = self.breakpoints.iter_mut().find(|bp| bp.id == id) {
             bp.enabled = !bp.enabled;
+            self.update_armed();
             true
         } else {
             false
         }
     }
     
-    /// アドレスにブレークポイントがあるかチェック
-    pub fn check_breakpoint(&mut self, address: u16, a: u8, x: u8, y: u8, memory: &[u8]) -> bool {
+    /// PCにブレークポイントがあり条件を満たすかチェック
+    pub fn check_breakpoint(&mut self, ctx: &EvalContext) -> bool {
+        self.find_breakpoint(ctx).is_some()
+    }
+
+    /// 条件を満たしたブレークポイントのIDを返す
+    fn find_breakpoint(&mut self, ctx: &EvalContext) -> Option<u32> {
+        let regs = ctx.regs;
         for bp in &mut self.breakpoints {
-            if bp.enabled && bp.address == address {
+            if bp.enabled && bp.address == regs.pc {
                 // 条件チェック
                 let condition_met = match &bp.condition {
                     None => true,
-                    Some(BreakCondition::AEquals(v)) => a == *v,
-                    Some(BreakCondition::XEquals(v)) => x == *v,
-                    Some(BreakCondition::YEquals(v)) => y == *v,
-                    Some(BreakCondition::MemEquals(addr, v)) => {
-                        memory.get(*addr as usize).copied() == Some(*v)
-                    }
+                    Some(BreakCondition::AEquals(v)) => regs.a == *v,
+                    Some(BreakCondition::XEquals(v)) => regs.x == *v,
+                    Some(BreakCondition::YEquals(v)) => regs.y == *v,
+                    Some(BreakCondition::MemEquals(addr, v)) => ctx.memory.peek(*addr) == *v,
                     Some(BreakCondition::HitCount(n)) => bp.hit_count >= *n,
+                    Some(BreakCondition::Expr(expr)) => expr.is_true(ctx),
                 };
                 
                 if condition_met {
                     bp.hit_count += 1;
-                    return true;
+                    return Some(bp.id);
                 }
             }
         }
-        false
+        None
     }
     
     /// 全ブレークポイントを取得
@@ -871,74 +1358,177 @@ impl Debugger {
         &self.breakpoints
     }
     
-    /// トレースログを追加
-    pub fn add_trace(&mut self, entry: String) {
-        if self.trace_enabled {
-            self.trace_buffer.push(entry);
-            if self.trace_buffer.len() > self.trace_buffer_limit {
-                self.trace_buffer.remove(0);
-            }
+    /// 命令トレースを開始（実行中のトレースは保存して置き換える）
+    pub fn start_trace(&mut self, tracer: Tracer) -> Result<(), String> {
+        let previous = self.stop_trace().map(|_| ());
+        self.tracer = Some(tracer);
+        // 停止中のブレークポイントを再開時に飛ばす状態は保つ
+        self.refresh_armed();
+        previous
+    }
+
+    /// 命令トレースを終了して保存。保存した命令数を返す
+    pub fn stop_trace(&mut self) -> Result<u64, String> {
+        let Some(mut tracer) = self.tracer.take() else { return Ok(0) };
+        self.refresh_armed();
+        tracer.save()
+    }
+
+    /// 命令トレース
+    pub fn tracer(&self) -> Option<&Tracer> {
+        self.tracer.as_ref()
+    }
+
+    pub fn tracer_mut(&mut self) -> Option<&mut Tracer> {
+        self.tracer.as_mut()
+    }
+
+    /// トレースに1命令を記録（巻き戻しの再実行中は記録しない）
+    #[inline]
+    pub fn trace(&mut self, entry: impl FnOnce() -> TraceEntry) {
+        if self.replay {
+            return;
+        }
+        if let Some(tracer) = self.tracer.as_mut() {
+            tracer.record(entry());
         }
     }
     
-    /// トレースログを取得
-    pub fn get_trace(&self, last_n: usize) -> &[String] {
-        let start = self.trace_buffer.len().saturating_sub(last_n);
-        &self.trace_buffer[start..]
+    /// ウォッチポイントを追加（IDはブレークポイントと共通）
+    pub fn add_watchpoint(&mut self, mut watchpoint: Watchpoint) -> u32 {
+        let id = self.next_bp_id;
+        self.next_bp_id += 1;
+        watchpoint.id = id;
+        self.watchpoints.push(watchpoint);
+        self.update_armed();
+        id
     }
-    
-    /// トレースログをクリア
-    pub fn clear_trace(&mut self) {
-        self.trace_buffer.clear();
+
+    /// ウォッチポイントを削除
+    pub fn remove_watchpoint(&mut self, id: u32) -> bool {
+        let before = self.watchpoints.len();
+        self.watchpoints.retain(|wp| wp.id != id);
+        self.update_armed();
+        self.watchpoints.len() != before
     }
-    
-    /// ウォッチポイントを追加
-    pub fn add_watchpoint(&mut self, address: u16, initial_value: u8) {
-        self.watchpoints.push((address, initial_value));
+
+    /// ウォッチポイントの有効/無効を切り替え
+    pub fn toggle_watchpoint(&mut self, id: u32) -> bool {
+        if let Some(wp) = self.watchpoints.iter_mut().find(|wp| wp.id == id) {
+            wp.enabled = !wp.enabled;
+            self.update_armed();
+            true
+        } else {
+            false
+        }
     }
-    
-    /// ウォッチポイントをチェック（値が変わったらtrue）
-    pub fn check_watchpoints(&mut self, memory: &[u8]) -> Option<(u16, u8, u8)> {
-        for (addr, last_val) in &mut self.watchpoints {
-            if let Some(&current) = memory.get(*addr as usize) {
-                if current != *last_val {
-                    let old = *last_val;
-                    *last_val = current;
-                    return Some((*addr, old, current));
-                }
-            }
+
+    /// 全ウォッチポイントを取得
+    pub fn watchpoints(&self) -> &[Watchpoint] {
+        &self.watchpoints
+    }
+
+    /// ウォッチ式を追加
+    pub fn add_watch_expr(&mut self, source: &str) -> Result<(), String> {
+        let expr = Expr::parse(source)?;
+        self.watch_exprs.push(WatchExpr { expr, value: None });
+        Ok(())
+    }
+
+    /// ウォッチ式を削除（0始まりの番号）
+    pub fn remove_watch_expr(&mut self, index: usize) -> bool {
+        if index < self.watch_exprs.len() {
+            self.watch_exprs.remove(index);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// 全ウォッチ式を取得
+    pub fn watch_exprs(&self) -> &[WatchExpr] {
+        &self.watch_exprs
+    }
+
+    /// ウォッチ式を評価し直す
+    pub fn refresh_watch_exprs(&mut self, ctx: &EvalContext) {
+        for watch in &mut self.watch_exprs {
+            watch.value = Some(watch.expr.eval(ctx));
         }
-        None
     }
     
+    /// 巻き戻しの再実行を始める（`count` 命令で止まり、`probe` への書き込みを記録する）
+    pub fn begin_replay(&mut self, count: u64, probe: Option<u16>) {
+        self.replay = true;
+        self.probe = probe;
+        self.probe_hit = None;
+        self.goal = Some(RunGoal::Instructions(count));
+        self.state = DebuggerState::Running;
+        self.update_armed();
+    }
+
+    /// 再実行を終えて停止状態に戻す
+    pub fn end_replay(&mut self) {
+        self.replay = false;
+        self.probe = None;
+        self.goal = None;
+        self.state = DebuggerState::Paused;
+        self.last_break = None;
+        self.break_pending = false;
+        self.update_armed();
+    }
+
+    /// 再実行中か
+    pub fn is_replaying(&self) -> bool {
+        self.replay
+    }
+
+    /// 再実行中に見つけた書き込み（PC, 値）を取り出す
+    pub fn take_probe_hit(&mut self) -> Option<(u16, u8)> {
+        self.probe_hit.take()
+    }
+
     /// 一時停止
     pub fn pause(&mut self) {
+        self.clear_goal();
         self.state = DebuggerState::Paused;
     }
     
     /// 再開
     pub fn resume(&mut self) {
+        self.clear_goal();
         self.state = DebuggerState::Running;
+        self.skip_next = true;
     }
     
-    /// ステップ実行
+    /// ステップ実行（1命令実行して停止）
     pub fn step(&mut self) {
+        self.clear_goal();
         self.state = DebuggerState::Stepping;
     }
     
-    /// ステップ完了後に停止
+    /// ステップ完了後に停止（ウォッチポイントで止まった場合はそのまま）
     pub fn step_complete(&mut self) {
-        self.state = DebuggerState::Paused;
+        if self.state == DebuggerState::Stepping {
+            self.state = DebuggerState::Paused;
+        }
     }
     
     /// リセット
     pub fn reset(&mut self) {
         self.state = DebuggerState::Running;
-        self.step_over_return = None;
-        self.trace_buffer.clear();
+        self.goal = None;
+        self.skip_next = false;
+        self.last_break = None;
+        self.break_pending = false;
+        self.call_stack.clear();
         for bp in &mut self.breakpoints {
             bp.hit_count = 0;
         }
+        for wp in &mut self.watchpoints {
+            wp.hit_count = 0;
+            wp.last_pc = None;
+        }
     }
 }
 
@@ -0,0 +1,375 @@
+//! 動画録画機能
+//!
+//! `Video.framebuffer` を毎フレーム取り込み、次の形式で保存する。
+//! - APNG: 可逆のアニメーションPNG
+//! - GIF: アニメーションGIF（16色以内のフレームはそのままパレット化）
+//! - Y4M: 非圧縮YUV 4:4:4 (60fps) と同名のWAV音声トラック
+//!
+//! APNG/GIFは直前と同じフレームを1枚にまとめ、表示時間を延ばして容量を抑える。
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use crate::sound::SAMPLE_RATE;
+use crate::wav::WavWriter;
+
+/// 録画のフレームレート（Apple IIのフレームレート）
+pub const RECORD_FPS: u32 = 60;
+
+/// 録画形式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecordFormat {
+    Apng,
+    Gif,
+    Y4m,
+}
+
+impl RecordFormat {
+    /// ファイル拡張子から形式を判定（.png/.apng, .gif, .y4m）
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+        match ext.as_str() {
+            "png" | "apng" => Some(RecordFormat::Apng),
+            "gif" => Some(RecordFormat::Gif),
+            "y4m" => Some(RecordFormat::Y4m),
+            _ => None,
+        }
+    }
+
+    /// 既定の拡張子
+    pub fn extension(self) -> &'static str {
+        match self {
+            RecordFormat::Apng => "png",
+            RecordFormat::Gif => "gif",
+            RecordFormat::Y4m => "y4m",
+        }
+    }
+}
+
+/// 形式ごとのエンコーダ
+enum Sink {
+    Apng(png::Writer<BufWriter<File>>),
+    Gif(gif::Encoder<BufWriter<File>>),
+    Y4m {
+        writer: BufWriter<File>,
+        wav: WavWriter,
+    },
+}
+
+/// 動画レコーダー
+pub struct VideoRecorder {
+    path: PathBuf,
+    format: RecordFormat,
+    /// 出力サイズ
+    width: usize,
+    height: usize,
+    sink: Option<Sink>,
+    /// 出力待ちのフレーム（RGB）と表示フレーム数（APNG/GIF用）
+    pending: Option<(Vec<u8>, u32)>,
+    /// GIFの遅延時間（1/100秒）の端数
+    gif_delay_error: u32,
+    /// 取り込んだフレーム数
+    frames_captured: u64,
+    /// ファイルに書き出したフレーム数
+    frames_written: u32,
+    /// Y4M用の変換バッファ
+    yuv: Vec<u8>,
+}
+
+impl VideoRecorder {
+    /// 録画を開始する
+    ///
+    /// `width`/`height` は出力サイズ。フレームバッファと異なる場合は最近傍で拡大縮小する。
+    pub fn start(path: &Path, width: usize, height: usize) -> io::Result<Self> {
+        let format = RecordFormat::from_path(path).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, format!("Unknown recording format: {}", path.display()))
+        })?;
+        if width == 0 || height == 0 || width > u16::MAX as usize || height > u16::MAX as usize {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("Invalid recording size: {}x{}", width, height)));
+        }
+
+        let file = BufWriter::new(File::create(path)?);
+        let sink = match format {
+            RecordFormat::Apng => {
+                let mut encoder = png::Encoder::new(file, width as u32, height as u32);
+                encoder.set_color(png::ColorType::Rgb);
+                encoder.set_depth(png::BitDepth::Eight);
+                // フレーム数は終了時に確定させる
+                encoder.set_animated(u32::MAX, 0).map_err(io::Error::other)?;
+                Sink::Apng(encoder.write_header().map_err(io::Error::other)?)
+            }
+            RecordFormat::Gif => {
+                let mut encoder = gif::Encoder::new(file, width as u16, height as u16, &[]).map_err(io::Error::other)?;
+                encoder.set_repeat(gif::Repeat::Infinite).map_err(io::Error::other)?;
+                Sink::Gif(encoder)
+            }
+            RecordFormat::Y4m => {
+                let mut writer = file;
+                writeln!(writer, "YUV4MPEG2 W{} H{} F{}:1 Ip A1:1 C444", width, height, RECORD_FPS)?;
+                let wav = WavWriter::create(path.with_extension("wav"), SAMPLE_RATE, 1)?;
+                Sink::Y4m { writer, wav }
+            }
+        };
+
+        Ok(VideoRecorder {
+            path: path.to_path_buf(),
+            format,
+            width,
+            height,
+            sink: Some(sink),
+            pending: None,
+            gif_delay_error: 0,
+            frames_captured: 0,
+            frames_written: 0,
+            yuv: Vec::new(),
+        })
+    }
+
+    /// 出力ファイルのパス
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// 録画形式
+    pub fn format(&self) -> RecordFormat {
+        self.format
+    }
+
+    /// 取り込んだフレーム数
+    pub fn frames_captured(&self) -> u64 {
+        self.frames_captured
+    }
+
+    /// Y4MのときのWAVファイルのパス
+    pub fn audio_path(&self) -> Option<PathBuf> {
+        (self.format == RecordFormat::Y4m).then(|| self.path.with_extension("wav"))
+    }
+
+    /// 1フレーム分の映像を追加する
+    pub fn push_frame(&mut self, fb: &[u32], fb_width: usize, fb_height: usize) -> io::Result<()> {
+        let rgb = scale_to_rgb(fb, fb_width, fb_height, self.width, self.height);
+        self.frames_captured += 1;
+
+        if self.format == RecordFormat::Y4m {
+            return self.write_y4m_frame(&rgb);
+        }
+
+        // 直前と同じ画面なら表示時間を延ばすだけ
+        if let Some((prev, ticks)) = &mut self.pending {
+            if *prev == rgb {
+                *ticks += 1;
+                return Ok(());
+            }
+        }
+        if let Some((prev, ticks)) = self.pending.take() {
+            self.write_animated_frame(&prev, ticks)?;
+        }
+        self.pending = Some((rgb, 1));
+        Ok(())
+    }
+
+    /// 1フレーム分の音声を追加する（Y4M録画時のみWAVに書き出す）
+    ///
+    /// `None` は無音フレームとして扱う。
+    pub fn push_audio(&mut self, samples: Option<&[f32]>, frame_samples: usize) -> io::Result<()> {
+        if let Some(Sink::Y4m { wav, .. }) = &mut self.sink {
+            match samples {
+                Some(s) => wav.write_f32(s)?,
+                None => wav.write_silence(frame_samples)?,
+            }
+        }
+        Ok(())
+    }
+
+    fn write_animated_frame(&mut self, rgb: &[u8], ticks: u32) -> io::Result<()> {
+        let (w, h) = (self.width as u16, self.height as u16);
+        match &mut self.sink {
+            Some(Sink::Apng(writer)) => {
+                let ticks = ticks.min(u16::MAX as u32) as u16;
+                writer.set_frame_delay(ticks, RECORD_FPS as u16).map_err(io::Error::other)?;
+                writer.write_image_data(rgb).map_err(io::Error::other)?;
+            }
+            Some(Sink::Gif(encoder)) => {
+                // 1/60秒単位を1/100秒単位に変換（端数は次のフレームに繰り越す）
+                let total = ticks * 100 + self.gif_delay_error;
+                let delay = total / RECORD_FPS;
+                self.gif_delay_error = total % RECORD_FPS;
+                let mut frame = gif_frame(w, h, rgb);
+                frame.delay = delay.clamp(1, u16::MAX as u32) as u16;
+                encoder.write_frame(&frame).map_err(io::Error::other)?;
+            }
+            _ => return Ok(()),
+        }
+        self.frames_written += 1;
+        Ok(())
+    }
+
+    fn write_y4m_frame(&mut self, rgb: &[u8]) -> io::Result<()> {
+        let plane = self.width * self.height;
+        self.yuv.resize(plane * 3, 0);
+        let (y_plane, rest) = self.yuv.split_at_mut(plane);
+        let (u_plane, v_plane) = rest.split_at_mut(plane);
+        for (i, px) in rgb.chunks_exact(3).enumerate() {
+            let (y, u, v) = rgb_to_yuv(px[0], px[1], px[2]);
+            y_plane[i] = y;
+            u_plane[i] = u;
+            v_plane[i] = v;
+        }
+        if let Some(Sink::Y4m { writer, .. }) = &mut self.sink {
+            writer.write_all(b"FRAME\n")?;
+            writer.write_all(&self.yuv)?;
+            self.frames_written += 1;
+        }
+        Ok(())
+    }
+
+    /// 録画を終了してファイルを閉じる
+    pub fn finish(mut self) -> io::Result<()> {
+        if let Some((prev, ticks)) = self.pending.take() {
+            self.write_animated_frame(&prev, ticks)?;
+        }
+        match self.sink.take() {
+            Some(Sink::Apng(writer)) => {
+                if self.frames_written == 0 {
+                    // 1フレームも無い場合は空のPNGになるので削除する
+                    drop(writer);
+                    std::fs::remove_file(&self.path)?;
+                    return Ok(());
+                }
+                writer.finish().map_err(io::Error::other)?;
+                patch_apng_frame_count(&self.path, self.frames_written)?;
+            }
+            Some(Sink::Gif(encoder)) => {
+                encoder.into_inner()?.flush()?;
+            }
+            Some(Sink::Y4m { mut writer, wav }) => {
+                writer.flush()?;
+                wav.finish()?;
+            }
+            None => {}
+        }
+        Ok(())
+    }
+}
+
+/// フレームバッファ（0x00RRGGBB）を出力サイズのRGBに変換（最近傍）
+fn scale_to_rgb(fb: &[u32], src_w: usize, src_h: usize, dst_w: usize, dst_h: usize) -> Vec<u8> {
+    let mut rgb = Vec::with_capacity(dst_w * dst_h * 3);
+    for y in 0..dst_h {
+        let sy = y * src_h / dst_h;
+        for x in 0..dst_w {
+            let sx = x * src_w / dst_w;
+            let pixel = fb.get(sy * src_w + sx).copied().unwrap_or(0);
+            rgb.push(((pixel >> 16) & 0xFF) as u8);
+            rgb.push(((pixel >> 8) & 0xFF) as u8);
+            rgb.push((pixel & 0xFF) as u8);
+        }
+    }
+    rgb
+}
+
+/// RGBからGIFフレームを作成
+///
+/// 256色以内ならそのままパレット化し、超える場合は減色する。
+fn gif_frame(width: u16, height: u16, rgb: &[u8]) -> gif::Frame<'static> {
+    let mut palette: Vec<u8> = Vec::new();
+    let mut index_of: HashMap<[u8; 3], u8> = HashMap::new();
+    let mut indices = Vec::with_capacity(rgb.len() / 3);
+    for px in rgb.chunks_exact(3) {
+        let key = [px[0], px[1], px[2]];
+        let index = match index_of.get(&key) {
+            Some(&i) => i,
+            None => {
+                if index_of.len() == 256 {
+                    return gif::Frame::from_rgb_speed(width, height, rgb, 10);
+                }
+                let i = index_of.len() as u8;
+                index_of.insert(key, i);
+                palette.extend_from_slice(&key);
+                i
+            }
+        };
+        indices.push(index);
+    }
+    gif::Frame {
+        width,
+        height,
+        buffer: Cow::Owned(indices),
+        palette: Some(palette),
+        ..gif::Frame::default()
+    }
+}
+
+/// RGB → YCbCr (BT.601, スタジオレンジ)
+fn rgb_to_yuv(r: u8, g: u8, b: u8) -> (u8, u8, u8) {
+    let (r, g, b) = (r as i32, g as i32, b as i32);
+    let y = ((66 * r + 129 * g + 25 * b + 128) >> 8) + 16;
+    let u = ((-38 * r - 74 * g + 112 * b + 128) >> 8) + 128;
+    let v = ((112 * r - 94 * g - 18 * b + 128) >> 8) + 128;
+    (y as u8, u as u8, v as u8)
+}
+
+/// APNGのacTLチャンクのフレーム数を書き換える
+///
+/// 開始時はフレーム数が分からないため仮の値で書き、終了時に確定させる。
+/// acTLはシグネチャ(8) + IHDR(25) の直後に置かれる。
+fn patch_apng_frame_count(path: &Path, num_frames: u32) -> io::Result<()> {
+    const ACTL_OFFSET: u64 = 8 + 25;
+    let mut file = OpenOptions::new().read(true).write(true).open(path)?;
+    let mut chunk = [0u8; 20];
+    file.seek(SeekFrom::Start(ACTL_OFFSET))?;
+    file.read_exact(&mut chunk)?;
+    if &chunk[4..8] != b"acTL" {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "acTL chunk not found"));
+    }
+    chunk[8..12].copy_from_slice(&num_frames.to_be_bytes());
+    let crc = crc32(&chunk[4..16]);
+    chunk[16..20].copy_from_slice(&crc.to_be_bytes());
+    file.seek(SeekFrom::Start(ACTL_OFFSET))?;
+    file.write_all(&chunk)?;
+    Ok(())
+}
+
+/// PNGチャンク用のCRC-32
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn apng_frame_count_is_patched() {
+        let path = std::env::temp_dir().join(format!("a2rs_rec_test_{}.png", std::process::id()));
+        let mut rec = VideoRecorder::start(&path, 4, 2).unwrap();
+        let black = [0u32; 8];
+        let white = [0xFFFFFFu32; 8];
+        rec.push_frame(&black, 4, 2).unwrap();
+        rec.push_frame(&black, 4, 2).unwrap();
+        rec.push_frame(&white, 4, 2).unwrap();
+        assert_eq!(rec.frames_captured(), 3);
+        rec.finish().unwrap();
+
+        let data = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+        assert_eq!(&data[37..41], b"acTL");
+        assert_eq!(&data[41..45], &2u32.to_be_bytes());
+        assert_eq!(&data[49..53], &crc32(&data[37..49]).to_be_bytes());
+    }
+
+    #[test]
+    fn crc32_matches_reference() {
+        assert_eq!(crc32(b"IEND"), 0xAE42_6082);
+    }
+}
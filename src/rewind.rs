@@ -0,0 +1,307 @@
+//! 逆実行（巻き戻し）
+//!
+//! 一定フレームごとにセーブステートを取り、その間の入力（キー、パドル、ボタン、マウス、
+//! 時計カードが読んだホストの時刻）を実行した命令数と一緒に記録しておく。過去の位置へ戻るときは、
+//! その手前のスナップショットを読み込んでから入力を同じ命令位置で与え直して再実行する
+//! （`Apple2::rewind_to`）。
+//!
+//! ディスクイメージは前のスナップショットから変わっていなければ共有し、変わったときだけ複製する。
+//! 再実行が元の実行と一致するのは、エミュレータの状態がスナップショットと入力記録で決まる場合に限る。
+//! ディスクの一部の内部状態は記録していない。
+//! リセット、セーブステートの読み込み、ディスクやテープの操作は記録できないので、そこで記録をやり直す
+//! （`Apple2::restart_rewind`）。
+
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+use chrono::NaiveDateTime;
+
+use crate::callstack::CallStack;
+use crate::savestate::SaveState;
+
+/// 記録する入力
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Input {
+    /// キーを押した（`Apple2::key_down`）
+    Key(u8),
+    /// パドルの値が変わった
+    Paddle(usize, u8),
+    /// ボタンの状態が変わった
+    Button(usize, bool),
+    /// マウスカードの座標かボタンが変わった（ホストの正規化座標, ボタン）
+    Mouse(f32, f32, bool),
+    /// 時計カードがホストの時刻を読んだ（次の命令で読む値）
+    Clock(NaiveDateTime),
+}
+
+/// 命令位置付きの入力
+#[derive(Debug, Clone, Copy)]
+struct InputEvent {
+    /// それまでに実行した命令数（この数の命令を実行した直後、次の命令の前に与えた）
+    instructions: u64,
+    input: Input,
+}
+
+/// スナップショット
+struct Snapshot {
+    instructions: u64,
+    /// ディスクイメージを除いたセーブステート
+    state: SaveState,
+    /// ディスクイメージ（変わっていなければ前のスナップショットと共有）
+    disks: [Arc<Vec<u8>>; 2],
+    /// デバッガのコールスタック（セーブステートには含まれない）
+    calls: CallStack,
+}
+
+/// 再実行中に見つけた書き込み
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WriteHit {
+    /// 書き込んだ命令を実行した直後の命令数
+    pub instructions: u64,
+    /// 書き込んだ命令のPC
+    pub pc: u16,
+    pub value: u8,
+}
+
+/// 巻き戻し用の記録
+pub struct Rewind {
+    snapshots: VecDeque<Snapshot>,
+    /// 保持するスナップショットの数
+    capacity: usize,
+    /// スナップショットの間隔（フレーム）
+    interval: u64,
+    /// 入力（命令数の順）
+    inputs: Vec<InputEvent>,
+    /// フレームの境界（フレーム番号, 命令数）
+    frames: VecDeque<(u64, u64)>,
+    /// 再実行で次に与える入力
+    cursor: usize,
+    /// 再実行中に見つけた最後の書き込み
+    pub last_write: Option<WriteHit>,
+}
+
+impl Rewind {
+    /// `interval` フレームごとにスナップショットを取り、`capacity` 個まで保持する
+    pub fn new(interval: u64, capacity: usize) -> Self {
+        Rewind {
+            snapshots: VecDeque::new(),
+            capacity: capacity.max(1),
+            interval: interval.max(1),
+            inputs: Vec::new(),
+            frames: VecDeque::new(),
+            cursor: 0,
+            last_write: None,
+        }
+    }
+
+    /// このフレームでスナップショットを取るか
+    pub fn snapshot_due(&self, frame: u64) -> bool {
+        frame.is_multiple_of(self.interval)
+    }
+
+    /// スナップショットを追加（古いものと、それより前の入力・フレーム境界は捨てる）
+    pub fn push_snapshot(&mut self, instructions: u64, mut state: SaveState, calls: CallStack) {
+        let disks = std::array::from_fn(|i| {
+            let data = std::mem::take(&mut state.disk.drives[i].data);
+            match self.snapshots.back() {
+                Some(last) if *last.disks[i] == data => Arc::clone(&last.disks[i]),
+                _ => Arc::new(data),
+            }
+        });
+        self.snapshots.push_back(Snapshot { instructions, state, disks, calls });
+        while self.snapshots.len() > self.capacity {
+            self.snapshots.pop_front();
+        }
+        let oldest = self.oldest();
+        let stale = self.inputs.partition_point(|e| e.instructions < oldest);
+        self.inputs.drain(..stale);
+        while self.frames.front().is_some_and(|&(_, i)| i < oldest) {
+            self.frames.pop_front();
+        }
+    }
+
+    /// 記録をすべて捨てる
+    pub fn clear(&mut self) {
+        self.snapshots.clear();
+        self.inputs.clear();
+        self.frames.clear();
+        self.cursor = 0;
+        self.last_write = None;
+    }
+
+    /// 入力を記録
+    pub fn record_input(&mut self, instructions: u64, input: Input) {
+        self.inputs.push(InputEvent { instructions, input });
+    }
+
+    /// フレームの終わりを記録
+    pub fn mark_frame(&mut self, frame: u64, instructions: u64) {
+        self.frames.push_back((frame, instructions));
+    }
+
+    /// 戻れる一番古い命令位置
+    pub fn oldest(&self) -> u64 {
+        self.snapshots.front().map_or(0, |s| s.instructions)
+    }
+
+    /// スナップショットの数
+    pub fn len(&self) -> usize {
+        self.snapshots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.snapshots.is_empty()
+    }
+
+    /// `instructions` 以前で最新のスナップショットの命令位置
+    pub fn snapshot_before(&self, instructions: u64) -> Option<u64> {
+        self.snapshots.iter().rev()
+            .find(|s| s.instructions <= instructions)
+            .map(|s| s.instructions)
+    }
+
+    /// 命令位置 `instructions` のスナップショット（ディスクイメージを戻したセーブステート）
+    pub fn snapshot_at(&self, instructions: u64) -> Option<(SaveState, CallStack)> {
+        let snapshot = self.snapshots.iter().rev().find(|s| s.instructions == instructions)?;
+        let mut state = snapshot.state.clone();
+        for (drive, data) in state.disk.drives.iter_mut().zip(&snapshot.disks) {
+            drive.data = data.to_vec();
+        }
+        Some((state, snapshot.calls.clone()))
+    }
+
+    /// 保持しているディスクイメージの数（共有しているものは1つと数える）
+    #[cfg(test)]
+    fn disk_images(&self) -> usize {
+        let mut images: Vec<*const Vec<u8>> = self.snapshots.iter()
+            .flat_map(|s| s.disks.iter().map(Arc::as_ptr))
+            .collect();
+        images.sort();
+        images.dedup();
+        images.len()
+    }
+
+    /// フレームの終わりの命令位置
+    pub fn frame_end(&self, frame: u64) -> Option<u64> {
+        self.frames.iter().find(|&&(f, _)| f == frame).map(|&(_, i)| i)
+    }
+
+    /// 再実行を `instructions` から始める
+    pub fn begin_replay(&mut self, instructions: u64) {
+        self.cursor = self.inputs.partition_point(|e| e.instructions < instructions);
+    }
+
+    /// 再実行で `instructions` までに与える入力を取り出す
+    pub fn next_input(&mut self, instructions: u64) -> Option<Input> {
+        let event = self.inputs.get(self.cursor).filter(|e| e.instructions <= instructions)?;
+        self.cursor += 1;
+        Some(event.input)
+    }
+
+    /// `instructions` より後の記録を捨てる（戻った位置から実行をやり直すため）
+    pub fn truncate(&mut self, instructions: u64) {
+        while self.snapshots.back().is_some_and(|s| s.instructions > instructions) {
+            self.snapshots.pop_back();
+        }
+        let keep = self.inputs.partition_point(|e| e.instructions <= instructions);
+        self.inputs.truncate(keep);
+        while self.frames.back().is_some_and(|&(_, i)| i > instructions) {
+            self.frames.pop_back();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Local, Timelike};
+
+    use crate::apple2::Apple2;
+    use crate::clock::{ThunderClock, THUNDERCLOCK_SLOT};
+    use crate::memory::AppleModel;
+    use crate::mouse::MouseCard;
+
+    #[test]
+    fn rewind_replays_inputs() {
+        let mut emu = Apple2::new(AppleModel::AppleIIPlus);
+        // LDX #0 / LDA $C000 / STA $20 / INX / STX $10 / JMP $0302
+        let program = [0xA2, 0x00, 0xAD, 0x00, 0xC0, 0x85, 0x20, 0xE8, 0x86, 0x10, 0x4C, 0x02, 0x03];
+        emu.memory.main_ram[0x0300..0x0300 + program.len()].copy_from_slice(&program);
+        emu.cpu.regs.pc = 0x0300;
+        emu.enable_rewind(1, 100);
+
+        let snapshot = |emu: &Apple2| (emu.instructions, emu.cpu.regs.pc, emu.cpu.regs.x, emu.memory.main_ram[0x10], emu.memory.main_ram[0x20]);
+        for _ in 0..3 {
+            emu.run_frame();
+        }
+        // フレームの途中でキーを押す
+        emu.run_instructions(7);
+        emu.run_frame();
+        emu.debugger.resume();
+        emu.key_down(0xC1);
+        emu.run_frame();
+        let mark = snapshot(&emu);
+        assert_eq!(mark.4, 0xC1);
+        for _ in 0..3 {
+            emu.run_frame();
+        }
+
+        emu.rewind_to(mark.0).unwrap();
+        assert_eq!(snapshot(&emu), mark);
+        emu.step_back(1).unwrap();
+        assert_eq!(emu.instructions, mark.0 - 1);
+
+        let hit = emu.rewind_to_last_write(0x0020).unwrap();
+        assert_eq!((hit.pc, hit.value), (0x0305, 0xC1));
+        assert_eq!(emu.cpu.regs.pc, 0x0307);
+        assert!(emu.rewind_to(emu.instructions + 1).is_err());
+    }
+
+    #[test]
+    fn rewind_restores_cards_and_clock() {
+        let mut emu = Apple2::new(AppleModel::AppleIIPlus);
+        emu.mouse = Some(MouseCard::new(4));
+        emu.thunderclock = Some(ThunderClock::new(THUNDERCLOCK_SLOT));
+        // LDA #'#' / STA $C0D9（数値モード）/ STA $C0D8（READ）/ INC $10 / JMP $0305
+        let program = [0xA9, 0xA3, 0x8D, 0xD9, 0xC0, 0x8D, 0xD8, 0xC0, 0xE6, 0x10, 0x4C, 0x05, 0x03];
+        emu.memory.main_ram[0x0300..0x0300 + program.len()].copy_from_slice(&program);
+        emu.cpu.regs.pc = 0x0300;
+        emu.enable_rewind(1, 100);
+
+        let snapshot = |emu: &Apple2| {
+            let mouse = emu.mouse.as_ref().unwrap();
+            (emu.instructions, emu.memory.main_ram[0x0200..0x0212].to_vec(), mouse.position(), mouse.button())
+        };
+        // フレームの途中で止めて、スナップショットから再実行する区間に時計の読み取りを含める
+        let run_into_frame = |emu: &mut Apple2| {
+            emu.run_instructions(300);
+            emu.run_frame();
+            emu.debugger.resume();
+            snapshot(emu)
+        };
+        emu.run_frame();
+        let before = run_into_frame(&mut emu);
+        emu.run_frame();
+        emu.set_mouse(0.5, 0.25, true);
+        let mark = run_into_frame(&mut emu);
+        let second = emu.memory.main_ram[0x0210];
+        emu.run_frame();
+        emu.set_mouse(1.0, 1.0, false);
+        emu.run_frame();
+
+        // 再実行がホストの時刻を読めば秒が変わって見える
+        while Local::now().second() % 10 == (second & 0x0F) as u32 {
+            std::thread::sleep(std::time::Duration::from_millis(20));
+        }
+        emu.rewind_to(mark.0).unwrap();
+        assert_eq!(snapshot(&emu), mark);
+
+        // ディスクイメージは変わっていないので、スナップショット間で共有する
+        let rewind = emu.rewind.as_ref().unwrap();
+        assert!(rewind.len() > 2);
+        assert_eq!(rewind.disk_images(), 2);
+
+        emu.rewind_to(before.0).unwrap();
+        assert_eq!(snapshot(&emu), before);
+        assert_ne!(before.2, mark.2);
+    }
+}
@@ -16,6 +16,12 @@ pub struct CpuState {
     pub total_cycles: u64,
     pub irq_pending: bool,
     pub nmi_pending: bool,
+    /// アサート中のIRQ要求元（ビットマスク）
+    #[serde(default)]
+    pub irq_sources: u16,
+    /// CLI/SEI/PLP直後の割り込み判定に使うIフラグ
+    #[serde(default)]
+    pub irq_disable_latched: Option<bool>,
 }
 
 /// メモリの状態（セーブ用）
@@ -42,6 +48,28 @@ pub struct MemoryState {
     
     // キーボード
     pub keyboard_latch: u8,
+
+    /// 補助メモリ（IIe、64KB）
+    #[serde(default)]
+    pub aux_ram: Vec<u8>,
+    // IIeのバンク切り替えとダブルHi-Res
+    #[serde(default)]
+    pub store80: bool,
+    #[serde(default)]
+    pub ramrd: bool,
+    #[serde(default)]
+    pub ramwrt: bool,
+    #[serde(default)]
+    pub altzp: bool,
+    #[serde(default)]
+    pub dhires: bool,
+    // ゲームコントローラ
+    #[serde(default)]
+    pub buttons: [bool; 3],
+    #[serde(default)]
+    pub paddles: [u8; 4],
+    #[serde(default)]
+    pub paddle_trigger_cycle: u64,
 }
 
 /// ディスクドライブの状態（セーブ用）
@@ -71,6 +99,72 @@ pub struct VideoState {
     pub frame_count: u64,
 }
 
+/// 時計の状態（セーブ用）
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct ClockState {
+    /// 凍結した時刻（Unixミリ秒、ホストの時刻ならNone）
+    pub frozen_ms: Option<i64>,
+    /// 時刻のずれ（ミリ秒）
+    pub offset_ms: i64,
+}
+
+/// ThunderClock Plusの状態（セーブ用）
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ThunderClockState {
+    pub shift: u64,
+    pub command: u8,
+    pub last_control: u8,
+    pub numeric: bool,
+}
+
+/// No-Slot Clockの状態（セーブ用）
+#[derive(Serialize, Deserialize, Clone)]
+pub struct NoSlotClockState {
+    pub matched_bits: u32,
+    pub enabled: bool,
+    pub position: u32,
+    pub register: u64,
+    pub written: u64,
+    pub written_bits: u32,
+}
+
+/// マウスカードの状態（セーブ用）
+#[derive(Serialize, Deserialize, Clone)]
+pub struct MouseState {
+    pub x: i32,
+    pub y: i32,
+    pub clamp: [(i32, i32); 2],
+    pub mode: u8,
+    pub button: bool,
+    pub last_button: bool,
+    pub vbl_button: bool,
+    pub moved: bool,
+    pub moved_since_vbl: bool,
+    pub pending: u8,
+    pub carry: bool,
+}
+
+/// Videxカードの状態（セーブ用）
+#[derive(Serialize, Deserialize, Clone)]
+pub struct VidexState {
+    pub screen: Vec<u8>,         // 画面RAM (2KB)
+    pub registers: Vec<u8>,      // CRTCレジスタ
+    pub register_select: u8,
+    pub bank: usize,
+    pub expansion_active: bool,
+    pub display: bool,
+}
+
+/// カセットデッキの状態（セーブ用、テープの内容は含まない）
+#[derive(Serialize, Deserialize, Clone)]
+pub struct CassetteState {
+    pub position: u64,
+    pub playing_since: Option<u64>,
+    pub edge_cursor: usize,
+    /// 録音中ならそれまでに録音したエッジの数と、高速書き込みで挿入した時間
+    pub recorded: Option<(usize, u64)>,
+}
+
 /// 完全なエミュレータ状態
 #[derive(Serialize, Deserialize, Clone)]
 pub struct SaveState {
@@ -81,6 +175,24 @@ pub struct SaveState {
     pub video: VideoState,
     pub total_cycles: u64,
     pub frame_count: u64,
+    /// 実行中のフレームの開始・終了サイクル（フレームの途中から正確に続けるため）
+    #[serde(default)]
+    pub frame_begin: u64,
+    #[serde(default)]
+    pub frame_end: u64,
+    /// 周辺機器（挿していない、または古いセーブステートならNone）
+    #[serde(default)]
+    pub clock: Option<ClockState>,
+    #[serde(default)]
+    pub thunderclock: Option<ThunderClockState>,
+    #[serde(default)]
+    pub no_slot_clock: Option<NoSlotClockState>,
+    #[serde(default)]
+    pub mouse: Option<MouseState>,
+    #[serde(default)]
+    pub videx: Option<VidexState>,
+    #[serde(default)]
+    pub cassette: Option<CassetteState>,
 }
 
 impl SaveState {
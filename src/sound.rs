@@ -4,19 +4,135 @@
 //! 波形は変えず、耳に刺さる成分だけを時間方向で丸める。
 
 use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+
+use crate::wav::WavWriter;
 
 #[cfg(feature = "audio")]
-use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
 
-/// サンプルレート (Hz)
+/// サンプルレート (Hz)（録音・録画と、設定がない場合の再生に使う）
 pub const SAMPLE_RATE: u32 = 44100;
 
 /// 1フレームあたりのサンプル数 (44100 / 60)
-const SAMPLES_PER_FRAME: usize = 735;
+pub const SAMPLES_PER_FRAME: usize = 735;
 
-/// リングバッファサイズ（約0.2秒分）
-#[cfg(feature = "audio")]
-const RING_BUFFER_SIZE: usize = 8192;
+/// 再生時のバッファ遅延の既定値 (ms)
+pub const DEFAULT_LATENCY_MS: u32 = 46;
+
+/// 再生サンプルレートの許容範囲 (Hz)
+const SAMPLE_RATE_RANGE: std::ops::RangeInclusive<u32> = 8000..=192_000;
+
+/// バッファ遅延の許容範囲 (ms)
+const LATENCY_MS_RANGE: std::ops::RangeInclusive<u32> = 5..=500;
+
+/// オーディオ再生の設定
+#[derive(Debug, Clone, PartialEq)]
+pub struct AudioSettings {
+    /// 再生サンプルレート (Hz)
+    pub sample_rate: u32,
+    /// 出力バッファに保つ遅延 (ms)
+    pub latency_ms: u32,
+    /// 出力デバイス名（`None` はシステム既定）
+    pub device: Option<String>,
+}
+
+impl Default for AudioSettings {
+    fn default() -> Self {
+        AudioSettings { sample_rate: SAMPLE_RATE, latency_ms: DEFAULT_LATENCY_MS, device: None }
+    }
+}
+
+impl AudioSettings {
+    /// 範囲外の値を丸めた設定
+    pub fn sanitized(&self) -> Self {
+        AudioSettings {
+            sample_rate: self.sample_rate.clamp(*SAMPLE_RATE_RANGE.start(), *SAMPLE_RATE_RANGE.end()),
+            latency_ms: self.latency_ms.clamp(*LATENCY_MS_RANGE.start(), *LATENCY_MS_RANGE.end()),
+            device: self.device.clone().filter(|d| !d.trim().is_empty()),
+        }
+    }
+
+    /// 再生中に保つバッファ残量（サンプル）
+    pub fn target_buffered(&self) -> usize {
+        (self.latency_ms as u64 * self.sample_rate as u64 / 1000).max(64) as usize
+    }
+
+    /// リングバッファの容量（目標残量の4倍、1フレームの変動を吸収できる大きさ）
+    pub fn ring_capacity(&self) -> usize {
+        (self.target_buffered() * 4).max(2048).next_power_of_two()
+    }
+}
+
+/// 出力バッファの統計（プロファイラ表示用）
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct AudioStats {
+    /// 再生サンプルレート (Hz)
+    pub sample_rate: u32,
+    /// リングバッファの容量（サンプル）
+    pub capacity: usize,
+    /// 再生待ちのサンプル数
+    pub buffered: usize,
+    /// バッファが空になった回数（音切れ）
+    pub underruns: u64,
+    /// バッファが満杯で書き込めなかった回数（サンプル破棄）
+    pub overruns: u64,
+}
+
+/// CPUクロック（1秒あたりのサイクル数、17030サイクル × 60フレーム）
+const CPU_HZ: u64 = 17030 * 60;
+
+/// DCブロッカーの係数（約35Hz以下をカット、コーンが中立位置へ戻る動きに相当）
+const DC_BLOCK_R: f32 = 0.995;
+
+/// クリックキューの上限（MAX速度の1更新分でも溢れない量）
+const CLICK_QUEUE_LIMIT: usize = 65536;
+
+/// ディスク機械音の同時発音数の上限
+const MECH_VOICE_LIMIT: usize = 16;
+
+/// ディスク機械音の種類
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum MechSound {
+    /// ヘッドのステップ音（短く高めのクリック）
+    Step,
+    /// トラック0のストッパーに当たる音（低めで長いガタつき）
+    Bump,
+}
+
+impl MechSound {
+    /// 発音時間（秒）
+    fn duration(self) -> f32 {
+        match self {
+            MechSound::Step => 0.015,
+            MechSound::Bump => 0.06,
+        }
+    }
+
+    /// 発音開始から `t` 秒後の波形
+    fn sample(self, t: f32, noise: f32) -> f32 {
+        let tau = 2.0 * std::f32::consts::PI;
+        match self {
+            MechSound::Step => {
+                let env = (-t / 0.003).exp();
+                (noise * 0.6 + (tau * 1800.0 * t).sin() * 0.4) * env * 0.5
+            }
+            MechSound::Bump => {
+                let env = (-t / 0.012).exp();
+                (noise * 0.5 + (tau * 420.0 * t).sin() * 0.7 + (tau * 1130.0 * t).sin() * 0.3) * env * 0.8
+            }
+        }
+    }
+}
+
+/// 発音中のディスク機械音
+struct MechVoice {
+    kind: MechSound,
+    /// ブロック先頭から発音開始までのサンプル数
+    delay: usize,
+    /// 発音開始からの経過サンプル数
+    age: usize,
+}
 
 /// 1-pole IIR ローパスフィルタ（シンプル・高速・十分）
 struct LowPass {
@@ -49,6 +165,11 @@ fn speaker_inertia(prev: f32, current: f32) -> f32 {
 }
 
 /// Apple IIスピーカーエミュレータ (1bit方式)
+///
+/// $C030のトグルをサイクル単位のタイムスタンプで受け取り、出力サンプル上の
+/// 小数位置に2点polyBLEPの帯域制限ステップとして配置する。サイクルとサンプルの
+/// 対応は呼び出し側が区間ごとに決めるため、速度倍率やホスト側の揺らぎに合わせて
+/// 変換比を変えてもエイリアスやドリフトが出ない。
 pub struct Speaker {
     /// クリックイベントキュー (サイクル数)
     click_queue: VecDeque<u64>,
@@ -56,20 +177,31 @@ pub struct Speaker {
     enabled: bool,
     /// ボリューム（0.0 - 1.0）
     volume: f32,
+    /// 出力サンプルレート
+    sample_rate: u32,
     /// 現在のスピーカー状態 (true = HIGH, false = LOW)
     speaker_state: bool,
+    /// サンプル時点でのステップ波形のレベル（-1.0 / +1.0）
+    level: f32,
+    /// 次のブロックに持ち越すステップ（サンプル境界ちょうどのトグル）
+    carry_step: f32,
+    /// 次のブロックに持ち越すBLEP残差
+    carry_residual: f32,
     /// 前回のスピーカー出力（慣性用）
     prev_speaker_output: f32,
+    /// DCブロッカーの状態（前回入力, 前回出力）
+    dc_prev_in: f32,
+    dc_prev_out: f32,
     /// サンプル生成用バッファ（再利用）
     sample_buffer: Vec<f32>,
+    /// ステップ位置のバッファ（再利用）
+    step_buffer: Vec<f32>,
+    /// BLEP残差のバッファ（再利用）
+    residual_buffer: Vec<f32>,
     /// ローパスフィルタ
     lpf: LowPass,
-    /// 最後に処理したサイクル
-    last_processed_cycle: u64,
-    /// 最後のクリックからの経過フレーム
-    silent_frames: u32,
-    /// フェードアウト中のゲイン
-    fade_gain: f32,
+    /// `generate_samples` のサンプル数計算の端数（サイクル × サンプルレート）
+    frame_remainder: u64,
     /// リセット音の残りサンプル数
     reset_sound_remaining: usize,
     /// リセット音の位相
@@ -78,47 +210,91 @@ pub struct Speaker {
     ui_click_remaining: usize,
     /// UIクリック音の位相
     ui_click_phase: f32,
+    /// ディスク機械音のボリューム（0.0 - 1.0、全体のボリュームに掛かる）
+    disk_volume: f32,
+    /// ディスク機械音のイベントキュー (サイクル数, 種類)
+    mech_queue: VecDeque<(u64, MechSound)>,
+    /// 発音中のディスク機械音
+    mech_voices: Vec<MechVoice>,
+    /// ディスクモーターが回っているか
+    disk_motor: bool,
+    /// モーター音のエンベロープ（0.0 - 1.0）
+    motor_level: f32,
+    /// モーター音の位相（秒）
+    motor_time: f32,
+    /// モーター音のノイズ用ローパス
+    motor_lpf: LowPass,
+    /// ノイズ生成器の状態（xorshift32）
+    noise_state: u32,
 }
 
 impl Speaker {
     pub fn new() -> Self {
+        Self::with_sample_rate(SAMPLE_RATE)
+    }
+
+    /// 出力サンプルレートを指定して作成
+    pub fn with_sample_rate(sample_rate: u32) -> Self {
+        let sample_rate = sample_rate.max(8000);
         Speaker {
             click_queue: VecDeque::with_capacity(4096),
             enabled: true,
             volume: 0.25,
+            sample_rate,
             speaker_state: false,
-            prev_speaker_output: 0.0,
-            sample_buffer: vec![0.0; SAMPLES_PER_FRAME],
-            lpf: LowPass::new(4000.0, SAMPLE_RATE as f32),
-            last_processed_cycle: 0,
-            silent_frames: 100,
-            fade_gain: 0.0,
+            level: -1.0,
+            carry_step: 0.0,
+            carry_residual: 0.0,
+            prev_speaker_output: -1.0,
+            dc_prev_in: soft_saturate(-1.0),
+            dc_prev_out: 0.0,
+            sample_buffer: Vec::with_capacity(SAMPLES_PER_FRAME * 2),
+            step_buffer: Vec::with_capacity(SAMPLES_PER_FRAME * 2),
+            residual_buffer: Vec::with_capacity(SAMPLES_PER_FRAME * 2),
+            lpf: {
+                let mut lpf = LowPass::new(4000.0, sample_rate as f32);
+                lpf.z = -1.0;
+                lpf
+            },
+            frame_remainder: 0,
             reset_sound_remaining: 0,
             reset_sound_phase: 0.0,
             ui_click_remaining: 0,
             ui_click_phase: 0.0,
+            disk_volume: 0.5,
+            mech_queue: VecDeque::new(),
+            mech_voices: Vec::with_capacity(MECH_VOICE_LIMIT),
+            disk_motor: false,
+            motor_level: 0.0,
+            motor_time: 0.0,
+            motor_lpf: LowPass::new(400.0, sample_rate as f32),
+            noise_state: 0x1234_5678,
         }
     }
 
+    /// 出力サンプルレート
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
     /// リセット音をトリガー（短いビープ音）
     pub fn trigger_reset_sound(&mut self) {
         // 約0.1秒間のリセット音
-        self.reset_sound_remaining = (SAMPLE_RATE as usize) / 10;
+        self.reset_sound_remaining = (self.sample_rate as usize) / 10;
         self.reset_sound_phase = 0.0;
     }
 
     /// UIクリック音をトリガー（短いクリック音）
     pub fn trigger_ui_click(&mut self) {
         // 約0.03秒間の短いクリック音
-        self.ui_click_remaining = (SAMPLE_RATE as usize) / 33;
+        self.ui_click_remaining = (self.sample_rate as usize) / 33;
         self.ui_click_phase = 0.0;
     }
 
     /// スピーカーをクリック（$C030アクセス時に呼ばれる）
     pub fn click(&mut self, cycle: u64) {
         self.click_queue.push_back(cycle);
-        self.silent_frames = 0;
-        if self.click_queue.len() > 8192 {
+        if self.click_queue.len() > CLICK_QUEUE_LIMIT {
             self.click_queue.pop_front();
         }
     }
@@ -129,100 +305,220 @@ impl Speaker {
     }
 
     /// ボリュームを設定（0.0 - 1.0）
-    #[allow(dead_code)]
     pub fn set_volume(&mut self, volume: f32) {
         self.volume = volume.clamp(0.0, 1.0);
     }
 
-    /// オーディオサンプルを生成
-    pub fn generate_samples(&mut self, base_cycle: u64, cycles_per_frame: u64) -> Option<&[f32]> {
-        if !self.enabled || cycles_per_frame == 0 {
-            // リセット音またはUIクリック音が残っている場合は処理を続ける
-            if self.reset_sound_remaining == 0 && self.ui_click_remaining == 0 {
-                return None;
-            }
+    /// 現在のボリューム
+    pub fn volume(&self) -> f32 {
+        self.volume
+    }
+
+    /// ディスク機械音のボリュームを設定（0.0 - 1.0）
+    pub fn set_disk_volume(&mut self, volume: f32) {
+        self.disk_volume = volume.clamp(0.0, 1.0);
+    }
+
+    /// ディスク機械音のボリューム
+    pub fn disk_volume(&self) -> f32 {
+        self.disk_volume
+    }
+
+    /// ディスクモーターの状態を設定（モーター音のオン/オフ）
+    pub fn set_disk_motor(&mut self, on: bool) {
+        self.disk_motor = on;
+    }
+
+    /// ヘッドのステップ音を鳴らす
+    pub fn disk_step(&mut self, cycle: u64) {
+        self.push_mech(cycle, MechSound::Step);
+    }
+
+    /// トラック0のストッパー音を鳴らす
+    pub fn disk_bump(&mut self, cycle: u64) {
+        self.push_mech(cycle, MechSound::Bump);
+    }
+
+    fn push_mech(&mut self, cycle: u64, kind: MechSound) {
+        self.mech_queue.push_back((cycle, kind));
+        if self.mech_queue.len() > CLICK_QUEUE_LIMIT {
+            self.mech_queue.pop_front();
         }
+    }
 
-        let end_cycle = base_cycle + cycles_per_frame;
-        
-        // このフレームでクリックがあるか確認
-        let has_clicks = self.click_queue.iter().any(|&c| c < end_cycle);
-        let has_reset_sound = self.reset_sound_remaining > 0;
-        let has_ui_click = self.ui_click_remaining > 0;
-        
-        if !has_clicks && !has_reset_sound && !has_ui_click {
-            self.silent_frames = self.silent_frames.saturating_add(1);
-            
-            // 音がフェードアウト中でない、または完全にフェードアウトした場合
-            if self.fade_gain < 0.001 {
-                return None;
+    /// -1.0〜+1.0の白色ノイズ
+    fn next_noise(&mut self) -> f32 {
+        let mut x = self.noise_state;
+        x ^= x << 13;
+        x ^= x >> 17;
+        x ^= x << 5;
+        self.noise_state = x;
+        (x as f32 / u32::MAX as f32) * 2.0 - 1.0
+    }
+
+    /// ディスク機械音の1サンプル分
+    fn next_mech_sample(&mut self) -> f32 {
+        let rate = self.sample_rate as f32;
+        let dt = 1.0 / rate;
+        let mut out = 0.0;
+
+        // モーター音: ローパスしたノイズと低いうなり、回転（300rpm = 5Hz）のゆらぎ
+        let target = if self.disk_motor { 1.0 } else { 0.0 };
+        self.motor_level += (target - self.motor_level) * (dt / 0.08).min(1.0);
+        if self.motor_level > 1e-4 {
+            let noise = self.next_noise();
+            let rumble = self.motor_lpf.process(noise) * 2.0;
+            let tau = 2.0 * std::f32::consts::PI;
+            let hum = (tau * 100.0 * self.motor_time).sin() * 0.25;
+            let wobble = 0.7 + 0.3 * (tau * 5.0 * self.motor_time).sin();
+            out += (rumble + hum) * wobble * self.motor_level * 0.15;
+            self.motor_time = (self.motor_time + dt) % 1.0;
+        }
+
+        // ステップ音・ストッパー音
+        let mut i = 0;
+        while i < self.mech_voices.len() {
+            if self.mech_voices[i].delay > 0 {
+                self.mech_voices[i].delay -= 1;
+                i += 1;
+                continue;
             }
-            
-            // フェードアウト処理
-            for sample in self.sample_buffer.iter_mut() {
-                self.fade_gain *= 0.995;
-                let s = self.prev_speaker_output * self.fade_gain;
-                *sample = self.lpf.process(s) * self.volume;
+            let noise = self.next_noise();
+            let voice = &mut self.mech_voices[i];
+            let t = voice.age as f32 * dt;
+            if t >= voice.kind.duration() {
+                self.mech_voices.swap_remove(i);
+                continue;
             }
-            
-            return Some(&self.sample_buffer);
+            out += voice.kind.sample(t, noise);
+            voice.age += 1;
+            i += 1;
         }
-        
-        // クリックがある場合はフェードインして処理
-        if has_clicks {
-            self.silent_frames = 0;
-            self.fade_gain = 1.0;
+        out
+    }
+
+    /// `end_cycle` より前の機械音イベントを発音予定に積む（位置は `pos_of` で求める）
+    fn schedule_mech(&mut self, end_cycle: u64, pos_of: impl Fn(u64) -> usize) {
+        while let Some(&(cycle, kind)) = self.mech_queue.front() {
+            if cycle >= end_cycle {
+                break;
+            }
+            self.mech_queue.pop_front();
+            if self.mech_voices.len() < MECH_VOICE_LIMIT {
+                self.mech_voices.push(MechVoice { kind, delay: pos_of(cycle), age: 0 });
+            }
         }
-        
-        let cycles_per_sample = if cycles_per_frame > 0 {
-            cycles_per_frame as f32 / SAMPLES_PER_FRAME as f32
-        } else {
-            17030.0 / SAMPLES_PER_FRAME as f32  // デフォルト値
-        };
-        
-        // 各サンプルを生成
-        for i in 0..SAMPLES_PER_FRAME {
-            let sample_cycle = base_cycle + (i as f32 * cycles_per_sample) as u64;
-            
-            // このサンプル時点までのクリックを処理
-            while let Some(&click_cycle) = self.click_queue.front() {
-                if click_cycle <= sample_cycle {
-                    self.click_queue.pop_front();
-                    self.speaker_state = !self.speaker_state;
-                } else {
+    }
+
+    /// エミュレート時間どおりのサンプル数でオーディオサンプルを生成
+    ///
+    /// `cycles` サイクルを実機の1.0倍速に相当するサンプル数に変換する（端数は次回に繰り越す）。
+    /// 録音・録画のように、映像フレームとサンプル数を厳密に揃えたい場合に使う。
+    pub fn generate_samples(&mut self, base_cycle: u64, cycles: u64) -> &[f32] {
+        let total = self.frame_remainder + cycles * self.sample_rate as u64;
+        let count = (total / CPU_HZ) as usize;
+        self.frame_remainder = total % CPU_HZ;
+        self.render(base_cycle, base_cycle + cycles, count)
+    }
+
+    /// `start_cycle`〜`end_cycle` の区間を `count` サンプルに変換して生成
+    ///
+    /// 区間とサンプル数の比が実効的な再生速度になる。`end_cycle` より前のクリックは
+    /// すべて消費され、それ以降のものはキューに残る。
+    pub fn render(&mut self, start_cycle: u64, end_cycle: u64, count: usize) -> &[f32] {
+        self.sample_buffer.clear();
+        self.sample_buffer.resize(count, 0.0);
+        if count == 0 {
+            // 出力がなくてもスピーカーの状態は進める
+            while let Some(&cycle) = self.click_queue.front() {
+                if cycle >= end_cycle {
                     break;
                 }
+                self.click_queue.pop_front();
+                self.speaker_state = !self.speaker_state;
+                self.level = if self.speaker_state { 1.0 } else { -1.0 };
             }
-            
-            // 1bit → PCM化（-1.0 〜 +1.0）
-            let raw_pcm = if self.speaker_state { 1.0 } else { -1.0 };
-            
+            while self.mech_queue.front().is_some_and(|&(cycle, _)| cycle < end_cycle) {
+                self.mech_queue.pop_front();
+            }
+            return &self.sample_buffer;
+        }
+
+        // ステップと残差を配置する（添字 count は次のブロックの先頭）
+        self.step_buffer.clear();
+        self.step_buffer.resize(count + 1, 0.0);
+        self.residual_buffer.clear();
+        self.residual_buffer.resize(count + 1, 0.0);
+        self.step_buffer[0] = self.carry_step;
+        self.residual_buffer[0] = self.carry_residual;
+
+        let span = end_cycle.saturating_sub(start_cycle).max(1) as f64;
+        let scale = count as f64 / span;
+        while let Some(&cycle) = self.click_queue.front() {
+            if cycle >= end_cycle {
+                break;
+            }
+            self.click_queue.pop_front();
+            self.speaker_state = !self.speaker_state;
+            let height = if self.speaker_state { 2.0 } else { -2.0 };
+
+            // サンプル k と k+1 の間、k からの距離 d の位置にステップがある
+            let pos = (cycle.saturating_sub(start_cycle) as f64 * scale).min(count as f64 - 1e-9);
+            let k = pos as usize;
+            let d = (pos - k as f64) as f32;
+            self.step_buffer[k + 1] += height;
+            // 2点polyBLEP残差
+            self.residual_buffer[k] += height * (1.0 - d) * (1.0 - d) * 0.5;
+            self.residual_buffer[k + 1] -= height * d * d * 0.5;
+        }
+        self.carry_step = self.step_buffer[count];
+        self.carry_residual = self.residual_buffer[count];
+        self.schedule_mech(end_cycle, |cycle| {
+            ((cycle.saturating_sub(start_cycle) as f64 * scale) as usize).min(count - 1)
+        });
+
+        let gain = if self.enabled { self.volume } else { 0.0 };
+        let mech_gain = gain * self.disk_volume;
+        let rate = self.sample_rate as f32;
+        for i in 0..count {
+            self.level += self.step_buffer[i];
+            let raw_pcm = self.level + self.residual_buffer[i];
+
             // スピーカー慣性（紙コーンの動き）
             let with_inertia = speaker_inertia(self.prev_speaker_output, raw_pcm);
             self.prev_speaker_output = with_inertia;
-            
-            // ローパスフィルタ
-            let filtered = self.lpf.process(with_inertia);
-            
-            // ソフトサチュレーション
-            let saturated = soft_saturate(filtered);
-            
-            // 通常の音量適用
-            let mut sample = saturated * self.volume;
-            
+
+            // ローパスフィルタ → ソフトサチュレーション
+            let saturated = soft_saturate(self.lpf.process(with_inertia));
+
+            // DCブロッカー（トグルが止まると無音に戻る）
+            let centered = saturated - self.dc_prev_in + DC_BLOCK_R * self.dc_prev_out;
+            self.dc_prev_in = saturated;
+            self.dc_prev_out = centered;
+
+            let mut sample = centered * gain;
+
+            // ディスクの機械音をミックス
+            if mech_gain > 0.0 && (self.motor_level > 1e-4 || self.disk_motor || !self.mech_voices.is_empty()) {
+                sample += self.next_mech_sample() * mech_gain;
+            } else {
+                self.mech_voices.clear();
+                self.motor_level = if self.disk_motor { 1.0 } else { 0.0 };
+            }
+
             // リセット音をミックス（800Hz + 1200Hzのビープ音、エンベロープ付き）
             if self.reset_sound_remaining > 0 {
                 let freq1 = 800.0;
                 let freq2 = 1200.0;
                 let t = self.reset_sound_phase;
-                
+
                 // 2つの周波数を合成
-                let beep1 = (2.0 * std::f32::consts::PI * freq1 * t / SAMPLE_RATE as f32).sin();
-                let beep2 = (2.0 * std::f32::consts::PI * freq2 * t / SAMPLE_RATE as f32).sin();
+                let beep1 = (2.0 * std::f32::consts::PI * freq1 * t / rate).sin();
+                let beep2 = (2.0 * std::f32::consts::PI * freq2 * t / rate).sin();
                 let beep = (beep1 * 0.6 + beep2 * 0.4) * 0.3;
-                
+
                 // エンベロープ（フェードイン・フェードアウト）
-                let total_samples = (SAMPLE_RATE as usize) / 10;
+                let total_samples = (self.sample_rate as usize) / 10;
                 let progress = 1.0 - (self.reset_sound_remaining as f32 / total_samples as f32);
                 let envelope = if progress < 0.1 {
                     progress * 10.0  // フェードイン
@@ -231,62 +527,50 @@ impl Speaker {
                 } else {
                     1.0
                 };
-                
+
                 sample += beep * envelope * self.volume;
-                
+
                 self.reset_sound_phase += 1.0;
                 self.reset_sound_remaining -= 1;
             }
-            
+
             // UIクリック音をミックス（短いポップ音）
             if self.ui_click_remaining > 0 {
                 let freq = 1500.0;  // 高めの周波数で軽快なクリック感
                 let t = self.ui_click_phase;
-                
+
                 // 減衰する正弦波
-                let total_samples = (SAMPLE_RATE as usize) / 33;
+                let total_samples = (self.sample_rate as usize) / 33;
                 let progress = 1.0 - (self.ui_click_remaining as f32 / total_samples as f32);
-                
+
                 // 急激な立ち上がりとフェードアウト
                 let envelope = if progress < 0.05 {
                     progress * 20.0  // 急速フェードイン
                 } else {
                     (1.0 - progress).powf(2.0)  // 二次関数的フェードアウト
                 };
-                
-                let click = (2.0 * std::f32::consts::PI * freq * t / SAMPLE_RATE as f32).sin() * 0.2;
+
+                let click = (2.0 * std::f32::consts::PI * freq * t / rate).sin() * 0.2;
                 sample += click * envelope * self.volume;
-                
+
                 self.ui_click_phase += 1.0;
                 self.ui_click_remaining -= 1;
             }
-            
-            self.sample_buffer[i] = sample;
-        }
 
-        // キューに残った古いイベントをクリーンアップ
-        while let Some(&cycle) = self.click_queue.front() {
-            if cycle < end_cycle {
-                self.click_queue.pop_front();
-                self.speaker_state = !self.speaker_state;
-            } else {
-                break;
-            }
+            self.sample_buffer[i] = sample;
         }
-        
-        self.last_processed_cycle = end_cycle;
 
-        Some(&self.sample_buffer)
+        &self.sample_buffer
     }
 
     #[allow(dead_code)]
     pub fn clear(&mut self) {
-        self.click_queue.clear();
-        self.speaker_state = false;
-        self.prev_speaker_output = 0.0;
-        self.lpf.z = 0.0;
-        self.silent_frames = 100;
-        self.fade_gain = 0.0;
+        *self = Speaker {
+            enabled: self.enabled,
+            volume: self.volume,
+            disk_volume: self.disk_volume,
+            ..Speaker::with_sample_rate(self.sample_rate)
+        };
     }
 }
 
@@ -296,44 +580,111 @@ impl Default for Speaker {
     }
 }
 
+/// リアルタイム再生用のサンプル数を決めるクロック
+///
+/// 1回の更新で経過したホスト時間をサンプル数に変換し、出力バッファの残量が目標から
+/// ずれていれば比率をわずかに補正する（動的リサンプリング）。エミュレーション速度が
+/// 何倍でも、実時間に対して出力が足りなくなったり溢れたりしない。
+pub struct PlaybackClock {
+    sample_rate: u32,
+    /// 目標とするバッファ残量（サンプル）
+    target_buffered: usize,
+    last: Option<std::time::Instant>,
+    /// サンプル数の端数
+    fraction: f64,
+}
+
+impl PlaybackClock {
+    /// 1回の更新で扱う最大の経過時間（これ以上の停止はなかったことにする）
+    const MAX_STEP_SECS: f64 = 0.1;
+    /// バッファ残量による補正の最大幅
+    const MAX_CORRECTION: f64 = 0.05;
+
+    pub fn new(sample_rate: u32, target_buffered: usize) -> Self {
+        PlaybackClock { sample_rate, target_buffered: target_buffered.max(1), last: None, fraction: 0.0 }
+    }
+
+    /// 一時停止などで更新が途切れたときに呼ぶ
+    pub fn reset(&mut self) {
+        self.last = None;
+        self.fraction = 0.0;
+    }
+
+    /// 前回の呼び出しからの経過時間に相当するサンプル数
+    ///
+    /// `buffered` は出力側に残っているサンプル数（わからなければ `None`）。
+    pub fn samples_due(&mut self, buffered: Option<usize>) -> usize {
+        let now = std::time::Instant::now();
+        let secs = match self.last.replace(now) {
+            Some(last) => now.duration_since(last).as_secs_f64().min(Self::MAX_STEP_SECS),
+            // 初回は1フレーム分
+            None => 1.0 / 60.0,
+        };
+        let mut wanted = secs * self.sample_rate as f64;
+        if let Some(buffered) = buffered {
+            let error = (self.target_buffered as f64 - buffered as f64) / self.target_buffered as f64;
+            wanted *= 1.0 + (error * Self::MAX_CORRECTION).clamp(-Self::MAX_CORRECTION, Self::MAX_CORRECTION);
+        }
+        wanted += self.fraction;
+        let count = wanted.floor();
+        self.fraction = wanted - count;
+        count as usize
+    }
+}
+
 // ============================================================
 // オーディオ出力（rodioが有効な場合のみ）
 // ============================================================
 
 #[cfg(feature = "audio")]
-use rodio::{OutputStream, Sink, Source};
+use rodio::{OutputStream, OutputStreamHandle, Sink, Source};
 
 #[cfg(feature = "audio")]
 pub struct AudioOutput {
     _stream: OutputStream,
     sink: Sink,
     ring_buffer: std::sync::Arc<RingBuffer>,
+    sample_rate: u32,
 }
 
 #[cfg(feature = "audio")]
 struct RingBuffer {
-    data: Box<[f32; RING_BUFFER_SIZE]>,
+    data: Box<[f32]>,
     write_pos: AtomicUsize,
     read_pos: AtomicUsize,
+    /// 空のバッファを読んだ状態か（アンダーランを1回として数えるため）
+    starved: AtomicBool,
+    underruns: AtomicU64,
+    overruns: AtomicU64,
 }
 
 #[cfg(feature = "audio")]
 impl RingBuffer {
-    fn new() -> Self {
+    fn new(capacity: usize) -> Self {
         RingBuffer {
-            data: Box::new([0.0; RING_BUFFER_SIZE]),
+            data: vec![0.0; capacity].into_boxed_slice(),
             write_pos: AtomicUsize::new(0),
             read_pos: AtomicUsize::new(0),
+            // 最初のサンプルが届くまではアンダーランとして数えない
+            starved: AtomicBool::new(true),
+            underruns: AtomicU64::new(0),
+            overruns: AtomicU64::new(0),
         }
     }
-    
+
+    fn capacity(&self) -> usize {
+        self.data.len()
+    }
+
     fn write(&self, samples: &[f32]) {
+        let capacity = self.capacity();
         let mut write_pos = self.write_pos.load(Ordering::Relaxed);
         let read_pos = self.read_pos.load(Ordering::Acquire);
         
         for &sample in samples {
-            let next_pos = (write_pos + 1) % RING_BUFFER_SIZE;
+            let next_pos = (write_pos + 1) % capacity;
             if next_pos == read_pos {
+                self.overruns.fetch_add(1, Ordering::Relaxed);
                 break;
             }
             unsafe {
@@ -350,15 +701,19 @@ impl RingBuffer {
         let read_pos = self.read_pos.load(Ordering::Relaxed);
         
         if read_pos == write_pos {
+            if !self.starved.swap(true, Ordering::Relaxed) {
+                self.underruns.fetch_add(1, Ordering::Relaxed);
+            }
             return 0.0;
         }
+        self.starved.store(false, Ordering::Relaxed);
         
         let sample = unsafe {
             let ptr = self.data.as_ptr();
             *ptr.add(read_pos)
         };
         
-        let next_pos = (read_pos + 1) % RING_BUFFER_SIZE;
+        let next_pos = (read_pos + 1) % self.capacity();
         self.read_pos.store(next_pos, Ordering::Release);
         sample
     }
@@ -370,7 +725,44 @@ impl RingBuffer {
         if write_pos >= read_pos {
             write_pos - read_pos
         } else {
-            RING_BUFFER_SIZE - read_pos + write_pos
+            self.capacity() - read_pos + write_pos
+        }
+    }
+}
+
+/// 出力デバイスを開く（見つからない場合はシステム既定にフォールバック）
+#[cfg(feature = "audio")]
+fn open_stream(device: Option<&str>) -> Result<(OutputStream, OutputStreamHandle), String> {
+    use rodio::cpal::traits::HostTrait;
+    use rodio::DeviceTrait;
+
+    if let Some(name) = device {
+        let found = rodio::cpal::default_host()
+            .output_devices()
+            .map_err(|e| format!("Failed to enumerate audio devices: {}", e))?
+            .find(|d| d.name().map(|n| n == name).unwrap_or(false));
+        match found {
+            Some(dev) => {
+                return OutputStream::try_from_device(&dev)
+                    .map_err(|e| format!("Failed to open audio device '{}': {}", name, e));
+            }
+            None => log::warn!("Audio device '{}' not found, using the default device", name),
+        }
+    }
+    OutputStream::try_default().map_err(|e| format!("Failed to create audio output: {}", e))
+}
+
+/// 利用可能な出力デバイス名の一覧
+#[cfg(feature = "audio")]
+pub fn output_device_names() -> Vec<String> {
+    use rodio::cpal::traits::HostTrait;
+    use rodio::DeviceTrait;
+
+    match rodio::cpal::default_host().output_devices() {
+        Ok(devices) => devices.filter_map(|d| d.name().ok()).collect(),
+        Err(e) => {
+            log::warn!("Failed to enumerate audio devices: {}", e);
+            Vec::new()
         }
     }
 }
@@ -378,17 +770,22 @@ impl RingBuffer {
 #[cfg(feature = "audio")]
 impl AudioOutput {
     pub fn new() -> Result<Self, String> {
-        let (stream, stream_handle) = OutputStream::try_default()
-            .map_err(|e| format!("Failed to create audio output: {}", e))?;
+        Self::open(&AudioSettings::default())
+    }
+
+    /// 設定に従って出力を開く
+    pub fn open(settings: &AudioSettings) -> Result<Self, String> {
+        let settings = settings.sanitized();
+        let (stream, stream_handle) = open_stream(settings.device.as_deref())?;
         
         let sink = Sink::try_new(&stream_handle)
             .map_err(|e| format!("Failed to create audio sink: {}", e))?;
         
-        let ring_buffer = std::sync::Arc::new(RingBuffer::new());
+        let ring_buffer = std::sync::Arc::new(RingBuffer::new(settings.ring_capacity()));
         
         let source = RingBufferSource {
             buffer: std::sync::Arc::clone(&ring_buffer),
-            sample_rate: SAMPLE_RATE,
+            sample_rate: settings.sample_rate,
             last_sample: 0.0,
         };
         
@@ -398,14 +795,32 @@ impl AudioOutput {
             _stream: stream,
             sink,
             ring_buffer,
+            sample_rate: settings.sample_rate,
         })
     }
 
-    pub fn play_samples(&mut self, samples: Option<&[f32]>) {
-        if let Some(samples) = samples {
-            if self.ring_buffer.available() < RING_BUFFER_SIZE - samples.len() - 100 {
-                self.ring_buffer.write(samples);
-            }
+    pub fn play_samples(&mut self, samples: &[f32]) {
+        let capacity = self.ring_buffer.capacity();
+        if self.ring_buffer.available() < capacity.saturating_sub(samples.len() + 100) {
+            self.ring_buffer.write(samples);
+        } else {
+            self.ring_buffer.overruns.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// 再生待ちのサンプル数
+    pub fn buffered_samples(&self) -> Option<usize> {
+        Some(self.ring_buffer.available())
+    }
+
+    /// 出力バッファの統計
+    pub fn stats(&self) -> AudioStats {
+        AudioStats {
+            sample_rate: self.sample_rate,
+            capacity: self.ring_buffer.capacity(),
+            buffered: self.ring_buffer.available(),
+            underruns: self.ring_buffer.underruns.load(Ordering::Relaxed),
+            overruns: self.ring_buffer.overruns.load(Ordering::Relaxed),
         }
     }
     
@@ -457,5 +872,196 @@ pub struct AudioOutput { _dummy: () }
 #[cfg(not(feature = "audio"))]
 impl AudioOutput {
     pub fn new() -> Result<Self, String> { Ok(AudioOutput { _dummy: () }) }
-    pub fn play_samples(&mut self, _samples: Option<&[f32]>) {}
+    pub fn open(_settings: &AudioSettings) -> Result<Self, String> { Self::new() }
+    pub fn play_samples(&mut self, _samples: &[f32]) {}
+    pub fn buffered_samples(&self) -> Option<usize> { None }
+    pub fn stats(&self) -> AudioStats { AudioStats::default() }
+}
+
+#[cfg(not(feature = "audio"))]
+pub fn output_device_names() -> Vec<String> { Vec::new() }
+
+// ============================================================
+// オーディオ録音（audio機能の有無に関係なく使用可能）
+// ============================================================
+
+/// 生トラックのサンプルレート（1サイクル = 1サンプル、17030サイクル × 60フレーム）
+pub const RAW_SAMPLE_RATE: u32 = 17030 * 60;
+
+/// 生トラックの振幅
+const RAW_AMPLITUDE: i16 = 0x4000;
+
+/// 1bitスピーカーの生の状態をサイクル単位で記録するトラック
+struct RawTrack {
+    wav: WavWriter,
+    /// 現在のスピーカー状態
+    level: bool,
+    /// 書き込み済みのサイクル（次に書くサイクル）
+    cycle: Option<u64>,
+}
+
+impl RawTrack {
+    /// `end_cycle` の直前までを書き込む（最初の呼び出しでは `start_cycle` から）
+    fn write_until(&mut self, clicks: &[u64], start_cycle: u64, end_cycle: u64) -> std::io::Result<()> {
+        let mut cycle = self.cycle.unwrap_or(start_cycle);
+        for &click in clicks {
+            let click = click.clamp(cycle, end_cycle);
+            self.wav.write_run(self.sample(), (click - cycle) as usize)?;
+            cycle = click;
+            self.level = !self.level;
+        }
+        self.wav.write_run(self.sample(), end_cycle.saturating_sub(cycle) as usize)?;
+        self.cycle = Some(end_cycle.max(cycle));
+        Ok(())
+    }
+
+    fn sample(&self) -> i16 {
+        if self.level { RAW_AMPLITUDE } else { -RAW_AMPLITUDE }
+    }
+}
+
+/// エミュレートした音声をWAVに録音する
+///
+/// フィルタ後のストリーム（`Speaker::generate_samples` の出力）を `SAMPLE_RATE` で記録する。
+/// 生トラックを有効にすると、$C030のトグルをフィルタなしで1サイクル単位の別ファイル
+/// （`RAW_SAMPLE_RATE`）にも記録する。
+pub struct AudioRecorder {
+    path: PathBuf,
+    filtered: WavWriter,
+    raw: Option<RawTrack>,
+}
+
+impl AudioRecorder {
+    /// 録音を開始する
+    ///
+    /// 生トラックは `<名前>.raw.wav` に書き出す。
+    pub fn start(path: &Path, raw: bool) -> std::io::Result<Self> {
+        let filtered = WavWriter::create(path, SAMPLE_RATE, 1)?;
+        let raw = if raw {
+            Some(RawTrack {
+                wav: WavWriter::create(Self::raw_path_for(path), RAW_SAMPLE_RATE, 1)?,
+                level: false,
+                cycle: None,
+            })
+        } else {
+            None
+        };
+        Ok(AudioRecorder { path: path.to_path_buf(), filtered, raw })
+    }
+
+    /// 生トラックのファイル名
+    pub fn raw_path_for(path: &Path) -> PathBuf {
+        path.with_extension("raw.wav")
+    }
+
+    /// 録音ファイルのパス
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// 生トラックのパス（有効な場合）
+    pub fn raw_path(&self) -> Option<PathBuf> {
+        self.raw.as_ref().map(|_| Self::raw_path_for(&self.path))
+    }
+
+    /// 録音した長さ（秒）
+    pub fn duration_secs(&self) -> f64 {
+        self.filtered.samples_written() as f64 / SAMPLE_RATE as f64
+    }
+
+    /// 1フレーム分のフィルタ後サンプルを追加（`None` は無音）
+    pub fn push_samples(&mut self, samples: Option<&[f32]>) -> std::io::Result<()> {
+        match samples {
+            Some(s) => self.filtered.write_f32(s),
+            None => self.filtered.write_silence(SAMPLES_PER_FRAME),
+        }
+    }
+
+    /// 1フレーム分のスピーカークリック（サイクル）を生トラックに追加
+    ///
+    /// `start_cycle`/`end_cycle` はフレームの開始/終了サイクル。
+    pub fn push_clicks(&mut self, clicks: &[u64], start_cycle: u64, end_cycle: u64) -> std::io::Result<()> {
+        match &mut self.raw {
+            Some(raw) => raw.write_until(clicks, start_cycle, end_cycle),
+            None => Ok(()),
+        }
+    }
+
+    /// 録音を終了してヘッダを確定
+    pub fn finish(self) -> std::io::Result<()> {
+        self.filtered.finish()?;
+        if let Some(raw) = self.raw {
+            raw.wav.finish()?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generate_samples_tracks_emulated_time() {
+        let mut speaker = Speaker::new();
+        assert_eq!(speaker.generate_samples(0, 17030).len(), SAMPLES_PER_FRAME);
+        // 端数は繰り越され、合計はサイクル数に比例する
+        let mut total = 0;
+        let mut cycle = 17030;
+        for _ in 0..60 {
+            total += speaker.generate_samples(cycle, 1000).len();
+            cycle += 1000;
+        }
+        assert_eq!(total, (60_000u64 * SAMPLE_RATE as u64 / CPU_HZ) as usize);
+    }
+
+    #[test]
+    fn audio_settings_are_clamped() {
+        let settings = AudioSettings { sample_rate: 1000, latency_ms: 10_000, device: Some(" ".into()) }.sanitized();
+        assert_eq!(settings.sample_rate, 8000);
+        assert_eq!(settings.latency_ms, 500);
+        assert_eq!(settings.device, None);
+
+        let default = AudioSettings::default();
+        assert_eq!(default.target_buffered(), 2028);
+        assert_eq!(default.ring_capacity(), 8192);
+    }
+
+    #[test]
+    fn disk_sounds_follow_disk_volume() {
+        let mut speaker = Speaker::new();
+        speaker.set_volume(1.0);
+        speaker.disk_step(1000);
+        speaker.disk_bump(9000);
+        let peak = speaker.render(0, 17030, SAMPLES_PER_FRAME).iter().fold(0.0f32, |m, s| m.max(s.abs()));
+        assert!(peak > 0.05);
+
+        speaker.set_disk_volume(0.0);
+        speaker.disk_step(18000);
+        speaker.set_disk_motor(true);
+        let peak = speaker.render(17030, 34060, SAMPLES_PER_FRAME).iter().fold(0.0f32, |m, s| m.max(s.abs()));
+        assert!(peak < 1e-3);
+    }
+
+    #[test]
+    fn compressed_render_stays_bounded_and_settles() {
+        let mut speaker = Speaker::new();
+        speaker.set_volume(1.0);
+        // 1kHzの矩形波を2倍速（2フレーム分を1フレームのサンプル数）で再生
+        for cycle in (0..34060).step_by(511) {
+            speaker.click(cycle);
+        }
+        let samples = speaker.render(0, 34060, SAMPLES_PER_FRAME);
+        assert_eq!(samples.len(), SAMPLES_PER_FRAME);
+        assert!(samples.iter().all(|s| s.is_finite() && s.abs() <= 2.0));
+        assert!(samples.iter().any(|s| s.abs() > 0.1));
+
+        // トグルが止まれば無音へ戻る
+        let mut last = 1.0f32;
+        for frame in 0..60u64 {
+            let start = 34060 + frame * 17030;
+            last = *speaker.render(start, start + 17030, SAMPLES_PER_FRAME).last().unwrap();
+        }
+        assert!(last.abs() < 1e-3);
+    }
 }
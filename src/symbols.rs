@@ -0,0 +1,313 @@
+//! シンボルテーブル
+//!
+//! 逆アセンブル、ブレークポイント（`bp COUT`）、停止位置の表示でアドレスを名前に置き換える。
+//! 組み込みのApple II ROM・DOS 3.3・ProDOSのエントリポイントに加えて、次の形式を読み込める。
+//!
+//! - ca65/ld65 のデバッグ情報（`.dbg`、`sym ... name="x",...,val=0x1234`）
+//! - VICE形式のラベルファイル（`al C:1234 .label`）
+//! - Merlinのリスト（`0300: A9 00  12 START LDA #0` のラベル列と `NAME EQU $1234` / `NAME =$1234`）
+//!
+//! 同じアドレスに複数の名前があるときは、後から読み込んだものを表示に使う。
+
+use std::collections::{BTreeMap, HashMap};
+
+/// 近くのシンボルからのオフセットで表示する範囲（`COUT+3` など）
+const MAX_OFFSET: u16 = 0x40;
+
+/// 組み込みシンボル（Monitor ROM、ゼロページ、ページ3のベクタ、I/O、DOS 3.3、ProDOS）
+const BUILTIN: &[(&str, u16)] = &[
+    // ゼロページ
+    ("WNDLFT", 0x20), ("WNDWDTH", 0x21), ("WNDTOP", 0x22), ("WNDBTM", 0x23),
+    ("CH", 0x24), ("CV", 0x25), ("GBASL", 0x26), ("GBASH", 0x27),
+    ("BASL", 0x28), ("BASH", 0x29), ("BAS2L", 0x2A), ("BAS2H", 0x2B),
+    ("H2", 0x2C), ("V2", 0x2D), ("COLOR", 0x30), ("INVFLG", 0x32),
+    ("PROMPT", 0x33), ("YSAV", 0x34), ("CSWL", 0x36), ("CSWH", 0x37),
+    ("KSWL", 0x38), ("KSWH", 0x39), ("A1L", 0x3C), ("A1H", 0x3D),
+    ("A2L", 0x3E), ("A2H", 0x3F), ("A3L", 0x40), ("A3H", 0x41),
+    ("A4L", 0x42), ("A4H", 0x43), ("RNDL", 0x4E), ("RNDH", 0x4F),
+    // ページ3のベクタ
+    ("DOSWARMV", 0x03D0), ("DOSCOLDV", 0x03D3), ("FMVEC", 0x03D6), ("RWTSVEC", 0x03D9),
+    ("LOCFPL", 0x03DC), ("LOCRPL", 0x03E3), ("CONNECT", 0x03EA),
+    ("BRKV", 0x03F0), ("SOFTEV", 0x03F2), ("PWREDUP", 0x03F4), ("AMPERV", 0x03F5),
+    ("USRADR", 0x03F8), ("NMI", 0x03FB), ("IRQLOC", 0x03FE),
+    // DOS 3.3（48K）
+    ("DOSCOLD", 0x9D84), ("DOSWARM", 0x9DBF), ("ENTERRWTS", 0xB7B5), ("IOB", 0xB7E8),
+    ("DCT", 0xB7FB), ("RWTS", 0xBD00),
+    // ProDOSのグローバルページ
+    ("MLI", 0xBF00), ("DATETIME", 0xBF06), ("SYSERR", 0xBF09), ("SYSDEATH", 0xBF0C),
+    ("SERR", 0xBF0F), ("DEVADR", 0xBF10), ("DEVNUM", 0xBF30), ("DEVCNT", 0xBF31),
+    ("DEVLST", 0xBF32), ("BITMAP", 0xBF58), ("DATE", 0xBF90), ("TIME", 0xBF92),
+    ("MACHID", 0xBF98), ("SLTBYT", 0xBF99), ("KVERSION", 0xBFFF),
+    // I/O
+    ("KBD", 0xC000), ("KBDSTRB", 0xC010), ("TAPEOUT", 0xC020), ("SPKR", 0xC030),
+    ("TXTCLR", 0xC050), ("TXTSET", 0xC051), ("MIXCLR", 0xC052), ("MIXSET", 0xC053),
+    ("LOWSCR", 0xC054), ("HISCR", 0xC055), ("LORES", 0xC056), ("HIRES", 0xC057),
+    ("TAPEIN", 0xC060), ("BUTN0", 0xC061), ("BUTN1", 0xC062), ("PADDL0", 0xC064),
+    ("PADDL1", 0xC065), ("PTRIG", 0xC070),
+    // Monitor ROM
+    ("PLOT", 0xF800), ("HLINE", 0xF819), ("VLINE", 0xF828), ("CLRSCR", 0xF832),
+    ("CLRTOP", 0xF836), ("GBASCALC", 0xF847), ("NXTCOL", 0xF85F), ("SETCOL", 0xF864),
+    ("SCRN", 0xF871), ("PRNTAX", 0xF941), ("PRNTX", 0xF944), ("PRBLNK", 0xF948),
+    ("PRBL2", 0xF94A), ("IRQ", 0xFA40), ("RESET", 0xFA62), ("PREAD", 0xFB1E),
+    ("INIT", 0xFB2F), ("SETTXT", 0xFB39), ("SETGR", 0xFB40), ("TABV", 0xFB5B),
+    ("BASCALC", 0xFBC1), ("BELL1", 0xFBDD), ("VTAB", 0xFC22), ("VTABZ", 0xFC24),
+    ("CLREOP", 0xFC42), ("HOME", 0xFC58), ("CR", 0xFC62), ("LF", 0xFC66),
+    ("SCROLL", 0xFC70), ("CLREOL", 0xFC9C), ("CLEOLZ", 0xFC9E), ("WAIT", 0xFCA8),
+    ("HEADR", 0xFCC9), ("RDKEY", 0xFD0C), ("KEYIN", 0xFD1B), ("RDCHAR", 0xFD35),
+    ("GETLNZ", 0xFD67), ("GETLN", 0xFD6A), ("GETLN1", 0xFD6F), ("CROUT1", 0xFD8B),
+    ("CROUT", 0xFD8E), ("PRBYTE", 0xFDDA), ("PRHEX", 0xFDE3), ("COUT", 0xFDED),
+    ("COUT1", 0xFDF0), ("MOVE", 0xFE2C), ("VERIFY", 0xFE36), ("SETINV", 0xFE80),
+    ("SETNORM", 0xFE84), ("SETKBD", 0xFE89), ("INPORT", 0xFE8B), ("SETVID", 0xFE93),
+    ("OUTPORT", 0xFE95), ("WRITE", 0xFECD), ("READ", 0xFEFD), ("PRERR", 0xFF2D),
+    ("BELL", 0xFF3A), ("IOREST", 0xFF3F), ("IOSAVE", 0xFF4A), ("OLDRST", 0xFF59),
+    ("MON", 0xFF65), ("MONZ", 0xFF69),
+];
+
+/// Merlinの疑似命令（ラベル列と区別する）
+const MERLIN_DIRECTIVES: &[&str] = &[
+    "ORG", "EQU", "DS", "DFB", "DB", "DA", "DW", "DDB", "HEX", "ASC", "DCI", "INV", "FLS",
+    "STR", "REV", "PUT", "USE", "LST", "EXP", "TR", "PAG", "SKP", "OBJ", "REL", "DSK",
+    "END", "DO", "ELSE", "FIN", "IF", "MAC", "EOM", "<<<", "PMC", "DUM", "DEND", "XC", "MX",
+];
+
+/// シンボルテーブル
+#[derive(Debug, Clone, Default)]
+pub struct SymbolTable {
+    /// アドレス → 表示する名前
+    names: BTreeMap<u16, String>,
+    /// 名前（大文字） → アドレス
+    addresses: HashMap<String, u16>,
+}
+
+impl SymbolTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 組み込みシンボルだけのテーブル
+    pub fn builtin() -> Self {
+        let mut table = Self::new();
+        for &(name, address) in BUILTIN {
+            table.insert(name, address);
+        }
+        table
+    }
+
+    /// シンボルの数
+    pub fn len(&self) -> usize {
+        self.addresses.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.addresses.is_empty()
+    }
+
+    /// シンボルを追加
+    pub fn insert(&mut self, name: &str, address: u16) {
+        self.addresses.insert(name.to_ascii_uppercase(), address);
+        self.names.insert(address, name.to_string());
+    }
+
+    /// ファイルから読み込み（形式は内容から判定）。追加した数を返す
+    pub fn load(&mut self, path: &str) -> Result<usize, String> {
+        let text = std::fs::read(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+        let count = self.load_text(&String::from_utf8_lossy(&text));
+        if count == 0 {
+            return Err(format!("No symbols found in {}", path));
+        }
+        Ok(count)
+    }
+
+    /// テキストから読み込み（ca65 .dbg / VICE / Merlin）。追加した数を返す
+    pub fn load_text(&mut self, text: &str) -> usize {
+        let symbols = if text.lines().any(|l| l.starts_with("sym\t")) {
+            parse_ca65(text)
+        } else if text.lines().any(|l| l.trim_start().starts_with("al ")) {
+            parse_vice(text)
+        } else {
+            parse_merlin(text)
+        };
+        for (name, address) in &symbols {
+            self.insert(name, *address);
+        }
+        symbols.len()
+    }
+
+    /// アドレスちょうどの名前
+    pub fn name(&self, address: u16) -> Option<&str> {
+        self.names.get(&address).map(String::as_str)
+    }
+
+    /// 名前からアドレスを引く（大文字小文字は区別しない）
+    pub fn lookup(&self, name: &str) -> Option<u16> {
+        self.addresses.get(&name.to_ascii_uppercase()).copied()
+    }
+
+    /// 直前のシンボルからの位置（"COUT"、"COUT+3"）。近くになければNone
+    pub fn location(&self, address: u16) -> Option<String> {
+        let (&base, name) = self.names.range(..=address).next_back()?;
+        match address - base {
+            0 => Some(name.clone()),
+            offset if offset < MAX_OFFSET => Some(format!("{}+{}", name, offset)),
+            _ => None,
+        }
+    }
+
+    /// "$FDF0 (COUT+3)" の形式（シンボルがなければ "$FDF0"）
+    pub fn describe(&self, address: u16) -> String {
+        match self.location(address) {
+            Some(location) => format!("${:04X} ({})", address, location),
+            None => format!("${:04X}", address),
+        }
+    }
+
+    /// 名前の一覧（アドレス順）
+    pub fn iter(&self) -> impl Iterator<Item = (u16, &str)> {
+        self.names.iter().map(|(&address, name)| (address, name.as_str()))
+    }
+}
+
+/// "$1234" / "0x1234" / "1234"（10進）
+fn parse_value(text: &str) -> Option<u16> {
+    let text = text.trim();
+    let value = if let Some(hex) = text.strip_prefix('$') {
+        u32::from_str_radix(hex, 16).ok()?
+    } else if let Some(hex) = text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        u32::from_str_radix(hex, 16).ok()?
+    } else {
+        text.parse().ok()?
+    };
+    u16::try_from(value).ok()
+}
+
+fn is_identifier(text: &str) -> bool {
+    let mut chars = text.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.')
+}
+
+/// ca65/ld65 `.dbg`: `sym id=0,name="main",...,val=0x801,...`
+fn parse_ca65(text: &str) -> Vec<(String, u16)> {
+    text.lines()
+        .filter_map(|line| line.strip_prefix("sym\t"))
+        .filter_map(|fields| {
+            let mut name = None;
+            let mut value = None;
+            for field in fields.split(',') {
+                match field.split_once('=') {
+                    Some(("name", v)) => name = Some(v.trim_matches('"')),
+                    Some(("val", v)) => value = parse_value(v),
+                    _ => {}
+                }
+            }
+            // @で始まるチープローカルラベルは省く
+            let name = name.filter(|n| !n.starts_with('@'))?;
+            Some((name.to_string(), value?))
+        })
+        .collect()
+}
+
+/// VICE: `al C:0801 .start`
+fn parse_vice(text: &str) -> Vec<(String, u16)> {
+    text.lines()
+        .filter_map(|line| {
+            let mut tokens = line.split_whitespace();
+            if tokens.next()? != "al" {
+                return None;
+            }
+            let address = tokens.next()?;
+            let address = address.split_once(':').map_or(address, |(_, a)| a);
+            let address = u16::from_str_radix(address, 16).ok()?;
+            let name = tokens.next()?.trim_start_matches('.');
+            is_identifier(name).then(|| (name.to_string(), address))
+        })
+        .collect()
+}
+
+/// Merlinのリスト
+fn parse_merlin(text: &str) -> Vec<(String, u16)> {
+    let mut symbols = Vec::new();
+    for line in text.lines() {
+        // コメント以降は無視
+        let code = line.split(';').next().unwrap_or("");
+        let tokens: Vec<&str> = code.split_whitespace().collect();
+
+        // NAME EQU $1234 / NAME = $1234 / NAME =$1234（シンボル表）
+        if let Some(i) = tokens.iter().position(|t| t.eq_ignore_ascii_case("EQU") || t.starts_with('=')) {
+            if i > 0 && is_identifier(tokens[i - 1]) {
+                let value = match tokens[i].strip_prefix('=').filter(|v| !v.is_empty()) {
+                    Some(value) => Some(value),
+                    None => tokens.get(i + 1).copied(),
+                };
+                if let Some(address) = value.and_then(parse_value) {
+                    symbols.push((tokens[i - 1].to_string(), address));
+                }
+            }
+            continue;
+        }
+
+        // ADDR: BYTES.. LINE LABEL OPCODE ..
+        let Some(address) = tokens.first()
+            .and_then(|t| t.strip_suffix(':'))
+            .and_then(|t| u16::from_str_radix(t, 16).ok()) else { continue };
+        // 2桁の行番号はバイト列と区別できないので、バイト列の次が行番号でなければ直前を行番号とみなす
+        let is_byte = |t: &&str| t.len() == 2 && u8::from_str_radix(t, 16).is_ok();
+        let is_number = |t: &&str| t.chars().all(|c| c.is_ascii_digit());
+        let Some(j) = tokens.iter().skip(1).position(|t| !is_byte(t)).map(|j| j + 1) else { continue };
+        let label_index = if is_number(&tokens[j]) {
+            j + 1
+        } else if j > 1 && is_number(&tokens[j - 1]) {
+            j
+        } else {
+            continue;
+        };
+        let Some(&label) = tokens.get(label_index) else { continue };
+        let upper = label.to_ascii_uppercase();
+        if is_identifier(label) && !is_mnemonic(&upper) && !MERLIN_DIRECTIVES.contains(&upper.as_str()) {
+            symbols.push((label.to_string(), address));
+        }
+    }
+    symbols
+}
+
+/// 6502/65C02のニーモニックか
+fn is_mnemonic(name: &str) -> bool {
+    const MNEMONICS: &str = "ADC AND ASL BCC BCS BEQ BIT BMI BNE BPL BRA BRK BVC BVS CLC CLD CLI CLV CMP CPX CPY \
+        DEC DEX DEY EOR INC INX INY JMP JSR LDA LDX LDY LSR NOP ORA PHA PHP PHX PHY PLA PLP PLX PLY \
+        ROL ROR RTI RTS SBC SEC SED SEI STA STP STX STY STZ TAX TAY TRB TSB TSX TXA TXS TYA WAI";
+    name.len() == 3 && MNEMONICS.split_whitespace().any(|m| m == name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn loads_symbol_formats() {
+        let mut table = SymbolTable::builtin();
+        assert_eq!(table.lookup("cout"), Some(0xFDED));
+        assert_eq!(table.location(0xFDF2).as_deref(), Some("COUT1+2"));
+        assert_eq!(table.describe(0x0300), "$0300");
+
+        let dbg = "version\tmajor=2,minor=0\n\
+            sym\tid=0,name=\"main\",addrsize=absolute,size=1,scope=0,def=1,val=0x803,seg=0,type=lab\n\
+            sym\tid=1,name=\"@loop\",addrsize=absolute,scope=0,def=2,val=0x805,type=lab\n";
+        assert_eq!(table.load_text(dbg), 1);
+        assert_eq!(table.name(0x0803), Some("main"));
+
+        assert_eq!(table.load_text("al C:0900 .vice_start\nal 0910 .other\n"), 2);
+        assert_eq!(table.lookup("VICE_START"), Some(0x0900));
+
+        let merlin = "            1 SCREEN   EQU   $0400\n\
+            0300: A9 00   12 START    LDA   #0\n\
+            0302: 60       3          RTS\n\
+            0303: 8D 00 04 4 STORE    STA   SCREEN\n\
+            0306: 20 ED FD 5          JSR   COUT\n\
+            FLAG   =$0006\n";
+        assert_eq!(table.load_text(merlin), 4);
+        assert_eq!(table.lookup("start"), Some(0x0300));
+        assert_eq!(table.lookup("store"), Some(0x0303));
+        assert_eq!(table.lookup("screen"), Some(0x0400));
+        assert_eq!(table.lookup("flag"), Some(0x0006));
+    }
+}
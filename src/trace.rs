@@ -0,0 +1,402 @@
+//! 命令トレース
+//!
+//! 1命令ごとにPC、命令バイト、レジスタ、サイクル数、メモリバンクの状態を記録する。
+//! ファイルへ書き続けるモードと、直前のN命令だけをメモリに残すリングバッファモード
+//! （クラッシュやブレークの直前を調べる用）がある。
+//!
+//! 形式は拡張子で選ぶ。`.txt` / `.log` はテキスト、それ以外はバイナリ
+//! （ヘッダ `A2TRACE1` とCPU種別の後に21バイト固定長のレコードが続く）。
+//! バイナリは `export_text` でテキストに変換できる。
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::fs::File;
+use std::io::{BufWriter, Read, Write};
+
+use crate::cpu::disassembler::disassemble_with_symbols;
+use crate::cpu::{CpuType, Registers};
+use crate::memory::Memory;
+use crate::symbols::SymbolTable;
+
+/// バイナリ形式のヘッダ
+const MAGIC: &[u8; 8] = b"A2TRACE1";
+/// 1レコードのバイト数
+const RECORD_SIZE: usize = 21;
+
+/// メモリバンク状態のビット
+pub mod bank {
+    pub const RAMRD: u16 = 1 << 0;
+    pub const RAMWRT: u16 = 1 << 1;
+    pub const ALTZP: u16 = 1 << 2;
+    pub const STORE80: u16 = 1 << 3;
+    pub const PAGE2: u16 = 1 << 4;
+    pub const HIRES: u16 = 1 << 5;
+    pub const LCBANK2: u16 = 1 << 6;
+    pub const LCREAD: u16 = 1 << 7;
+    pub const LCWRITE: u16 = 1 << 8;
+
+    const NAMES: &[(u16, &str)] = &[
+        (RAMRD, "ramrd"), (RAMWRT, "ramwrt"), (ALTZP, "altzp"), (STORE80, "80store"),
+        (PAGE2, "page2"), (HIRES, "hires"), (LCBANK2, "lc2"), (LCREAD, "lcr"), (LCWRITE, "lcw"),
+    ];
+
+    /// 立っているビットの名前（空白区切り）
+    pub fn names(bits: u16) -> String {
+        NAMES.iter().filter(|(bit, _)| bits & bit != 0).map(|(_, name)| *name).collect::<Vec<_>>().join(" ")
+    }
+}
+
+/// トレースの1命令分
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TraceEntry {
+    pub pc: u16,
+    /// PCから3バイト（命令長は逆アセンブル時に決まる）
+    pub bytes: [u8; 3],
+    pub a: u8,
+    pub x: u8,
+    pub y: u8,
+    pub sp: u8,
+    pub p: u8,
+    /// 命令を実行する前の総サイクル数
+    pub cycles: u64,
+    /// `bank` のビット
+    pub banks: u16,
+}
+
+impl TraceEntry {
+    /// 実行する直前の状態を記録
+    pub fn capture(regs: &Registers, memory: &Memory, cycles: u64) -> Self {
+        let s = &memory.switches;
+        let banks = [
+            (s.ramrd, bank::RAMRD), (s.ramwrt, bank::RAMWRT), (s.altzp, bank::ALTZP),
+            (s.store_80, bank::STORE80), (s.page2, bank::PAGE2), (s.hires, bank::HIRES),
+            (s.lc_bank2, bank::LCBANK2), (s.lc_read_enable, bank::LCREAD), (s.lc_write_enable, bank::LCWRITE),
+        ].iter().filter(|(on, _)| *on).fold(0, |bits, (_, bit)| bits | bit);
+        TraceEntry {
+            pc: regs.pc,
+            bytes: [0, 1, 2].map(|i| memory.peek(regs.pc.wrapping_add(i))),
+            a: regs.a,
+            x: regs.x,
+            y: regs.y,
+            sp: regs.sp,
+            p: regs.status,
+            cycles,
+            banks,
+        }
+    }
+
+    fn encode(&self) -> [u8; RECORD_SIZE] {
+        let mut out = [0u8; RECORD_SIZE];
+        out[0..2].copy_from_slice(&self.pc.to_le_bytes());
+        out[2..5].copy_from_slice(&self.bytes);
+        out[5..10].copy_from_slice(&[self.a, self.x, self.y, self.sp, self.p]);
+        out[10..18].copy_from_slice(&self.cycles.to_le_bytes());
+        out[18..20].copy_from_slice(&self.banks.to_le_bytes());
+        // 予備
+        out[20] = 0;
+        out
+    }
+
+    fn decode(data: &[u8; RECORD_SIZE]) -> Self {
+        TraceEntry {
+            pc: u16::from_le_bytes([data[0], data[1]]),
+            bytes: [data[2], data[3], data[4]],
+            a: data[5],
+            x: data[6],
+            y: data[7],
+            sp: data[8],
+            p: data[9],
+            cycles: u64::from_le_bytes(data[10..18].try_into().unwrap_or_default()),
+            banks: u16::from_le_bytes([data[18], data[19]]),
+        }
+    }
+
+    /// テキスト1行（サイクル、PC、バイト、逆アセンブル、レジスタ、フラグ、バンク）
+    pub fn to_text(&self, cpu_type: CpuType, symbols: &SymbolTable) -> String {
+        let ins = disassemble_with_symbols(cpu_type, self.pc, |a| self.bytes[a.wrapping_sub(self.pc) as usize % 3], symbols);
+        let bytes: Vec<String> = self.bytes[..ins.len as usize].iter().map(|b| format!("{:02X}", b)).collect();
+        let flags: String = "NV-BDIZC".chars().enumerate().map(|(i, c)| {
+            if self.p & (0x80 >> i) != 0 { c } else { c.to_ascii_lowercase() }
+        }).collect();
+        let label = symbols.name(self.pc).map_or_else(String::new, |name| format!("{}:", name));
+        format!(
+            "{:>10} {:04X}: {:<8} {:<10} {:<18} A={:02X} X={:02X} Y={:02X} S={:02X} P={:02X} {} {}",
+            self.cycles, self.pc, bytes.join(" "), label, ins.text,
+            self.a, self.x, self.y, self.sp, self.p, flags, bank::names(self.banks)
+        ).trim_end().to_string()
+    }
+}
+
+/// トレースの開始・停止条件（一度成立したら消える）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TraceTrigger {
+    /// PCがこのアドレスに来た
+    Address(u16),
+    /// 総サイクル数がこの値に達した
+    Cycle(u64),
+}
+
+impl TraceTrigger {
+    /// "ADDR"（16進かシンボル名）または "cycle:N"
+    pub fn parse(text: &str, symbols: &SymbolTable) -> Result<TraceTrigger, String> {
+        let text = text.trim();
+        if let Some(count) = text.strip_prefix("cycle:").or_else(|| text.strip_prefix("cycles:")) {
+            return count.parse().map(TraceTrigger::Cycle).map_err(|_| format!("Invalid cycle count '{}'", count));
+        }
+        crate::profiler::parse_hex(text)
+            .or_else(|| symbols.lookup(text))
+            .map(TraceTrigger::Address)
+            .ok_or_else(|| format!("Invalid trace trigger '{}' (ADDR or cycle:N)", text))
+    }
+
+    fn matches(&self, entry: &TraceEntry) -> bool {
+        match *self {
+            TraceTrigger::Address(address) => entry.pc == address,
+            TraceTrigger::Cycle(cycle) => entry.cycles >= cycle,
+        }
+    }
+}
+
+impl fmt::Display for TraceTrigger {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TraceTrigger::Address(address) => write!(f, "${:04X}", address),
+            TraceTrigger::Cycle(cycle) => write!(f, "cycle {}", cycle),
+        }
+    }
+}
+
+/// トレースの出力先
+enum Sink {
+    /// ファイルへ書き続ける
+    Stream(BufWriter<File>),
+    /// 直前のN命令だけ保持し、保存時に書き出す
+    Ring(VecDeque<TraceEntry>, usize),
+}
+
+/// 命令トレーサ
+pub struct Tracer {
+    path: String,
+    text: bool,
+    cpu_type: CpuType,
+    sink: Sink,
+    /// テキスト形式で使うシンボル（開始時にコピー）
+    symbols: SymbolTable,
+    /// 開始条件（なければ作成直後から記録）
+    start: Option<TraceTrigger>,
+    /// 停止条件
+    stop: Option<TraceTrigger>,
+    active: bool,
+    /// 記録した命令数
+    count: u64,
+    /// 書き込みエラー（以後は記録しない）
+    error: Option<String>,
+}
+
+impl Tracer {
+    /// `path` へのトレースを作る。`ring` を指定するとリングバッファモード
+    pub fn new(path: &str, ring: Option<usize>, cpu_type: CpuType, symbols: &SymbolTable) -> Result<Tracer, String> {
+        let text = is_text_path(path);
+        let sink = match ring {
+            Some(size) => Sink::Ring(VecDeque::with_capacity(size.min(1 << 20)), size.max(1)),
+            None => {
+                let mut writer = create(path)?;
+                if !text {
+                    write_header(&mut writer, cpu_type).map_err(|e| format!("Failed to write {}: {}", path, e))?;
+                }
+                Sink::Stream(writer)
+            }
+        };
+        Ok(Tracer {
+            path: path.to_string(),
+            text,
+            cpu_type,
+            sink,
+            symbols: symbols.clone(),
+            start: None,
+            stop: None,
+            active: true,
+            count: 0,
+            error: None,
+        })
+    }
+
+    /// 開始・停止条件を設定（開始条件があれば成立するまで記録しない）
+    pub fn set_triggers(&mut self, start: Option<TraceTrigger>, stop: Option<TraceTrigger>) {
+        self.active = start.is_none();
+        self.start = start;
+        self.stop = stop;
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// 記録中か
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    /// 記録した命令数
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// リングバッファモードか
+    pub fn is_ring(&self) -> bool {
+        matches!(self.sink, Sink::Ring(..))
+    }
+
+    /// 記録の一時停止・再開（ホットキー）
+    pub fn toggle(&mut self) {
+        self.active = !self.active;
+        self.start = None;
+    }
+
+    /// 1命令を記録（条件を調べる）
+    pub fn record(&mut self, entry: TraceEntry) {
+        if self.error.is_some() {
+            return;
+        }
+        if !self.active && self.start.is_some_and(|t| t.matches(&entry)) {
+            self.start = None;
+            self.active = true;
+        }
+        if !self.active {
+            return;
+        }
+        self.count += 1;
+        match &mut self.sink {
+            Sink::Ring(ring, size) => {
+                if ring.len() == *size {
+                    ring.pop_front();
+                }
+                ring.push_back(entry);
+            }
+            Sink::Stream(writer) => {
+                let result = if self.text {
+                    writeln!(writer, "{}", entry.to_text(self.cpu_type, &self.symbols))
+                } else {
+                    writer.write_all(&entry.encode())
+                };
+                if let Err(e) = result {
+                    log::warn!("Trace write to {} failed: {}", self.path, e);
+                    self.error = Some(e.to_string());
+                }
+            }
+        }
+        if self.stop.is_some_and(|t| t.matches(&entry)) {
+            self.stop = None;
+            self.active = false;
+        }
+    }
+
+    /// ファイルへ書き出す（リングバッファは保持している命令をすべて書く）。書いた命令数を返す
+    pub fn save(&mut self) -> Result<u64, String> {
+        if let Some(e) = &self.error {
+            return Err(format!("Trace write to {} failed: {}", self.path, e));
+        }
+        let path = &self.path;
+        match &mut self.sink {
+            Sink::Stream(writer) => {
+                writer.flush().map_err(|e| format!("Failed to write {}: {}", path, e))?;
+                Ok(self.count)
+            }
+            Sink::Ring(ring, _) => {
+                let mut writer = create(path)?;
+                let result = (|| {
+                    if !self.text {
+                        write_header(&mut writer, self.cpu_type)?;
+                    }
+                    for entry in ring.iter() {
+                        if self.text {
+                            writeln!(writer, "{}", entry.to_text(self.cpu_type, &self.symbols))?;
+                        } else {
+                            writer.write_all(&entry.encode())?;
+                        }
+                    }
+                    writer.flush()
+                })();
+                result.map_err(|e| format!("Failed to write {}: {}", path, e))?;
+                Ok(ring.len() as u64)
+            }
+        }
+    }
+}
+
+/// テキスト形式のパスか
+fn is_text_path(path: &str) -> bool {
+    let lower = path.to_ascii_lowercase();
+    lower.ends_with(".txt") || lower.ends_with(".log")
+}
+
+fn create(path: &str) -> Result<BufWriter<File>, String> {
+    File::create(path)
+        .map(BufWriter::new)
+        .map_err(|e| format!("Failed to create {}: {}", path, e))
+}
+
+fn write_header(writer: &mut impl Write, cpu_type: CpuType) -> std::io::Result<()> {
+    writer.write_all(MAGIC)?;
+    writer.write_all(&[u8::from(cpu_type == CpuType::Cpu65C02)])
+}
+
+/// バイナリ形式のトレースを読む
+pub fn read_binary(data: &[u8]) -> Result<(CpuType, Vec<TraceEntry>), String> {
+    let body = data.strip_prefix(MAGIC.as_slice()).ok_or("Not an a2rs binary trace")?;
+    let (&cpu, records) = body.split_first().ok_or("Truncated trace header")?;
+    let cpu_type = if cpu != 0 { CpuType::Cpu65C02 } else { CpuType::Cpu6502 };
+    let entries = records
+        .chunks_exact(RECORD_SIZE)
+        .map(|chunk| TraceEntry::decode(chunk.try_into().unwrap_or(&[0; RECORD_SIZE])))
+        .collect();
+    Ok((cpu_type, entries))
+}
+
+/// バイナリ形式のトレースをテキストに変換して `out` に書く。変換した命令数を返す
+pub fn export_text(path: &str, symbols: &SymbolTable, out: &mut impl Write) -> Result<usize, String> {
+    let mut data = Vec::new();
+    File::open(path)
+        .and_then(|mut f| f.read_to_end(&mut data))
+        .map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    let (cpu_type, entries) = read_binary(&data)?;
+    for entry in &entries {
+        writeln!(out, "{}", entry.to_text(cpu_type, symbols)).map_err(|e| e.to_string())?;
+    }
+    Ok(entries.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::AppleModel;
+
+    #[test]
+    fn ring_buffer_triggers_and_binary_round_trip() {
+        let mut memory = Memory::new(AppleModel::AppleIIPlus);
+        memory.main_ram[0x0300..0x0303].copy_from_slice(&[0x20, 0xED, 0xFD]);
+        let mut regs = Registers { pc: 0x0300, a: 0x41, ..Registers::default() };
+        let symbols = SymbolTable::builtin();
+
+        let path = std::env::temp_dir().join(format!("a2rs_trace_{}.bin", std::process::id()));
+        let path = path.to_str().unwrap();
+        let mut tracer = Tracer::new(path, Some(3), CpuType::Cpu6502, &symbols).unwrap();
+        tracer.set_triggers(Some(TraceTrigger::Cycle(2)), Some(TraceTrigger::Cycle(7)));
+        for cycle in 0..10 {
+            regs.x = cycle as u8;
+            tracer.record(TraceEntry::capture(&regs, &memory, cycle));
+        }
+        // 2..=7 を記録し、リングには最後の3命令が残る
+        assert_eq!(tracer.count(), 6);
+        assert_eq!(tracer.save().unwrap(), 3);
+
+        let (cpu_type, entries) = read_binary(&std::fs::read(path).unwrap()).unwrap();
+        std::fs::remove_file(path).ok();
+        assert_eq!(cpu_type, CpuType::Cpu6502);
+        assert_eq!(entries.iter().map(|e| e.cycles).collect::<Vec<_>>(), vec![5, 6, 7]);
+        assert_eq!(entries[0].bytes, [0x20, 0xED, 0xFD]);
+        let text = entries[0].to_text(cpu_type, &symbols);
+        assert!(text.starts_with("         5 0300: 20 ED FD"), "{}", text);
+        assert!(text.contains("JSR COUT") && text.contains("A=41 X=05"), "{}", text);
+    }
+}
@@ -3,6 +3,7 @@
 //! テキスト、Lo-Res、Hi-Res各モードのレンダリング
 
 use crate::memory::Memory;
+use crate::videx::VidexCard;
 
 /// 画面サイズ
 pub const SCREEN_WIDTH: usize = 560;  // 280 * 2 for double width
@@ -346,12 +347,7 @@ impl Video {
 
     /// 画面を更新
     pub fn render(&mut self, memory: &Memory) {
-        // 点滅カウンターを更新（約4Hzで点滅）
-        self.flash_counter += 1;
-        if self.flash_counter >= 15 {  // 60fps / 4 = 15フレーム
-            self.flash_state = !self.flash_state;
-            self.flash_counter = 0;
-        }
+        self.tick_flash();
         
         // 画面をクリア
         for pixel in self.framebuffer.iter_mut() {
@@ -378,7 +374,11 @@ impl Video {
                 }
             }
         } else {
-            self.render_lores(memory);
+            if memory.switches.dhires && memory.switches.col_80 && memory.is_iie() {
+                self.render_double_lores(memory);
+            } else {
+                self.render_lores(memory);
+            }
             if memory.switches.mixed_mode {
                 if memory.switches.col_80 && memory.is_iie() {
                     self.render_text_80_bottom(memory);
@@ -389,9 +389,65 @@ impl Video {
         }
     }
 
+    /// 点滅カウンターを更新（約4Hzで点滅）
+    fn tick_flash(&mut self) {
+        self.flash_counter += 1;
+        if self.flash_counter >= 15 {  // 60fps / 4 = 15フレーム
+            self.flash_state = !self.flash_state;
+            self.flash_counter = 0;
+        }
+    }
+
+    /// Videx 80桁カードの画面をレンダリング
+    /// CRTCの桁数（R1）・行数（R6）・1行のライン数（R9）で画面全体に引き伸ばす。
+    /// 画面RAMのビット7は反転表示、カーソルはR10/R11のラインを反転する
+    pub fn render_videx(&mut self, card: &VidexCard) {
+        self.tick_flash();
+
+        let regs = card.registers();
+        let cols = (regs[1] as usize).clamp(1, 80);
+        let rows = (regs[6] as usize).clamp(1, 24);
+        let lines_per_row = ((regs[9] & 0x1F) as usize + 1).min(16);
+        let total_lines = rows * lines_per_row;
+        let start = card.start_address();
+        let cursor = card.cursor_address();
+        // R10ビット6-5: 00=常時表示, 01=非表示, 1x=点滅
+        let cursor_visible = match (regs[10] >> 5) & 0x03 {
+            0 => true,
+            1 => false,
+            _ => self.flash_state,
+        };
+        let cursor_lines = (regs[10] & 0x1F) as usize..=(regs[11] & 0x1F) as usize;
+        let screen = card.screen_ram();
+        let char_rom = card.char_rom();
+        let color_on = if self.monochrome { self.mono_color } else { 0xFFFFFF };
+
+        for y in 0..SCREEN_HEIGHT {
+            let raster = y * total_lines / SCREEN_HEIGHT;
+            let row = raster / lines_per_row;
+            let line = raster % lines_per_row;
+            let fb_line = y * SCREEN_WIDTH;
+            for x in 0..SCREEN_WIDTH {
+                let dot = x * cols * 8 / SCREEN_WIDTH;
+                let col = dot / 8;
+                let addr = (start + row * cols + col) & (screen.len() - 1);
+                let ch = screen[addr];
+                let mut bits = char_rom[(ch & 0x7F) as usize * 16 + line];
+                if ch & 0x80 != 0 {
+                    bits = !bits;
+                }
+                if cursor_visible && addr == cursor && cursor_lines.contains(&line) {
+                    bits = !bits;
+                }
+                let on = (bits >> (7 - dot % 8)) & 1 != 0;
+                self.framebuffer[fb_line + x] = if on { color_on } else { 0x000000 };
+            }
+        }
+    }
+
     /// テキストモードのレンダリング（40桁）
     fn render_text(&mut self, memory: &Memory) {
-        let base = if memory.switches.page2 { 0x0800 } else { 0x0400 };
+        let base = if Self::display_page2(memory) { 0x0800 } else { 0x0400 };
         
         for row in 0..24 {
             let row_addr = base + Self::text_row_offset(row);
@@ -404,7 +460,7 @@ impl Video {
 
     /// テキストモード下部4行（mixedモード用）
     fn render_text_bottom(&mut self, memory: &Memory) {
-        let base = if memory.switches.page2 { 0x0800 } else { 0x0400 };
+        let base = if Self::display_page2(memory) { 0x0800 } else { 0x0400 };
         
         for row in 20..24 {
             let row_addr = base + Self::text_row_offset(row);
@@ -426,6 +482,12 @@ impl Video {
         group * 0x28 + line * 0x80
     }
 
+    /// 表示ページ2が選択されているか
+    /// IIeで80STOREがオンの場合、PAGE2はメイン/AUXの切り替えに使われ表示ページは1のまま
+    fn display_page2(memory: &Memory) -> bool {
+        memory.switches.page2 && !(memory.is_iie() && memory.switches.store_80)
+    }
+
     /// 1文字を描画
     /// Apple IIの文字コード:
     ///   $00-$3F: Inverse (反転表示) - 文字ROM $00-$3F (大文字・記号)
@@ -496,7 +558,7 @@ impl Video {
 
     /// Lo-Resグラフィックスのレンダリング
     fn render_lores(&mut self, memory: &Memory) {
-        let base = if memory.switches.page2 { 0x0800 } else { 0x0400 };
+        let base = if Self::display_page2(memory) { 0x0800 } else { 0x0400 };
         let max_row = if memory.switches.mixed_mode { 20 } else { 24 };
         
         for row in 0..max_row {
@@ -535,9 +597,42 @@ impl Video {
         }
     }
 
+    /// ダブルLo-Resグラフィックスのレンダリング（80x48、16色）
+    /// 偶数列はAux RAM、奇数列はMain RAM。各ブロックは7ドット幅
+    fn render_double_lores(&mut self, memory: &Memory) {
+        let base = if Self::display_page2(memory) { 0x0800 } else { 0x0400 };
+        let max_row = if memory.switches.mixed_mode { 20 } else { 24 };
+        
+        // Aux側は7ドット早くシフトアウトされるため、色のビット列が1ビット回転している
+        let rol_nibble = |n: u8| ((n << 1) | (n >> 3)) & 0x0F;
+        
+        for row in 0..max_row {
+            let row_addr = base + Self::text_row_offset(row);
+            for col in 0..80 {
+                let (top, bottom) = if (col & 1) == 0 {
+                    let byte = memory.aux_ram[row_addr + col / 2];
+                    (rol_nibble(byte & 0x0F), rol_nibble(byte >> 4))
+                } else {
+                    let byte = memory.main_ram[row_addr + col / 2];
+                    (byte & 0x0F, byte >> 4)
+                };
+                
+                let x_start = col * 7;
+                let y_start = row * 16;
+                for y in 0..16 {
+                    let color = COLORS[if y < 8 { top } else { bottom } as usize];
+                    let line = (y_start + y) * SCREEN_WIDTH;
+                    for x in x_start..x_start + 7 {
+                        self.framebuffer[line + x] = color;
+                    }
+                }
+            }
+        }
+    }
+
     /// Hi-Resグラフィックスのレンダリング
     fn render_hires(&mut self, memory: &Memory) {
-        let base = if memory.switches.page2 { 0x4000 } else { 0x2000 };
+        let base = if Self::display_page2(memory) { 0x4000 } else { 0x2000 };
         let max_row = if memory.switches.mixed_mode { 160 } else { 192 };
         
         // Hi-Res color lookup table
@@ -635,11 +730,7 @@ impl Video {
     
     /// 80桁テキストモードのレンダリング
     fn render_text_80(&mut self, memory: &Memory) {
-        let base = if memory.switches.page2 && !memory.switches.store_80 { 
-            0x0800 
-        } else { 
-            0x0400 
-        };
+        let base = if Self::display_page2(memory) { 0x0800 } else { 0x0400 };
         
         for row in 0..24 {
             let row_addr = base + Self::text_row_offset(row);
@@ -657,11 +748,7 @@ impl Video {
     
     /// 80桁テキストモード下部4行（mixedモード用）
     fn render_text_80_bottom(&mut self, memory: &Memory) {
-        let base = if memory.switches.page2 && !memory.switches.store_80 { 
-            0x0800 
-        } else { 
-            0x0400 
-        };
+        let base = if Self::display_page2(memory) { 0x0800 } else { 0x0400 };
         
         for row in 20..24 {
             let row_addr = base + Self::text_row_offset(row);
@@ -724,68 +811,113 @@ impl Video {
     }
     
     /// ダブルHi-Resモードのレンダリング（560x192、16色）
+    /// 1ラインは Aux[0], Main[0], Aux[1], Main[1], ... の各下位7ビットを
+    /// LSBから順に並べた560ドット。4ドットごとに1色（140x192）となる
     fn render_dhires(&mut self, memory: &Memory) {
-        let base = if memory.switches.page2 && !memory.switches.store_80 {
-            0x4000
-        } else {
-            0x2000
-        };
-        
+        let base = if Self::display_page2(memory) { 0x4000 } else { 0x2000 };
         let max_row = if memory.switches.mixed_mode { 160 } else { 192 };
         
+        let mut dots = [false; SCREEN_WIDTH];
         for y in 0..max_row {
             let row_addr = base + Self::hires_row_offset(y);
             
-            // 各行は80バイト（Aux 40バイト + Main 40バイト が交互）
+            // ドット列を組み立て
             for byte_x in 0..40 {
-                // Aux RAM のバイト（偶数バイト位置）
-                let aux_byte = memory.aux_ram[(row_addr + byte_x) as usize];
-                // Main RAM のバイト（奇数バイト位置）
-                let main_byte = memory.main_ram[(row_addr + byte_x) as usize];
-                
-                // 2バイト（14ピクセル分、各7ビット）から4ピクセルを抽出
-                // ダブルHi-Resは4ビット/ピクセル
-                // Aux[6:0] + Main[6:0] = 14ビット → 3.5ピクセル(4ビット*3 + 2ビット余り)
-                // 実際は連続する28ビット（4バイト）から7ピクセルを生成
-                
-                // 簡略化: 各バイトの7ビットを14ピクセル分として描画
-                let combined = ((main_byte as u16 & 0x7F) << 7) | (aux_byte as u16 & 0x7F);
-                
-                // 14ピクセル分を処理
-                for bit in 0..14 {
-                    let screen_x = byte_x as usize * 14 + bit;
-                    let screen_y = y * 2;
-                    
-                    // 4ビットカラーを近似的に計算
-                    // 実際のDHIRESは4ビット連続でカラーを決定
-                    let nibble_pos = bit / 4;
-                    let nibble = if nibble_pos == 0 {
-                        aux_byte & 0x0F
-                    } else if nibble_pos == 1 {
-                        ((aux_byte >> 4) & 0x07) | ((main_byte & 0x01) << 3)
-                    } else if nibble_pos == 2 {
-                        (main_byte >> 1) & 0x0F
-                    } else {
-                        (main_byte >> 5) & 0x07
-                    };
-                    
-                    // ピクセルがオンかどうか
-                    let pixel_on = ((combined >> bit) & 1) != 0;
-                    
+                let aux_byte = memory.aux_ram[row_addr + byte_x];
+                let main_byte = memory.main_ram[row_addr + byte_x];
+                for bit in 0..7 {
+                    dots[byte_x * 14 + bit] = (aux_byte >> bit) & 1 != 0;
+                    dots[byte_x * 14 + 7 + bit] = (main_byte >> bit) & 1 != 0;
+                }
+            }
+            
+            let screen_y = y * 2;
+            for (group, chunk) in dots.chunks(4).enumerate() {
+                // 先頭のドットが色番号のビット0
+                let nibble = chunk
+                    .iter()
+                    .enumerate()
+                    .fold(0usize, |acc, (i, &on)| acc | ((on as usize) << i));
+                for (i, &on) in chunk.iter().enumerate() {
                     let color = if self.monochrome {
-                        if pixel_on { self.mono_color } else { 0x000000 }
+                        if on { self.mono_color } else { 0x000000 }
                     } else {
-                        // DHIRESの16色パレット
-                        COLORS[nibble as usize & 0x0F]
+                        COLORS[nibble]
                     };
-                    
-                    if screen_x < SCREEN_WIDTH && screen_y + 1 < SCREEN_HEIGHT {
-                        let fb_idx = screen_y * SCREEN_WIDTH + screen_x;
-                        self.framebuffer[fb_idx] = color;
-                        self.framebuffer[fb_idx + SCREEN_WIDTH] = color;
-                    }
+                    let fb_idx = screen_y * SCREEN_WIDTH + group * 4 + i;
+                    self.framebuffer[fb_idx] = color;
+                    self.framebuffer[fb_idx + SCREEN_WIDTH] = color;
                 }
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::AppleModel;
+
+    fn pixel(video: &Video, x: usize, y: usize) -> u32 {
+        video.framebuffer[y * SCREEN_WIDTH + x]
+    }
+
+    /// IIeのグラフィックスモード（text_modeだけ下ろした状態）
+    fn graphics_memory() -> Memory {
+        let mut memory = Memory::new(AppleModel::AppleIIe);
+        memory.switches.text_mode = false;
+        memory
+    }
+
+    #[test]
+    fn double_lores_rotates_aux_nibbles() {
+        let mut memory = graphics_memory();
+        memory.switches.dhires = true;
+        memory.switches.col_80 = true;
+        // Aux: 上 $1 → $2、下 $8 → $1（4ビットで左回転）。Mainはそのまま
+        memory.aux_ram[0x0400] = 0x81;
+        memory.main_ram[0x0400] = 0x81;
+        let mut video = Video::new();
+        video.render(&memory);
+        assert_eq!(pixel(&video, 0, 0), COLORS[2]);
+        assert_eq!(pixel(&video, 0, 8), COLORS[1]);
+        assert_eq!(pixel(&video, 7, 0), COLORS[1]);
+        assert_eq!(pixel(&video, 7, 8), COLORS[8]);
+    }
+
+    #[test]
+    fn page2_selects_display_page_without_80store() {
+        let mut memory = graphics_memory();
+        memory.main_ram[0x0800] = 0xFF;
+        memory.switches.page2 = true;
+        let mut video = Video::new();
+        video.render(&memory);
+        assert_eq!(pixel(&video, 0, 0), COLORS[15]);
+
+        // 80STOREではPAGE2はメモリの切り替えになり、表示はページ1のまま
+        memory.switches.store_80 = true;
+        video.render(&memory);
+        assert_eq!(pixel(&video, 0, 0), COLORS[0]);
+    }
+
+    #[test]
+    fn mixed_dhires_shows_80_column_text() {
+        let mut memory = graphics_memory();
+        memory.switches.hires = true;
+        memory.switches.dhires = true;
+        memory.switches.col_80 = true;
+        memory.switches.mixed_mode = true;
+        // DHGR: Auxの先頭バイトのビット0-3 → 最初の4ドットが色$F
+        memory.aux_ram[0x2000] = 0x0F;
+        // 20行目の0桁目（Aux）に反転スペース
+        memory.aux_ram[0x0650] = 0x20;
+        let mut video = Video::new();
+        video.render(&memory);
+        assert_eq!(pixel(&video, 0, 0), COLORS[15]);
+        assert_eq!(pixel(&video, 4, 0), COLORS[0]);
+        assert_eq!(pixel(&video, 0, 20 * 16), 0xFFFFFF);
+        assert_eq!(pixel(&video, 6, 20 * 16 + 15), 0xFFFFFF);
+        // テキスト領域にはDHGRを描かない
+        assert_eq!(pixel(&video, 0, 20 * 16 - 1), COLORS[0]);
+    }
+}
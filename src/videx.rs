@@ -0,0 +1,439 @@
+//! Videx VideoTerm 80桁カード（Apple II / II+ 用、スロット3）
+//!
+//! 6845 CRTCと2KBの画面RAM、文字ROMを持つ。画面RAMは `$CC00-$CDFF` の
+//! 512バイトの窓に見え、`$C0Bx` をアクセスしたときのA3-A2でバンク（0-3）を選ぶ。
+//! 窓は `$C3xx` をアクセスするとカードの拡張ROM領域として有効になり、
+//! `$CFFF` のアクセスで解放される。
+//!
+//! 実機のファームウェアROMを読み込めばそれを使う。ない場合はマウスカードと
+//! 同じく、スロットROMを小さなスタブに差し替えて出力処理をエミュレータ側で行う。
+//! - `$C300`（PR#3）: 初期化してCSW/KSWをカードに向け、Aの文字を出力
+//! - `$C328`: COUT（Aの文字を出力）
+//! - `$C330`: KSW（RDKEYのカーソルを戻してキーを待つ）
+//!
+//! 出力位置はCH（$24）/CV（$25）を使い、INVFLG（$32）が$FF以外なら反転で書く。
+//! スクロールは6845の表示開始アドレス（R12/R13）を1行分進めて行う。
+
+use crate::savestate::VidexState;
+
+/// Videxカードを挿すスロット（ファームウェアがスロット3前提）
+pub const VIDEX_SLOT: usize = 3;
+
+/// 画面RAMのサイズ
+pub const SCREEN_RAM_SIZE: usize = 2048;
+
+/// 文字ROMのサイズ（128文字 x 16ライン）
+pub const CHAR_ROM_SIZE: usize = 2048;
+
+/// ファームウェアROMのサイズ（$C800-$CBFF、$C3xxはその$300-$3FF）
+pub const FIRMWARE_SIZE: usize = 1024;
+
+/// 6845のレジスタ数
+const CRTC_REGISTERS: usize = 18;
+
+/// 6845の初期値（80x24、9ライン/行、点滅カーソル）
+const CRTC_DEFAULTS: [u8; CRTC_REGISTERS] = [
+    0x7B, 0x50, 0x62, 0x29, 0x1B, 0x08, 0x18, 0x19, 0x00, 0x08, 0xC0, 0x08, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00,
+];
+
+/// スタブが叩くレジスタ（実機ファームウェアがないときのみ）
+const HLE_INIT: u16 = 0x0E;
+const HLE_OUTPUT: u16 = 0x0F;
+
+/// 画面の大きさ
+const COLUMNS: usize = 80;
+const ROWS: usize = 24;
+
+/// ゼロページ
+const ZP_CH: usize = 0x24;
+const ZP_CV: usize = 0x25;
+const ZP_INVFLG: usize = 0x32;
+const ZP_CSW: usize = 0x36;
+const ZP_KSW: usize = 0x38;
+
+/// スタブの位置
+const STUB_INIT: u8 = 0x20;
+const STUB_OUTPUT: u8 = 0x28;
+const STUB_INPUT: u8 = 0x30;
+
+/// Videx VideoTerm互換カード
+pub struct VidexCard {
+    rom: [u8; 256],
+    firmware: Option<Box<[u8; FIRMWARE_SIZE]>>,
+    char_rom: Box<[u8; CHAR_ROM_SIZE]>,
+    screen: Box<[u8; SCREEN_RAM_SIZE]>,
+    registers: [u8; CRTC_REGISTERS],
+    /// 選択中のCRTCレジスタ
+    register_select: u8,
+    /// `$CC00` に見える画面RAMのバンク
+    bank: usize,
+    /// `$C800-$CFFF` をカードが使っているか
+    expansion_active: bool,
+    /// 80桁表示が有効か
+    display: bool,
+}
+
+impl VidexCard {
+    /// カードを作成（`font` はVideoの文字ROM。内蔵の文字ROMをここから作る）
+    pub fn new(font: &[u8]) -> Self {
+        VidexCard {
+            rom: build_stub_rom(),
+            firmware: None,
+            char_rom: build_char_rom(font),
+            screen: Box::new([b' '; SCREEN_RAM_SIZE]),
+            registers: CRTC_DEFAULTS,
+            register_select: 0,
+            bank: 0,
+            expansion_active: false,
+            display: false,
+        }
+    }
+
+    /// 実機のファームウェアROM（1KB）を読み込む
+    pub fn load_firmware(&mut self, data: &[u8]) -> Result<(), String> {
+        if data.len() != FIRMWARE_SIZE {
+            return Err(format!("Videx firmware ROM must be {} bytes (got {})", FIRMWARE_SIZE, data.len()));
+        }
+        let mut firmware = Box::new([0u8; FIRMWARE_SIZE]);
+        firmware.copy_from_slice(data);
+        self.rom.copy_from_slice(&firmware[0x300..0x400]);
+        self.firmware = Some(firmware);
+        Ok(())
+    }
+
+    /// 文字ROM（2KB、4KBの場合は前半）を読み込む
+    pub fn load_char_rom(&mut self, data: &[u8]) -> Result<(), String> {
+        if data.len() < CHAR_ROM_SIZE {
+            return Err(format!("Videx character ROM must be at least {} bytes (got {})", CHAR_ROM_SIZE, data.len()));
+        }
+        self.char_rom.copy_from_slice(&data[..CHAR_ROM_SIZE]);
+        Ok(())
+    }
+
+    /// カードが応答するアドレスか（スロットI/O、スロットROM、有効な拡張ROM領域）
+    pub fn claims(&self, address: u16) -> bool {
+        let io = 0xC080 + (VIDEX_SLOT as u16) * 16;
+        let rom = 0xC000 + (VIDEX_SLOT as u16) * 256;
+        (io..io + 16).contains(&address)
+            || (rom..rom + 256).contains(&address)
+            || (self.expansion_active && (0xC800..=0xCFFF).contains(&address))
+    }
+
+    /// バス読み取り（`claims` が真のアドレスのみ）
+    pub fn read(&mut self, address: u16) -> u8 {
+        match address {
+            0xC080..=0xC0FF => {
+                self.select_bank(address);
+                if address & 1 == 1 {
+                    self.read_register()
+                } else {
+                    0
+                }
+            }
+            0xC100..=0xC7FF => {
+                self.expansion_active = true;
+                self.rom[(address & 0xFF) as usize]
+            }
+            _ => {
+                let value = self.read_expansion(address);
+                if address == 0xCFFF {
+                    self.expansion_active = false;
+                }
+                value
+            }
+        }
+    }
+
+    /// バス書き込み（`claims` が真のアドレスのみ）
+    ///
+    /// `ram` はスタブがCH/CV/CSW/KSWを読み書きするメインRAM。
+    pub fn write(&mut self, address: u16, value: u8, ram: &mut [u8]) {
+        match address {
+            0xC080..=0xC0FF => {
+                let reg = address & 0x0F;
+                if self.firmware.is_none() && (reg == HLE_INIT || reg == HLE_OUTPUT) {
+                    if reg == HLE_INIT {
+                        self.init(ram);
+                    }
+                    self.output(value, ram);
+                    return;
+                }
+                self.select_bank(address);
+                if address & 1 == 0 {
+                    self.register_select = value & 0x1F;
+                } else if let Some(r) = self.registers.get_mut(self.register_select as usize) {
+                    *r = value;
+                    self.display = true;
+                }
+            }
+            0xC100..=0xC7FF => self.expansion_active = true,
+            0xCC00..=0xCDFF => {
+                self.screen[self.bank * 512 + (address as usize & 0x1FF)] = value;
+            }
+            _ => {
+                if address == 0xCFFF {
+                    self.expansion_active = false;
+                }
+            }
+        }
+    }
+
+    /// リセット（80桁表示と拡張ROM領域を解放する）
+    pub fn reset(&mut self) {
+        self.display = false;
+        self.expansion_active = false;
+    }
+
+    /// CRTCと画面RAMの状態を保存
+    pub fn save_state(&self) -> VidexState {
+        VidexState {
+            screen: self.screen.to_vec(),
+            registers: self.registers.to_vec(),
+            register_select: self.register_select,
+            bank: self.bank,
+            expansion_active: self.expansion_active,
+            display: self.display,
+        }
+    }
+
+    /// CRTCと画面RAMの状態を復元
+    pub fn load_state(&mut self, state: &VidexState) {
+        if state.screen.len() == self.screen.len() {
+            self.screen.copy_from_slice(&state.screen);
+        }
+        if state.registers.len() == self.registers.len() {
+            self.registers.copy_from_slice(&state.registers);
+        }
+        self.register_select = state.register_select;
+        self.bank = state.bank & 0x03;
+        self.expansion_active = state.expansion_active;
+        self.display = state.display;
+    }
+
+    /// 80桁表示が有効か
+    pub fn display_enabled(&self) -> bool {
+        self.display
+    }
+
+    /// CRTCレジスタ
+    pub fn registers(&self) -> &[u8] {
+        &self.registers
+    }
+
+    /// 画面RAM
+    pub fn screen_ram(&self) -> &[u8] {
+        &self.screen[..]
+    }
+
+    /// 文字ROM（1文字16ライン、MSBが左端）
+    pub fn char_rom(&self) -> &[u8] {
+        &self.char_rom[..]
+    }
+
+    /// 表示開始アドレス（R12/R13）
+    pub fn start_address(&self) -> usize {
+        ((self.registers[12] as usize) << 8 | self.registers[13] as usize) & (SCREEN_RAM_SIZE - 1)
+    }
+
+    /// カーソルアドレス（R14/R15）
+    pub fn cursor_address(&self) -> usize {
+        ((self.registers[14] as usize) << 8 | self.registers[15] as usize) & (SCREEN_RAM_SIZE - 1)
+    }
+
+    fn select_bank(&mut self, address: u16) {
+        self.bank = ((address >> 2) & 0x03) as usize;
+    }
+
+    /// R14-R17のみ読める
+    fn read_register(&self) -> u8 {
+        match self.register_select {
+            14..=17 => self.registers[self.register_select as usize],
+            _ => 0,
+        }
+    }
+
+    fn read_expansion(&self, address: u16) -> u8 {
+        match address {
+            0xC800..=0xCBFF => self.firmware.as_ref().map_or(0, |f| f[(address - 0xC800) as usize]),
+            0xCC00..=0xCDFF => self.screen[self.bank * 512 + (address as usize & 0x1FF)],
+            _ => 0,
+        }
+    }
+
+    // ============================================================
+    // ファームウェアの代わりの出力処理
+    // ============================================================
+
+    /// PR#3: CRTCを初期化し、画面を消してCSW/KSWをカードに向ける
+    fn init(&mut self, ram: &mut [u8]) {
+        let page = 0xC0 + VIDEX_SLOT as u8;
+        self.registers = CRTC_DEFAULTS;
+        self.screen.fill(b' ');
+        self.display = true;
+        ram[ZP_CH] = 0;
+        ram[ZP_CV] = 0;
+        ram[ZP_CSW] = STUB_OUTPUT;
+        ram[ZP_CSW + 1] = page;
+        ram[ZP_KSW] = STUB_INPUT;
+        ram[ZP_KSW + 1] = page;
+    }
+
+    /// 1文字出力
+    fn output(&mut self, value: u8, ram: &mut [u8]) {
+        let mut col = (ram[ZP_CH] as usize).min(COLUMNS - 1);
+        let mut row = (ram[ZP_CV] as usize).min(ROWS - 1);
+        match value & 0x7F {
+            // CR
+            0x0D => {
+                col = 0;
+                row = self.line_feed(row);
+            }
+            // LF
+            0x0A => row = self.line_feed(row),
+            // BS
+            0x08 => {
+                if col > 0 {
+                    col -= 1;
+                } else if row > 0 {
+                    row -= 1;
+                    col = COLUMNS - 1;
+                }
+            }
+            // ^K: 画面末尾まで消去
+            0x0B => {
+                let from = row * COLUMNS + col;
+                self.clear_cells(from, ROWS * COLUMNS - from);
+            }
+            // ^L: 画面消去してホーム
+            0x0C => {
+                self.clear_cells(0, ROWS * COLUMNS);
+                col = 0;
+                row = 0;
+            }
+            // ^Y: ホーム
+            0x19 => {
+                col = 0;
+                row = 0;
+            }
+            // ^\: 右へ
+            0x1C => col = (col + 1).min(COLUMNS - 1),
+            // ^]: 行末まで消去
+            0x1D => self.clear_cells(row * COLUMNS + col, COLUMNS - col),
+            // ^_: 上へ
+            0x1F => row = row.saturating_sub(1),
+            c if c >= 0x20 => {
+                let inverse = if ram[ZP_INVFLG] != 0xFF { 0x80 } else { 0 };
+                let addr = self.cell_address(row * COLUMNS + col);
+                self.screen[addr] = c | inverse;
+                col += 1;
+                if col == COLUMNS {
+                    col = 0;
+                    row = self.line_feed(row);
+                }
+            }
+            _ => {}
+        }
+        ram[ZP_CH] = col as u8;
+        ram[ZP_CV] = row as u8;
+        let cursor = self.cell_address(row * COLUMNS + col);
+        self.registers[14] = (cursor >> 8) as u8;
+        self.registers[15] = cursor as u8;
+    }
+
+    /// 改行（最下行なら表示開始アドレスを進めてスクロール）
+    fn line_feed(&mut self, row: usize) -> usize {
+        if row + 1 < ROWS {
+            return row + 1;
+        }
+        let start = (self.start_address() + COLUMNS) & (SCREEN_RAM_SIZE - 1);
+        self.registers[12] = (start >> 8) as u8;
+        self.registers[13] = start as u8;
+        self.clear_cells((ROWS - 1) * COLUMNS, COLUMNS);
+        ROWS - 1
+    }
+
+    fn cell_address(&self, offset: usize) -> usize {
+        (self.start_address() + offset) & (SCREEN_RAM_SIZE - 1)
+    }
+
+    fn clear_cells(&mut self, from: usize, count: usize) {
+        for i in from..from + count {
+            let addr = self.cell_address(i);
+            self.screen[addr] = b' ';
+        }
+    }
+}
+
+/// スタブのスロットROMを生成
+fn build_stub_rom() -> [u8; 256] {
+    let mut rom = [0u8; 256];
+    let page = 0xC0 + VIDEX_SLOT as u8;
+    let io = 0x80 + (VIDEX_SLOT as u8) * 16;
+    // $C300: JMP 初期化
+    rom[0x00..0x03].copy_from_slice(&[0x4C, STUB_INIT, page]);
+    // Pascal 1.1 ファームウェアの識別バイト（80桁カード）
+    rom[0x05] = 0x38;
+    rom[0x07] = 0x18;
+    rom[0x0B] = 0x01;
+    rom[0x0C] = 0x82;
+    // 初期化: STA $C0BE / RTS
+    rom[STUB_INIT as usize..STUB_INIT as usize + 4].copy_from_slice(&[0x8D, io + HLE_INIT as u8, 0xC0, 0x60]);
+    // COUT: STA $C0BF / RTS
+    rom[STUB_OUTPUT as usize..STUB_OUTPUT as usize + 4].copy_from_slice(&[0x8D, io + HLE_OUTPUT as u8, 0xC0, 0x60]);
+    // KSW: STA (BASL),Y / LDA $C000 / BPL *-3 / BIT $C010 / RTS
+    rom[STUB_INPUT as usize..STUB_INPUT as usize + 11]
+        .copy_from_slice(&[0x91, 0x28, 0xAD, 0x00, 0xC0, 0x10, 0xFB, 0x2C, 0x10, 0xC0, 0x60]);
+    rom
+}
+
+/// Apple IIの7x8フォントからVidexの文字ROM（ASCII順、16ライン/文字）を作る
+///
+/// フォントのビット6-0をビット7-1に置き、ビット0を文字間の隙間にする。
+fn build_char_rom(font: &[u8]) -> Box<[u8; CHAR_ROM_SIZE]> {
+    let mut rom = Box::new([0u8; CHAR_ROM_SIZE]);
+    for code in 0x20..0x80usize {
+        // Apple IIの文字ROMの並び: $00-$3F = '@'-'?'、$40-$5F = '`'-DEL
+        let index = match code {
+            0x20..=0x3F => code,
+            0x40..=0x5F => code - 0x40,
+            _ => code - 0x20,
+        };
+        for line in 0..8 {
+            let byte = font.get(index * 8 + line).copied().unwrap_or(0);
+            rom[code * 16 + line] = (byte & 0x7F) << 1;
+        }
+    }
+    rom
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stub_output_scrolls_with_start_address() {
+        let mut card = VidexCard::new(&[0u8; 2048]);
+        let mut ram = vec![0u8; 0x10000];
+        ram[ZP_INVFLG] = 0xFF;
+        card.write(0xC0BE, b'A' | 0x80, &mut ram);
+        assert!(card.display_enabled());
+        assert_eq!((ram[ZP_CSW], ram[ZP_CSW + 1]), (STUB_OUTPUT, 0xC3));
+        assert_eq!(card.screen_ram()[0], b'A');
+
+        for _ in 0..ROWS {
+            card.write(0xC0BF, 0x8D, &mut ram);
+        }
+        assert_eq!(ram[ZP_CV] as usize, ROWS - 1);
+        assert_eq!(card.start_address(), COLUMNS);
+        card.write(0xC0BF, b'Z' | 0x80, &mut ram);
+        assert_eq!(card.cursor_address(), COLUMNS + (ROWS - 1) * COLUMNS + 1);
+
+        // $C3xxアクセス後は$CC00から画面RAMが見える（$C0B0-$C0B3でバンク0）
+        card.read(0xC300);
+        card.read(0xC0B0);
+        assert_eq!(card.read(0xCC00), b'A');
+        card.read(0xCFFF);
+        assert!(!card.claims(0xCC00));
+    }
+}
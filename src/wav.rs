@@ -0,0 +1,240 @@
+//! WAVファイルの読み書き
+//!
+//! 16bit PCMのRIFF/WAVEファイルをストリーミングで書き出す。
+//! ヘッダのサイズ欄は `finish` で確定させる。
+//! サイズ欄は32bitなので、約4GiBに達したら書き込みをエラーにする（それまでの内容は有効なファイルとして残る）。
+//! 読み込みは8/16/24/32bit PCMと32bit floatに対応する。
+
+use std::fs::File;
+use std::io::{self, BufWriter, Seek, SeekFrom, Write};
+use std::path::Path;
+
+/// WAVヘッダのサイズ（RIFF + fmt + dataチャンクヘッダ）
+const HEADER_SIZE: u32 = 44;
+
+/// dataチャンクの最大バイト数（RIFFのサイズ欄がu32に収まる範囲、サンプル単位の偶数）
+const MAX_DATA_BYTES: u32 = (u32::MAX - (HEADER_SIZE - 8)) & !1;
+
+/// 16bit PCM WAVライター
+pub struct WavWriter {
+    writer: BufWriter<File>,
+    sample_rate: u32,
+    channels: u16,
+    /// 書き込んだデータのバイト数
+    data_bytes: u32,
+}
+
+impl WavWriter {
+    /// ファイルを作成してヘッダを書き込む
+    pub fn create<P: AsRef<Path>>(path: P, sample_rate: u32, channels: u16) -> io::Result<Self> {
+        let file = File::create(path)?;
+        let mut wav = WavWriter {
+            writer: BufWriter::new(file),
+            sample_rate,
+            channels,
+            data_bytes: 0,
+        };
+        wav.write_header()?;
+        Ok(wav)
+    }
+
+    fn write_header(&mut self) -> io::Result<()> {
+        let block_align = self.channels * 2;
+        let byte_rate = self.sample_rate * block_align as u32;
+        let w = &mut self.writer;
+        w.write_all(b"RIFF")?;
+        w.write_all(&(HEADER_SIZE - 8 + self.data_bytes).to_le_bytes())?;
+        w.write_all(b"WAVE")?;
+        w.write_all(b"fmt ")?;
+        w.write_all(&16u32.to_le_bytes())?;
+        w.write_all(&1u16.to_le_bytes())?; // PCM
+        w.write_all(&self.channels.to_le_bytes())?;
+        w.write_all(&self.sample_rate.to_le_bytes())?;
+        w.write_all(&byte_rate.to_le_bytes())?;
+        w.write_all(&block_align.to_le_bytes())?;
+        w.write_all(&16u16.to_le_bytes())?; // bits per sample
+        w.write_all(b"data")?;
+        w.write_all(&self.data_bytes.to_le_bytes())?;
+        Ok(())
+    }
+
+    /// サンプルレート
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// `samples` 個書き込んだ後のデータのバイト数（サイズの上限を超える場合はエラー）
+    fn grow(&self, samples: usize) -> io::Result<u32> {
+        let bytes = (samples as u64).saturating_mul(2).saturating_add(self.data_bytes as u64);
+        if bytes > MAX_DATA_BYTES as u64 {
+            return Err(io::Error::other("WAV file size limit (4 GiB) reached"));
+        }
+        Ok(bytes as u32)
+    }
+
+    /// 16bitサンプルを書き込む（複数チャンネルはインターリーブ済みで渡す）
+    pub fn write_i16(&mut self, samples: &[i16]) -> io::Result<()> {
+        let data_bytes = self.grow(samples.len())?;
+        for &s in samples {
+            self.writer.write_all(&s.to_le_bytes())?;
+        }
+        self.data_bytes = data_bytes;
+        Ok(())
+    }
+
+    /// -1.0〜+1.0のサンプルを16bitに変換して書き込む
+    pub fn write_f32(&mut self, samples: &[f32]) -> io::Result<()> {
+        let data_bytes = self.grow(samples.len())?;
+        for &s in samples {
+            let v = (s.clamp(-1.0, 1.0) * i16::MAX as f32) as i16;
+            self.writer.write_all(&v.to_le_bytes())?;
+        }
+        self.data_bytes = data_bytes;
+        Ok(())
+    }
+
+    /// 同じ値を指定数だけ書き込む
+    pub fn write_run(&mut self, sample: i16, count: usize) -> io::Result<()> {
+        let data_bytes = self.grow(count)?;
+        let bytes = sample.to_le_bytes();
+        for _ in 0..count {
+            self.writer.write_all(&bytes)?;
+        }
+        self.data_bytes = data_bytes;
+        Ok(())
+    }
+
+    /// 無音を書き込む
+    pub fn write_silence(&mut self, samples: usize) -> io::Result<()> {
+        self.write_run(0, samples)
+    }
+
+    /// 書き込んだサンプル数（チャンネルあたり）
+    pub fn samples_written(&self) -> u64 {
+        self.data_bytes as u64 / (self.channels as u64 * 2)
+    }
+
+    /// ヘッダのサイズ欄を確定してファイルを閉じる
+    pub fn finish(mut self) -> io::Result<()> {
+        self.writer.seek(SeekFrom::Start(0))?;
+        self.write_header()?;
+        self.writer.flush()
+    }
+}
+
+/// 読み込んだWAVデータ（先頭チャンネルのみ）
+pub struct WavData {
+    pub sample_rate: u32,
+    /// -1.0〜+1.0のサンプル
+    pub samples: Vec<f32>,
+}
+
+/// WAVファイルを読み込む
+pub fn read_wav<P: AsRef<Path>>(path: P) -> io::Result<WavData> {
+    parse_wav(&std::fs::read(path)?)
+}
+
+/// WAVデータを解析する
+pub fn parse_wav(data: &[u8]) -> io::Result<WavData> {
+    let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, msg.to_string());
+    if data.len() < 12 || &data[0..4] != b"RIFF" || &data[8..12] != b"WAVE" {
+        return Err(invalid("Not a RIFF/WAVE file"));
+    }
+
+    let mut format = None;
+    let mut pos = 12;
+    while pos + 8 <= data.len() {
+        let id = &data[pos..pos + 4];
+        let size = u32::from_le_bytes([data[pos + 4], data[pos + 5], data[pos + 6], data[pos + 7]]) as usize;
+        let body = &data[pos + 8..(pos + 8 + size).min(data.len())];
+        match id {
+            b"fmt " if body.len() >= 16 => {
+                let mut tag = u16::from_le_bytes([body[0], body[1]]);
+                // WAVE_FORMAT_EXTENSIBLE はサブフォーマットGUIDの先頭2バイトで判定する
+                if tag == 0xFFFE && body.len() >= 26 {
+                    tag = u16::from_le_bytes([body[24], body[25]]);
+                }
+                let channels = u16::from_le_bytes([body[2], body[3]]).max(1) as usize;
+                let sample_rate = u32::from_le_bytes([body[4], body[5], body[6], body[7]]);
+                let bits = u16::from_le_bytes([body[14], body[15]]);
+                format = Some((tag, channels, sample_rate, bits));
+            }
+            b"data" => {
+                let (tag, channels, sample_rate, bits) = format.ok_or_else(|| invalid("data chunk before fmt chunk"))?;
+                let bytes = (bits as usize).div_ceil(8);
+                let frame = bytes * channels;
+                let float = tag == 3;
+                let decode = |s: &[u8]| -> Option<f32> {
+                    Some(match (bytes, float) {
+                        (1, false) => (s[0] as f32 - 128.0) / 128.0,
+                        (2, false) => i16::from_le_bytes([s[0], s[1]]) as f32 / 32768.0,
+                        (3, false) => (i32::from_le_bytes([0, s[0], s[1], s[2]]) >> 8) as f32 / 8_388_608.0,
+                        (4, false) => i32::from_le_bytes([s[0], s[1], s[2], s[3]]) as f32 / 2_147_483_648.0,
+                        (4, true) => f32::from_le_bytes([s[0], s[1], s[2], s[3]]),
+                        _ => return None,
+                    })
+                };
+                if tag != 1 && tag != 3 {
+                    return Err(invalid("Unsupported WAV encoding"));
+                }
+                let mut samples = Vec::with_capacity(body.len() / frame.max(1));
+                for chunk in body.chunks_exact(frame.max(1)) {
+                    samples.push(decode(chunk).ok_or_else(|| invalid("Unsupported WAV sample format"))?);
+                }
+                return Ok(WavData { sample_rate, samples });
+            }
+            _ => {}
+        }
+        // チャンクは偶数バイト境界に揃える
+        pos += 8 + size + (size & 1);
+    }
+    Err(invalid("No data chunk in WAV file"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn header_sizes_are_patched() {
+        let path = std::env::temp_dir().join(format!("a2rs_wav_test_{}.wav", std::process::id()));
+        let mut wav = WavWriter::create(&path, 44100, 1).unwrap();
+        wav.write_f32(&[0.0, 1.0, -1.0]).unwrap();
+        wav.write_silence(2).unwrap();
+        assert_eq!(wav.samples_written(), 5);
+        wav.finish().unwrap();
+
+        let data = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+        assert_eq!(data.len(), 44 + 10);
+        assert_eq!(&data[4..8], &(36u32 + 10).to_le_bytes());
+        assert_eq!(&data[40..44], &10u32.to_le_bytes());
+        assert_eq!(&data[46..48], &i16::MAX.to_le_bytes());
+
+        let wav = parse_wav(&data).unwrap();
+        assert_eq!(wav.sample_rate, 44100);
+        assert_eq!(wav.samples.len(), 5);
+        assert!(wav.samples[1] > 0.99 && wav.samples[2] < -0.99);
+    }
+
+    #[test]
+    fn stops_at_size_limit() {
+        let path = std::env::temp_dir().join(format!("a2rs_wav_limit_{}.wav", std::process::id()));
+        let mut wav = WavWriter::create(&path, 44100, 1).unwrap();
+        // 4GiBを実際に書かずに上限の手前まで進めたことにする
+        wav.data_bytes = MAX_DATA_BYTES - 4;
+        wav.write_i16(&[1, 2]).unwrap();
+        assert!(wav.write_silence(1).is_err());
+        assert!(wav.write_f32(&[0.0]).is_err());
+        assert!(wav.write_run(0, usize::MAX).is_err());
+        assert_eq!(wav.data_bytes, MAX_DATA_BYTES);
+        wav.finish().unwrap();
+
+        let data = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+        // 上限を超える書き込みはファイルに何も足さない
+        assert_eq!(data.len(), 44 + 4);
+        assert_eq!(&data[4..8], &(MAX_DATA_BYTES + 36).to_le_bytes());
+        assert_eq!(&data[40..44], &MAX_DATA_BYTES.to_le_bytes());
+    }
+}
@@ -0,0 +1,26 @@
+//! Bruce Clark の10進モードテスト（6502 / 65C02）
+//!
+//! 不正なBCD値を含む全組み合わせでADC/SBCの結果とフラグを確かめ、
+//! 10進モードで65C02だけ1サイクル多いことも確認する。
+
+use a2rs::cpu::decimal;
+use a2rs::cpu::CpuType;
+
+fn check(cpu_type: CpuType) {
+    let result = decimal::run(cpu_type).unwrap();
+    assert!(result.completed, "{:?}: did not reach HALT (PC=${:04X})", cpu_type, result.pc);
+    assert_eq!(result.failure, None, "{:?}", cpu_type);
+    for opcode in [0x69, 0xE9] {
+        assert_eq!(decimal::measure_cycles(cpu_type, opcode), decimal::expected_cycles(cpu_type), "{:?} {:02X}", cpu_type, opcode);
+    }
+}
+
+#[test]
+fn decimal_mode_6502() {
+    check(CpuType::Cpu6502);
+}
+
+#[test]
+fn decimal_mode_65c02() {
+    check(CpuType::Cpu65C02);
+}
@@ -0,0 +1,77 @@
+//! Tom Harte SingleStepTests（6502 / 65C02）
+//!
+//! `tests/fixtures/65x02` の少数のケース（同じJSON形式、バスアクセス付き）は常に実行する。
+//! 全データは `tests/65x02`（または環境変数 `A2RS_SST_DIR`）に配置すると追加で実行し、
+//! ない場合はスキップする。`A2RS_SST_BUS=1` で全データのバスアクセスも比較する。
+
+use std::path::PathBuf;
+
+use a2rs::cpu::single_step::{self, CheckOptions, OpcodeResult};
+use a2rs::cpu::CpuType;
+
+/// 同梱のケース（オペコード, JSON）
+const FIXTURES_6502: &[(u8, &str)] = &[
+    (0x69, include_str!("fixtures/65x02/6502/v1/69.json")),
+    (0x8D, include_str!("fixtures/65x02/6502/v1/8d.json")),
+    (0xA9, include_str!("fixtures/65x02/6502/v1/a9.json")),
+];
+const FIXTURES_65C02: &[(u8, &str)] = &[
+    (0x64, include_str!("fixtures/65x02/wdc65c02/v1/64.json")),
+    (0xB2, include_str!("fixtures/65x02/wdc65c02/v1/b2.json")),
+];
+
+fn assert_passed(cpu_type: CpuType, results: &[OpcodeResult]) {
+    single_step::print_report(results);
+    let failed: Vec<String> = results
+        .iter()
+        .filter(|r| !r.all_passed())
+        .map(|r| format!("{:02X}", r.opcode))
+        .collect();
+    assert!(failed.is_empty(), "{:?} failed: {}", cpu_type, failed.join(" "));
+}
+
+fn run_fixtures(cpu_type: CpuType, fixtures: &[(u8, &str)]) {
+    let options = CheckOptions { check_bus: true };
+    let results: Vec<OpcodeResult> = fixtures
+        .iter()
+        .map(|&(opcode, json)| single_step::run_json(json, opcode, cpu_type, options).unwrap())
+        .collect();
+    assert!(results.iter().all(|r| r.total > 0));
+    assert_passed(cpu_type, &results);
+}
+
+fn run(cpu_type: CpuType) {
+    let root = single_step::resolve_root(PathBuf::from(env!("CARGO_MANIFEST_DIR")).join(single_step::DEFAULT_ROOT));
+    let dir = single_step::test_dir(&root, cpu_type);
+    if !dir.is_dir() {
+        println!("SingleStepTests not found at {}, skipping", dir.display());
+        return;
+    }
+
+    let options = CheckOptions {
+        check_bus: std::env::var("A2RS_SST_BUS").is_ok_and(|v| v == "1"),
+    };
+    let opcodes = single_step::implemented_opcodes(cpu_type);
+    let results = single_step::run_dir(&dir, cpu_type, &opcodes, options).unwrap();
+    assert_passed(cpu_type, &results);
+}
+
+#[test]
+fn single_step_fixtures_6502() {
+    run_fixtures(CpuType::Cpu6502, FIXTURES_6502);
+}
+
+#[test]
+fn single_step_fixtures_65c02() {
+    run_fixtures(CpuType::Cpu65C02, FIXTURES_65C02);
+}
+
+#[test]
+fn single_step_6502() {
+    run(CpuType::Cpu6502);
+}
+
+#[test]
+fn single_step_65c02() {
+    run(CpuType::Cpu65C02);
+}